@@ -0,0 +1,118 @@
+//! Benchmarks of the line counting strategies.
+//!
+//! Each group counts a synthetic tree with every [`ReadStrategy`], so that
+//! the buffered and memory-mapped paths can be compared and the mapping
+//! threshold checked:
+//!
+//! - `small_files`: many source files of a few kilobytes, as in most repositories
+//! - `large_files`: a few generated sources and SQL dumps of several megabytes
+//! - `mixed_tree`: both, as counted by `gitlsf` itself
+//! - `huge_file`: one generated source of about 100 MB next to a few small
+//!   files, which only intra-file parallelism spreads over several cores
+//!
+//! Run with `cargo bench`; results are written to `target/criterion`.
+
+use std::fs;
+use std::path::Path;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use gitlsf::counter::{ReadStrategy, count_lines_parallel_with_strategy};
+use tempfile::TempDir;
+
+/// The strategies compared in every group.
+const STRATEGIES: [(&str, ReadStrategy); 3] = [
+    ("auto", ReadStrategy::Auto),
+    ("buffered", ReadStrategy::Buffered),
+    ("mapped", ReadStrategy::Mapped),
+];
+
+/// A Rust source file of about `lines` lines, with code, comments and blank lines.
+fn rust_source(lines: usize) -> String {
+    let mut source = String::with_capacity(lines * 32);
+    for i in 0..lines / 4 {
+        source.push_str(&format!("/// Returns the value {i}.\n"));
+        source.push_str(&format!("fn value_{i}() -> usize {{\n"));
+        source.push_str(&format!("    {i} // the value\n}}\n\n"));
+    }
+    source
+}
+
+/// A SQL dump of about `lines` lines.
+fn sql_dump(lines: usize) -> String {
+    let mut dump = String::with_capacity(lines * 64);
+    dump.push_str("-- Dump of table `events`\n");
+    for i in 0..lines {
+        dump.push_str(&format!(
+            "INSERT INTO events VALUES ({i}, 'event-{i}', '2024-01-01 00:00:00');\n"
+        ));
+    }
+    dump
+}
+
+/// Writes the files of a synthetic tree and returns their paths.
+fn write_tree(root: &Path, files: &[(String, String)]) -> Vec<String> {
+    for (path, content) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    files.iter().map(|(path, _)| path.clone()).collect()
+}
+
+/// Source files of about 120 lines, spread over directories.
+fn small_files(count: usize) -> Vec<(String, String)> {
+    let source = rust_source(120);
+    (0..count)
+        .map(|i| (format!("src/module_{}/file_{i}.rs", i % 50), source.clone()))
+        .collect()
+}
+
+/// Generated sources and SQL dumps of several megabytes each.
+fn large_files() -> Vec<(String, String)> {
+    vec![
+        ("generated/schema.rs".to_string(), rust_source(400_000)),
+        ("generated/bindings.rs".to_string(), rust_source(250_000)),
+        ("dumps/events.sql".to_string(), sql_dump(200_000)),
+        ("dumps/users.sql".to_string(), sql_dump(100_000)),
+    ]
+}
+
+/// Counts one tree with every strategy.
+fn bench_tree(c: &mut Criterion, name: &str, files: Vec<(String, String)>) {
+    let temp_dir = TempDir::new().unwrap();
+    let bytes: usize = files.iter().map(|(_, content)| content.len()).sum();
+    let paths = write_tree(temp_dir.path(), &files);
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(bytes as u64));
+    for (label, strategy) in STRATEGIES {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(label),
+            &strategy,
+            |b, &strategy| {
+                b.iter(|| count_lines_parallel_with_strategy(temp_dir.path(), &paths, strategy));
+            },
+        );
+    }
+    group.finish();
+}
+
+fn counting(c: &mut Criterion) {
+    bench_tree(c, "small_files", small_files(2000));
+    bench_tree(c, "large_files", large_files());
+
+    let mut mixed = small_files(1000);
+    mixed.extend(large_files());
+    bench_tree(c, "mixed_tree", mixed);
+
+    let mut huge = small_files(10);
+    huge.push(("generated/huge.rs".to_string(), rust_source(3_000_000)));
+    bench_tree(c, "huge_file", huge);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = counting
+}
+criterion_main!(benches);
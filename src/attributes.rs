@@ -0,0 +1,299 @@
+//! Git attributes module.
+//!
+//! This module reads the `.gitattributes` entries that GitHub Linguist uses
+//! for its language statistics, so that gitlsf agrees with them:
+//!
+//! - `linguist-generated`, `linguist-vendored` and `linguist-documentation`
+//!   mark files that are not counted
+//! - `binary`, `-diff` and `-text` mark files that are not counted either,
+//!   as binary files
+//! - `linguist-language=<name>` assigns a language to matching files
+//!
+//! ```text
+//! *.pb.go          linguist-generated
+//! third_party/**   linguist-vendored
+//! *.h              linguist-language=C++
+//! assets/*.dat     binary
+//! ```
+//!
+//! As with Linguist, a flag attribute applies when it is set or has the value
+//! `true`; `-linguist-vendored` or `linguist-vendored=false` clears it.
+//! Language names are matched like the `[languages]` table of the
+//! configuration file, with `-` also accepted in place of a space.
+
+use std::path::Path;
+
+use crate::counter::SkipReason;
+use crate::error::Result;
+use crate::git::{self, AttributeValue};
+use crate::language::Language;
+
+/// The attributes looked up for every file, in the order [`Attributes::from_values`] expects.
+const ATTRIBUTE_NAMES: [&str; 7] = [
+    "linguist-generated",
+    "linguist-vendored",
+    "linguist-documentation",
+    "linguist-language",
+    "binary",
+    "diff",
+    "text",
+];
+
+/// The Linguist attributes of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Attributes {
+    /// The file is marked `linguist-generated`.
+    pub generated: bool,
+    /// The file is marked `linguist-vendored`.
+    pub vendored: bool,
+    /// The file is marked `linguist-documentation`.
+    pub documentation: bool,
+    /// The file is marked `binary`, `-diff` or `-text`.
+    pub binary: bool,
+    /// The language given by `linguist-language`, if it is known.
+    pub language: Option<Language>,
+}
+
+impl Attributes {
+    /// Returns `true` if the attributes exclude the file from the count.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::attributes::Attributes;
+    ///
+    /// assert!(!Attributes::default().is_excluded());
+    /// assert!(Attributes { vendored: true, ..Attributes::default() }.is_excluded());
+    /// ```
+    pub fn is_excluded(&self) -> bool {
+        self.skip_reason().is_some()
+    }
+
+    /// Returns the reason the attributes exclude the file, if they do.
+    ///
+    /// A file with several of the attributes is reported under the first of
+    /// generated, vendored, documentation and binary.
+    pub fn skip_reason(&self) -> Option<SkipReason> {
+        if self.generated {
+            Some(SkipReason::Generated)
+        } else if self.vendored {
+            Some(SkipReason::Vendored)
+        } else if self.documentation {
+            Some(SkipReason::Documentation)
+        } else if self.binary {
+            Some(SkipReason::Binary)
+        } else {
+            None
+        }
+    }
+
+    /// Interprets the values of [`ATTRIBUTE_NAMES`], in order.
+    fn from_values(values: &[AttributeValue]) -> Self {
+        let flag = |value: &AttributeValue| match value {
+            AttributeValue::Set => true,
+            AttributeValue::Value(value) => value.eq_ignore_ascii_case("true"),
+            AttributeValue::Unset | AttributeValue::Unspecified => false,
+        };
+
+        let language = match &values[3] {
+            AttributeValue::Value(name) => {
+                Language::from_name(name).or_else(|| Language::from_name(&name.replace('-', " ")))
+            }
+            _ => None,
+        };
+
+        Attributes {
+            generated: flag(&values[0]),
+            vendored: flag(&values[1]),
+            documentation: flag(&values[2]),
+            language,
+            binary: flag(&values[4])
+                || values[5] == AttributeValue::Unset
+                || values[6] == AttributeValue::Unset,
+        }
+    }
+}
+
+/// Reads the Linguist attributes of files.
+///
+/// Uses a single `git check-attr` process for all files; see
+/// [`git::check_attributes`]. With the `native-index` feature, no process is
+/// run for the working tree if no attributes file may apply to the files,
+/// whose attributes are then all unset.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository (or a subdirectory within it).
+/// * `revision` - The revision whose `.gitattributes` files apply, or `None`
+///   for the working tree.
+/// * `files` - File paths relative to `path`.
+///
+/// # Returns
+///
+/// The attributes of each file, in the order of `files`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::attributes::read_attributes;
+/// use gitlsf::git::{FileSet, list_files};
+///
+/// let files = list_files(".", FileSet::Tracked, &[]).unwrap();
+/// for (file, attributes) in files.iter().zip(read_attributes(".", None, &files).unwrap()) {
+///     if attributes.is_excluded() {
+///         println!("skipped: {}", file.display());
+///     }
+/// }
+/// ```
+pub fn read_attributes<S: AsRef<Path>>(
+    path: impl AsRef<Path>,
+    revision: Option<&str>,
+    files: &[S],
+) -> Result<Vec<Attributes>> {
+    #[cfg(feature = "native-index")]
+    if revision.is_none() && !crate::index::has_attributes(path.as_ref(), files)? {
+        return Ok(vec![Attributes::default(); files.len()]);
+    }
+
+    let values = git::check_attributes(path, revision, files, &ATTRIBUTE_NAMES)?;
+
+    Ok(values
+        .iter()
+        .map(|values| Attributes::from_values(values))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup_git_repo(gitattributes: &str) -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        git(path, &["init", "-q"]);
+        git(path, &["config", "user.email", "test@test.com"]);
+        git(path, &["config", "user.name", "Test User"]);
+        fs::write(path.join(".gitattributes"), gitattributes).unwrap();
+        temp_dir
+    }
+
+    fn value(value: &str) -> AttributeValue {
+        AttributeValue::Value(value.to_string())
+    }
+
+    #[test]
+    fn test_from_values() {
+        use AttributeValue::{Set, Unset, Unspecified};
+
+        let attributes = Attributes::from_values(&[
+            Set,
+            value("false"),
+            value("true"),
+            value("Protocol-Buffer"),
+            Unspecified,
+            Unspecified,
+            Unspecified,
+        ]);
+        assert!(attributes.generated);
+        assert!(!attributes.vendored);
+        assert!(attributes.documentation);
+        assert!(!attributes.binary);
+        assert_eq!(attributes.language, None);
+
+        let attributes = Attributes::from_values(&[
+            Unset,
+            Unspecified,
+            Unspecified,
+            value("c++"),
+            Unspecified,
+            Unset,
+            Unspecified,
+        ]);
+        assert!(attributes.binary);
+        assert_eq!(attributes.skip_reason(), Some(SkipReason::Binary));
+        assert_eq!(attributes.language, Some(Language::Cpp));
+    }
+
+    #[test]
+    fn test_read_attributes() {
+        let temp_dir = setup_git_repo(
+            "*.pb.go linguist-generated\n\
+             vendor/** linguist-vendored\n\
+             vendor/keep.go -linguist-vendored\n\
+             *.h linguist-language=C++\n\
+             *.dat binary\n\
+             *.bin -text\n",
+        );
+
+        let attributes = read_attributes(
+            temp_dir.path(),
+            None,
+            &[
+                "api.pb.go",
+                "vendor/lib.go",
+                "vendor/keep.go",
+                "include/a.h",
+                "blob.dat",
+                "main.go",
+                "firmware.bin",
+            ],
+        )
+        .unwrap();
+
+        let reasons: Vec<Option<SkipReason>> =
+            attributes.iter().map(Attributes::skip_reason).collect();
+        assert_eq!(
+            reasons,
+            [
+                Some(SkipReason::Generated),
+                Some(SkipReason::Vendored),
+                None,
+                None,
+                Some(SkipReason::Binary),
+                None,
+                Some(SkipReason::Binary),
+            ]
+        );
+        assert_eq!(attributes[3].language, Some(Language::Cpp));
+        assert_eq!(attributes[5], Attributes::default());
+    }
+
+    #[test]
+    fn test_read_attributes_at_revision() {
+        let temp_dir = setup_git_repo("*.go linguist-generated\n");
+        let path = temp_dir.path();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "initial"]);
+
+        // The working tree no longer marks Go files, but HEAD does
+        fs::write(path.join(".gitattributes"), "").unwrap();
+
+        let head = read_attributes(path, Some("HEAD"), &["main.go"]).unwrap();
+        let working_tree = read_attributes(path, None, &["main.go"]).unwrap();
+
+        assert!(head[0].generated);
+        assert!(!working_tree[0].generated);
+    }
+
+    #[test]
+    fn test_read_attributes_no_files() {
+        let temp_dir = setup_git_repo("");
+        let files: [&str; 0] = [];
+
+        assert!(
+            read_attributes(temp_dir.path(), None, &files)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,726 @@
+//! Line attribution module.
+//!
+//! This module attributes the surviving lines of counted files to the
+//! authors of the commits that last changed them, using `git blame`. Files
+//! are blamed in parallel, one `git blame` process each, and the authors of
+//! all blamed commits are then looked up at once, so the cost grows with the
+//! number of files but not with the number of commits.
+//!
+//! Authors are identified by email address. With `.mailmap` resolution, names
+//! and addresses are mapped as `git shortlog` maps them, so that a person who
+//! committed under several addresses is counted once. Lines of the working
+//! tree that are not committed yet are attributed to `Not Committed Yet
+//! <not.committed.yet>`, as `git blame` shows them.
+//!
+//! ## JSON schema
+//!
+//! [`AttributionReport::write_json`] produces a single JSON object with the
+//! `schema_version`, `tool`, `repository`, `revision` and `filter` fields of
+//! the [counting report](crate::output#json-schema), followed by:
+//!
+//! | Field | Type | Description |
+//! |-------|------|-------------|
+//! | `mailmap` | boolean | Whether authors were resolved through `.mailmap` |
+//! | `totals` | object | `files` and `lines` totals |
+//! | `authors` | array of objects | Per-author `name`, `email`, `files` and `lines`, largest first |
+//! | `directories` | array of objects, optional | Per-directory `path`, `files`, `lines` and `authors`, sorted by path; only present when requested |
+//!
+//! ## CSV and TSV
+//!
+//! [`write_csv`] and [`write_tsv`] emit one row per author, largest first,
+//! under the header `email,name,files,lines`. With a directory depth, there is
+//! one row per author of each directory instead, sorted by directory, under
+//! the header `directory,email,name,files,lines`. Directories are written with
+//! [`table_path`] and fields quoted as in the
+//! [counting tables](crate::output#csv-and-tsv).
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::counter::CountSummary;
+use crate::diff::truncate_directory;
+use crate::error::{GitlsfError, Result};
+use crate::filter::FileFilter;
+use crate::git::{self, Author};
+use crate::output::{
+    FilterInfo, RevisionInfo, SCHEMA_VERSION, ToolInfo, serialize_path, table_path, write_row,
+};
+
+/// The name `git blame` gives the author of lines that are not committed yet.
+pub const UNCOMMITTED_NAME: &str = "Not Committed Yet";
+
+/// The email address `git blame` gives the author of lines that are not committed yet.
+pub const UNCOMMITTED_EMAIL: &str = "not.committed.yet";
+
+/// The lines of one file, attributed to their authors.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FileAttribution {
+    /// The path of the file relative to the repository root.
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+    /// Number of lines of each author, keyed by email address.
+    pub authors: BTreeMap<String, usize>,
+}
+
+impl FileAttribution {
+    /// Returns the number of lines of the file.
+    pub fn lines(&self) -> usize {
+        self.authors.values().sum()
+    }
+}
+
+/// The lines attributed to one author.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AuthorSummary {
+    /// The author name.
+    pub name: String,
+    /// The author email address.
+    pub email: String,
+    /// Number of files with at least one line of the author.
+    pub files: usize,
+    /// Number of lines of the author.
+    pub lines: usize,
+}
+
+/// The lines of the files in one directory, attributed to their authors.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DirectoryAttribution {
+    /// The directory path relative to the repository root, or `.` for the
+    /// root.
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+    /// Number of files in the directory and its subdirectories.
+    pub files: usize,
+    /// Number of lines of those files.
+    pub lines: usize,
+    /// Per-author totals, largest first.
+    pub authors: Vec<AuthorSummary>,
+}
+
+/// The lines of a set of files, attributed to their authors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attribution {
+    /// Per-file attribution, sorted by path.
+    pub files: Vec<FileAttribution>,
+    /// The name of each author, keyed by email address. An address used with
+    /// several names is given the name most of its lines were committed under.
+    pub names: BTreeMap<String, String>,
+    /// Whether names and email addresses were resolved through `.mailmap`.
+    pub mailmap: bool,
+}
+
+impl Attribution {
+    /// Returns the total number of lines.
+    pub fn total_lines(&self) -> usize {
+        self.files.iter().map(FileAttribution::lines).sum()
+    }
+
+    /// Aggregates the lines of all files per author, largest first.
+    ///
+    /// Authors with the same number of lines are sorted by email address.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use gitlsf::blame::{Attribution, FileAttribution};
+    ///
+    /// let file = |path: &str, authors: &[(&str, usize)]| FileAttribution {
+    ///     path: path.into(),
+    ///     authors: authors.iter().map(|(email, lines)| (email.to_string(), *lines)).collect(),
+    /// };
+    /// let attribution = Attribution {
+    ///     files: vec![
+    ///         file("a.rs", &[("ann@x.org", 10), ("bob@x.org", 2)]),
+    ///         file("b.rs", &[("bob@x.org", 5)]),
+    ///     ],
+    ///     names: BTreeMap::from([
+    ///         ("ann@x.org".to_string(), "Ann".to_string()),
+    ///         ("bob@x.org".to_string(), "Bob".to_string()),
+    ///     ]),
+    ///     mailmap: true,
+    /// };
+    ///
+    /// let authors = attribution.by_author();
+    /// assert_eq!(authors[0].name, "Ann");
+    /// assert_eq!(authors[1].files, 2);
+    /// assert_eq!(authors[1].lines, 7);
+    /// ```
+    pub fn by_author(&self) -> Vec<AuthorSummary> {
+        self.summarize(self.files.iter())
+    }
+
+    /// Aggregates the lines per directory and author.
+    ///
+    /// Directories are keyed by their first `depth` path components, so each
+    /// also covers the files of its subdirectories beyond that depth. Files
+    /// at the repository root are grouped under `.`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::path::Path;
+    /// use gitlsf::blame::{Attribution, FileAttribution};
+    ///
+    /// let file = |path: &str, lines: usize| FileAttribution {
+    ///     path: path.into(),
+    ///     authors: BTreeMap::from([("ann@x.org".to_string(), lines)]),
+    /// };
+    /// let attribution = Attribution {
+    ///     files: vec![file("build.rs", 1), file("src/a/x.rs", 2), file("src/b/y.rs", 3)],
+    ///     ..Attribution::default()
+    /// };
+    ///
+    /// let directories = attribution.by_directory(1);
+    /// assert_eq!(directories[0].path, Path::new("."));
+    /// assert_eq!(directories[1].path, Path::new("src"));
+    /// assert_eq!(directories[1].lines, 5);
+    /// assert_eq!(attribution.by_directory(2).len(), 3);
+    /// ```
+    pub fn by_directory(&self, depth: usize) -> Vec<DirectoryAttribution> {
+        let mut grouped: BTreeMap<OsString, (PathBuf, Vec<&FileAttribution>)> = BTreeMap::new();
+
+        for file in &self.files {
+            let parent = file
+                .path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let key = truncate_directory(parent, depth);
+            grouped
+                .entry(key.clone().into_os_string())
+                .or_insert_with(|| (key, Vec::new()))
+                .1
+                .push(file);
+        }
+
+        grouped
+            .into_values()
+            .map(|(path, files)| DirectoryAttribution {
+                path,
+                files: files.len(),
+                lines: files.iter().map(|file| file.lines()).sum(),
+                authors: self.summarize(files.into_iter()),
+            })
+            .collect()
+    }
+
+    /// Sums the lines of some of the files per author, largest first.
+    fn summarize<'a>(
+        &self,
+        files: impl Iterator<Item = &'a FileAttribution>,
+    ) -> Vec<AuthorSummary> {
+        let mut authors: BTreeMap<&str, AuthorSummary> = BTreeMap::new();
+
+        for file in files {
+            for (email, &lines) in &file.authors {
+                let author = authors.entry(email).or_insert_with(|| AuthorSummary {
+                    name: self.names.get(email).cloned().unwrap_or_default(),
+                    email: email.clone(),
+                    ..AuthorSummary::default()
+                });
+                author.files += 1;
+                author.lines += lines;
+            }
+        }
+
+        let mut authors: Vec<AuthorSummary> = authors.into_values().collect();
+        authors.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.email.cmp(&b.email)));
+        authors
+    }
+}
+
+/// Attributes the lines of counted files to their authors.
+///
+/// Each counted file of `summary` is blamed at `revision`, or in the working
+/// tree without one; files of submodules are left out. Use the same revision
+/// the summary was counted at.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `revision` - The revision to blame files at, or `None` for the working tree.
+/// * `summary` - The counting results whose files are blamed.
+/// * `mailmap` - Whether to resolve authors through `.mailmap`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - A file cannot be blamed, such as one that is not tracked
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::blame::attribute;
+///
+/// let summary = gitlsf::count_repository(".").unwrap();
+/// let attribution = attribute(".", None, &summary, true).unwrap();
+/// for author in attribution.by_author() {
+///     println!("{} {} <{}>", author.lines, author.name, author.email);
+/// }
+/// ```
+pub fn attribute(
+    path: impl AsRef<Path>,
+    revision: Option<&str>,
+    summary: &CountSummary,
+    mailmap: bool,
+) -> Result<Attribution> {
+    let path = path.as_ref();
+
+    let mut files: Vec<&Path> = summary
+        .files
+        .iter()
+        .filter(|file| file.submodule.is_none())
+        .map(|file| file.path.as_path())
+        .collect();
+    files.sort_by(|a, b| a.as_os_str().cmp(b.as_os_str()));
+
+    let blames = files
+        .par_iter()
+        .map(|file| git::blame_file(path, revision, file))
+        .collect::<Result<Vec<_>>>()?;
+
+    let oids: Vec<&str> = blames
+        .iter()
+        .flat_map(HashMap::keys)
+        .map(String::as_str)
+        .filter(|oid| !git::is_uncommitted(oid))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let authors = git::commit_authors(path, &oids, mailmap)?;
+    let uncommitted = Author {
+        name: UNCOMMITTED_NAME.to_string(),
+        email: UNCOMMITTED_EMAIL.to_string(),
+    };
+
+    // Lines per email address and name, to pick the most used name of each address
+    let mut names: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    let mut attributed = Vec::with_capacity(files.len());
+
+    for (file, blame) in files.into_iter().zip(&blames) {
+        let mut file_authors = BTreeMap::new();
+
+        for (oid, &lines) in blame {
+            let author = if git::is_uncommitted(oid) {
+                &uncommitted
+            } else {
+                authors.get(oid).ok_or_else(|| {
+                    GitlsfError::git(format!("git log did not return the author of {oid}"))
+                })?
+            };
+
+            *file_authors.entry(author.email.clone()).or_insert(0) += lines;
+            *names.entry((&author.email, &author.name)).or_insert(0) += lines;
+        }
+
+        attributed.push(FileAttribution {
+            path: file.to_path_buf(),
+            authors: file_authors,
+        });
+    }
+
+    let mut best: BTreeMap<String, (usize, String)> = BTreeMap::new();
+    for ((email, name), lines) in names {
+        // Names are visited in order, so ties go to the first one
+        match best.get(email) {
+            Some((most, _)) if *most >= lines => {}
+            _ => {
+                best.insert(email.to_string(), (lines, name.to_string()));
+            }
+        }
+    }
+
+    Ok(Attribution {
+        files: attributed,
+        names: best
+            .into_iter()
+            .map(|(email, (_, name))| (email, name))
+            .collect(),
+        mailmap,
+    })
+}
+
+/// Totals of an attribution report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AttributionTotals {
+    /// Number of files blamed.
+    pub files: usize,
+    /// Total number of lines.
+    pub lines: usize,
+}
+
+/// A complete, self-describing attribution report.
+///
+/// See the [module documentation](self) for the JSON schema.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttributionReport {
+    /// Version of the report layout, see [`SCHEMA_VERSION`].
+    pub schema_version: u32,
+    /// The tool that produced the report.
+    pub tool: ToolInfo,
+    /// Path of the repository, quoted by [`quote_path`](crate::output::quote_path).
+    pub repository: String,
+    /// The revision blamed, or `None` for the working tree.
+    pub revision: Option<RevisionInfo>,
+    /// The filter configuration used to select files.
+    pub filter: FilterInfo,
+    /// Whether authors were resolved through `.mailmap`.
+    pub mailmap: bool,
+    /// Totals across all files.
+    pub totals: AttributionTotals,
+    /// Per-author totals, largest first.
+    pub authors: Vec<AuthorSummary>,
+    /// Per-directory totals, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directories: Option<Vec<DirectoryAttribution>>,
+}
+
+impl AttributionReport {
+    /// Creates a report of the working tree of a repository.
+    pub fn new(
+        repository: impl Into<String>,
+        attribution: &Attribution,
+        filter: &FileFilter,
+    ) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            tool: ToolInfo::default(),
+            repository: repository.into(),
+            revision: None,
+            filter: FilterInfo::from(filter),
+            mailmap: attribution.mailmap,
+            totals: AttributionTotals {
+                files: attribution.files.len(),
+                lines: attribution.total_lines(),
+            },
+            authors: attribution.by_author(),
+            directories: None,
+        }
+    }
+
+    /// Records the revision the files were blamed at.
+    pub fn with_revision(mut self, name: impl Into<String>, commit: impl Into<String>) -> Self {
+        self.revision = Some(RevisionInfo {
+            name: name.into(),
+            commit: commit.into(),
+        });
+        self
+    }
+
+    /// Adds the per-directory breakdown, see [`Attribution::by_directory`].
+    pub fn with_directories(mut self, attribution: &Attribution, depth: usize) -> Self {
+        self.directories = Some(attribution.by_directory(depth));
+        self
+    }
+
+    /// Writes the report as pretty-printed JSON, followed by a newline.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)
+    }
+}
+
+/// Writes per-author totals as comma-separated values.
+///
+/// With a `depth`, the totals are broken down per directory. See the
+/// [module documentation](self) for the columns.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use gitlsf::blame::{Attribution, FileAttribution, write_csv};
+///
+/// let attribution = Attribution {
+///     files: vec![FileAttribution {
+///         path: "src/main.rs".into(),
+///         authors: BTreeMap::from([("ann@x.org".to_string(), 3)]),
+///     }],
+///     names: BTreeMap::from([("ann@x.org".to_string(), "Ann, Jr.".to_string())]),
+///     mailmap: true,
+/// };
+///
+/// let mut csv = Vec::new();
+/// write_csv(&mut csv, &attribution, Some(1)).unwrap();
+/// assert_eq!(
+///     String::from_utf8(csv).unwrap(),
+///     "directory,email,name,files,lines\nsrc,ann@x.org,\"Ann, Jr.\",1,3\n"
+/// );
+/// ```
+pub fn write_csv<W: Write>(
+    writer: W,
+    attribution: &Attribution,
+    depth: Option<usize>,
+) -> io::Result<()> {
+    write_delimited(writer, attribution, depth, ',')
+}
+
+/// Writes per-author totals as tab-separated values.
+///
+/// With a `depth`, the totals are broken down per directory. See the
+/// [module documentation](self) for the columns.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+pub fn write_tsv<W: Write>(
+    writer: W,
+    attribution: &Attribution,
+    depth: Option<usize>,
+) -> io::Result<()> {
+    write_delimited(writer, attribution, depth, '\t')
+}
+
+/// Writes the table shared by the CSV and TSV formats.
+fn write_delimited<W: Write>(
+    mut writer: W,
+    attribution: &Attribution,
+    depth: Option<usize>,
+    delimiter: char,
+) -> io::Result<()> {
+    let author_fields = |author: &AuthorSummary| {
+        [
+            author.email.clone(),
+            author.name.clone(),
+            author.files.to_string(),
+            author.lines.to_string(),
+        ]
+    };
+
+    match depth {
+        Some(depth) => {
+            write_row(
+                &mut writer,
+                delimiter,
+                &["directory", "email", "name", "files", "lines"],
+            )?;
+            for directory in attribution.by_directory(depth) {
+                for author in &directory.authors {
+                    let [email, name, files, lines] = author_fields(author);
+                    write_row(
+                        &mut writer,
+                        delimiter,
+                        &[&table_path(&directory.path), &email, &name, &files, &lines],
+                    )?;
+                }
+            }
+        }
+        None => {
+            write_row(&mut writer, delimiter, &["email", "name", "files", "lines"])?;
+            for author in attribution.by_author() {
+                let [email, name, files, lines] = author_fields(&author);
+                write_row(&mut writer, delimiter, &[&email, &name, &files, &lines])?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::FileCount;
+    use crate::test_util::{commit_as, git};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn summary(files: &[&str]) -> CountSummary {
+        CountSummary::from_counts(files.iter().map(|path| FileCount::new(*path, 0)).collect())
+    }
+
+    fn attribution() -> Attribution {
+        let file = |path: &str, authors: &[(&str, usize)]| FileAttribution {
+            path: path.into(),
+            authors: authors
+                .iter()
+                .map(|(email, lines)| (email.to_string(), *lines))
+                .collect(),
+        };
+
+        Attribution {
+            files: vec![
+                file("main.rs", &[("bob@x.org", 4)]),
+                file("src/a.rs", &[("ann@x.org", 6), ("bob@x.org", 1)]),
+                file("src/b/c.rs", &[("ann@x.org", 2)]),
+            ],
+            names: BTreeMap::from([
+                ("ann@x.org".to_string(), "Ann".to_string()),
+                ("bob@x.org".to_string(), "Bob".to_string()),
+            ]),
+            mailmap: false,
+        }
+    }
+
+    #[test]
+    fn test_by_author() {
+        let authors = attribution().by_author();
+
+        assert_eq!(
+            authors,
+            [
+                AuthorSummary {
+                    name: "Ann".to_string(),
+                    email: "ann@x.org".to_string(),
+                    files: 2,
+                    lines: 8,
+                },
+                AuthorSummary {
+                    name: "Bob".to_string(),
+                    email: "bob@x.org".to_string(),
+                    files: 2,
+                    lines: 5,
+                },
+            ]
+        );
+        assert_eq!(attribution().total_lines(), 13);
+    }
+
+    #[test]
+    fn test_by_directory() {
+        let directories = attribution().by_directory(1);
+        let paths: Vec<&Path> = directories.iter().map(|d| d.path.as_path()).collect();
+
+        assert_eq!(paths, [Path::new("."), Path::new("src")]);
+        assert_eq!(directories[1].files, 2);
+        assert_eq!(directories[1].lines, 9);
+        assert_eq!(directories[1].authors[0].email, "ann@x.org");
+        assert_eq!(directories[1].authors[1].lines, 1);
+
+        let paths: Vec<PathBuf> = attribution()
+            .by_directory(0)
+            .into_iter()
+            .map(|d| d.path)
+            .collect();
+        assert_eq!(paths, [Path::new(".")]);
+    }
+
+    #[test]
+    fn test_attribute() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        git(path, &["init", "-q"]);
+
+        fs::create_dir(path.join("src")).unwrap();
+        fs::write(path.join("src/a.rs"), "one\ntwo\n").unwrap();
+        fs::write(path.join("b.rs"), "one\n").unwrap();
+        commit_as(path, "Ann", "ann@old.org", "first");
+
+        fs::write(path.join("src/a.rs"), "one\ntwo\nthree\n").unwrap();
+        commit_as(path, "Bob", "bob@x.org", "second");
+
+        fs::write(path.join("src/a.rs"), "one\ntwo\nthree\nfour\n").unwrap();
+        commit_as(path, "Ann Smith", "ann@x.org", "third");
+        fs::write(
+            path.join(".mailmap"),
+            "Ann Smith <ann@x.org> <ann@old.org>\n",
+        )
+        .unwrap();
+
+        let files = summary(&["src/a.rs", "b.rs"]);
+
+        let attribution = attribute(path, Some("HEAD"), &files, false).unwrap();
+        assert_eq!(attribution.files[0].path, Path::new("b.rs"));
+        assert_eq!(attribution.files[1].authors["ann@old.org"], 2);
+        assert_eq!(attribution.names["ann@old.org"], "Ann");
+        assert_eq!(attribution.by_author().len(), 3);
+        assert_eq!(attribution.total_lines(), 5);
+
+        let attribution = attribute(path, Some("HEAD"), &files, true).unwrap();
+        let authors = attribution.by_author();
+        assert_eq!(authors.len(), 2);
+        assert_eq!(authors[0].email, "ann@x.org");
+        assert_eq!(authors[0].name, "Ann Smith");
+        assert_eq!((authors[0].files, authors[0].lines), (2, 4));
+
+        fs::write(path.join("b.rs"), "one\nuncommitted\n").unwrap();
+        let attribution = attribute(path, None, &files, true).unwrap();
+        assert_eq!(attribution.files[0].authors[UNCOMMITTED_EMAIL], 1);
+        assert_eq!(attribution.names[UNCOMMITTED_EMAIL], UNCOMMITTED_NAME);
+
+        assert!(attribute(path, Some("HEAD"), &summary(&["missing.rs"]), true).is_err());
+    }
+
+    #[test]
+    fn test_write_tables() {
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &attribution(), None).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "email,name,files,lines\nann@x.org,Ann,2,8\nbob@x.org,Bob,2,5\n"
+        );
+
+        let mut tsv = Vec::new();
+        write_tsv(&mut tsv, &attribution(), Some(1)).unwrap();
+        assert_eq!(
+            String::from_utf8(tsv).unwrap(),
+            "directory\temail\tname\tfiles\tlines\n\
+             .\tbob@x.org\tBob\t1\t4\n\
+             src\tann@x.org\tAnn\t2\t8\n\
+             src\tbob@x.org\tBob\t1\t1\n"
+        );
+    }
+
+    #[test]
+    fn test_report_json() {
+        let attribution = attribution();
+        let report = AttributionReport::new(".", &attribution, &FileFilter::new())
+            .with_revision("HEAD", "abc")
+            .with_directories(&attribution, 1);
+
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(value["mailmap"], false);
+        assert_eq!(value["totals"]["lines"], 13);
+        assert_eq!(value["authors"][0]["name"], "Ann");
+        assert_eq!(value["directories"][1]["path"], "src");
+        assert_eq!(value["directories"][1]["authors"][0]["lines"], 8);
+
+        let report = AttributionReport::new(".", &attribution, &FileFilter::new());
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        assert!(!String::from_utf8(json).unwrap().contains("directories"));
+    }
+
+    #[test]
+    fn test_directory_with_special_characters() {
+        let mut attribution = attribution();
+        attribution.files[1].path = "new\nline/a.rs".into();
+
+        // Tables hold the path as it is, JSON quotes it as Git does
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &attribution, Some(1)).unwrap();
+        assert!(
+            String::from_utf8(csv)
+                .unwrap()
+                .contains("\n\"new\nline\",ann@x.org,Ann,1,6\n")
+        );
+
+        let report = AttributionReport::new(".", &attribution, &FileFilter::new())
+            .with_directories(&attribution, 1);
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["directories"][1]["path"], "\"new\\nline\"");
+    }
+}
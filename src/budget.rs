@@ -0,0 +1,339 @@
+//! Line count budget module.
+//!
+//! This module checks a [`CountSummary`] against agreed size limits, so that
+//! growth past them can fail a CI job or a pre-commit hook. Three kinds of
+//! limits are supported:
+//!
+//! - A maximum number of lines per file
+//! - A maximum total number of lines
+//! - Budgets for the files matching a glob pattern, such as a directory
+//!   (`src/parser/`) or a file type (`**/*.sql`), in the syntax of the
+//!   [filter](crate::filter) module
+//!
+//! A limit is violated when the line count exceeds it; reaching it exactly is
+//! allowed.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::counter::CountSummary;
+use crate::error::Result;
+use crate::filter::PatternList;
+use crate::output::quote_path;
+
+/// A budget for the files matching a glob pattern.
+#[derive(Debug, Clone)]
+struct PatternBudget {
+    /// The pattern as given.
+    pattern: String,
+    /// The compiled pattern.
+    matcher: PatternList,
+    /// The maximum number of lines of the matching files together.
+    limit: usize,
+}
+
+/// A set of line count limits.
+#[derive(Debug, Clone, Default)]
+pub struct Budgets {
+    /// Maximum number of lines of any single file.
+    max_file_lines: Option<usize>,
+    /// Maximum total number of lines.
+    max_total_lines: Option<usize>,
+    /// Budgets for glob patterns, in the order they were added.
+    patterns: Vec<PatternBudget>,
+}
+
+/// A limit that was exceeded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// A file is longer than the per-file limit.
+    File {
+        /// The path of the file.
+        path: PathBuf,
+        /// The number of lines of the file.
+        lines: usize,
+        /// The per-file limit.
+        limit: usize,
+    },
+    /// The files matching a pattern exceed its budget together.
+    Pattern {
+        /// The pattern as given.
+        pattern: String,
+        /// The number of lines of the matching files.
+        lines: usize,
+        /// The budget of the pattern.
+        limit: usize,
+    },
+    /// The total line count exceeds the total limit.
+    Total {
+        /// The total number of lines.
+        lines: usize,
+        /// The total limit.
+        limit: usize,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::File { path, lines, limit } => {
+                write!(
+                    f,
+                    "{}: {lines} lines exceed the per-file limit of {limit}",
+                    quote_path(path)
+                )
+            }
+            Violation::Pattern {
+                pattern,
+                lines,
+                limit,
+            } => write!(f, "{pattern}: {lines} lines exceed the budget of {limit}"),
+            Violation::Total { lines, limit } => {
+                write!(f, "total: {lines} lines exceed the limit of {limit}")
+            }
+        }
+    }
+}
+
+impl Budgets {
+    /// Creates an empty set of limits, which nothing violates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of lines of any single file.
+    pub fn max_file_lines(mut self, limit: usize) -> Self {
+        self.max_file_lines = Some(limit);
+        self
+    }
+
+    /// Sets the maximum total number of lines.
+    pub fn max_total_lines(mut self, limit: usize) -> Self {
+        self.max_total_lines = Some(limit);
+        self
+    }
+
+    /// Sets the budget for the files matching a glob pattern.
+    ///
+    /// A pattern that was added before has its budget replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GitlsfError::Pattern`](crate::GitlsfError::Pattern) if the
+    /// pattern is not a valid glob.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::budget::Budgets;
+    ///
+    /// let budgets = Budgets::new().budget("src/parser/", 5000)?;
+    /// assert_eq!(budgets.pattern_budgets().collect::<Vec<_>>(), [("src/parser/", 5000)]);
+    /// # Ok::<(), gitlsf::GitlsfError>(())
+    /// ```
+    pub fn budget(mut self, pattern: impl Into<String>, limit: usize) -> Result<Self> {
+        let pattern = pattern.into();
+
+        match self
+            .patterns
+            .iter_mut()
+            .find(|budget| budget.pattern == pattern)
+        {
+            Some(budget) => budget.limit = limit,
+            None => {
+                let mut matcher = PatternList::default();
+                matcher.push(pattern.clone())?;
+                self.patterns.push(PatternBudget {
+                    pattern,
+                    matcher,
+                    limit,
+                });
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Returns the glob patterns and their budgets, in the order they were added.
+    pub fn pattern_budgets(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.patterns
+            .iter()
+            .map(|budget| (budget.pattern.as_str(), budget.limit))
+    }
+
+    /// Returns `true` if no limit is set.
+    pub fn is_empty(&self) -> bool {
+        self.max_file_lines.is_none() && self.max_total_lines.is_none() && self.patterns.is_empty()
+    }
+
+    /// Checks a summary against the limits.
+    ///
+    /// # Returns
+    ///
+    /// The exceeded limits: files over the per-file limit sorted by path,
+    /// then pattern budgets in the order they were added, then the total.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::budget::{Budgets, Violation};
+    /// use gitlsf::{CountSummary, FileCount};
+    ///
+    /// let summary = CountSummary::from_counts(vec![
+    ///     FileCount::new("src/parser/expr.rs", 1200),
+    ///     FileCount::new("src/main.rs", 40),
+    /// ]);
+    /// let budgets = Budgets::new().max_file_lines(1000).budget("src/parser/", 2000)?;
+    ///
+    /// assert_eq!(
+    ///     budgets.check(&summary),
+    ///     [Violation::File { path: "src/parser/expr.rs".into(), lines: 1200, limit: 1000 }]
+    /// );
+    /// # Ok::<(), gitlsf::GitlsfError>(())
+    /// ```
+    pub fn check(&self, summary: &CountSummary) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        if let Some(limit) = self.max_file_lines {
+            let mut files: Vec<_> = summary
+                .files
+                .iter()
+                .filter(|file| file.lines > limit)
+                .collect();
+            files.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+
+            violations.extend(files.into_iter().map(|file| Violation::File {
+                path: file.path.clone(),
+                lines: file.lines,
+                limit,
+            }));
+        }
+
+        for budget in &self.patterns {
+            let lines: usize = summary
+                .files
+                .iter()
+                .filter(|file| budget.matcher.matched(&file.path) == Some(true))
+                .map(|file| file.lines)
+                .sum();
+
+            if lines > budget.limit {
+                violations.push(Violation::Pattern {
+                    pattern: budget.pattern.clone(),
+                    lines,
+                    limit: budget.limit,
+                });
+            }
+        }
+
+        if let Some(limit) = self.max_total_lines
+            && summary.total_lines > limit
+        {
+            violations.push(Violation::Total {
+                lines: summary.total_lines,
+                limit,
+            });
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::FileCount;
+
+    fn summary() -> CountSummary {
+        CountSummary::from_counts(vec![
+            FileCount::new("src/parser/lexer.rs", 600),
+            FileCount::new("src/parser/expr.rs", 900),
+            FileCount::new("src/main.rs", 100),
+            FileCount::new("schema.sql", 400),
+        ])
+    }
+
+    #[test]
+    fn test_empty_budgets() {
+        let budgets = Budgets::new();
+
+        assert!(budgets.is_empty());
+        assert!(budgets.check(&summary()).is_empty());
+    }
+
+    #[test]
+    fn test_limits_are_inclusive() {
+        let budgets = Budgets::new()
+            .max_file_lines(900)
+            .max_total_lines(2000)
+            .budget("src/parser/", 1500)
+            .unwrap();
+
+        assert!(budgets.check(&summary()).is_empty());
+    }
+
+    #[test]
+    fn test_violations() {
+        let budgets = Budgets::new()
+            .max_file_lines(500)
+            .max_total_lines(1999)
+            .budget("src/parser/", 1000)
+            .unwrap()
+            .budget("*.sql", 1000)
+            .unwrap();
+
+        assert_eq!(
+            budgets.check(&summary()),
+            [
+                Violation::File {
+                    path: "src/parser/expr.rs".into(),
+                    lines: 900,
+                    limit: 500
+                },
+                Violation::File {
+                    path: "src/parser/lexer.rs".into(),
+                    lines: 600,
+                    limit: 500
+                },
+                Violation::Pattern {
+                    pattern: "src/parser/".into(),
+                    lines: 1500,
+                    limit: 1000
+                },
+                Violation::Total {
+                    lines: 2000,
+                    limit: 1999
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_budget_replaces_same_pattern() {
+        let budgets = Budgets::new()
+            .budget("src/", 10)
+            .unwrap()
+            .budget("src/", 20)
+            .unwrap();
+
+        assert_eq!(
+            budgets.pattern_budgets().collect::<Vec<_>>(),
+            [("src/", 20)]
+        );
+        assert!(Budgets::new().budget("src/{a", 1).is_err());
+    }
+
+    #[test]
+    fn test_violation_display() {
+        let violation = Violation::Pattern {
+            pattern: "src/parser/".into(),
+            lines: 1500,
+            limit: 1000,
+        };
+
+        assert_eq!(
+            violation.to_string(),
+            "src/parser/: 1500 lines exceed the budget of 1000"
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! Line count cache module.
+//!
+//! This module keeps the line counts of blobs between runs, keyed by their
+//! Git object ID, so that files which did not change since the last run do
+//! not need to be read again. The cache is stored in the Git directory as
+//! `gitlsf-cache`, shared by all worktrees of a repository.
+//!
+//! Since the detected language depends on the file name as well as the
+//! content, an entry is keyed by the object ID together with the file name,
+//! or with the language assigned by an override instead of the file name.
+//!
+//! The file starts with a line naming the format version and the gitlsf
+//! version that wrote it; a file written by another version, or one that
+//! cannot be parsed, is discarded rather than reported as an error, so the
+//! counts are computed again after an upgrade.
+//! It is written to a temporary file that is renamed over the cache, so runs
+//! in parallel never see a partially written cache. When two runs save at
+//! the same time, the last one wins. After a run that counted every tracked
+//! file, only the entries looked up or added since the cache was opened are
+//! saved, which keeps the file from growing with blobs that are no longer in
+//! the repository; runs limited to some files keep the other entries too.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::classify::LineStats;
+use crate::error::{GitlsfError, Result};
+use crate::git;
+use crate::language::Language;
+
+/// Name of the cache file in the Git directory.
+pub const CACHE_FILE_NAME: &str = "gitlsf-cache";
+
+/// First line of a cache file of the current format.
+///
+/// It names the gitlsf version as well, since another version may detect
+/// languages or classify lines differently.
+const CACHE_HEADER: &str = concat!("gitlsf-cache 1 ", env!("CARGO_PKG_VERSION"));
+
+/// The cached line counts of a blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachedCount {
+    /// The blob is text, with the given language and line counts.
+    Text {
+        /// The detected or assigned language.
+        language: Option<Language>,
+        /// The line counts.
+        stats: LineStats,
+    },
+    /// The blob content is binary.
+    Binary,
+}
+
+/// The key of a cache entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    /// The object ID of the blob.
+    oid: String,
+    /// The language assigned by an override, if any.
+    language: Option<Language>,
+    /// The file name, or an empty string if a language was assigned.
+    name: String,
+}
+
+impl CacheKey {
+    /// Creates the key of a blob at `path`.
+    ///
+    /// Returns `None` if the file name is needed but is not valid UTF-8;
+    /// such files are not cached.
+    fn new(oid: &str, path: &Path, language: Option<Language>) -> Option<Self> {
+        let name = match language {
+            Some(_) => String::new(),
+            None => path.file_name()?.to_str()?.to_string(),
+        };
+
+        Some(CacheKey {
+            oid: oid.to_string(),
+            language,
+            name,
+        })
+    }
+}
+
+/// Line counts of blobs, persisted between runs.
+#[derive(Debug, Default)]
+pub struct CountCache {
+    /// The cache file.
+    path: PathBuf,
+    /// Entries read from the cache file.
+    entries: HashMap<CacheKey, CachedCount>,
+    /// Entries looked up or added since the cache was opened.
+    used: HashMap<CacheKey, CachedCount>,
+}
+
+impl CountCache {
+    /// Opens the cache of the repository containing `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is not within a Git repository. A missing
+    /// or unreadable cache file results in an empty cache.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::cache::CountCache;
+    ///
+    /// let cache = CountCache::open(".").unwrap();
+    /// println!("{} cached blobs", cache.len());
+    /// ```
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let git_dir = git::common_dir(path)?;
+        Ok(Self::load(git_dir.join(CACHE_FILE_NAME)))
+    }
+
+    /// Loads a cache from a file, or creates an empty cache if the file does
+    /// not exist or is not a valid cache.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| parse(&content))
+            .unwrap_or_default();
+
+        CountCache {
+            path,
+            entries,
+            used: HashMap::new(),
+        }
+    }
+
+    /// Returns the path of the cache file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of entries read from the cache file.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the cache file held no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Looks up the counts of the blob `oid` at `path`.
+    ///
+    /// `language` is the language assigned by an override, if any; the same
+    /// blob may be counted differently with and without one.
+    pub fn get(
+        &mut self,
+        oid: &str,
+        path: impl AsRef<Path>,
+        language: Option<Language>,
+    ) -> Option<CachedCount> {
+        let key = CacheKey::new(oid, path.as_ref(), language)?;
+        let count = *self.entries.get(&key)?;
+        self.used.insert(key, count);
+        Some(count)
+    }
+
+    /// Adds the counts of the blob `oid` at `path`.
+    ///
+    /// Nothing is added for a file name that is not valid UTF-8.
+    pub fn insert(
+        &mut self,
+        oid: &str,
+        path: impl AsRef<Path>,
+        language: Option<Language>,
+        count: CachedCount,
+    ) {
+        let Some(key) = CacheKey::new(oid, path.as_ref(), language) else {
+            return;
+        };
+        self.entries.insert(key.clone(), count);
+        self.used.insert(key, count);
+    }
+
+    /// Writes the cache.
+    ///
+    /// With `prune`, only the entries looked up or added since the cache was
+    /// opened are written, dropping those of blobs that were not needed; a
+    /// run that counted every tracked file prunes the cache this way. Without
+    /// it, the entries read from the cache file are written as well.
+    ///
+    /// The cache is written to a temporary file next to it, which then
+    /// replaces the cache file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GitlsfError::Io`] if the file cannot be written.
+    pub fn save(&self, prune: bool) -> Result<()> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let entries = if prune { &self.used } else { &self.entries };
+        let mut content = String::with_capacity(entries.len() * 80);
+        content.push_str(CACHE_HEADER);
+        content.push('\n');
+        for (key, count) in entries {
+            // Such names cannot be written; they are counted again next time
+            if key.name.contains(['\t', '\n']) {
+                continue;
+            }
+            content.push_str(&format_entry(key, count));
+            content.push('\n');
+        }
+
+        let temporary = self.path.with_file_name(format!(
+            "{CACHE_FILE_NAME}.{}-{}.tmp",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = fs::File::create(&temporary)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .and_then(|()| fs::rename(&temporary, &self.path));
+
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temporary);
+            return Err(GitlsfError::io(&self.path, e));
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats an entry as tab-separated fields:
+/// `<oid> <language> <name> <result>`, where the result is `binary` or
+/// `<detected language> <lines> <code> <comment> <blank>`, and `-` stands
+/// for no language.
+fn format_entry(key: &CacheKey, count: &CachedCount) -> String {
+    let language = |language: Option<Language>| language.map_or("-", Language::name);
+    let prefix = format!("{}\t{}\t{}", key.oid, language(key.language), key.name);
+
+    match count {
+        CachedCount::Binary => format!("{prefix}\tbinary"),
+        CachedCount::Text {
+            language: detected,
+            stats,
+        } => format!(
+            "{prefix}\t{}\t{}\t{}\t{}\t{}",
+            language(*detected),
+            stats.lines,
+            stats.code,
+            stats.comment,
+            stats.blank
+        ),
+    }
+}
+
+/// Parses the contents of a cache file, or returns `None` if it is not a
+/// valid cache of the current format.
+fn parse(content: &str) -> Option<HashMap<CacheKey, CachedCount>> {
+    let mut lines = content.lines();
+    if lines.next()? != CACHE_HEADER {
+        return None;
+    }
+
+    let language = |name: &str| match name {
+        "-" => Some(None),
+        name => Language::from_name(name).map(Some),
+    };
+
+    let mut entries = HashMap::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let (key, count) = match fields[..] {
+            [oid, assigned, name, "binary"] => (
+                CacheKey {
+                    oid: oid.to_string(),
+                    language: language(assigned)?,
+                    name: name.to_string(),
+                },
+                CachedCount::Binary,
+            ),
+            [oid, assigned, name, detected, lines, code, comment, blank] => (
+                CacheKey {
+                    oid: oid.to_string(),
+                    language: language(assigned)?,
+                    name: name.to_string(),
+                },
+                CachedCount::Text {
+                    language: language(detected)?,
+                    stats: LineStats {
+                        lines: lines.parse().ok()?,
+                        code: code.parse().ok()?,
+                        comment: comment.parse().ok()?,
+                        blank: blank.parse().ok()?,
+                    },
+                },
+            ),
+            _ => return None,
+        };
+        entries.insert(key, count);
+    }
+
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const OID: &str = "3b18e512dba79e4c8300dd08aeb37f8e728b8dad";
+
+    fn stats(lines: usize) -> LineStats {
+        LineStats {
+            lines,
+            code: lines,
+            comment: 0,
+            blank: 0,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(CACHE_FILE_NAME);
+
+        let mut cache = CountCache::load(&path);
+        assert!(cache.is_empty());
+
+        let rust = CachedCount::Text {
+            language: Some(Language::Rust),
+            stats: stats(3),
+        };
+        let plain = CachedCount::Text {
+            language: None,
+            stats: stats(1),
+        };
+        cache.insert(OID, "src/main.rs", None, rust);
+        cache.insert(OID, "notes", None, plain);
+        cache.insert(OID, "data.bin", Some(Language::C), CachedCount::Binary);
+        cache.save(true).unwrap();
+
+        let mut cache = CountCache::load(&path);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(OID, "lib/main.rs", None), Some(rust));
+        assert_eq!(cache.get(OID, "docs/notes", None), Some(plain));
+        assert_eq!(
+            cache.get(OID, "a.h", Some(Language::C)),
+            Some(CachedCount::Binary)
+        );
+        assert_eq!(cache.get(OID, "a.h", None), None);
+        assert_eq!(cache.get(OID, "main.py", None), None);
+    }
+
+    #[test]
+    fn test_save_keeps_used_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(CACHE_FILE_NAME);
+
+        let mut cache = CountCache::load(&path);
+        cache.insert(OID, "a.rs", None, CachedCount::Binary);
+        cache.insert(OID, "b.rs", None, CachedCount::Binary);
+        cache.save(true).unwrap();
+
+        // Unless pruned, entries that were not looked up are kept
+        let mut cache = CountCache::load(&path);
+        assert!(cache.get(OID, "a.rs", None).is_some());
+        cache.insert(OID, "c.rs", None, CachedCount::Binary);
+        cache.save(false).unwrap();
+        assert_eq!(CountCache::load(&path).len(), 3);
+
+        let mut cache = CountCache::load(&path);
+        assert!(cache.get(OID, "a.rs", None).is_some());
+        cache.save(true).unwrap();
+
+        let mut cache = CountCache::load(&path);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(OID, "b.rs", None).is_none());
+
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_invalid_cache_is_discarded() {
+        assert!(parse("gitlsf-cache 0\n").is_none());
+        assert!(parse("gitlsf-cache 1\n").is_none());
+        assert!(parse("gitlsf-cache 1 0.0.0\n").is_none());
+        assert!(parse("").is_none());
+        assert!(parse(&format!("{CACHE_HEADER}\n{OID}\t-\ta.rs\n")).is_none());
+        assert!(
+            parse(&format!(
+                "{CACHE_HEADER}\n{OID}\t-\ta.rs\tRust\tx\t0\t0\t0\n"
+            ))
+            .is_none()
+        );
+        assert_eq!(parse(&format!("{CACHE_HEADER}\n")), Some(HashMap::new()));
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(CACHE_FILE_NAME);
+        fs::write(&path, "not a cache").unwrap();
+        assert!(CountCache::load(&path).is_empty());
+    }
+
+    #[test]
+    fn test_every_language_round_trips() {
+        for language in Language::all() {
+            let key = CacheKey::new(OID, Path::new("file"), Some(language)).unwrap();
+            let count = CachedCount::Text {
+                language: Some(language),
+                stats: stats(1),
+            };
+            let content = format!("{CACHE_HEADER}\n{}\n", format_entry(&key, &count));
+
+            assert_eq!(parse(&content).unwrap()[&key], count, "{}", language.name());
+        }
+    }
+}
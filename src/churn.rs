@@ -0,0 +1,907 @@
+//! Commit churn module.
+//!
+//! This module sums the lines added and deleted by the commits of a revision
+//! range or date window, as listed by `git log --numstat`, and aggregates
+//! them per file, directory, author and language. Line counts show how large
+//! the code is; churn shows how much of it changed.
+//!
+//! The same [`FileFilter`] as for line counts selects the files: excluded
+//! extensions, filenames and glob patterns, and files marked generated,
+//! vendored or documentation in `.gitattributes`, are left out. Attributes
+//! are read from the working tree, so the current markings apply to the
+//! whole history. Binary files and submodules are left out as well.
+//!
+//! Changes are recorded under the path of the file after each commit, with
+//! renames detected, so a file that was moved appears under both paths.
+//! Languages are detected from paths alone, by extension and filename, with
+//! the filter's language overrides and `linguist-language` attributes taking
+//! precedence.
+//!
+//! ## JSON schema
+//!
+//! [`ChurnReport::write_json`] produces a single JSON object with the
+//! `schema_version`, `tool`, `repository` and `filter` fields of the
+//! [counting report](crate::output#json-schema), followed by:
+//!
+//! | Field | Type | Description |
+//! |-------|------|-------------|
+//! | `range` | string | The revision range, such as `"v1.0..HEAD"` |
+//! | `since` | string or null | The lower bound on the commit date, as given |
+//! | `until` | string or null | The upper bound on the commit date, as given |
+//! | `mailmap` | boolean | Whether authors were resolved through `.mailmap` |
+//! | `totals` | object | `files`, `commits`, `added` and `deleted` totals |
+//! | `authors` | array of objects | Per-author `name`, `email`, `files`, `commits`, `added` and `deleted`, most churn first |
+//! | `languages` | array of objects | Per-language `language`, `files`, `commits`, `added` and `deleted`, most churn first |
+//! | `files` | array of objects | Per-file `path`, `language`, `files`, `commits`, `added` and `deleted`, sorted by path |
+//! | `directories` | array of objects, optional | Per-directory `path`, `files`, `commits`, `added` and `deleted`, sorted by path; only present when requested |
+//!
+//! `files` counts distinct files and `commits` distinct commits with changes
+//! to counted files; merge commits have no changes of their own.
+//!
+//! ## CSV and TSV
+//!
+//! [`write_csv`] and [`write_tsv`] emit one row per group of a [`Grouping`],
+//! in the order of the JSON arrays, under one of these headers:
+//!
+//! - `path,language,commits,added,deleted` for files
+//! - `directory,files,commits,added,deleted` for directories
+//! - `email,name,files,commits,added,deleted` for authors
+//! - `language,files,commits,added,deleted` for languages
+//!
+//! Paths are written with [`table_path`] and fields quoted as in the
+//! [counting tables](crate::output#csv-and-tsv).
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::diff::truncate_directory;
+use crate::error::Result;
+use crate::filter::FileFilter;
+use crate::git::{self, Author};
+use crate::language::Language;
+use crate::output::{FilterInfo, SCHEMA_VERSION, ToolInfo, serialize_path, table_path, write_row};
+
+/// A commit with changes to counted files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChurnCommit {
+    /// The commit object ID.
+    pub oid: String,
+    /// The author date as seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// The author of the commit.
+    pub author: Author,
+}
+
+/// The lines added and deleted in one file by one commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// The index of the commit in [`Churn::commits`].
+    pub commit: usize,
+    /// The path of the file after the commit, relative to the repository root.
+    pub path: PathBuf,
+    /// The detected language, if known.
+    pub language: Option<Language>,
+    /// Number of lines added.
+    pub added: usize,
+    /// Number of lines deleted.
+    pub deleted: usize,
+}
+
+/// Totals of the lines added and deleted by a set of changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ChurnStats {
+    /// Number of distinct files changed.
+    pub files: usize,
+    /// Number of distinct commits.
+    pub commits: usize,
+    /// Number of lines added.
+    pub added: usize,
+    /// Number of lines deleted.
+    pub deleted: usize,
+}
+
+impl ChurnStats {
+    /// Returns the number of lines added and deleted together.
+    pub fn churn(&self) -> usize {
+        self.added + self.deleted
+    }
+}
+
+/// The churn of one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileChurn {
+    /// The path of the file relative to the repository root.
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+    /// The detected language, if known.
+    pub language: Option<Language>,
+    /// Totals of the changes to the file.
+    #[serde(flatten)]
+    pub stats: ChurnStats,
+}
+
+/// The churn of the files in one directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirectoryChurn {
+    /// The directory path relative to the repository root, or `.` for the
+    /// root.
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+    /// Totals of the changes to files in the directory and its subdirectories.
+    #[serde(flatten)]
+    pub stats: ChurnStats,
+}
+
+/// The churn of the commits of one author.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuthorChurn {
+    /// The author name.
+    pub name: String,
+    /// The author email address.
+    pub email: String,
+    /// Totals of the changes by the author.
+    #[serde(flatten)]
+    pub stats: ChurnStats,
+}
+
+/// The churn of the files in one language.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LanguageChurn {
+    /// The language, or `None` for files in unknown languages.
+    pub language: Option<Language>,
+    /// Totals of the changes to files in the language.
+    #[serde(flatten)]
+    pub stats: ChurnStats,
+}
+
+impl LanguageChurn {
+    /// Returns the display name of the language, `"Other"` for unknown languages.
+    pub fn name(&self) -> &'static str {
+        self.language.map_or("Other", Language::name)
+    }
+}
+
+/// How churn is grouped in tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// One row per file, see [`Churn::by_file`].
+    File,
+    /// One row per directory, down to the given depth, see [`Churn::by_directory`].
+    Directory(usize),
+    /// One row per author, see [`Churn::by_author`].
+    Author,
+    /// One row per language, see [`Churn::by_language`].
+    Language,
+}
+
+/// The lines added and deleted by the commits of a range.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Churn {
+    /// The commits with changes to counted files, newest first.
+    pub commits: Vec<ChurnCommit>,
+    /// The changes to counted files, in the order of their commits.
+    pub changes: Vec<FileChange>,
+    /// Whether author names and email addresses were resolved through `.mailmap`.
+    pub mailmap: bool,
+}
+
+impl Churn {
+    /// Returns the totals of all changes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::churn::{Churn, FileChange};
+    ///
+    /// let change = |commit: usize, path: &str, added: usize, deleted: usize| FileChange {
+    ///     commit,
+    ///     path: path.into(),
+    ///     language: None,
+    ///     added,
+    ///     deleted,
+    /// };
+    /// let churn = Churn {
+    ///     changes: vec![
+    ///         change(0, "a.rs", 5, 1),
+    ///         change(1, "a.rs", 2, 2),
+    ///         change(1, "b.rs", 3, 0),
+    ///     ],
+    ///     ..Churn::default()
+    /// };
+    ///
+    /// let totals = churn.totals();
+    /// assert_eq!((totals.files, totals.commits), (2, 2));
+    /// assert_eq!((totals.added, totals.deleted), (10, 3));
+    /// ```
+    pub fn totals(&self) -> ChurnStats {
+        self.aggregate(|_| ()).remove(&()).unwrap_or_default()
+    }
+
+    /// Aggregates the changes per file, sorted by path.
+    pub fn by_file(&self) -> Vec<FileChurn> {
+        let languages: BTreeMap<&Path, Option<Language>> = self
+            .changes
+            .iter()
+            .map(|change| (change.path.as_path(), change.language))
+            .collect();
+
+        self.aggregate(|change| change.path.as_os_str())
+            .into_iter()
+            .map(|(path, stats)| {
+                let path = Path::new(path);
+                FileChurn {
+                    path: path.to_path_buf(),
+                    language: languages[path],
+                    stats,
+                }
+            })
+            .collect()
+    }
+
+    /// Aggregates the changes per directory, sorted by path.
+    ///
+    /// Directories are keyed by their first `depth` path components, so each
+    /// also covers the files of its subdirectories beyond that depth. Files
+    /// at the repository root are grouped under `.`.
+    pub fn by_directory(&self, depth: usize) -> Vec<DirectoryChurn> {
+        self.aggregate(|change| {
+            let parent = change
+                .path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            truncate_directory(parent, depth)
+        })
+        .into_iter()
+        .map(|(path, stats)| DirectoryChurn { path, stats })
+        .collect()
+    }
+
+    /// Aggregates the changes per author email address, most churn first.
+    ///
+    /// An address used with several names is given the name of its most
+    /// recent commit.
+    pub fn by_author(&self) -> Vec<AuthorChurn> {
+        let mut names: BTreeMap<&str, &str> = BTreeMap::new();
+        for commit in self.commits.iter().rev() {
+            names.insert(&commit.author.email, &commit.author.name);
+        }
+
+        let mut authors: Vec<AuthorChurn> = self
+            .aggregate(|change| self.commits[change.commit].author.email.as_str())
+            .into_iter()
+            .map(|(email, stats)| AuthorChurn {
+                name: names[email].to_string(),
+                email: email.to_string(),
+                stats,
+            })
+            .collect();
+
+        authors.sort_by(|a, b| {
+            b.stats
+                .churn()
+                .cmp(&a.stats.churn())
+                .then_with(|| a.email.cmp(&b.email))
+        });
+        authors
+    }
+
+    /// Aggregates the changes per language, most churn first.
+    pub fn by_language(&self) -> Vec<LanguageChurn> {
+        let mut languages: Vec<LanguageChurn> = self
+            .aggregate(|change| change.language)
+            .into_iter()
+            .map(|(language, stats)| LanguageChurn { language, stats })
+            .collect();
+
+        languages.sort_by(|a, b| {
+            b.stats
+                .churn()
+                .cmp(&a.stats.churn())
+                .then_with(|| a.name().cmp(b.name()))
+        });
+        languages
+    }
+
+    /// Sums the changes per key, counting distinct files and commits.
+    fn aggregate<'a, K: Ord>(
+        &'a self,
+        key: impl Fn(&'a FileChange) -> K,
+    ) -> BTreeMap<K, ChurnStats> {
+        let mut groups: BTreeMap<K, (ChurnStats, HashSet<&Path>, HashSet<usize>)> = BTreeMap::new();
+
+        for change in &self.changes {
+            let (stats, files, commits) = groups.entry(key(change)).or_default();
+            stats.added += change.added;
+            stats.deleted += change.deleted;
+            files.insert(&change.path);
+            commits.insert(change.commit);
+        }
+
+        groups
+            .into_iter()
+            .map(|(key, (stats, files, commits))| {
+                (
+                    key,
+                    ChurnStats {
+                        files: files.len(),
+                        commits: commits.len(),
+                        ..stats
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+/// Sums the lines added and deleted by the commits of a range.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `range` - The commits to walk, as accepted by `git log`: a revision such
+///   as `HEAD` for its whole history, or a range such as `v1.0..main`.
+/// * `since` - An optional lower bound on the commit date, in any format
+///   accepted by `git log --since`.
+/// * `until` - An optional upper bound on the commit date, in any format
+///   accepted by `git log --until`.
+/// * `filter` - The file filter selecting the files whose changes are summed.
+/// * `mailmap` - Whether to resolve authors through `.mailmap`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The range names a revision that does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::churn::churn;
+/// use gitlsf::filter::FileFilter;
+///
+/// let churn = churn(".", "HEAD", Some("3 months ago"), None, &FileFilter::new(), true).unwrap();
+/// for author in churn.by_author() {
+///     println!("+{} -{} {}", author.stats.added, author.stats.deleted, author.name);
+/// }
+/// ```
+pub fn churn(
+    path: impl AsRef<Path>,
+    range: &str,
+    since: Option<&str>,
+    until: Option<&str>,
+    filter: &FileFilter,
+    mailmap: bool,
+) -> Result<Churn> {
+    let path = path.as_ref();
+    let log = git::log_numstat(path, range, since, until, filter.pathspecs(), mailmap)?;
+
+    // Filter each path once, however many commits changed it
+    let mut paths: Vec<&Path> = log
+        .iter()
+        .flat_map(|commit| &commit.files)
+        .filter(|file| file.added.is_some() && file.deleted.is_some())
+        .map(|file| file.path.as_path())
+        .filter(|path| filter.should_count(path))
+        .collect();
+    paths.sort_by(|a, b| a.as_os_str().cmp(b.as_os_str()));
+    paths.dedup();
+
+    let selection = crate::apply_attributes(path, None, filter, paths, |path| path)?;
+    let languages: BTreeMap<&Path, Option<Language>> = selection
+        .files
+        .iter()
+        .map(|&path| {
+            let language = filter
+                .language_override(path)
+                .or_else(|| selection.languages.get(path).copied())
+                .or_else(|| Language::from_path(path));
+            (path, language)
+        })
+        .collect();
+
+    let mut churn = Churn {
+        mailmap,
+        ..Churn::default()
+    };
+
+    for commit in &log {
+        let changes: Vec<FileChange> = commit
+            .files
+            .iter()
+            .filter_map(|file| {
+                let language = languages.get(file.path.as_path())?;
+                Some(FileChange {
+                    commit: churn.commits.len(),
+                    path: file.path.clone(),
+                    language: *language,
+                    added: file.added?,
+                    deleted: file.deleted?,
+                })
+            })
+            .collect();
+
+        if !changes.is_empty() {
+            churn.changes.extend(changes);
+            churn.commits.push(ChurnCommit {
+                oid: commit.oid.clone(),
+                timestamp: commit.timestamp,
+                author: commit.author.clone(),
+            });
+        }
+    }
+
+    Ok(churn)
+}
+
+/// A complete, self-describing churn report.
+///
+/// See the [module documentation](self) for the JSON schema.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChurnReport {
+    /// Version of the report layout, see [`SCHEMA_VERSION`].
+    pub schema_version: u32,
+    /// The tool that produced the report.
+    pub tool: ToolInfo,
+    /// Path of the repository, quoted by [`quote_path`](crate::output::quote_path).
+    pub repository: String,
+    /// The filter configuration used to select files.
+    pub filter: FilterInfo,
+    /// The revision range walked.
+    pub range: String,
+    /// The lower bound on the commit date, as given.
+    pub since: Option<String>,
+    /// The upper bound on the commit date, as given.
+    pub until: Option<String>,
+    /// Whether authors were resolved through `.mailmap`.
+    pub mailmap: bool,
+    /// Totals across all changes.
+    pub totals: ChurnStats,
+    /// Per-author totals, most churn first.
+    pub authors: Vec<AuthorChurn>,
+    /// Per-language totals, most churn first.
+    pub languages: Vec<LanguageChurn>,
+    /// Per-file totals, sorted by path.
+    pub files: Vec<FileChurn>,
+    /// Per-directory totals, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directories: Option<Vec<DirectoryChurn>>,
+}
+
+impl ChurnReport {
+    /// Creates a report of the churn of a range.
+    pub fn new(
+        repository: impl Into<String>,
+        range: impl Into<String>,
+        churn: &Churn,
+        filter: &FileFilter,
+    ) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            tool: ToolInfo::default(),
+            repository: repository.into(),
+            filter: FilterInfo::from(filter),
+            range: range.into(),
+            since: None,
+            until: None,
+            mailmap: churn.mailmap,
+            totals: churn.totals(),
+            authors: churn.by_author(),
+            languages: churn.by_language(),
+            files: churn.by_file(),
+            directories: None,
+        }
+    }
+
+    /// Records the date window of the range.
+    pub fn with_dates(mut self, since: Option<&str>, until: Option<&str>) -> Self {
+        self.since = since.map(str::to_string);
+        self.until = until.map(str::to_string);
+        self
+    }
+
+    /// Adds the per-directory breakdown, see [`Churn::by_directory`].
+    pub fn with_directories(mut self, churn: &Churn, depth: usize) -> Self {
+        self.directories = Some(churn.by_directory(depth));
+        self
+    }
+
+    /// Writes the report as pretty-printed JSON, followed by a newline.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)
+    }
+}
+
+/// Writes churn as comma-separated values, one row per group.
+///
+/// See the [module documentation](self) for the columns.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::churn::{Churn, FileChange, Grouping, write_csv};
+///
+/// let churn = Churn {
+///     changes: vec![FileChange {
+///         commit: 0,
+///         path: "src/main.rs".into(),
+///         language: None,
+///         added: 10,
+///         deleted: 4,
+///     }],
+///     ..Churn::default()
+/// };
+///
+/// let mut csv = Vec::new();
+/// write_csv(&mut csv, &churn, Grouping::Directory(1)).unwrap();
+/// assert_eq!(
+///     String::from_utf8(csv).unwrap(),
+///     "directory,files,commits,added,deleted\nsrc,1,1,10,4\n"
+/// );
+/// ```
+pub fn write_csv<W: Write>(writer: W, churn: &Churn, grouping: Grouping) -> io::Result<()> {
+    write_delimited(writer, churn, grouping, ',')
+}
+
+/// Writes churn as tab-separated values, one row per group.
+///
+/// See the [module documentation](self) for the columns.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+pub fn write_tsv<W: Write>(writer: W, churn: &Churn, grouping: Grouping) -> io::Result<()> {
+    write_delimited(writer, churn, grouping, '\t')
+}
+
+/// Writes the table shared by the CSV and TSV formats.
+fn write_delimited<W: Write>(
+    mut writer: W,
+    churn: &Churn,
+    grouping: Grouping,
+    delimiter: char,
+) -> io::Result<()> {
+    let numbers = |stats: &ChurnStats| {
+        [
+            stats.files.to_string(),
+            stats.commits.to_string(),
+            stats.added.to_string(),
+            stats.deleted.to_string(),
+        ]
+    };
+
+    match grouping {
+        Grouping::File => {
+            write_row(
+                &mut writer,
+                delimiter,
+                &["path", "language", "commits", "added", "deleted"],
+            )?;
+            for file in churn.by_file() {
+                let [_, commits, added, deleted] = numbers(&file.stats);
+                write_row(
+                    &mut writer,
+                    delimiter,
+                    &[
+                        &table_path(&file.path),
+                        file.language.map_or("", |language| language.name()),
+                        &commits,
+                        &added,
+                        &deleted,
+                    ],
+                )?;
+            }
+        }
+        Grouping::Directory(depth) => {
+            write_row(
+                &mut writer,
+                delimiter,
+                &["directory", "files", "commits", "added", "deleted"],
+            )?;
+            for directory in churn.by_directory(depth) {
+                let [files, commits, added, deleted] = numbers(&directory.stats);
+                write_row(
+                    &mut writer,
+                    delimiter,
+                    &[
+                        &table_path(&directory.path),
+                        &files,
+                        &commits,
+                        &added,
+                        &deleted,
+                    ],
+                )?;
+            }
+        }
+        Grouping::Author => {
+            write_row(
+                &mut writer,
+                delimiter,
+                &["email", "name", "files", "commits", "added", "deleted"],
+            )?;
+            for author in churn.by_author() {
+                let [files, commits, added, deleted] = numbers(&author.stats);
+                write_row(
+                    &mut writer,
+                    delimiter,
+                    &[
+                        &author.email,
+                        &author.name,
+                        &files,
+                        &commits,
+                        &added,
+                        &deleted,
+                    ],
+                )?;
+            }
+        }
+        Grouping::Language => {
+            write_row(
+                &mut writer,
+                delimiter,
+                &["language", "files", "commits", "added", "deleted"],
+            )?;
+            for language in churn.by_language() {
+                let [files, commits, added, deleted] = numbers(&language.stats);
+                write_row(
+                    &mut writer,
+                    delimiter,
+                    &[language.name(), &files, &commits, &added, &deleted],
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{commit_as, git};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn author(name: &str, email: &str) -> Author {
+        Author {
+            name: name.to_string(),
+            email: email.to_string(),
+        }
+    }
+
+    fn sample() -> Churn {
+        let commit = |oid: &str, author: Author| ChurnCommit {
+            oid: oid.to_string(),
+            timestamp: 0,
+            author,
+        };
+        let change = |commit: usize, path: &str, added: usize, deleted: usize| FileChange {
+            commit,
+            path: path.into(),
+            language: Language::from_path(path),
+            added,
+            deleted,
+        };
+
+        Churn {
+            commits: vec![
+                commit("c2", author("Ann Smith", "ann@x.org")),
+                commit("c1", author("Bob", "bob@x.org")),
+                commit("c0", author("Ann", "ann@x.org")),
+            ],
+            changes: vec![
+                change(0, "src/a.rs", 4, 2),
+                change(0, "build.py", 1, 0),
+                change(1, "src/a.rs", 10, 0),
+                change(2, "src/b/c.rs", 3, 3),
+            ],
+            mailmap: true,
+        }
+    }
+
+    #[test]
+    fn test_totals_and_by_file() {
+        let churn = sample();
+
+        assert_eq!(
+            churn.totals(),
+            ChurnStats {
+                files: 3,
+                commits: 3,
+                added: 18,
+                deleted: 5,
+            }
+        );
+
+        let files = churn.by_file();
+        let paths: Vec<&Path> = files.iter().map(|f| f.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new("build.py"),
+                Path::new("src/a.rs"),
+                Path::new("src/b/c.rs")
+            ]
+        );
+        assert_eq!(files[1].language, Some(Language::Rust));
+        assert_eq!(files[1].stats.commits, 2);
+        assert_eq!(files[1].stats.churn(), 16);
+
+        assert_eq!(Churn::default().totals(), ChurnStats::default());
+    }
+
+    #[test]
+    fn test_by_directory() {
+        let directories = sample().by_directory(1);
+        let summary: Vec<(&Path, usize, usize)> = directories
+            .iter()
+            .map(|d| (d.path.as_path(), d.stats.files, d.stats.commits))
+            .collect();
+
+        assert_eq!(summary, [(Path::new("."), 1, 1), (Path::new("src"), 2, 3)]);
+        assert_eq!(sample().by_directory(2).len(), 3);
+    }
+
+    #[test]
+    fn test_by_author_and_language() {
+        let authors = sample().by_author();
+
+        assert_eq!(authors[0].name, "Ann Smith");
+        assert_eq!(authors[0].email, "ann@x.org");
+        assert_eq!(
+            authors[0].stats,
+            ChurnStats {
+                files: 3,
+                commits: 2,
+                added: 8,
+                deleted: 5,
+            }
+        );
+        assert_eq!(authors[1].email, "bob@x.org");
+
+        let languages = sample().by_language();
+        assert_eq!(languages[0].name(), "Rust");
+        assert_eq!(languages[0].stats.churn(), 22);
+        assert_eq!(languages[1].name(), "Python");
+    }
+
+    #[test]
+    fn test_churn() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        git(path, &["init", "-q"]);
+
+        fs::create_dir(path.join("src")).unwrap();
+        fs::write(path.join("src/a.rs"), "one\ntwo\n").unwrap();
+        fs::write(path.join("Cargo.lock"), "lock\n").unwrap();
+        fs::write(path.join("README.md"), "# readme\n").unwrap();
+        fs::write(path.join("gen.rs"), "generated\n").unwrap();
+        fs::write(path.join(".gitattributes"), "gen.rs linguist-generated\n").unwrap();
+        commit_as(path, "Ann", "ann@x.org", "first");
+
+        fs::write(path.join("src/a.rs"), "one\n2\nthree\n").unwrap();
+        fs::write(path.join("Cargo.lock"), "lock\nmore\n").unwrap();
+        fs::write(path.join("gen.rs"), "regenerated\n").unwrap();
+        commit_as(path, "Bob", "bob@x.org", "second");
+
+        fs::write(path.join("README.md"), "# readme\nmore\n").unwrap();
+        commit_as(path, "Bob", "bob@x.org", "docs only");
+
+        let filter = FileFilter::new();
+        let churn = churn(path, "HEAD", None, None, &filter, true).unwrap();
+
+        assert_eq!(churn.commits.len(), 2);
+        assert_eq!(churn.commits[0].author.name, "Bob");
+        let files = churn.by_file();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, Path::new("src/a.rs"));
+        assert_eq!(files[0].language, Some(Language::Rust));
+        assert_eq!((files[0].stats.added, files[0].stats.deleted), (4, 1));
+
+        let churn = super::churn(path, "HEAD~2..HEAD", None, None, &filter, true).unwrap();
+        assert_eq!(churn.totals().commits, 1);
+        assert_eq!(churn.by_author()[0].email, "bob@x.org");
+
+        let filter = FileFilter::new().without_attributes();
+        let churn = super::churn(path, "HEAD", None, None, &filter, true).unwrap();
+        assert_eq!(churn.by_file().len(), 2);
+
+        let filter = FileFilter::new().pathspec("gen.rs").without_attributes();
+        let churn = super::churn(path, "HEAD", None, None, &filter, true).unwrap();
+        assert_eq!(churn.totals().added, 2);
+    }
+
+    #[test]
+    fn test_write_tables() {
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &sample(), Grouping::File).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "path,language,commits,added,deleted\n\
+             build.py,Python,1,1,0\n\
+             src/a.rs,Rust,2,14,2\n\
+             src/b/c.rs,Rust,1,3,3\n"
+        );
+
+        let mut tsv = Vec::new();
+        write_tsv(&mut tsv, &sample(), Grouping::Author).unwrap();
+        assert_eq!(
+            String::from_utf8(tsv).unwrap(),
+            "email\tname\tfiles\tcommits\tadded\tdeleted\n\
+             ann@x.org\tAnn Smith\t3\t2\t8\t5\n\
+             bob@x.org\tBob\t1\t1\t10\t0\n"
+        );
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &sample(), Grouping::Language).unwrap();
+        assert!(
+            String::from_utf8(csv)
+                .unwrap()
+                .ends_with("Python,1,1,1,0\n")
+        );
+    }
+
+    #[test]
+    fn test_report_json() {
+        let churn = sample();
+        let report = ChurnReport::new(".", "v1.0..HEAD", &churn, &FileFilter::new())
+            .with_dates(Some("1 year ago"), None)
+            .with_directories(&churn, 1);
+
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(value["range"], "v1.0..HEAD");
+        assert_eq!(value["since"], "1 year ago");
+        assert_eq!(value["until"], serde_json::Value::Null);
+        assert_eq!(value["totals"]["added"], 18);
+        assert_eq!(value["authors"][0]["commits"], 2);
+        assert_eq!(value["languages"][0]["language"], "Rust");
+        assert_eq!(value["files"][0]["path"], "build.py");
+        assert_eq!(value["directories"][1]["path"], "src");
+    }
+
+    #[test]
+    fn test_directory_with_special_characters() {
+        let mut churn = sample();
+        churn.changes[0].path = "tab\tdir/a.rs".into();
+
+        // Tables hold the path as it is, JSON quotes it as Git does
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &churn, Grouping::Directory(1)).unwrap();
+        assert!(
+            String::from_utf8(csv)
+                .unwrap()
+                .contains("\ntab\tdir,1,1,4,2\n")
+        );
+
+        let mut tsv = Vec::new();
+        write_tsv(&mut tsv, &churn, Grouping::Directory(1)).unwrap();
+        assert!(
+            String::from_utf8(tsv)
+                .unwrap()
+                .contains("\n\"tab\tdir\"\t1\t1\t4\t2\n")
+        );
+
+        let report =
+            ChurnReport::new(".", "HEAD", &churn, &FileFilter::new()).with_directories(&churn, 1);
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["directories"][2]["path"], "\"tab\\tdir\"");
+    }
+}
@@ -0,0 +1,711 @@
+//! Line classification module.
+//!
+//! This module splits file contents into lines and classifies each one as
+//! code, comment or blank, following the comment and string syntax of a
+//! [`Language`](crate::language::Language).
+//!
+//! Large contents in memory can be classified by several threads with
+//! [`classify_parallel`], which splits them into byte ranges at line
+//! boundaries.
+
+use std::ops::{Add, AddAssign};
+
+use memchr::memchr;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::language::Syntax;
+
+/// Size of the byte ranges that [`classify_parallel`] splits content into (4 MB).
+pub const PARALLEL_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Line counts broken down by kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LineStats {
+    /// Total number of lines.
+    pub lines: usize,
+    /// Lines containing code.
+    pub code: usize,
+    /// Lines containing only comments.
+    pub comment: usize,
+    /// Lines containing only whitespace.
+    pub blank: usize,
+}
+
+impl LineStats {
+    /// Records a single line of the given kind.
+    pub fn record(&mut self, kind: LineKind) {
+        self.lines += 1;
+        match kind {
+            LineKind::Code => self.code += 1,
+            LineKind::Comment => self.comment += 1,
+            LineKind::Blank => self.blank += 1,
+        }
+    }
+}
+
+impl Add for LineStats {
+    type Output = LineStats;
+
+    fn add(mut self, rhs: LineStats) -> LineStats {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for LineStats {
+    fn add_assign(&mut self, rhs: LineStats) {
+        self.lines += rhs.lines;
+        self.code += rhs.code;
+        self.comment += rhs.comment;
+        self.blank += rhs.blank;
+    }
+}
+
+/// The kind of a single line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// The line contains code (anything outside of comments).
+    Code,
+    /// The line contains comments and whitespace only.
+    Comment,
+    /// The line contains whitespace only.
+    Blank,
+}
+
+/// Multi-line construct the scanner is inside of at a line boundary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Context {
+    /// Regular code.
+    #[default]
+    Code,
+    /// Inside a block comment, identified by its index in the syntax.
+    Block { index: usize, depth: usize },
+    /// Inside a string literal, identified by its index in the syntax.
+    Str { index: usize },
+    /// Inside a raw string closed by `"` and the given number of `#`.
+    RawStr { hashes: usize },
+}
+
+/// Classifies a stream of bytes line by line.
+///
+/// Input can be fed in arbitrary chunks; lines that span chunk boundaries
+/// are buffered until they are complete.
+///
+/// Classification rules:
+///
+/// - A line with anything outside of comments is code, including lines that
+///   are part of a multi-line string literal.
+/// - A line with only comments and whitespace is a comment line.
+/// - A line with only whitespace is blank, even inside a block comment.
+/// - Comment markers inside string literals are ignored, and nested block
+///   comments are tracked for languages that allow them.
+/// - Backslash escapes, character literals, raw strings and digit separators
+///   are recognised where the [`Syntax`] has them.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::classify::LineClassifier;
+/// use gitlsf::language::Language;
+///
+/// let mut classifier = LineClassifier::new(Language::Rust.syntax());
+/// classifier.update(b"// comment\n\nfn main() {}\n");
+/// let stats = classifier.finish();
+///
+/// assert_eq!(stats.lines, 3);
+/// assert_eq!((stats.code, stats.comment, stats.blank), (1, 1, 1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LineClassifier {
+    syntax: &'static Syntax,
+    context: Context,
+    pending: Vec<u8>,
+    stats: LineStats,
+}
+
+impl LineClassifier {
+    /// Creates a new classifier for the given syntax.
+    pub fn new(syntax: &'static Syntax) -> Self {
+        Self::with_context(syntax, Context::Code)
+    }
+
+    /// Creates a classifier that starts inside the given context.
+    fn with_context(syntax: &'static Syntax, context: Context) -> Self {
+        Self {
+            syntax,
+            context,
+            pending: Vec::new(),
+            stats: LineStats::default(),
+        }
+    }
+
+    /// Feeds the next chunk of bytes into the classifier.
+    pub fn update(&mut self, bytes: &[u8]) {
+        let mut rest = bytes;
+
+        if !self.pending.is_empty() {
+            match memchr(b'\n', rest) {
+                Some(pos) => {
+                    let mut line = std::mem::take(&mut self.pending);
+                    line.extend_from_slice(&rest[..pos]);
+                    self.line(&line);
+                    line.clear();
+                    self.pending = line;
+                    rest = &rest[pos + 1..];
+                }
+                None => {
+                    self.pending.extend_from_slice(rest);
+                    return;
+                }
+            }
+        }
+
+        while let Some(pos) = memchr(b'\n', rest) {
+            self.line(&rest[..pos]);
+            rest = &rest[pos + 1..];
+        }
+
+        self.pending.extend_from_slice(rest);
+    }
+
+    /// Finishes classification and returns the accumulated statistics.
+    ///
+    /// A trailing line without a final newline is counted as a line.
+    pub fn finish(self) -> LineStats {
+        self.finish_with_context().0
+    }
+
+    /// Like [`finish`](Self::finish), but also returns the context at the end.
+    fn finish_with_context(mut self) -> (LineStats, Context) {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.line(&line);
+        }
+        (self.stats, self.context)
+    }
+
+    fn line(&mut self, line: &[u8]) {
+        let kind = if self.syntax.is_plain() {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                LineKind::Blank
+            } else {
+                LineKind::Code
+            }
+        } else {
+            classify_line(line, self.syntax, &mut self.context)
+        };
+        self.stats.record(kind);
+    }
+}
+
+/// Classifies the lines of content in memory, using several threads for large content.
+///
+/// Content larger than [`PARALLEL_CHUNK_SIZE`] is split into byte ranges
+/// that end at line boundaries, and the ranges are classified in parallel as
+/// if each started outside of any comment or string. The results are then
+/// combined in order; a range that actually starts inside a block comment or
+/// a multi-line string is classified again from that context, so the result
+/// always equals that of a single [`LineClassifier`].
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::classify::{LineClassifier, classify_parallel};
+/// use gitlsf::language::Language;
+///
+/// let content = "/* header */\nint x;\n\n".repeat(1000);
+/// let stats = classify_parallel(content.as_bytes(), Language::C.syntax());
+///
+/// let mut classifier = LineClassifier::new(Language::C.syntax());
+/// classifier.update(content.as_bytes());
+/// assert_eq!(stats, classifier.finish());
+/// ```
+pub fn classify_parallel(content: &[u8], syntax: &'static Syntax) -> LineStats {
+    classify_chunked(content, syntax, PARALLEL_CHUNK_SIZE)
+}
+
+/// Classifies content split into ranges of about `chunk_size` bytes; see [`classify_parallel`].
+fn classify_chunked(content: &[u8], syntax: &'static Syntax, chunk_size: usize) -> LineStats {
+    let classify = |chunk: &[u8], context: Context| {
+        let mut classifier = LineClassifier::with_context(syntax, context);
+        classifier.update(chunk);
+        classifier.finish_with_context()
+    };
+
+    let chunks = split_lines(content, chunk_size);
+    if chunks.len() <= 1 {
+        return classify(content, Context::Code).0;
+    }
+
+    let speculative: Vec<(LineStats, Context)> = chunks
+        .par_iter()
+        .map(|chunk| classify(chunk, Context::Code))
+        .collect();
+
+    // Ranges that start inside a multi-line construct were classified wrongly
+    let mut total = LineStats::default();
+    let mut context = Context::Code;
+    for (chunk, (stats, end)) in chunks.iter().zip(speculative) {
+        let (stats, end) = if context == Context::Code {
+            (stats, end)
+        } else {
+            classify(chunk, context)
+        };
+        total += stats;
+        context = end;
+    }
+
+    total
+}
+
+/// Splits content into ranges of at least `chunk_size` bytes that end with a
+/// newline, except for the last one.
+fn split_lines(content: &[u8], chunk_size: usize) -> Vec<&[u8]> {
+    let mut chunks = Vec::with_capacity(content.len() / chunk_size.max(1) + 1);
+    let mut rest = content;
+
+    while rest.len() > chunk_size {
+        match memchr(b'\n', &rest[chunk_size..]) {
+            Some(pos) => {
+                let (chunk, tail) = rest.split_at(chunk_size + pos + 1);
+                chunks.push(chunk);
+                rest = tail;
+            }
+            None => break,
+        }
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+
+    chunks
+}
+
+/// Classifies a single line, updating the multi-line context.
+fn classify_line(line: &[u8], syntax: &Syntax, context: &mut Context) -> LineKind {
+    let mut has_code = matches!(context, Context::Str { .. } | Context::RawStr { .. });
+    let mut has_comment = false;
+    let mut i = 0;
+
+    while i < line.len() {
+        let rest = &line[i..];
+
+        match *context {
+            Context::Block { index, depth } => {
+                let (open, close) = syntax.block_comments[index];
+                if rest.starts_with(close.as_bytes()) {
+                    *context = if depth > 1 {
+                        Context::Block {
+                            index,
+                            depth: depth - 1,
+                        }
+                    } else {
+                        Context::Code
+                    };
+                    has_comment = true;
+                    i += close.len();
+                } else if syntax.nested && rest.starts_with(open.as_bytes()) {
+                    *context = Context::Block {
+                        index,
+                        depth: depth + 1,
+                    };
+                    has_comment = true;
+                    i += open.len();
+                } else {
+                    has_comment |= !rest[0].is_ascii_whitespace();
+                    i += 1;
+                }
+            }
+            Context::Str { index } => {
+                let (_, close) = syntax.quotes[index];
+                if rest[0] == b'\\' && !syntax.verbatim_strings {
+                    i += 2;
+                } else if rest.starts_with(close.as_bytes()) {
+                    *context = Context::Code;
+                    i += close.len();
+                } else {
+                    i += 1;
+                }
+            }
+            Context::RawStr { hashes } => {
+                if rest[0] == b'"' && closes_raw_string(&rest[1..], hashes) {
+                    *context = Context::Code;
+                    i += 1 + hashes;
+                } else {
+                    i += 1;
+                }
+            }
+            Context::Code => {
+                if rest[0].is_ascii_whitespace() {
+                    i += 1;
+                } else if let Some((hashes, len)) = raw_string_start(line, i, syntax) {
+                    *context = Context::RawStr { hashes };
+                    has_code = true;
+                    i += len;
+                } else if let Some(len) = char_literal(rest, syntax) {
+                    has_code = true;
+                    i += len;
+                } else if rest[0] == b'\'' && is_digit_separator(line, i, syntax) {
+                    has_code = true;
+                    i += 1;
+                } else if let Some(index) = find_marker(rest, syntax.block_comments) {
+                    *context = Context::Block { index, depth: 1 };
+                    has_comment = true;
+                    i += syntax.block_comments[index].0.len();
+                } else if syntax
+                    .line_comments
+                    .iter()
+                    .any(|marker| rest.starts_with(marker.as_bytes()))
+                {
+                    has_comment = true;
+                    break;
+                } else if let Some(index) = find_marker(rest, syntax.quotes) {
+                    *context = Context::Str { index };
+                    has_code = true;
+                    i += syntax.quotes[index].0.len();
+                } else {
+                    has_code = true;
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    if has_code {
+        LineKind::Code
+    } else if has_comment {
+        LineKind::Comment
+    } else {
+        LineKind::Blank
+    }
+}
+
+/// Returns the number of `#` and the length of the opening marker if a raw
+/// string such as `r#"` or `br"` starts at `line[i..]`.
+fn raw_string_start(line: &[u8], i: usize, syntax: &Syntax) -> Option<(usize, usize)> {
+    if !syntax.raw_strings || (i > 0 && is_identifier_byte(line[i - 1])) {
+        return None;
+    }
+    let rest = &line[i..];
+    let prefix = if rest.starts_with(b"br") {
+        2
+    } else if rest.starts_with(b"r") {
+        1
+    } else {
+        return None;
+    };
+    let hashes = rest[prefix..].iter().take_while(|&&b| b == b'#').count();
+    (rest.get(prefix + hashes) == Some(&b'"')).then_some((hashes, prefix + hashes + 1))
+}
+
+/// Returns `true` if `bytes` starts with `hashes` times `#`.
+fn closes_raw_string(bytes: &[u8], hashes: usize) -> bool {
+    bytes.len() >= hashes && bytes[..hashes].iter().all(|&b| b == b'#')
+}
+
+/// Returns the length of the character literal, such as `'"'`, `'\''` or
+/// `'\u{1F600}'`, that starts `bytes`, if any.
+///
+/// A `'` that does not start a character literal, such as that of a Rust
+/// lifetime, is not one either.
+fn char_literal(bytes: &[u8], syntax: &Syntax) -> Option<usize> {
+    if !syntax.char_literals || bytes[0] != b'\'' {
+        return None;
+    }
+    let len = match *bytes.get(1)? {
+        // An escape runs to the next quote; `\u{10FFFF}` is the longest
+        b'\\' => bytes.iter().skip(3).take(9).position(|&b| b == b'\'')? + 3,
+        b'\'' | b'\n' => return None,
+        lead => 1 + utf8_len(lead),
+    };
+    (bytes.get(len) == Some(&b'\'')).then_some(len + 1)
+}
+
+/// Returns the length of the UTF-8 sequence starting with `lead`.
+fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0xF0..=0xF7 => 4,
+        0xE0..=0xEF => 3,
+        0xC0..=0xDF => 2,
+        _ => 1,
+    }
+}
+
+/// Returns `true` if the `'` at `line[i]` separates the digits of a number.
+fn is_digit_separator(line: &[u8], i: usize, syntax: &Syntax) -> bool {
+    if !syntax.digit_separators || !line.get(i + 1).is_some_and(u8::is_ascii_alphanumeric) {
+        return false;
+    }
+    // The token before the quote must be a number, such as `1` or `0xFF'`
+    let start = line[..i]
+        .iter()
+        .rposition(|&b| !(is_identifier_byte(b) || b == b'\'' || b == b'.'))
+        .map_or(0, |pos| pos + 1);
+    line[start..i].first().is_some_and(u8::is_ascii_digit)
+}
+
+/// Returns `true` if `byte` can be part of an identifier or number.
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80
+}
+
+/// Returns the index of the first delimiter pair whose opening marker starts `bytes`.
+fn find_marker(bytes: &[u8], pairs: &[(&str, &str)]) -> Option<usize> {
+    pairs
+        .iter()
+        .position(|(open, _)| bytes.starts_with(open.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::Language;
+
+    fn classify(language: Language, source: &str) -> LineStats {
+        let mut classifier = LineClassifier::new(language.syntax());
+        classifier.update(source.as_bytes());
+        classifier.finish()
+    }
+
+    fn counts(stats: LineStats) -> (usize, usize, usize) {
+        (stats.code, stats.comment, stats.blank)
+    }
+
+    #[test]
+    fn test_split_lines() {
+        assert_eq!(
+            split_lines(b"ab\ncd\nef", 2),
+            [&b"ab\n"[..], &b"cd\n"[..], &b"ef"[..]]
+        );
+        assert_eq!(split_lines(b"abcdef\n", 2), [&b"abcdef\n"[..]]);
+        assert_eq!(split_lines(b"a\nb\n", 10), [&b"a\nb\n"[..]]);
+        assert!(split_lines(b"", 2).is_empty());
+    }
+
+    #[test]
+    fn test_classify_chunked_matches_sequential() {
+        let sources = [
+            (
+                Language::C,
+                "int a;\n/* a block comment\n   spanning\n\n   many lines */\nint b; // x\n\"s /* no\"\n",
+            ),
+            (
+                Language::Rust,
+                "/* outer /* nested\n */ still\n*/\nlet s = \"multi\nline\nstring\";\n\n// end",
+            ),
+            (
+                Language::Python,
+                "x = '''\ndoc\n# not a comment\n'''\n# comment\n\ny = 1\n",
+            ),
+            (
+                Language::Rust,
+                "let s = r#\"raw\n// not a comment\n\"#;\n// comment\n",
+            ),
+            (Language::Rust, ""),
+        ];
+
+        for (language, source) in sources {
+            let expected = classify(language, source);
+            for chunk_size in [1, 2, 3, 5, 8, 13, 64] {
+                assert_eq!(
+                    classify_chunked(source.as_bytes(), language.syntax(), chunk_size),
+                    expected,
+                    "{} with chunks of {chunk_size} bytes",
+                    language.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_line_comments_and_blanks() {
+        let stats = classify(
+            Language::Rust,
+            "// header\n\nfn main() {\n    let x = 1; // trailing\n}\n",
+        );
+
+        assert_eq!(stats.lines, 5);
+        assert_eq!(counts(stats), (3, 1, 1));
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let stats = classify(
+            Language::C,
+            "/*\n * License\n\n */\nint x; /* inline */\n/* a */ int y;\n",
+        );
+
+        assert_eq!(stats.lines, 6);
+        assert_eq!(counts(stats), (2, 3, 1));
+    }
+
+    #[test]
+    fn test_nested_block_comments() {
+        let stats = classify(
+            Language::Rust,
+            "/* outer\n/* inner */\nstill comment\n*/\nfn f() {}\n",
+        );
+
+        assert_eq!(counts(stats), (1, 4, 0));
+    }
+
+    #[test]
+    fn test_non_nested_block_comments() {
+        let stats = classify(Language::C, "/* outer\n/* inner */\nint x;\n");
+
+        assert_eq!(counts(stats), (1, 2, 0));
+    }
+
+    #[test]
+    fn test_comment_markers_inside_strings() {
+        let stats = classify(
+            Language::Rust,
+            "let url = \"http://example.com\";\nlet s = \"/* not a comment\";\nlet t = 1;\n",
+        );
+
+        assert_eq!(counts(stats), (3, 0, 0));
+    }
+
+    #[test]
+    fn test_escaped_quotes() {
+        let stats = classify(
+            Language::C,
+            "char *s = \"\\\" // still string\";\n// comment\n",
+        );
+
+        assert_eq!(counts(stats), (1, 1, 0));
+    }
+
+    #[test]
+    fn test_rust_char_literals() {
+        let stats = classify(
+            Language::Rust,
+            "let q = '\"';\n'\"'\n// comment\nlet e = '\\'';\n// comment\nlet u = '\\u{1F600}';\n",
+        );
+
+        assert_eq!(counts(stats), (4, 2, 0));
+    }
+
+    #[test]
+    fn test_rust_byte_literals() {
+        let stats = classify(
+            Language::Rust,
+            "let q = b'\"';\n// comment\nlet e = b'\\\\';\n",
+        );
+
+        assert_eq!(counts(stats), (2, 1, 0));
+    }
+
+    #[test]
+    fn test_rust_lifetimes_are_not_char_literals() {
+        let stats = classify(
+            Language::Rust,
+            "fn f<'a>(s: &'a str) -> &'a str {\n    // comment\n    s\n}\n",
+        );
+
+        assert_eq!(counts(stats), (3, 1, 0));
+    }
+
+    #[test]
+    fn test_rust_raw_strings() {
+        let stats = classify(
+            Language::Rust,
+            "let a = r\"C:\\\";\n// comment\nlet b = r#\"say \"hi\" // no\n\"#;\n// comment\nlet c = br##\"\"#\"##;\n",
+        );
+
+        assert_eq!(counts(stats), (4, 2, 0));
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let stats = classify(
+            Language::Cpp,
+            "int n = 1'000'000;\n// comment\nint h = 0xFF'FF;\nchar c = L'x';\n// comment\n",
+        );
+
+        assert_eq!(counts(stats), (3, 2, 0));
+    }
+
+    #[test]
+    fn test_verbatim_strings() {
+        let stats = classify(
+            Language::Sql,
+            "SELECT 'C:\\';\n-- comment\nSELECT 'it''s';\n-- comment\n",
+        );
+
+        assert_eq!(counts(stats), (2, 2, 0));
+    }
+
+    #[test]
+    fn test_multiline_strings_are_code() {
+        let stats = classify(
+            Language::Python,
+            "x = \"\"\"\n# not a comment\n\n\"\"\"\n# comment\n",
+        );
+
+        assert_eq!(counts(stats), (4, 1, 0));
+    }
+
+    #[test]
+    fn test_plain_syntax() {
+        let stats = classify(Language::Markdown, "# Title\n\ntext\n");
+
+        assert_eq!(counts(stats), (2, 0, 1));
+    }
+
+    #[test]
+    fn test_chunked_input_matches_whole_input() {
+        let source = "/* a\n b */\nfn main() {\n    // c\n}\n\nlet s = \"x\";";
+        let whole = classify(Language::Rust, source);
+
+        for chunk_size in 1..8 {
+            let mut classifier = LineClassifier::new(Language::Rust.syntax());
+            for chunk in source.as_bytes().chunks(chunk_size) {
+                classifier.update(chunk);
+            }
+            assert_eq!(classifier.finish(), whole, "chunk size {chunk_size}");
+        }
+    }
+
+    #[test]
+    fn test_trailing_line_without_newline() {
+        let stats = classify(Language::Rust, "fn a() {}\nfn b() {}");
+
+        assert_eq!(stats.lines, 2);
+        assert_eq!(stats.code, 2);
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let stats = classify(Language::Rust, "// a\r\n\r\nfn b() {}\r\n");
+
+        assert_eq!(counts(stats), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_line_stats_add() {
+        let a = LineStats {
+            lines: 3,
+            code: 1,
+            comment: 1,
+            blank: 1,
+        };
+        let b = LineStats {
+            lines: 2,
+            code: 2,
+            comment: 0,
+            blank: 0,
+        };
+
+        assert_eq!(
+            a + b,
+            LineStats {
+                lines: 5,
+                code: 3,
+                comment: 1,
+                blank: 1,
+            }
+        );
+    }
+}
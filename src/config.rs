@@ -0,0 +1,500 @@
+//! Configuration file module.
+//!
+//! This module loads filter and output settings from TOML configuration
+//! files, so that a team can share the same exclusions for a repository.
+//!
+//! Two files are read, if they exist:
+//!
+//! 1. The user configuration, `$XDG_CONFIG_HOME/gitlsf/config.toml`, or
+//!    `~/.config/gitlsf/config.toml` when `XDG_CONFIG_HOME` is not set
+//! 2. The repository configuration, [`FILE_NAME`] at the repository root, or
+//!    in the counted directory when it is not a Git repository
+//!
+//! Settings are applied in order of precedence, from lowest to highest:
+//! built-in defaults, the user configuration, the repository configuration,
+//! then command-line flags. Single values (`format`, `data_extensions`,
+//! `doc_extensions`, limits) are replaced by a higher-precedence source, while
+//! lists of patterns and exclusions are appended to, so that later patterns
+//! win. A budget for a pattern that already has one replaces it.
+//!
+//! ## Format
+//!
+//! ```toml
+//! # gitignore-style globs, see the filter module
+//! include = ["src/**", "!src/generated/"]
+//! exclude = ["vendor/", "**/*_generated.go"]
+//!
+//! # Added to the built-in exclusions
+//! exclude_extensions = ["log"]
+//! exclude_filenames = ["CODEOWNERS"]
+//!
+//! # Replace the built-in data and documentation extension lists
+//! data_extensions = ["lock", "csv"]
+//! doc_extensions = ["md", "rst", "adoc"]
+//!
+//! # Default output format: "text", "json", "csv" or "tsv"
+//! format = "json"
+//!
+//! # Glob pattern to language name; the last matching pattern wins
+//! [languages]
+//! "*.h" = "C++"
+//! "scripts/*" = "Shell"
+//!
+//! # Limits enforced by `gitlsf check`
+//! [check]
+//! max_file_lines = 1000
+//! max_total_lines = 200000
+//!
+//! # Glob pattern to the maximum lines of the matching files together
+//! [check.budgets]
+//! "src/parser/" = 5000
+//! ```
+//!
+//! Unknown keys and unknown language names are errors.
+
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde::de::{Deserializer, MapAccess, Visitor};
+
+use crate::budget::Budgets;
+use crate::error::{GitlsfError, Result};
+use crate::filter::FileFilter;
+use crate::git;
+use crate::language::Language;
+
+/// Name of the repository configuration file.
+pub const FILE_NAME: &str = ".gitlsf.toml";
+
+/// Settings loaded from configuration files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Glob patterns that files must match to be counted.
+    pub include: Vec<String>,
+    /// Glob patterns of files that should not be counted.
+    pub exclude: Vec<String>,
+    /// Extensions to exclude in addition to the built-in lists.
+    pub exclude_extensions: Vec<String>,
+    /// Filenames to exclude in addition to the built-in list.
+    pub exclude_filenames: Vec<String>,
+    /// Replacement for the built-in data extension list.
+    pub data_extensions: Option<Vec<String>>,
+    /// Replacement for the built-in documentation extension list.
+    pub doc_extensions: Option<Vec<String>>,
+    /// Default output format.
+    pub format: Option<String>,
+    /// Glob patterns and the language assigned to matching files, in file order.
+    #[serde(deserialize_with = "ordered_table")]
+    pub languages: Vec<(String, Language)>,
+    /// Limits enforced by `gitlsf check`.
+    pub check: CheckConfig,
+}
+
+/// Line count limits from the `[check]` table.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckConfig {
+    /// Maximum number of lines of any single file.
+    pub max_file_lines: Option<usize>,
+    /// Maximum total number of lines.
+    pub max_total_lines: Option<usize>,
+    /// Glob patterns and the maximum lines of their matching files, in file order.
+    #[serde(deserialize_with = "ordered_table")]
+    pub budgets: Vec<(String, usize)>,
+}
+
+impl Config {
+    /// Loads a configuration file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid
+    /// configuration.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::config::Config;
+    ///
+    /// let config = Config::load(".gitlsf.toml").unwrap();
+    /// println!("Excluded: {:?}", config.exclude);
+    /// ```
+    pub fn load(path: impl AsRef<Path>) -> Result<Config> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| GitlsfError::io(path, e))?;
+
+        Self::parse(&text, path)
+    }
+
+    /// Loads and merges the user and repository configuration files.
+    ///
+    /// Missing files are skipped. See the [module documentation](self) for
+    /// the locations and the order of precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path within the Git repository.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The path is not within a Git repository
+    /// - A configuration file exists but cannot be read or parsed
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::config::Config;
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// let config = Config::discover(".").unwrap();
+    /// let filter = config.apply(FileFilter::new()).unwrap();
+    /// let summary = gitlsf::count_repository_with_filter(".", filter).unwrap();
+    /// ```
+    pub fn discover(path: impl AsRef<Path>) -> Result<Config> {
+        Self::discover_in(git::repository_root(path)?)
+    }
+
+    /// Loads and merges the user configuration file and the one in `directory`.
+    ///
+    /// Like [`discover`](Self::discover), but `.gitlsf.toml` is looked up in
+    /// `directory` itself, which need not be in a Git repository. Missing
+    /// files are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a configuration file exists but cannot be read or
+    /// parsed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::config::Config;
+    ///
+    /// let config = Config::discover_in("unpacked-release").unwrap();
+    /// ```
+    pub fn discover_in(directory: impl AsRef<Path>) -> Result<Config> {
+        let mut config = match user_config_path() {
+            Some(user_path) => Self::load_if_exists(&user_path)?.unwrap_or_default(),
+            None => Config::default(),
+        };
+
+        if let Some(local) = Self::load_if_exists(&directory.as_ref().join(FILE_NAME))? {
+            config = config.merge(local);
+        }
+
+        Ok(config)
+    }
+
+    /// Combines two configurations, with `other` taking precedence.
+    ///
+    /// Single values set in `other` replace those in `self`; lists are
+    /// concatenated with the entries of `other` last.
+    pub fn merge(mut self, other: Config) -> Config {
+        self.include.extend(other.include);
+        self.exclude.extend(other.exclude);
+        self.exclude_extensions.extend(other.exclude_extensions);
+        self.exclude_filenames.extend(other.exclude_filenames);
+        self.languages.extend(other.languages);
+
+        let mut budgets = self.check.budgets;
+        for (pattern, limit) in other.check.budgets {
+            budgets.retain(|(existing, _)| *existing != pattern);
+            budgets.push((pattern, limit));
+        }
+
+        Config {
+            data_extensions: other.data_extensions.or(self.data_extensions),
+            doc_extensions: other.doc_extensions.or(self.doc_extensions),
+            format: other.format.or(self.format),
+            check: CheckConfig {
+                max_file_lines: other.check.max_file_lines.or(self.check.max_file_lines),
+                max_total_lines: other.check.max_total_lines.or(self.check.max_total_lines),
+                budgets,
+            },
+            ..self
+        }
+    }
+
+    /// Adds the configured filter settings to a file filter.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GitlsfError::Pattern`] if a glob pattern is invalid.
+    pub fn apply(&self, mut filter: FileFilter) -> Result<FileFilter> {
+        for pattern in &self.include {
+            filter = filter.include_pattern(pattern)?;
+        }
+        for pattern in &self.exclude {
+            filter = filter.exclude_pattern(pattern)?;
+        }
+        for extension in &self.exclude_extensions {
+            filter = filter.exclude_extension(extension);
+        }
+        for filename in &self.exclude_filenames {
+            filter = filter.exclude_filename(filename);
+        }
+        if let Some(extensions) = &self.data_extensions {
+            filter = filter.with_data_extensions(extensions);
+        }
+        if let Some(extensions) = &self.doc_extensions {
+            filter = filter.with_doc_extensions(extensions);
+        }
+        for (pattern, language) in &self.languages {
+            filter = filter.override_language(pattern, *language)?;
+        }
+
+        Ok(filter)
+    }
+
+    /// Builds the line count limits of the `[check]` table.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GitlsfError::Pattern`] if a budget pattern is invalid.
+    pub fn budgets(&self) -> Result<Budgets> {
+        let mut budgets = Budgets::new();
+        if let Some(limit) = self.check.max_file_lines {
+            budgets = budgets.max_file_lines(limit);
+        }
+        if let Some(limit) = self.check.max_total_lines {
+            budgets = budgets.max_total_lines(limit);
+        }
+        for (pattern, limit) in &self.check.budgets {
+            budgets = budgets.budget(pattern, *limit)?;
+        }
+
+        Ok(budgets)
+    }
+
+    /// Parses the contents of a configuration file.
+    fn parse(text: &str, path: &Path) -> Result<Config> {
+        toml::from_str(text).map_err(|e| {
+            let message = match e.span() {
+                Some(span) => {
+                    let line = text[..span.start].matches('\n').count() + 1;
+                    format!("line {line}: {}", e.message())
+                }
+                None => e.message().to_string(),
+            };
+            GitlsfError::config(path, message)
+        })
+    }
+
+    /// Loads a configuration file, or returns `None` if it does not exist.
+    fn load_if_exists(path: &Path) -> Result<Option<Config>> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text, path).map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(GitlsfError::io(path, e)),
+        }
+    }
+}
+
+/// Returns the location of the user configuration file.
+///
+/// This is `$XDG_CONFIG_HOME/gitlsf/config.toml`, falling back to
+/// `$HOME/.config/gitlsf/config.toml`. Returns `None` if neither variable is set.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(config_home.join("gitlsf").join("config.toml"))
+}
+
+/// Deserializes a table of patterns to values, keeping the order of the file.
+fn ordered_table<'de, D, T>(deserializer: D) -> std::result::Result<Vec<(String, T)>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct TableVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for TableVisitor<T> {
+        type Value = Vec<(String, T)>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a table of glob patterns")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> std::result::Result<Self::Value, A::Error> {
+            let mut entries = Vec::new();
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(TableVisitor(PhantomData))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn parse(text: &str) -> Result<Config> {
+        Config::parse(text, Path::new(FILE_NAME))
+    }
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = parse(
+            r#"
+            include = ["src/**"]
+            exclude = ["vendor/"]
+            exclude_extensions = ["log"]
+            exclude_filenames = ["CODEOWNERS"]
+            data_extensions = ["lock"]
+            doc_extensions = ["md", "rst"]
+            format = "json"
+
+            [languages]
+            "*.h" = "C++"
+            "scripts/*" = "sh"
+            "a/*" = "Rust"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.include, ["src/**"]);
+        assert_eq!(config.exclude, ["vendor/"]);
+        assert_eq!(config.exclude_extensions, ["log"]);
+        assert_eq!(config.exclude_filenames, ["CODEOWNERS"]);
+        assert_eq!(config.data_extensions, Some(vec!["lock".to_string()]));
+        assert_eq!(config.format.as_deref(), Some("json"));
+        assert_eq!(
+            config.languages,
+            [
+                ("*.h".to_string(), Language::Cpp),
+                ("scripts/*".to_string(), Language::Shell),
+                ("a/*".to_string(), Language::Rust),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let unknown_key = parse("format = \"text\"\nexclud = [\"vendor/\"]").unwrap_err();
+        assert!(
+            unknown_key
+                .to_string()
+                .contains("line 2: unknown field `exclud`")
+        );
+
+        let unknown_language = parse("[languages]\n\"*.x\" = \"Klingon\"").unwrap_err();
+        assert!(
+            unknown_language
+                .to_string()
+                .contains("unknown language 'Klingon'")
+        );
+
+        assert!(matches!(
+            parse("include = 3"),
+            Err(GitlsfError::Config { .. })
+        ));
+    }
+
+    #[test]
+    fn test_merge_precedence() {
+        let user = parse(
+            "exclude = [\"*.log\"]\nformat = \"csv\"\ndoc_extensions = [\"md\"]\n[languages]\n\"*.h\" = \"C\"",
+        )
+        .unwrap();
+        let repository =
+            parse("exclude = [\"!keep.log\"]\nformat = \"json\"\n[languages]\n\"*.h\" = \"C++\"")
+                .unwrap();
+
+        let merged = user.merge(repository);
+
+        assert_eq!(merged.exclude, ["*.log", "!keep.log"]);
+        assert_eq!(merged.format.as_deref(), Some("json"));
+        assert_eq!(merged.doc_extensions, Some(vec!["md".to_string()]));
+
+        let filter = merged.apply(FileFilter::new()).unwrap();
+        assert!(!filter.should_count("debug.log"));
+        assert!(filter.should_count("keep.log"));
+        assert_eq!(filter.language_override("a.h"), Some(Language::Cpp));
+    }
+
+    #[test]
+    fn test_apply() {
+        let config = parse(
+            "include = [\"src/**\"]\nexclude_extensions = [\"txt\"]\ndata_extensions = []\ndoc_extensions = [\"rst\"]",
+        )
+        .unwrap();
+        let filter = config.apply(FileFilter::new()).unwrap();
+
+        assert!(filter.should_count("src/config.json"));
+        assert!(filter.should_count("src/README.md"));
+        assert!(!filter.should_count("src/index.rst"));
+        assert!(!filter.should_count("src/notes.txt"));
+        assert!(!filter.should_count("build.rs"));
+    }
+
+    #[test]
+    fn test_check_budgets() {
+        let user = parse(
+            "[check]\nmax_file_lines = 500\nmax_total_lines = 9000\n[check.budgets]\n\"src/\" = 10\n\"lib/\" = 20",
+        )
+        .unwrap();
+        let repository =
+            parse("[check]\nmax_file_lines = 800\n[check.budgets]\n\"src/\" = 30").unwrap();
+
+        let merged = user.merge(repository);
+
+        assert_eq!(merged.check.max_file_lines, Some(800));
+        assert_eq!(merged.check.max_total_lines, Some(9000));
+        assert_eq!(
+            merged.check.budgets,
+            [("lib/".to_string(), 20), ("src/".to_string(), 30)]
+        );
+
+        let budgets = merged.budgets().unwrap();
+        assert_eq!(
+            budgets.pattern_budgets().collect::<Vec<_>>(),
+            [("lib/", 20), ("src/", 30)]
+        );
+
+        assert!(parse("[check]\nmax_lines = 1").is_err());
+    }
+
+    #[test]
+    fn test_discover_reads_repository_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        fs::create_dir(path.join("src")).unwrap();
+        fs::write(path.join(FILE_NAME), "exclude = [\"vendor/\"]\n").unwrap();
+
+        let config = Config::discover(path.join("src")).unwrap();
+
+        assert!(config.exclude.ends_with(&["vendor/".to_string()]));
+    }
+
+    #[test]
+    fn test_discover_in_reads_directory_config() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(FILE_NAME), "exclude = [\"dist/\"]\n").unwrap();
+
+        let config = Config::discover_in(temp_dir.path()).unwrap();
+
+        assert!(config.exclude.ends_with(&["dist/".to_string()]));
+    }
+}
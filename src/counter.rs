@@ -2,45 +2,318 @@
 //!
 //! This module provides functionality for counting lines in files,
 //! with support for parallel processing to handle large repositories efficiently.
+//! Each counted file is tagged with its language and broken down into code,
+//! comment and blank lines. Files whose content looks binary, or that
+//! cannot be read, are skipped and reported in [`CountSummary::skipped`].
+//!
+//! Files are read through a 64 KB buffer, except for files of at least
+//! [`MMAP_THRESHOLD`] bytes, such as large generated sources and SQL dumps,
+//! which are memory-mapped and scanned in place. [`ReadStrategy`] selects
+//! either path explicitly, which the benchmarks under `benches/` use to
+//! compare them. Mapped files larger than
+//! [`PARALLEL_CHUNK_SIZE`](crate::classify::PARALLEL_CHUNK_SIZE) are also
+//! split into byte ranges counted by several threads, so that a single huge
+//! file does not leave all but one core idle.
 
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use memchr::memchr_iter;
+use memchr::{memchr, memchr_iter};
+use memmap2::Mmap;
 use rayon::prelude::*;
+use serde::{Serialize, Serializer};
 
+use crate::classify::{LineClassifier, LineStats, PARALLEL_CHUNK_SIZE, classify_parallel};
+use crate::detect::{detect, detect_whole};
 use crate::error::{GitlsfError, Result};
+use crate::language::{Language, Syntax};
+use crate::output::serialize_path;
 
 /// Buffer size for reading files (64KB).
 const BUFFER_SIZE: usize = 64 * 1024;
 
+/// Number of leading bytes searched for a NUL byte by [`is_binary`], as in Git.
+const BINARY_SNIFF_LENGTH: usize = 8000;
+
+/// Size in bytes from which files are memory-mapped instead of read (1 MB).
+pub const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// How the content of working tree files is read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadStrategy {
+    /// Memory-map files of at least [`MMAP_THRESHOLD`] bytes, and read
+    /// smaller files through a buffer.
+    #[default]
+    Auto,
+    /// Read every file through a buffer.
+    Buffered,
+    /// Memory-map every non-empty file.
+    Mapped,
+}
+
+impl ReadStrategy {
+    /// Returns `true` if a file of `size` bytes is memory-mapped.
+    ///
+    /// Empty files are never mapped, since mapping zero bytes fails on
+    /// some platforms.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::counter::{MMAP_THRESHOLD, ReadStrategy};
+    ///
+    /// assert!(ReadStrategy::Auto.maps(MMAP_THRESHOLD));
+    /// assert!(!ReadStrategy::Auto.maps(4096));
+    /// assert!(ReadStrategy::Mapped.maps(4096));
+    /// assert!(!ReadStrategy::Mapped.maps(0));
+    /// ```
+    pub fn maps(self, size: u64) -> bool {
+        match self {
+            ReadStrategy::Auto => size >= MMAP_THRESHOLD,
+            ReadStrategy::Buffered => false,
+            ReadStrategy::Mapped => size > 0,
+        }
+    }
+}
+
+/// Memory-maps a file for reading.
+///
+/// The mapping reflects later changes to the file; if the file is truncated
+/// while it is mapped, reading past its new end raises `SIGBUS`. The same
+/// holds for every tool that maps files (ripgrep, Git itself), and tracked
+/// source files are not expected to change during a count.
+fn map_file(file: &File) -> std::io::Result<Mmap> {
+    // SAFETY: The mapping is only read, and only while the file is open; see above
+    unsafe { Mmap::map(file) }
+}
+
 /// Result of counting lines in a single file.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct FileCount {
     /// The path to the file.
-    pub path: String,
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+    /// The detected language of the file, if known.
+    pub language: Option<Language>,
     /// The number of lines in the file.
     pub lines: usize,
+    /// The number of lines containing code.
+    pub code: usize,
+    /// The number of lines containing only comments.
+    pub comment: usize,
+    /// The number of blank lines.
+    pub blank: usize,
+    /// The path of the submodule the file belongs to, relative to the
+    /// repository root, or `None` for files of the repository itself.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_submodule"
+    )]
+    pub submodule: Option<PathBuf>,
 }
 
 impl FileCount {
-    /// Creates a new file count result.
-    pub fn new(path: impl Into<String>, lines: usize) -> Self {
+    /// Creates a new file count result where every line is treated as code.
+    pub fn new(path: impl Into<PathBuf>, lines: usize) -> Self {
         Self {
             path: path.into(),
+            language: None,
             lines,
+            code: lines,
+            comment: 0,
+            blank: 0,
+            submodule: None,
+        }
+    }
+
+    /// Creates a new file count result from classified line statistics.
+    pub fn with_stats(path: impl Into<PathBuf>, stats: LineStats) -> Self {
+        Self {
+            path: path.into(),
+            language: None,
+            lines: stats.lines,
+            code: stats.code,
+            comment: stats.comment,
+            blank: stats.blank,
+            submodule: None,
+        }
+    }
+
+    /// Sets the language of the file.
+    pub fn with_language(mut self, language: Option<Language>) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Returns the line statistics of the file.
+    pub fn stats(&self) -> LineStats {
+        LineStats {
+            lines: self.lines,
+            code: self.code,
+            comment: self.comment,
+            blank: self.blank,
+        }
+    }
+}
+
+/// Line counts of all files written in one language.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LanguageSummary {
+    /// The language, or `None` for files in unknown languages.
+    pub language: Option<Language>,
+    /// Number of files in the language.
+    pub files: usize,
+    /// Line statistics summed over the files.
+    #[serde(flatten)]
+    pub stats: LineStats,
+}
+
+impl LanguageSummary {
+    /// Returns the display name of the language, `"Other"` for unknown languages.
+    pub fn name(&self) -> &'static str {
+        self.language.map_or("Other", Language::name)
+    }
+}
+
+/// Line counts of all files of one submodule.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SubmoduleSummary {
+    /// The path of the submodule relative to the repository root.
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+    /// Number of files counted in the submodule.
+    pub files: usize,
+    /// Line statistics summed over the files.
+    #[serde(flatten)]
+    pub stats: LineStats,
+}
+
+/// The reason a file was not counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkipReason {
+    /// The content contains a NUL byte, or the file is marked `binary`,
+    /// `-diff` or `-text` in `.gitattributes`.
+    Binary,
+    /// The file is marked `linguist-generated` in `.gitattributes`.
+    Generated,
+    /// The file is marked `linguist-vendored` in `.gitattributes`.
+    Vendored,
+    /// The file is marked `linguist-documentation` in `.gitattributes`.
+    Documentation,
+    /// The file could not be read, for example because it was deleted or
+    /// is a broken symbolic link.
+    Unreadable,
+    /// The entry is a submodule, and submodules are not recursed into.
+    Submodule,
+    /// The entry is a submodule that is not initialized, so its files are
+    /// not checked out.
+    Uninitialized,
+}
+
+impl SkipReason {
+    /// Returns the name of the reason, as used in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SkipReason::Binary => "binary",
+            SkipReason::Generated => "generated",
+            SkipReason::Vendored => "vendored",
+            SkipReason::Documentation => "documentation",
+            SkipReason::Unreadable => "unreadable",
+            SkipReason::Submodule => "submodule",
+            SkipReason::Uninitialized => "uninitialized",
+        }
+    }
+}
+
+/// A file that was selected for counting but skipped.
+///
+/// Entries compare equal if their paths, reasons and error messages are equal.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedFile {
+    /// The path to the file.
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+    /// Why the file was not counted.
+    pub reason: SkipReason,
+    /// The error that prevented reading the file, for [`SkipReason::Unreadable`].
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_error"
+    )]
+    pub error: Option<Arc<GitlsfError>>,
+}
+
+impl SkippedFile {
+    /// Creates a new skipped file entry.
+    pub fn new(path: impl Into<PathBuf>, reason: SkipReason) -> Self {
+        Self {
+            path: path.into(),
+            reason,
+            error: None,
+        }
+    }
+
+    /// Creates an entry for a file that could not be read.
+    pub fn unreadable(path: impl Into<PathBuf>, error: GitlsfError) -> Self {
+        Self {
+            path: path.into(),
+            reason: SkipReason::Unreadable,
+            error: Some(Arc::new(error)),
         }
     }
 }
 
+impl PartialEq for SkippedFile {
+    fn eq(&self, other: &Self) -> bool {
+        let message = |file: &Self| file.error.as_ref().map(|e| e.to_string());
+
+        self.path == other.path && self.reason == other.reason && message(self) == message(other)
+    }
+}
+
+impl Eq for SkippedFile {}
+
+/// Serializes an error as its message.
+fn serialize_error<S: Serializer>(
+    error: &Option<Arc<GitlsfError>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match error {
+        Some(error) => serializer.serialize_str(&error.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serializes the submodule of a file like its path.
+fn serialize_submodule<S: Serializer>(
+    submodule: &Option<PathBuf>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match submodule {
+        Some(path) => serialize_path(path, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
 /// Summary of counting results for multiple files.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CountSummary {
     /// Individual file counts.
     pub files: Vec<FileCount>,
+    /// Files that were skipped rather than counted, sorted by path.
+    pub skipped: Vec<SkippedFile>,
     /// Total number of lines across all files.
     pub total_lines: usize,
+    /// Total number of code lines across all files.
+    pub total_code: usize,
+    /// Total number of comment lines across all files.
+    pub total_comment: usize,
+    /// Total number of blank lines across all files.
+    pub total_blank: usize,
     /// Total number of files counted.
     pub file_count: usize,
 }
@@ -48,19 +321,144 @@ pub struct CountSummary {
 impl CountSummary {
     /// Creates a new count summary from file counts.
     pub fn from_counts(files: Vec<FileCount>) -> Self {
-        let total_lines = files.iter().map(|f| f.lines).sum();
+        let totals = files
+            .iter()
+            .fold(LineStats::default(), |acc, f| acc + f.stats());
         let file_count = files.len();
 
         Self {
             files,
-            total_lines,
+            skipped: Vec::new(),
+            total_lines: totals.lines,
+            total_code: totals.code,
+            total_comment: totals.comment,
+            total_blank: totals.blank,
             file_count,
         }
     }
+
+    /// Adds skipped files to the summary.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::counter::{SkipReason, SkippedFile};
+    /// use gitlsf::{CountSummary, FileCount};
+    ///
+    /// let summary = CountSummary::from_counts(vec![FileCount::new("a.rs", 10)])
+    ///     .with_skipped(vec![SkippedFile::new("lib.so", SkipReason::Binary)]);
+    ///
+    /// assert_eq!(summary.file_count, 1);
+    /// assert_eq!(summary.skipped[0].reason, SkipReason::Binary);
+    /// ```
+    pub fn with_skipped(mut self, skipped: impl IntoIterator<Item = SkippedFile>) -> Self {
+        self.skipped.extend(skipped);
+        self.skipped
+            .sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+        self
+    }
+
+    /// Returns the files that could not be read.
+    pub fn unreadable(&self) -> impl Iterator<Item = &SkippedFile> {
+        self.skipped
+            .iter()
+            .filter(|file| file.reason == SkipReason::Unreadable)
+    }
+
+    /// Groups the counted files by language.
+    ///
+    /// Languages are sorted by total line count, largest first; files in
+    /// unknown languages are grouped together under `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::{CountSummary, FileCount, Language};
+    ///
+    /// let summary = CountSummary::from_counts(vec![
+    ///     FileCount::new("a.rs", 10).with_language(Some(Language::Rust)),
+    ///     FileCount::new("b.rs", 5).with_language(Some(Language::Rust)),
+    ///     FileCount::new("c.ts", 20).with_language(Some(Language::TypeScript)),
+    /// ]);
+    ///
+    /// let languages = summary.by_language();
+    /// assert_eq!(languages[0].language, Some(Language::TypeScript));
+    /// assert_eq!(languages[1].files, 2);
+    /// assert_eq!(languages[1].stats.lines, 15);
+    /// ```
+    pub fn by_language(&self) -> Vec<LanguageSummary> {
+        let mut groups: BTreeMap<Option<Language>, LanguageSummary> = BTreeMap::new();
+
+        for file in &self.files {
+            let group = groups
+                .entry(file.language)
+                .or_insert_with(|| LanguageSummary {
+                    language: file.language,
+                    ..LanguageSummary::default()
+                });
+            group.files += 1;
+            group.stats += file.stats();
+        }
+
+        let mut languages: Vec<LanguageSummary> = groups.into_values().collect();
+        languages.sort_by_key(|l| std::cmp::Reverse(l.stats.lines));
+        languages
+    }
+
+    /// Groups the files counted in submodules by submodule.
+    ///
+    /// Submodules are sorted by path. Files of the repository itself are
+    /// left out, and files of a nested submodule only count towards that
+    /// submodule.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::{CountSummary, FileCount};
+    ///
+    /// let mut vendored = FileCount::new("vendor/lib/src/lib.rs", 20);
+    /// vendored.submodule = Some("vendor/lib".into());
+    /// let summary = CountSummary::from_counts(vec![FileCount::new("src/main.rs", 10), vendored]);
+    ///
+    /// let submodules = summary.by_submodule();
+    /// assert_eq!(submodules.len(), 1);
+    /// assert_eq!(submodules[0].path, std::path::Path::new("vendor/lib"));
+    /// assert_eq!(submodules[0].stats.lines, 20);
+    /// ```
+    pub fn by_submodule(&self) -> Vec<SubmoduleSummary> {
+        let mut groups: BTreeMap<&Path, SubmoduleSummary> = BTreeMap::new();
+
+        for file in &self.files {
+            let Some(submodule) = &file.submodule else {
+                continue;
+            };
+            let group = groups.entry(submodule).or_insert_with(|| SubmoduleSummary {
+                path: submodule.clone(),
+                ..SubmoduleSummary::default()
+            });
+            group.files += 1;
+            group.stats += file.stats();
+        }
+
+        groups.into_values().collect()
+    }
+
+    /// Returns the total line statistics across all files.
+    pub fn totals(&self) -> LineStats {
+        LineStats {
+            lines: self.total_lines,
+            code: self.total_code,
+            comment: self.total_comment,
+            blank: self.total_blank,
+        }
+    }
 }
 
 /// Counts lines in a single file using fast byte-level scanning.
 ///
+/// Files of at least [`MMAP_THRESHOLD`] bytes are memory-mapped and scanned
+/// in byte ranges by several threads.
+///
 /// # Arguments
 ///
 /// * `base_path` - The base directory of the repository.
@@ -88,6 +486,20 @@ pub fn count_lines(base_path: impl AsRef<Path>, file_path: impl AsRef<Path>) ->
     let full_path = base.join(file);
 
     let mut f = File::open(&full_path).map_err(|e| GitlsfError::io(&full_path, e))?;
+    let size = f
+        .metadata()
+        .map_err(|e| GitlsfError::io(&full_path, e))?
+        .len();
+
+    if ReadStrategy::Auto.maps(size) {
+        let content = map_file(&f).map_err(|e| GitlsfError::io(&full_path, e))?;
+        let count: usize = content
+            .par_chunks(PARALLEL_CHUNK_SIZE)
+            .map(|chunk| memchr_iter(b'\n', chunk).count())
+            .sum();
+        let unterminated = content.last().is_some_and(|&b| b != b'\n');
+        return Ok(count + usize::from(unterminated));
+    }
 
     let mut buffer = [0u8; BUFFER_SIZE];
     let mut count = 0usize;
@@ -117,9 +529,295 @@ pub fn count_lines(base_path: impl AsRef<Path>, file_path: impl AsRef<Path>) ->
     Ok(count)
 }
 
+/// Counts and classifies the lines of a single file.
+///
+/// The language is detected from the path and the first bytes of the file
+/// (see [`detect`]); files in unknown languages have every non-blank line
+/// counted as code.
+///
+/// # Arguments
+///
+/// * `base_path` - The base directory of the repository.
+/// * `file_path` - The path to the file relative to the base directory.
+///
+/// # Returns
+///
+/// The line statistics of the file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::counter::count_file;
+///
+/// let count = count_file(".", "src/main.rs").unwrap();
+/// println!("Code: {}, comments: {}", count.code, count.comment);
+/// ```
+pub fn count_file(base_path: impl AsRef<Path>, file_path: impl AsRef<Path>) -> Result<FileCount> {
+    count_file_with_language(base_path, file_path, None)
+}
+
+/// Counts and classifies the lines of a single file in a known language.
+///
+/// Like [`count_file`], but `language` replaces the detected language when
+/// it is `Some`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+pub fn count_file_with_language(
+    base_path: impl AsRef<Path>,
+    file_path: impl AsRef<Path>,
+    language: Option<Language>,
+) -> Result<FileCount> {
+    let file = file_path.as_ref();
+    let full_path = base_path.as_ref().join(file);
+
+    count_path(&full_path, file, language, false, ReadStrategy::Auto)
+        .map(|count| count.expect("binary content is counted"))
+        .map_err(|e| GitlsfError::io(&full_path, e))
+}
+
+/// Counts and classifies the lines of content read from `reader`.
+///
+/// This is the counting core shared by working-tree files and Git blobs;
+/// `path` is used for language detection and recorded in the result.
+///
+/// # Errors
+///
+/// Returns the underlying IO error if reading fails.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::counter::count_reader;
+/// use gitlsf::Language;
+///
+/// let mut content: &[u8] = b"# comment\nprint('hi')\n";
+/// let count = count_reader("hello.py", &mut content).unwrap();
+///
+/// assert_eq!(count.language, Some(Language::Python));
+/// assert_eq!((count.code, count.comment), (1, 1));
+/// ```
+pub fn count_reader<R: Read + ?Sized>(
+    path: impl AsRef<Path>,
+    reader: &mut R,
+) -> std::io::Result<FileCount> {
+    count_reader_with_language(path, reader, None)
+}
+
+/// Counts and classifies the lines of content read from `reader` in a known language.
+///
+/// Like [`count_reader`], but `language` replaces the detected language
+/// when it is `Some`.
+///
+/// # Errors
+///
+/// Returns the underlying IO error if reading fails.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::counter::count_reader_with_language;
+/// use gitlsf::Language;
+///
+/// let mut content: &[u8] = b"# comment\n";
+/// let count = count_reader_with_language("notes.txt", &mut content, Some(Language::Shell))
+///     .unwrap();
+///
+/// assert_eq!(count.language, Some(Language::Shell));
+/// assert_eq!(count.comment, 1);
+/// ```
+pub fn count_reader_with_language<R: Read + ?Sized>(
+    path: impl AsRef<Path>,
+    reader: &mut R,
+    language: Option<Language>,
+) -> std::io::Result<FileCount> {
+    count_content(path.as_ref(), reader, language, false)
+        .map(|count| count.expect("binary content is counted"))
+}
+
+/// Counts and classifies the lines of content read from `reader`, unless it is binary.
+///
+/// Like [`count_reader_with_language`], but returns `None` if [`is_binary`]
+/// holds for the first bytes of the content.
+///
+/// # Errors
+///
+/// Returns the underlying IO error if reading fails.
+pub(crate) fn count_text_reader<R: Read + ?Sized>(
+    path: &Path,
+    reader: &mut R,
+    language: Option<Language>,
+) -> std::io::Result<Option<FileCount>> {
+    count_content(path, reader, language, true)
+}
+
+/// Returns `true` if content looks binary.
+///
+/// Like Git, this checks for a NUL byte in the first 8000 bytes.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::counter::is_binary;
+///
+/// assert!(is_binary(b"\x7fELF\x02\x01\x01\x00"));
+/// assert!(!is_binary("fn main() {}\n".as_bytes()));
+/// ```
+pub fn is_binary(content: &[u8]) -> bool {
+    memchr(0, &content[..content.len().min(BINARY_SNIFF_LENGTH)]).is_some()
+}
+
+/// Counts a file in the working tree, reading it as `strategy` selects.
+///
+/// `path` is used for language detection and recorded in the result.
+fn count_path(
+    full_path: &Path,
+    path: &Path,
+    language: Option<Language>,
+    skip_binary: bool,
+    strategy: ReadStrategy,
+) -> std::io::Result<Option<FileCount>> {
+    let mut file = File::open(full_path)?;
+
+    if strategy.maps(file.metadata()?.len()) {
+        let content = map_file(&file)?;
+        Ok(count_slice(path, &content, language, skip_binary))
+    } else {
+        count_content(path, &mut file, language, skip_binary)
+    }
+}
+
+/// The counting core for content in memory, optionally skipping binary content.
+///
+/// Equivalent to [`count_content`] without copying the content into a buffer.
+fn count_slice(
+    path: &Path,
+    content: &[u8],
+    language: Option<Language>,
+    skip_binary: bool,
+) -> Option<FileCount> {
+    if skip_binary && is_binary(content) {
+        return None;
+    }
+
+    // The end of content is only searched for modelines when it fits the
+    // buffer of `count_content`, so both detect the same language
+    let language = language.or_else(|| {
+        if content.len() < BUFFER_SIZE {
+            detect_whole(path, content)
+        } else {
+            detect(path, &content[..BUFFER_SIZE])
+        }
+    });
+    let syntax = language.map_or(&Syntax::PLAIN, Language::syntax);
+
+    Some(FileCount::with_stats(path, classify_parallel(content, syntax)).with_language(language))
+}
+
+/// The counting core, optionally skipping binary content.
+fn count_content<R: Read + ?Sized>(
+    path: &Path,
+    reader: &mut R,
+    language: Option<Language>,
+    skip_binary: bool,
+) -> std::io::Result<Option<FileCount>> {
+    let mut buffer = [0u8; BUFFER_SIZE];
+
+    // Short reads are possible, so fill the buffer, which holds the whole
+    // content of small files for detecting their language
+    let mut bytes_read = 0;
+    let mut whole = false;
+    while bytes_read < BUFFER_SIZE {
+        match reader.read(&mut buffer[bytes_read..])? {
+            0 => {
+                whole = true;
+                break;
+            }
+            n => bytes_read += n,
+        }
+    }
+
+    if skip_binary && is_binary(&buffer[..bytes_read]) {
+        return Ok(None);
+    }
+
+    let head = &buffer[..bytes_read];
+    let language = language.or_else(|| {
+        if whole {
+            detect_whole(path, head)
+        } else {
+            detect(path, head)
+        }
+    });
+    let syntax = language.map_or(&Syntax::PLAIN, Language::syntax);
+    let mut classifier = LineClassifier::new(syntax);
+
+    while bytes_read > 0 {
+        classifier.update(&buffer[..bytes_read]);
+        bytes_read = reader.read(&mut buffer)?;
+    }
+
+    Ok(Some(
+        FileCount::with_stats(path, classifier.finish()).with_language(language),
+    ))
+}
+
+/// The outcome of counting a file in the working tree.
+enum Outcome {
+    /// The file was counted.
+    Counted(FileCount),
+    /// The file was binary or could not be read.
+    Skipped(SkippedFile),
+    /// The path is a directory, such as a submodule, and is not a file to count.
+    Directory,
+}
+
+/// Counts a file in the working tree, unless its content is binary.
+fn count_text_file(
+    base: &Path,
+    file: &Path,
+    language: Option<Language>,
+    strategy: ReadStrategy,
+) -> Outcome {
+    let full_path = base.join(file);
+
+    let result = count_path(&full_path, file, language, true, strategy)
+        .map_err(|e| GitlsfError::io(&full_path, e));
+
+    match result {
+        Ok(Some(count)) => Outcome::Counted(count),
+        Ok(None) => Outcome::Skipped(SkippedFile::new(file, SkipReason::Binary)),
+        Err(_) if full_path.is_dir() => Outcome::Directory,
+        Err(e) => Outcome::Skipped(SkippedFile::unreadable(file, e)),
+    }
+}
+
+/// Collects the outcomes of counting files into a summary.
+fn summarize(outcomes: Vec<Outcome>) -> CountSummary {
+    let mut counts = Vec::with_capacity(outcomes.len());
+    let mut skipped = Vec::new();
+
+    for outcome in outcomes {
+        match outcome {
+            Outcome::Counted(count) => counts.push(count),
+            Outcome::Skipped(file) => skipped.push(file),
+            Outcome::Directory => {}
+        }
+    }
+
+    CountSummary::from_counts(counts).with_skipped(skipped)
+}
+
 /// Counts lines in multiple files in parallel.
 ///
-/// Files that cannot be read are skipped.
+/// Binary files and files that cannot be read are skipped and listed in
+/// [`CountSummary::skipped`], the latter with their error. Directories, such
+/// as submodules, are ignored.
 ///
 /// # Arguments
 ///
@@ -142,27 +840,94 @@ pub fn count_lines(base_path: impl AsRef<Path>, file_path: impl AsRef<Path>) ->
 pub fn count_lines_parallel<I, S>(base_path: impl AsRef<Path>, files: I) -> CountSummary
 where
     I: IntoIterator<Item = S>,
-    S: AsRef<str>,
+    S: AsRef<Path>,
 {
-    let base = base_path.as_ref();
-    let files: Vec<String> = files.into_iter().map(|f| f.as_ref().to_string()).collect();
+    count_lines_parallel_with_languages(base_path, files, |_| None)
+}
+
+/// Counts lines in multiple files in parallel, with per-file language overrides.
+///
+/// Like [`count_lines_parallel`], but `language` is called with each file
+/// path and a `Some` result replaces the detected language.
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::counter::count_lines_parallel_with_languages;
+/// use gitlsf::Language;
+///
+/// let files = vec!["include/api.h", "src/main.c"];
+/// let summary = count_lines_parallel_with_languages(".", files, |path| {
+///     path.extension().is_some_and(|ext| ext == "h").then_some(Language::Cpp)
+/// });
+/// ```
+pub fn count_lines_parallel_with_languages<I, S, F>(
+    base_path: impl AsRef<Path>,
+    files: I,
+    language: F,
+) -> CountSummary
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<Path>,
+    F: Fn(&Path) -> Option<Language> + Sync,
+{
+    count_files_parallel(base_path.as_ref(), files, language, ReadStrategy::Auto)
+}
 
-    let counts: Vec<FileCount> = files
+/// Counts lines in multiple files in parallel, reading them with a given strategy.
+///
+/// Like [`count_lines_parallel`], which uses [`ReadStrategy::Auto`]. The
+/// results do not depend on the strategy, only the time taken does.
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::counter::{count_lines_parallel_with_strategy, ReadStrategy};
+///
+/// let files = vec!["dump.sql", "src/main.rs"];
+/// let summary = count_lines_parallel_with_strategy(".", files, ReadStrategy::Mapped);
+/// ```
+pub fn count_lines_parallel_with_strategy<I, S>(
+    base_path: impl AsRef<Path>,
+    files: I,
+    strategy: ReadStrategy,
+) -> CountSummary
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<Path>,
+{
+    count_files_parallel(base_path.as_ref(), files, |_| None, strategy)
+}
+
+/// The parallel counting core.
+fn count_files_parallel<I, S, F>(
+    base: &Path,
+    files: I,
+    language: F,
+    strategy: ReadStrategy,
+) -> CountSummary
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<Path>,
+    F: Fn(&Path) -> Option<Language> + Sync,
+{
+    let files: Vec<PathBuf> = files
+        .into_iter()
+        .map(|f| f.as_ref().to_path_buf())
+        .collect();
+
+    let outcomes: Vec<Outcome> = files
         .par_iter()
-        .filter_map(|file_path| {
-            match count_lines(base, file_path) {
-                Ok(lines) => Some(FileCount::new(file_path.clone(), lines)),
-                Err(_) => None, // Skip files that can't be read
-            }
-        })
+        .map(|file_path| count_text_file(base, file_path, language(file_path), strategy))
         .collect();
 
-    CountSummary::from_counts(counts)
+    summarize(outcomes)
 }
 
 /// Counts lines in multiple files sequentially.
 ///
 /// This is useful for testing or when parallel processing is not desired.
+/// Files are skipped like in [`count_lines_parallel`].
 ///
 /// # Arguments
 ///
@@ -175,20 +940,20 @@ where
 pub fn count_lines_sequential<I, S>(base_path: impl AsRef<Path>, files: I) -> CountSummary
 where
     I: IntoIterator<Item = S>,
-    S: AsRef<str>,
+    S: AsRef<Path>,
 {
     let base = base_path.as_ref();
-    let files: Vec<String> = files.into_iter().map(|f| f.as_ref().to_string()).collect();
+    let files: Vec<PathBuf> = files
+        .into_iter()
+        .map(|f| f.as_ref().to_path_buf())
+        .collect();
 
-    let counts: Vec<FileCount> = files
+    let outcomes: Vec<Outcome> = files
         .iter()
-        .filter_map(|file_path| match count_lines(base, file_path) {
-            Ok(lines) => Some(FileCount::new(file_path.clone(), lines)),
-            Err(_) => None,
-        })
+        .map(|file_path| count_text_file(base, file_path, None, ReadStrategy::Auto))
         .collect();
 
-    CountSummary::from_counts(counts)
+    summarize(outcomes)
 }
 
 #[cfg(test)]
@@ -260,6 +1025,80 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_count_file_classifies_lines() {
+        let temp_dir = setup_test_files();
+        fs::write(
+            temp_dir.path().join("src/lib.rs"),
+            "//! Docs\n\n/* block\n   comment */\npub fn f() {}\n",
+        )
+        .unwrap();
+
+        let count = count_file(temp_dir.path(), "src/lib.rs").unwrap();
+
+        assert_eq!(count.path, Path::new("src/lib.rs"));
+        assert_eq!(count.language, Some(Language::Rust));
+        assert_eq!(count.lines, 5);
+        assert_eq!(count.code, 1);
+        assert_eq!(count.comment, 3);
+        assert_eq!(count.blank, 1);
+    }
+
+    #[test]
+    fn test_count_file_unknown_language() {
+        let temp_dir = setup_test_files();
+        fs::write(temp_dir.path().join("notes.txt"), "// text\n\nmore\n").unwrap();
+
+        let count = count_file(temp_dir.path(), "notes.txt").unwrap();
+
+        assert_eq!(count.language, None);
+        assert_eq!((count.code, count.comment, count.blank), (2, 0, 1));
+    }
+
+    #[test]
+    fn test_count_file_detects_shebang() {
+        let temp_dir = setup_test_files();
+        fs::write(
+            temp_dir.path().join("deploy"),
+            "#!/usr/bin/env bash\n# deploy\necho hi\n",
+        )
+        .unwrap();
+
+        let count = count_file(temp_dir.path(), "deploy").unwrap();
+
+        assert_eq!(count.language, Some(Language::Shell));
+        assert_eq!((count.code, count.comment, count.blank), (1, 2, 0));
+    }
+
+    #[test]
+    fn test_count_lines_parallel_with_languages() {
+        let temp_dir = setup_test_files();
+        fs::write(temp_dir.path().join("header.h"), "// c++\nclass A {};\n").unwrap();
+
+        let summary = count_lines_parallel_with_languages(
+            temp_dir.path(),
+            ["header.h", "src/main.rs"],
+            |path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "h")
+                    .then_some(Language::Cpp)
+            },
+        );
+        let header = summary
+            .files
+            .iter()
+            .find(|f| f.path == Path::new("header.h"))
+            .unwrap();
+        let main = summary
+            .files
+            .iter()
+            .find(|f| f.path == Path::new("src/main.rs"))
+            .unwrap();
+
+        assert_eq!(header.language, Some(Language::Cpp));
+        assert_eq!(main.language, Some(Language::Rust));
+    }
+
     #[test]
     fn test_count_lines_parallel() {
         let temp_dir = setup_test_files();
@@ -274,6 +1113,42 @@ mod tests {
 
         assert_eq!(summary.file_count, 3);
         assert_eq!(summary.total_lines, 1 + 3 + 3);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.skipped[0].path, Path::new("nonexistent.txt"));
+        assert_eq!(summary.skipped[0].reason, SkipReason::Unreadable);
+        assert!(matches!(
+            summary.skipped[0].error.as_deref(),
+            Some(GitlsfError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn test_count_lines_parallel_ignores_directories() {
+        let temp_dir = setup_test_files();
+
+        let summary = count_lines_parallel(temp_dir.path(), ["src", "one_line.txt"]);
+
+        assert_eq!(summary.file_count, 1);
+        assert!(summary.skipped.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_count_lines_sequential_reports_broken_symlink() {
+        let temp_dir = setup_test_files();
+        std::os::unix::fs::symlink("missing.rs", temp_dir.path().join("link.rs")).unwrap();
+
+        let summary = count_lines_sequential(temp_dir.path(), ["link.rs"]);
+
+        assert_eq!(summary.unreadable().count(), 1);
+        assert!(
+            summary.skipped[0]
+                .error
+                .as_ref()
+                .unwrap()
+                .to_string()
+                .contains("link.rs")
+        );
     }
 
     #[test]
@@ -287,10 +1162,135 @@ mod tests {
         assert_eq!(summary.total_lines, 4);
     }
 
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"\0"));
+        assert!(is_binary(b"text\0more"));
+        assert!(!is_binary(b""));
+        assert!(!is_binary("héllo\n".as_bytes()));
+
+        // Only the first 8000 bytes are inspected, as in Git
+        let mut late_nul = vec![b'a'; BINARY_SNIFF_LENGTH];
+        late_nul.push(0);
+        assert!(!is_binary(&late_nul));
+    }
+
+    #[test]
+    fn test_count_lines_parallel_skips_binary() {
+        let temp_dir = setup_test_files();
+        fs::write(temp_dir.path().join("lib.so"), b"\x7fELF\x02\0\0\n\n").unwrap();
+
+        let summary = count_lines_parallel(temp_dir.path(), ["lib.so", "one_line.txt"]);
+
+        assert_eq!(summary.file_count, 1);
+        assert_eq!(
+            summary.skipped,
+            [SkippedFile::new("lib.so", SkipReason::Binary)]
+        );
+
+        let summary = count_lines_sequential(temp_dir.path(), ["lib.so", "one_line.txt"]);
+        assert_eq!(summary.skipped.len(), 1);
+    }
+
+    #[test]
+    fn test_count_text_reader_short_reads() {
+        // Yields one byte per read, so the NUL byte is not in the first read
+        struct Trickle<'a>(&'a [u8]);
+
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(1);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let binary = count_text_reader(Path::new("data"), &mut Trickle(b"ab\0c\n"), None).unwrap();
+        assert_eq!(binary, None);
+
+        let text = count_text_reader(Path::new("text"), &mut Trickle(b"a\nb\n"), None).unwrap();
+        assert_eq!(text.map(|count| count.lines), Some(2));
+    }
+
+    #[test]
+    fn test_read_strategies_agree() {
+        let temp_dir = setup_test_files();
+        let path = temp_dir.path();
+
+        // Larger than the mapping threshold, with lines spanning buffer boundaries
+        let mut large = String::from("#!/usr/bin/env python3\n");
+        while large.len() as u64 <= MMAP_THRESHOLD {
+            large.push_str("# a comment\n\nvalue = 'x' * 40  # trailing comment\n");
+        }
+        large.push_str("no_trailing_newline = 1");
+        fs::write(path.join("generate"), &large).unwrap();
+        fs::write(path.join("data.bin"), b"\x7fELF\x02\0\0\n").unwrap();
+
+        let files = [
+            "generate",
+            "data.bin",
+            "empty.txt",
+            "three_lines.txt",
+            "missing.txt",
+        ];
+        let auto = count_lines_parallel_with_strategy(path, files, ReadStrategy::Auto);
+        let buffered = count_lines_parallel_with_strategy(path, files, ReadStrategy::Buffered);
+        let mapped = count_lines_parallel_with_strategy(path, files, ReadStrategy::Mapped);
+
+        assert_eq!(auto.files, buffered.files);
+        assert_eq!(mapped.files, buffered.files);
+        assert_eq!(mapped.skipped, buffered.skipped);
+        assert_eq!(mapped.skipped.len(), 2);
+        assert_eq!(mapped.files[0].language, Some(Language::Python));
+
+        let lines = count_lines(path, "generate").unwrap();
+        assert_eq!(lines, large.lines().count());
+        assert_eq!(mapped.files[0].lines, lines);
+    }
+
+    #[test]
+    fn test_trailing_modeline_only_in_small_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+
+        let small = "echo 1\n".repeat(6) + "# vim: ft=sh\n";
+        let large = "echo 1\n".repeat(BUFFER_SIZE / 7 + 1) + "# vim: ft=sh\n";
+        fs::write(path.join("small"), &small).unwrap();
+        fs::write(path.join("large"), &large).unwrap();
+
+        for strategy in [ReadStrategy::Buffered, ReadStrategy::Mapped] {
+            let summary = count_lines_parallel_with_strategy(path, ["large", "small"], strategy);
+            let languages: Vec<_> = summary.files.iter().map(|file| file.language).collect();
+            assert_eq!(languages, [None, Some(Language::Shell)], "{strategy:?}");
+        }
+    }
+
+    #[test]
+    fn test_count_lines_in_parallel_ranges() {
+        let temp_dir = TempDir::new().unwrap();
+        let line = "x".repeat(99) + "\n";
+        let lines = 2 * PARALLEL_CHUNK_SIZE / line.len() + 10;
+
+        let mut content = line.repeat(lines);
+        fs::write(temp_dir.path().join("terminated.txt"), &content).unwrap();
+        content.push_str("last");
+        fs::write(temp_dir.path().join("unterminated.txt"), &content).unwrap();
+
+        assert_eq!(
+            count_lines(temp_dir.path(), "terminated.txt").unwrap(),
+            lines
+        );
+        assert_eq!(
+            count_lines(temp_dir.path(), "unterminated.txt").unwrap(),
+            lines + 1
+        );
+    }
+
     #[test]
     fn test_file_count_new() {
         let fc = FileCount::new("test.rs", 100);
-        assert_eq!(fc.path, "test.rs");
+        assert_eq!(fc.path, Path::new("test.rs"));
         assert_eq!(fc.lines, 100);
     }
 
@@ -306,5 +1306,55 @@ mod tests {
 
         assert_eq!(summary.file_count, 3);
         assert_eq!(summary.total_lines, 60);
+        assert_eq!(summary.total_code, 60);
+        assert_eq!(summary.total_comment, 0);
+        assert_eq!(summary.total_blank, 0);
+    }
+
+    #[test]
+    fn test_count_summary_by_language() {
+        let counts = vec![
+            FileCount::new("a.rs", 10).with_language(Some(Language::Rust)),
+            FileCount::new("b.py", 30).with_language(Some(Language::Python)),
+            FileCount::new("c.rs", 5).with_language(Some(Language::Rust)),
+            FileCount::new("notes.txt", 1),
+        ];
+
+        let languages = CountSummary::from_counts(counts).by_language();
+
+        assert_eq!(languages.len(), 3);
+        assert_eq!(languages[0].language, Some(Language::Python));
+        assert_eq!(languages[1].language, Some(Language::Rust));
+        assert_eq!(languages[1].files, 2);
+        assert_eq!(languages[1].stats.lines, 15);
+        assert_eq!(languages[2].name(), "Other");
+    }
+
+    #[test]
+    fn test_count_summary_totals() {
+        let counts = vec![
+            FileCount::with_stats(
+                "a.rs",
+                LineStats {
+                    lines: 4,
+                    code: 2,
+                    comment: 1,
+                    blank: 1,
+                },
+            ),
+            FileCount::new("b.rs", 3),
+        ];
+
+        let summary = CountSummary::from_counts(counts);
+
+        assert_eq!(
+            summary.totals(),
+            LineStats {
+                lines: 7,
+                code: 5,
+                comment: 1,
+                blank: 1,
+            }
+        );
     }
 }
@@ -0,0 +1,305 @@
+//! Language detection module.
+//!
+//! This module decides which [`Language`] a file is written in, using its
+//! path together with the first few lines of its content.
+//!
+//! Like Vim, a Vim modeline is also looked for in the last lines of a file,
+//! but only when its whole content is at hand, through [`detect_whole`]. The
+//! line counter does so for files smaller than its 64 KiB read buffer, which
+//! it reads in full before counting; the end of larger files is not searched.
+
+use std::path::Path;
+
+use crate::language::Language;
+
+/// Number of leading lines searched for editor modelines, and of trailing
+/// lines searched for Vim modelines.
+const MODELINE_LINES: usize = 5;
+
+/// Detects the language of a file.
+///
+/// Detection strategies are tried in order, and the first match wins:
+///
+/// 1. An Emacs or Vim modeline in the first five lines of `head`
+/// 2. The exact filename (`Makefile`, `Dockerfile`, `CMakeLists.txt`)
+/// 3. The interpreter named on a `#!` shebang line
+/// 4. The file extension
+///
+/// # Arguments
+///
+/// * `path` - The path to the file.
+/// * `head` - The beginning of the file content (may be empty).
+///
+/// # Returns
+///
+/// The detected language, or `None` if the language is unknown.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::detect::detect;
+/// use gitlsf::language::Language;
+///
+/// assert_eq!(detect("src/main.rs", b""), Some(Language::Rust));
+/// assert_eq!(detect("bin/deploy", b"#!/usr/bin/env bash\n"), Some(Language::Shell));
+/// assert_eq!(detect("notes.txt", b""), None);
+/// ```
+pub fn detect(path: impl AsRef<Path>, head: &[u8]) -> Option<Language> {
+    detect_with_tail(path.as_ref(), head, None)
+}
+
+/// Detects the language of a file from its whole content.
+///
+/// Like [`detect`], but a Vim modeline in the last five lines of `content`
+/// is found as well, after one in the first five lines.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::detect::{detect, detect_whole};
+/// use gitlsf::language::Language;
+///
+/// let content = b"1\n2\n3\n4\n5\n6\n# vim: ft=sh\n";
+/// assert_eq!(detect_whole("notes.txt", content), Some(Language::Shell));
+/// assert_eq!(detect("notes.txt", content), None);
+/// ```
+pub fn detect_whole(path: impl AsRef<Path>, content: &[u8]) -> Option<Language> {
+    detect_with_tail(path.as_ref(), content, Some(content))
+}
+
+/// Tries the detection strategies of [`detect`], with a trailing modeline in
+/// `tail`, if given, after a leading one.
+fn detect_with_tail(path: &Path, head: &[u8], tail: Option<&[u8]>) -> Option<Language> {
+    from_modeline(head)
+        .or_else(|| tail.and_then(from_trailing_modeline))
+        .or_else(|| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(Language::from_filename)
+        })
+        .or_else(|| from_shebang(head))
+        .or_else(|| Language::from_path(path))
+}
+
+/// Detects a language from a `#!` shebang on the first line.
+///
+/// Both direct interpreter paths (`#!/bin/sh`) and `env` invocations
+/// (`#!/usr/bin/env -S python3 -u`) are understood. Version suffixes such
+/// as `python3.12` or `perl5` are ignored when the exact name is unknown.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::detect::from_shebang;
+/// use gitlsf::language::Language;
+///
+/// assert_eq!(from_shebang(b"#!/usr/bin/python3.12\n"), Some(Language::Python));
+/// assert_eq!(from_shebang(b"#!/usr/bin/env node\n"), Some(Language::JavaScript));
+/// assert_eq!(from_shebang(b"print('hi')\n"), None);
+/// ```
+pub fn from_shebang(head: &[u8]) -> Option<Language> {
+    let line = first_line(head).strip_prefix(b"#!")?;
+    let line = std::str::from_utf8(line).ok()?;
+    let mut words = line.split_whitespace();
+
+    let mut interpreter = basename(words.next()?);
+    if interpreter == "env" {
+        interpreter = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+        interpreter = basename(interpreter);
+    }
+
+    Language::from_name(interpreter).or_else(|| {
+        let unversioned = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        Language::from_name(unversioned)
+    })
+}
+
+/// Detects a language from an Emacs or Vim modeline in the first lines of `head`.
+///
+/// Recognised forms:
+///
+/// - Emacs: `-*- mode: python -*-` and the short form `-*- python -*-`
+/// - Vim: `vim: set ft=python:`, `vi: filetype=sh` and `ex: syntax=c`
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::detect::from_modeline;
+/// use gitlsf::language::Language;
+///
+/// assert_eq!(from_modeline(b"// -*- mode: c++ -*-\n"), Some(Language::Cpp));
+/// assert_eq!(from_modeline(b"# vim: set ft=ruby ts=2:\n"), Some(Language::Ruby));
+/// ```
+pub fn from_modeline(head: &[u8]) -> Option<Language> {
+    head.split(|&b| b == b'\n')
+        .take(MODELINE_LINES)
+        .filter_map(|line| std::str::from_utf8(line).ok())
+        .find_map(|line| emacs_modeline(line).or_else(|| vim_modeline(line)))
+}
+
+/// Detects a language from a Vim modeline in the last lines of `content`.
+///
+/// Only Vim reads modelines at the end of a file; Emacs `-*- ... -*-` lines
+/// are only recognised at the start by [`from_modeline`].
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::detect::from_trailing_modeline;
+/// use gitlsf::language::Language;
+///
+/// let content = b"puts 1\n# vim: set ft=ruby:\n";
+/// assert_eq!(from_trailing_modeline(content), Some(Language::Ruby));
+/// ```
+pub fn from_trailing_modeline(content: &[u8]) -> Option<Language> {
+    let content = content.strip_suffix(b"\n").unwrap_or(content);
+
+    content
+        .rsplit(|&b| b == b'\n')
+        .take(MODELINE_LINES)
+        .filter_map(|line| std::str::from_utf8(line).ok())
+        .find_map(vim_modeline)
+}
+
+/// Parses an Emacs `-*- ... -*-` modeline.
+fn emacs_modeline(line: &str) -> Option<Language> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (inner, _) = rest.split_once("-*-")?;
+
+    let mode = if inner.contains(':') {
+        inner.split(';').find_map(|var| {
+            let (key, value) = var.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case("mode")
+                .then(|| value.trim())
+        })?
+    } else {
+        inner.trim()
+    };
+
+    Language::from_name(mode)
+}
+
+/// Parses a Vim `vim:`, `vi:` or `ex:` modeline.
+fn vim_modeline(line: &str) -> Option<Language> {
+    let options = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(pos, _)| *pos == 0 || line.as_bytes()[pos - 1].is_ascii_whitespace())
+            .map(|(pos, _)| &line[pos + marker.len()..])
+    })?;
+
+    options
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .filter_map(|option| option.split_once('='))
+        .find(|(key, _)| matches!(*key, "ft" | "filetype" | "syn" | "syntax"))
+        .and_then(|(_, value)| Language::from_name(value))
+}
+
+/// Returns the first line of `bytes`, without the trailing newline.
+fn first_line(bytes: &[u8]) -> &[u8] {
+    bytes.split(|&b| b == b'\n').next().unwrap_or_default()
+}
+
+/// Returns the last component of a `/`-separated path.
+fn basename(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_by_extension() {
+        assert_eq!(detect("lib/app.ts", b""), Some(Language::TypeScript));
+        assert_eq!(detect("include/foo.hpp", b""), Some(Language::Cpp));
+    }
+
+    #[test]
+    fn test_detect_by_filename() {
+        assert_eq!(detect("Makefile", b""), Some(Language::Makefile));
+        assert_eq!(detect("docker/Dockerfile", b""), Some(Language::Dockerfile));
+        assert_eq!(detect("src/CMakeLists.txt", b""), Some(Language::CMake));
+    }
+
+    #[test]
+    fn test_detect_by_shebang() {
+        assert_eq!(
+            detect("bin/tool", b"#!/bin/sh\nexit 0\n"),
+            Some(Language::Shell)
+        );
+        assert_eq!(
+            detect("scripts/run", b"#!/usr/bin/env -S python3 -u\n"),
+            Some(Language::Python)
+        );
+        assert_eq!(
+            detect("scripts/run", b"#!/usr/bin/env LANG=C perl -w\n"),
+            Some(Language::Perl)
+        );
+        assert_eq!(detect("scripts/run", b"#!/opt/unknown\n"), None);
+    }
+
+    #[test]
+    fn test_shebang_does_not_override_filename() {
+        assert_eq!(
+            detect("Makefile", b"#!/usr/bin/make -f\n"),
+            Some(Language::Makefile)
+        );
+    }
+
+    #[test]
+    fn test_modeline_overrides_extension() {
+        assert_eq!(
+            detect("include/api.h", b"/* -*- C++ -*- */\n"),
+            Some(Language::Cpp)
+        );
+        assert_eq!(
+            detect("config.inc", b"\n\n# vim: set filetype=sh :\n"),
+            Some(Language::Shell)
+        );
+    }
+
+    #[test]
+    fn test_emacs_modeline_variables() {
+        assert_eq!(
+            from_modeline(b"# -*- coding: utf-8; mode: python; -*-\n"),
+            Some(Language::Python)
+        );
+        assert_eq!(from_modeline(b"# -*- coding: utf-8 -*-\n"), None);
+    }
+
+    #[test]
+    fn test_vim_modeline_requires_marker_boundary() {
+        assert_eq!(from_modeline(b"// see devi: ft=ruby\n"), None);
+        assert_eq!(from_modeline(b"// vi: ft=ruby\n"), Some(Language::Ruby));
+        assert_eq!(from_modeline(b"// vim: ts=4 sw=4\n"), None);
+    }
+
+    #[test]
+    fn test_modeline_only_in_first_lines() {
+        let head = b"1\n2\n3\n4\n5\n# vim: ft=python\n";
+        assert_eq!(from_modeline(head), None);
+    }
+
+    #[test]
+    fn test_trailing_modeline() {
+        let content = b"# vim: ft=python\n1\n2\n3\n4\n5\n# vim: ft=ruby\n1\n2\n3\n4\n";
+        assert_eq!(from_trailing_modeline(content), Some(Language::Ruby));
+        assert_eq!(
+            from_trailing_modeline(b"# vim: ft=ruby\n1\n2\n3\n4\n5\n"),
+            None
+        );
+        assert_eq!(from_trailing_modeline(b"x\n/* -*- C++ -*- */\n"), None);
+
+        // A leading modeline wins, and only whole content is searched at the end
+        assert_eq!(detect_whole("a.txt", content), Some(Language::Python));
+        assert_eq!(
+            detect_whole("a.c", b"1\n2\n3\n4\n5\n6\n// vi: ft=cpp\n"),
+            Some(Language::Cpp)
+        );
+        assert_eq!(
+            detect("a.c", b"1\n2\n3\n4\n5\n6\n// vi: ft=cpp\n"),
+            Some(Language::C)
+        );
+    }
+}
@@ -0,0 +1,387 @@
+//! Line count comparison module.
+//!
+//! This module compares two [`CountSummary`] values, typically taken at two
+//! revisions, and reports which files were added, removed or changed along
+//! with their line deltas.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::counter::{CountSummary, FileCount};
+use crate::output::quote_path;
+
+/// How a file differs between the two summaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileStatus {
+    /// The file only exists in the new summary.
+    Added,
+    /// The file only exists in the old summary.
+    Removed,
+    /// The file exists in both summaries with a different line count.
+    Changed,
+}
+
+impl FileStatus {
+    /// Returns a single-letter code for the status, as used by `git diff --name-status`.
+    pub fn code(self) -> char {
+        match self {
+            FileStatus::Added => 'A',
+            FileStatus::Removed => 'D',
+            FileStatus::Changed => 'M',
+        }
+    }
+}
+
+/// The line count change of a single file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDelta {
+    /// The path to the file.
+    pub path: PathBuf,
+    /// How the file differs.
+    pub status: FileStatus,
+    /// The number of lines before (zero for added files).
+    pub old_lines: usize,
+    /// The number of lines after (zero for removed files).
+    pub new_lines: usize,
+}
+
+impl FileDelta {
+    /// Returns the signed change in line count.
+    pub fn delta(&self) -> i64 {
+        self.new_lines as i64 - self.old_lines as i64
+    }
+}
+
+/// The line count change of all files below a directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectoryDelta {
+    /// The directory path quoted by [`quote_path`], or `.` for files at the
+    /// top level.
+    pub path: String,
+    /// The number of lines before.
+    pub old_lines: usize,
+    /// The number of lines after.
+    pub new_lines: usize,
+}
+
+impl DirectoryDelta {
+    /// Returns the signed change in line count.
+    pub fn delta(&self) -> i64 {
+        self.new_lines as i64 - self.old_lines as i64
+    }
+}
+
+/// The difference between two count summaries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CountDiff {
+    /// Files that were added, removed or changed, sorted by path.
+    pub files: Vec<FileDelta>,
+    /// Directory totals, keyed by the full parent directory of each file.
+    directories: BTreeMap<OsString, DirectoryDelta>,
+    /// Total number of lines before.
+    pub old_total: usize,
+    /// Total number of lines after.
+    pub new_total: usize,
+}
+
+impl CountDiff {
+    /// Compares two summaries.
+    ///
+    /// Files are matched by path. A file present in both summaries with the
+    /// same line count is considered unchanged and is not listed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::diff::{CountDiff, FileStatus};
+    /// use gitlsf::{CountSummary, FileCount};
+    ///
+    /// let old = CountSummary::from_counts(vec![
+    ///     FileCount::new("src/lib.rs", 10),
+    ///     FileCount::new("src/old.rs", 5),
+    /// ]);
+    /// let new = CountSummary::from_counts(vec![
+    ///     FileCount::new("src/lib.rs", 12),
+    ///     FileCount::new("src/new.rs", 7),
+    /// ]);
+    ///
+    /// let diff = CountDiff::between(&old, &new);
+    /// assert_eq!(diff.files.len(), 3);
+    /// assert_eq!(diff.files[0].status, FileStatus::Changed);
+    /// assert_eq!(diff.delta(), 4);
+    /// ```
+    pub fn between(old: &CountSummary, new: &CountSummary) -> Self {
+        let old_files: HashMap<&Path, &FileCount> =
+            old.files.iter().map(|f| (f.path.as_path(), f)).collect();
+        let new_files: HashMap<&Path, &FileCount> =
+            new.files.iter().map(|f| (f.path.as_path(), f)).collect();
+
+        let mut files = Vec::new();
+        let mut directories: BTreeMap<OsString, DirectoryDelta> = BTreeMap::new();
+
+        for file in &old.files {
+            let new_lines = new_files.get(file.path.as_path()).map(|f| f.lines);
+            directory_entry(&mut directories, &file.path).old_lines += file.lines;
+
+            match new_lines {
+                None => files.push(FileDelta {
+                    path: file.path.clone(),
+                    status: FileStatus::Removed,
+                    old_lines: file.lines,
+                    new_lines: 0,
+                }),
+                Some(lines) if lines != file.lines => files.push(FileDelta {
+                    path: file.path.clone(),
+                    status: FileStatus::Changed,
+                    old_lines: file.lines,
+                    new_lines: lines,
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for file in &new.files {
+            directory_entry(&mut directories, &file.path).new_lines += file.lines;
+
+            if !old_files.contains_key(file.path.as_path()) {
+                files.push(FileDelta {
+                    path: file.path.clone(),
+                    status: FileStatus::Added,
+                    old_lines: 0,
+                    new_lines: file.lines,
+                });
+            }
+        }
+
+        files.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+
+        Self {
+            files,
+            directories,
+            old_total: old.total_lines,
+            new_total: new.total_lines,
+        }
+    }
+
+    /// Returns the signed change in total line count.
+    pub fn delta(&self) -> i64 {
+        self.new_total as i64 - self.old_total as i64
+    }
+
+    /// Returns the number of lines gained by files that grew or were added.
+    pub fn lines_added(&self) -> usize {
+        self.files
+            .iter()
+            .map(|f| f.new_lines.saturating_sub(f.old_lines))
+            .sum()
+    }
+
+    /// Returns the number of lines lost by files that shrank or were removed.
+    pub fn lines_removed(&self) -> usize {
+        self.files
+            .iter()
+            .map(|f| f.old_lines.saturating_sub(f.new_lines))
+            .sum()
+    }
+
+    /// Returns the number of files with the given status.
+    pub fn count(&self, status: FileStatus) -> usize {
+        self.files.iter().filter(|f| f.status == status).count()
+    }
+
+    /// Breaks the totals down by directory.
+    ///
+    /// Directories are truncated to their first `depth` components, so a
+    /// depth of 1 groups by top-level directory. Files at the top level are
+    /// grouped under `.`. Directories whose totals did not change are omitted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::diff::CountDiff;
+    /// use gitlsf::{CountSummary, FileCount};
+    ///
+    /// let old = CountSummary::from_counts(vec![FileCount::new("src/a/x.rs", 10)]);
+    /// let new = CountSummary::from_counts(vec![
+    ///     FileCount::new("src/a/x.rs", 10),
+    ///     FileCount::new("src/b/y.rs", 4),
+    /// ]);
+    ///
+    /// let diff = CountDiff::between(&old, &new);
+    /// let top = diff.by_directory(1);
+    /// assert_eq!(top[0].path, "src");
+    /// assert_eq!(top[0].delta(), 4);
+    ///
+    /// let nested = diff.by_directory(2);
+    /// assert_eq!(nested.len(), 1);
+    /// assert_eq!(nested[0].path, "src/b");
+    /// ```
+    pub fn by_directory(&self, depth: usize) -> Vec<DirectoryDelta> {
+        let mut grouped: BTreeMap<OsString, DirectoryDelta> = BTreeMap::new();
+
+        for (path, directory) in &self.directories {
+            let key = truncate_directory(Path::new(path), depth);
+            let entry = grouped
+                .entry(key.clone().into_os_string())
+                .or_insert_with(|| DirectoryDelta {
+                    path: quote_path(&key).into_owned(),
+                    ..DirectoryDelta::default()
+                });
+            entry.old_lines += directory.old_lines;
+            entry.new_lines += directory.new_lines;
+        }
+
+        grouped
+            .into_values()
+            .filter(|directory| directory.delta() != 0)
+            .collect()
+    }
+}
+
+/// Returns the totals entry of the parent directory of `path`.
+fn directory_entry<'a>(
+    directories: &'a mut BTreeMap<OsString, DirectoryDelta>,
+    path: &Path,
+) -> &'a mut DirectoryDelta {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    directories
+        .entry(parent.as_os_str().to_owned())
+        .or_insert_with(|| DirectoryDelta {
+            path: quote_path(parent).into_owned(),
+            ..DirectoryDelta::default()
+        })
+}
+
+/// Keeps the first `depth` components of a directory path.
+pub(crate) fn truncate_directory(path: &Path, depth: usize) -> PathBuf {
+    if path == Path::new(".") || depth == 0 {
+        return PathBuf::from(".");
+    }
+
+    path.components().take(depth).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(files: &[(&str, usize)]) -> CountSummary {
+        CountSummary::from_counts(
+            files
+                .iter()
+                .map(|(path, lines)| FileCount::new(*path, *lines))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_between_statuses() {
+        let old = summary(&[("a.rs", 10), ("b.rs", 20), ("c.rs", 30)]);
+        let new = summary(&[("a.rs", 10), ("b.rs", 25), ("d.rs", 5)]);
+
+        let diff = CountDiff::between(&old, &new);
+
+        assert_eq!(
+            diff.files,
+            vec![
+                FileDelta {
+                    path: "b.rs".into(),
+                    status: FileStatus::Changed,
+                    old_lines: 20,
+                    new_lines: 25,
+                },
+                FileDelta {
+                    path: "c.rs".into(),
+                    status: FileStatus::Removed,
+                    old_lines: 30,
+                    new_lines: 0,
+                },
+                FileDelta {
+                    path: "d.rs".into(),
+                    status: FileStatus::Added,
+                    old_lines: 0,
+                    new_lines: 5,
+                },
+            ]
+        );
+        assert_eq!(diff.count(FileStatus::Added), 1);
+        assert_eq!(diff.count(FileStatus::Removed), 1);
+        assert_eq!(diff.count(FileStatus::Changed), 1);
+    }
+
+    #[test]
+    fn test_totals() {
+        let old = summary(&[("a.rs", 10), ("b.rs", 20)]);
+        let new = summary(&[("a.rs", 4), ("c.rs", 50)]);
+
+        let diff = CountDiff::between(&old, &new);
+
+        assert_eq!(diff.old_total, 30);
+        assert_eq!(diff.new_total, 54);
+        assert_eq!(diff.delta(), 24);
+        assert_eq!(diff.lines_added(), 50);
+        assert_eq!(diff.lines_removed(), 26);
+    }
+
+    #[test]
+    fn test_identical_summaries() {
+        let old = summary(&[("a.rs", 10)]);
+
+        let diff = CountDiff::between(&old, &old.clone());
+
+        assert!(diff.files.is_empty());
+        assert!(diff.by_directory(1).is_empty());
+        assert_eq!(diff.delta(), 0);
+    }
+
+    #[test]
+    fn test_by_directory() {
+        let old = summary(&[("main.rs", 5), ("src/a/x.rs", 10), ("src/b/y.rs", 10)]);
+        let new = summary(&[
+            ("main.rs", 7),
+            ("src/a/x.rs", 15),
+            ("src/b/y.rs", 5),
+            ("tests/t.rs", 3),
+        ]);
+
+        let diff = CountDiff::between(&old, &new);
+
+        let top: Vec<(String, i64)> = diff
+            .by_directory(1)
+            .into_iter()
+            .map(|d| (d.path.clone(), d.delta()))
+            .collect();
+        // src grew by 5 in src/a and shrank by 5 in src/b, so it is omitted
+        assert_eq!(top, vec![(".".to_string(), 2), ("tests".to_string(), 3)]);
+
+        let nested: Vec<(String, i64)> = diff
+            .by_directory(2)
+            .into_iter()
+            .map(|d| (d.path.clone(), d.delta()))
+            .collect();
+        assert_eq!(
+            nested,
+            vec![
+                (".".to_string(), 2),
+                ("src/a".to_string(), 5),
+                ("src/b".to_string(), -5),
+                ("tests".to_string(), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncate_directory() {
+        let truncate = |path: &str, depth| truncate_directory(Path::new(path), depth);
+
+        assert_eq!(truncate(".", 2), Path::new("."));
+        assert_eq!(truncate("a/b/c", 0), Path::new("."));
+        assert_eq!(truncate("a/b/c", 2), Path::new("a/b"));
+        assert_eq!(truncate("a", 3), Path::new("a"));
+    }
+}
@@ -39,9 +39,62 @@ pub enum GitlsfError {
         source: std::string::FromUtf8Error,
     },
 
+    /// A file of the Git directory, such as the index, that cannot be parsed.
+    #[error("Invalid Git file '{path}': {message}")]
+    GitFile {
+        /// The path to the file.
+        path: PathBuf,
+        /// Description of what is wrong.
+        message: String,
+    },
+
+    /// An archive that cannot be read, or whose format is not supported.
+    #[error("Invalid archive '{path}': {message}")]
+    Archive {
+        /// The path to the archive.
+        path: PathBuf,
+        /// Description of what is wrong.
+        message: String,
+    },
+
     /// Not a Git repository.
     #[error("Not a Git repository (or any parent up to mount point)")]
     NotAGitRepository,
+
+    /// A revision that Git cannot resolve to a commit.
+    #[error("Unknown revision '{revision}'")]
+    UnknownRevision {
+        /// The revision as given by the user.
+        revision: String,
+    },
+
+    /// An include or exclude glob pattern that cannot be parsed.
+    #[error("Invalid pattern '{pattern}': {source}")]
+    Pattern {
+        /// The pattern as given by the user.
+        pattern: String,
+        /// The underlying glob error.
+        #[source]
+        source: ignore::Error,
+    },
+
+    /// A path given to limit counting that is not inside the repository.
+    #[error("'{path}' is outside the repository at '{root}'")]
+    OutsideRepository {
+        /// The path as given by the user.
+        path: PathBuf,
+        /// The root of the repository.
+        root: PathBuf,
+    },
+
+    /// A configuration file that cannot be parsed.
+    #[error("Invalid configuration file '{path}': {message}")]
+    Config {
+        /// The path to the configuration file.
+        path: PathBuf,
+        /// Description of what is wrong.
+        message: String,
+    },
 }
 
 /// A specialized Result type for gitlsf operations.
@@ -64,6 +117,22 @@ impl GitlsfError {
         }
     }
 
+    /// Creates a new invalid Git file error.
+    pub fn git_file(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self::GitFile {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Creates a new invalid archive error.
+    pub fn archive(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self::Archive {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
     /// Creates a new IO error.
     pub fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
         Self::Io {
@@ -79,6 +148,22 @@ impl GitlsfError {
             source,
         }
     }
+
+    /// Creates a new configuration error.
+    pub fn config(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self::Config {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Creates a new invalid pattern error.
+    pub fn pattern(pattern: impl Into<String>, source: ignore::Error) -> Self {
+        Self::Pattern {
+            pattern: pattern.into(),
+            source,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -100,6 +185,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_unknown_revision_display() {
+        let err = GitlsfError::UnknownRevision {
+            revision: "v9.9".to_string(),
+        };
+        assert_eq!(err.to_string(), "Unknown revision 'v9.9'");
+    }
+
+    #[test]
+    fn test_outside_repository_display() {
+        let err = GitlsfError::OutsideRepository {
+            path: PathBuf::from("../other"),
+            root: PathBuf::from("/home/me/project"),
+        };
+        assert_eq!(
+            err.to_string(),
+            "'../other' is outside the repository at '/home/me/project'"
+        );
+    }
+
+    #[test]
+    fn test_config_error_display() {
+        let err = GitlsfError::config(".gitlsf.toml", "unknown field `foo`");
+        assert_eq!(
+            err.to_string(),
+            "Invalid configuration file '.gitlsf.toml': unknown field `foo`"
+        );
+    }
+
+    #[test]
+    fn test_git_file_error_display() {
+        let err = GitlsfError::git_file(".git/index", "unsupported version 5");
+        assert_eq!(
+            err.to_string(),
+            "Invalid Git file '.git/index': unsupported version 5"
+        );
+    }
+
+    #[test]
+    fn test_archive_error_display() {
+        let err = GitlsfError::archive("release.rar", "unsupported format");
+        assert_eq!(
+            err.to_string(),
+            "Invalid archive 'release.rar': unsupported format"
+        );
+    }
+
     #[test]
     fn test_io_error_display() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
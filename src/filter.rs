@@ -2,8 +2,72 @@
 //!
 //! This module provides functionality to filter out files that should not be
 //! counted, such as binary files, configuration files, and documentation.
+//!
+//! ## Glob patterns
+//!
+//! Besides the built-in lists, a [`FileFilter`] accepts include and exclude
+//! patterns written in `.gitignore` syntax, matched against paths relative to
+//! the repository root:
+//!
+//! - `*` matches anything except `/`, `?` matches one character and `**`
+//!   matches any number of directories (`**/*_generated.go`, `vendor/**`)
+//! - A pattern without a `/` matches at any depth (`*.pb.go`), while a
+//!   pattern with a leading or inner `/` is anchored to the root (`/build.rs`,
+//!   `src/gen`)
+//! - A pattern ending in `/` matches a directory and everything below it
+//!   (`third_party/`); so does a pattern naming a directory (`vendor`)
+//! - A leading `!` negates the pattern
+//!
+//! A pattern matches a file if it matches the file's path or the path of any
+//! of its parent directories, and the last matching pattern wins. A file is
+//! counted if:
+//!
+//! 1. There are no include patterns, or the last include pattern it matches
+//!    is not negated
+//! 2. The last exclude pattern it matches is negated, or it matches no
+//!    exclude pattern and is not on a built-in list
+//!
+//! A negated exclude pattern therefore re-includes files that an earlier
+//! exclude pattern or a built-in list would skip. Unlike in `.gitignore`,
+//! this also works for files below an excluded directory.
+//!
+//! ## Git attributes
+//!
+//! Files marked `linguist-generated`, `linguist-vendored`,
+//! `linguist-documentation`, `binary` or `-diff` in `.gitattributes` are not
+//! counted either, and `linguist-language` assigns languages. Since this
+//! needs Git, it is applied by the counting functions rather than by
+//! [`FileFilter::should_count`]; see the [`attributes`](crate::attributes)
+//! module. Language overrides of the filter take precedence over
+//! `linguist-language`.
+//!
+//! ## Paths
+//!
+//! A filter can also be limited to files and directories with
+//! [`FileFilter::pathspec`], as in `gitlsf src/ tests/`. Pathspecs are passed
+//! to Git when the files are listed, so files outside of them are never
+//! considered; [`FileFilter::should_count`] does not check them. Likewise,
+//! [`FileFilter::with_file_set`] selects the untracked, modified or staged
+//! files instead of all tracked files, such as to measure a change before it
+//! is committed.
+//!
+//! ## Submodules
+//!
+//! Submodules are only counted if the filter
+//! [recurses into them](FileFilter::recurse_submodules). Each is then counted
+//! as a repository of its own, with its own `.gitattributes`, but patterns and
+//! pathspecs keep referring to paths relative to the root of the outermost
+//! repository: `vendor/lib/src/` matches the `src` directory of a submodule at
+//! `vendor/lib`.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 
-use std::path::Path;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::error::{GitlsfError, Result};
+use crate::git::FileSet;
+use crate::language::Language;
 
 /// Extensions for media/binary files to exclude.
 const MEDIA_EXTENSIONS: &[&str] = &[
@@ -20,7 +84,14 @@ const DATA_EXTENSIONS: &[&str] = &[
 const DOC_EXTENSIONS: &[&str] = &["md"];
 
 /// Specific filenames to exclude.
-const EXCLUDED_FILENAMES: &[&str] = &["LICENSE", "LICENSE-MIT", "LICENSE-APACHE", ".gitignore"];
+const EXCLUDED_FILENAMES: &[&str] = &[
+    "LICENSE",
+    "LICENSE-MIT",
+    "LICENSE-APACHE",
+    ".gitignore",
+    ".gitattributes",
+    ".gitmodules",
+];
 
 /// A file filter that determines which files should be counted.
 #[derive(Debug, Clone, Default)]
@@ -29,6 +100,80 @@ pub struct FileFilter {
     extra_excluded_extensions: Vec<String>,
     /// Additional filenames to exclude.
     extra_excluded_filenames: Vec<String>,
+    /// Include patterns.
+    includes: PatternList,
+    /// Exclude patterns.
+    excludes: PatternList,
+    /// Replacement for the built-in data extension list, in lowercase.
+    data_extensions: Option<Vec<String>>,
+    /// Replacement for the built-in documentation extension list, in lowercase.
+    doc_extensions: Option<Vec<String>>,
+    /// Patterns of files whose language is overridden.
+    language_patterns: PatternList,
+    /// The language of each override pattern.
+    languages: Vec<Language>,
+    /// Whether `.gitattributes` are disregarded.
+    ignore_attributes: bool,
+    /// Files and directories the count is limited to, empty for all files.
+    pathspecs: Vec<PathBuf>,
+    /// The files listed for counting.
+    file_set: FileSet,
+    /// Whether the files of submodules are counted.
+    recurse_submodules: bool,
+    /// Path of the submodule being counted, relative to the outermost
+    /// repository, or empty for the outermost repository itself.
+    submodule: PathBuf,
+}
+
+/// An ordered list of `.gitignore`-style patterns.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PatternList {
+    /// The patterns as given.
+    patterns: Vec<String>,
+    /// One compiled matcher per pattern.
+    matchers: Vec<Gitignore>,
+}
+
+impl PatternList {
+    /// Compiles and appends a pattern.
+    pub(crate) fn push(&mut self, pattern: String) -> Result<()> {
+        let mut builder = GitignoreBuilder::new("");
+        builder
+            .add_line(None, &pattern)
+            .map_err(|e| GitlsfError::pattern(&pattern, e))?;
+        let matcher = builder
+            .build()
+            .map_err(|e| GitlsfError::pattern(&pattern, e))?;
+
+        self.patterns.push(pattern);
+        self.matchers.push(matcher);
+        Ok(())
+    }
+
+    /// Returns whether the last pattern matching the path or one of its
+    /// parent directories is a positive (`Some(true)`) or negated
+    /// (`Some(false)`) pattern, or `None` if no pattern matches.
+    pub(crate) fn matched(&self, path: &Path) -> Option<bool> {
+        self.last_match(path).map(|(_, positive)| positive)
+    }
+
+    /// Returns the index of the last pattern matching the path or one of its
+    /// parent directories, and whether that pattern is positive.
+    fn last_match(&self, path: &Path) -> Option<(usize, bool)> {
+        self.matchers
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, matcher)| {
+                let matched = matcher.matched_path_or_any_parents(path, false);
+                (!matched.is_none()).then(|| (index, matched.is_ignore()))
+            })
+    }
+
+    /// Returns `true` if the list has no patterns.
+    fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
 }
 
 impl FileFilter {
@@ -85,6 +230,313 @@ impl FileFilter {
         self
     }
 
+    /// Adds a glob pattern that files must match to be counted.
+    ///
+    /// Once any include pattern is added, only matching files are counted.
+    /// A pattern starting with `!` removes files matched by earlier include
+    /// patterns. See the [module documentation](self) for the syntax.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - A `.gitignore`-style glob pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GitlsfError::Pattern`] if the pattern is not a valid glob.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// let filter = FileFilter::new()
+    ///     .include_pattern("src/**")?
+    ///     .include_pattern("!src/generated/")?;
+    ///
+    /// assert!(filter.should_count("src/main.rs"));
+    /// assert!(!filter.should_count("src/generated/api.rs"));
+    /// assert!(!filter.should_count("build.rs"));
+    /// # Ok::<(), gitlsf::GitlsfError>(())
+    /// ```
+    pub fn include_pattern(mut self, pattern: impl Into<String>) -> Result<Self> {
+        self.includes.push(pattern.into())?;
+        Ok(self)
+    }
+
+    /// Adds a glob pattern for files that should not be counted.
+    ///
+    /// A pattern starting with `!` re-includes files matched by earlier
+    /// exclude patterns or by the built-in lists. See the
+    /// [module documentation](self) for the syntax.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - A `.gitignore`-style glob pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GitlsfError::Pattern`] if the pattern is not a valid glob.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// let filter = FileFilter::new()
+    ///     .exclude_pattern("vendor/")?
+    ///     .exclude_pattern("**/*_generated.go")?
+    ///     .exclude_pattern("!docs/**/*.md")?;
+    ///
+    /// assert!(!filter.should_count("vendor/lib/x.go"));
+    /// assert!(!filter.should_count("api/types_generated.go"));
+    /// assert!(filter.should_count("docs/guide/intro.md"));
+    /// # Ok::<(), gitlsf::GitlsfError>(())
+    /// ```
+    pub fn exclude_pattern(mut self, pattern: impl Into<String>) -> Result<Self> {
+        self.excludes.push(pattern.into())?;
+        Ok(self)
+    }
+
+    /// Replaces the built-in list of data and configuration file extensions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// // Count JSON and YAML, but still skip lock files
+    /// let filter = FileFilter::new().with_data_extensions(["lock"]);
+    /// assert!(filter.should_count("config.json"));
+    /// assert!(!filter.should_count("Cargo.lock"));
+    /// ```
+    pub fn with_data_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.data_extensions = Some(lowercase(extensions));
+        self
+    }
+
+    /// Replaces the built-in list of documentation file extensions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// let filter = FileFilter::new().with_doc_extensions(["md", "rst"]);
+    /// assert!(!filter.should_count("docs/index.rst"));
+    /// ```
+    pub fn with_doc_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.doc_extensions = Some(lowercase(extensions));
+        self
+    }
+
+    /// Assigns a language to files matching a glob pattern.
+    ///
+    /// The language replaces the one detected from the file's name and
+    /// content. When several patterns match, the last one added wins, and a
+    /// negated pattern restores detection for the files it matches. See the
+    /// [module documentation](self) for the pattern syntax.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GitlsfError::Pattern`] if the pattern is not a valid glob.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    /// use gitlsf::Language;
+    ///
+    /// let filter = FileFilter::new().override_language("*.h", Language::Cpp)?;
+    /// assert_eq!(filter.language_override("include/api.h"), Some(Language::Cpp));
+    /// assert_eq!(filter.language_override("src/main.c"), None);
+    /// # Ok::<(), gitlsf::GitlsfError>(())
+    /// ```
+    pub fn override_language(
+        mut self,
+        pattern: impl Into<String>,
+        language: Language,
+    ) -> Result<Self> {
+        self.language_patterns.push(pattern.into())?;
+        self.languages.push(language);
+        Ok(self)
+    }
+
+    /// Disregards the Linguist attributes set in `.gitattributes`.
+    ///
+    /// By default, files marked as generated, vendored, documentation or
+    /// binary are not counted, and `linguist-language` assigns languages.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// assert!(FileFilter::new().uses_attributes());
+    /// assert!(!FileFilter::new().without_attributes().uses_attributes());
+    /// ```
+    pub fn without_attributes(mut self) -> Self {
+        self.ignore_attributes = true;
+        self
+    }
+
+    /// Returns `true` if the Linguist attributes in `.gitattributes` apply.
+    pub fn uses_attributes(&self) -> bool {
+        !self.ignore_attributes
+    }
+
+    /// Limits counting to a file, or to the files below a directory.
+    ///
+    /// The path is relative to the directory being counted, usually the
+    /// repository root, and is matched literally. With several pathspecs,
+    /// files named or contained by any of them are counted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// let filter = FileFilter::new().pathspec("src").pathspec("tests");
+    /// assert_eq!(filter.pathspecs(), [PathBuf::from("src"), PathBuf::from("tests")]);
+    /// ```
+    pub fn pathspec(mut self, path: impl Into<PathBuf>) -> Self {
+        self.pathspecs.push(path.into());
+        self
+    }
+
+    /// Returns the pathspecs, in the order they were added.
+    pub fn pathspecs(&self) -> &[PathBuf] {
+        &self.pathspecs
+    }
+
+    /// Selects the files listed for counting, instead of the tracked files.
+    ///
+    /// Untracked and modified files are read from the working tree, while
+    /// staged files are read from the index, so that their count is that of
+    /// the next commit. Counting a revision always covers its whole tree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    /// use gitlsf::git::FileSet;
+    ///
+    /// assert_eq!(FileFilter::new().file_set(), FileSet::Tracked);
+    /// let filter = FileFilter::new().with_file_set(FileSet::Staged);
+    /// assert_eq!(filter.file_set(), FileSet::Staged);
+    /// ```
+    pub fn with_file_set(mut self, file_set: FileSet) -> Self {
+        self.file_set = file_set;
+        self
+    }
+
+    /// Returns the files listed for counting.
+    pub fn file_set(&self) -> FileSet {
+        self.file_set
+    }
+
+    /// Counts the files of initialized submodules as well.
+    ///
+    /// Their paths are prefixed by the path of the submodule, and each
+    /// [`FileCount`](crate::FileCount) records the submodule it belongs to.
+    /// Without this, submodules are reported as skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// assert!(!FileFilter::new().recurses_submodules());
+    /// assert!(FileFilter::new().recurse_submodules().recurses_submodules());
+    /// ```
+    pub fn recurse_submodules(mut self) -> Self {
+        self.recurse_submodules = true;
+        self
+    }
+
+    /// Returns `true` if the files of submodules are counted.
+    pub fn recurses_submodules(&self) -> bool {
+        self.recurse_submodules
+    }
+
+    /// Returns the filter for counting the submodule at `path`, relative to
+    /// the repository this filter counts.
+    ///
+    /// Pathspecs are made relative to the submodule. Returns `None` if no
+    /// pathspec names or contains a file of the submodule.
+    pub(crate) fn for_submodule(&self, path: &Path) -> Option<Self> {
+        let mut filter = self.clone();
+        filter.submodule = self.submodule.join(path);
+
+        if !self.pathspecs.is_empty() {
+            filter.pathspecs.clear();
+            for pathspec in &self.pathspecs {
+                if path.starts_with(pathspec) {
+                    // The whole submodule is counted
+                    filter.pathspecs.clear();
+                    return Some(filter);
+                }
+                if let Ok(inner) = pathspec.strip_prefix(path) {
+                    filter.pathspecs.push(inner.to_path_buf());
+                }
+            }
+            if filter.pathspecs.is_empty() {
+                return None;
+            }
+        }
+
+        Some(filter)
+    }
+
+    /// Returns the path patterns are matched against: `path` relative to the
+    /// outermost repository rather than to the submodule being counted.
+    fn pattern_path<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        if self.submodule.as_os_str().is_empty() {
+            Cow::Borrowed(path)
+        } else {
+            Cow::Owned(self.submodule.join(path))
+        }
+    }
+
+    /// Returns the language assigned to a file by an override pattern, if any.
+    pub fn language_override(&self, path: impl AsRef<Path>) -> Option<Language> {
+        match self
+            .language_patterns
+            .last_match(&self.pattern_path(path.as_ref()))
+        {
+            Some((index, true)) => Some(self.languages[index]),
+            _ => None,
+        }
+    }
+
+    /// Returns the language override patterns and their languages, in the
+    /// order they were added.
+    pub fn language_overrides(&self) -> impl Iterator<Item = (&str, Language)> {
+        self.language_patterns
+            .patterns
+            .iter()
+            .map(String::as_str)
+            .zip(self.languages.iter().copied())
+    }
+
+    /// Returns the include patterns, in the order they were added.
+    pub fn include_patterns(&self) -> &[String] {
+        &self.includes.patterns
+    }
+
+    /// Returns the exclude patterns, in the order they were added.
+    pub fn exclude_patterns(&self) -> &[String] {
+        &self.excludes.patterns
+    }
+
     /// Determines if a file should be counted based on its path.
     ///
     /// # Arguments
@@ -113,6 +565,17 @@ impl FileFilter {
     /// ```
     pub fn should_count(&self, path: impl AsRef<Path>) -> bool {
         let path = path.as_ref();
+        let pattern_path = self.pattern_path(path);
+
+        // Check include patterns
+        if !self.includes.is_empty() && self.includes.matched(&pattern_path) != Some(true) {
+            return false;
+        }
+
+        // Check exclude patterns, which take precedence over the built-in lists
+        if let Some(excluded) = self.excludes.matched(&pattern_path) {
+            return !excluded;
+        }
 
         // Check filename
         if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
@@ -133,8 +596,8 @@ impl FileFilter {
 
             // Check against default excluded extensions
             if MEDIA_EXTENSIONS.iter().any(|&e| e == ext_lower)
-                || DATA_EXTENSIONS.iter().any(|&e| e == ext_lower)
-                || DOC_EXTENSIONS.iter().any(|&e| e == ext_lower)
+                || self.data_extension_list().any(|e| e == ext_lower)
+                || self.doc_extension_list().any(|e| e == ext_lower)
             {
                 return false;
             }
@@ -152,6 +615,63 @@ impl FileFilter {
         true
     }
 
+    /// Returns every excluded extension, built-in and additional, in lowercase.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// let filter = FileFilter::new().exclude_extension("LOG");
+    /// let extensions = filter.excluded_extensions();
+    /// assert!(extensions.contains(&"png".to_string()));
+    /// assert!(extensions.contains(&"log".to_string()));
+    /// ```
+    pub fn excluded_extensions(&self) -> Vec<String> {
+        MEDIA_EXTENSIONS
+            .iter()
+            .copied()
+            .chain(self.data_extension_list())
+            .chain(self.doc_extension_list())
+            .map(|e| e.to_string())
+            .chain(
+                self.extra_excluded_extensions
+                    .iter()
+                    .map(|e| e.to_lowercase()),
+            )
+            .collect()
+    }
+
+    /// Returns the data extensions in effect, built-in or replaced.
+    fn data_extension_list(&self) -> impl Iterator<Item = &str> {
+        extension_list(&self.data_extensions, DATA_EXTENSIONS)
+    }
+
+    /// Returns the documentation extensions in effect, built-in or replaced.
+    fn doc_extension_list(&self) -> impl Iterator<Item = &str> {
+        extension_list(&self.doc_extensions, DOC_EXTENSIONS)
+    }
+
+    /// Returns every excluded filename, built-in and additional.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::filter::FileFilter;
+    ///
+    /// let filter = FileFilter::new().exclude_filename("Makefile.bak");
+    /// let filenames = filter.excluded_filenames();
+    /// assert!(filenames.contains(&"LICENSE".to_string()));
+    /// assert!(filenames.contains(&"Makefile.bak".to_string()));
+    /// ```
+    pub fn excluded_filenames(&self) -> Vec<String> {
+        EXCLUDED_FILENAMES
+            .iter()
+            .map(|f| f.to_string())
+            .chain(self.extra_excluded_filenames.iter().cloned())
+            .collect()
+    }
+
     /// Filters a list of file paths, returning only those that should be counted.
     ///
     /// # Arguments
@@ -165,31 +685,56 @@ impl FileFilter {
     /// # Example
     ///
     /// ```
+    /// use std::path::PathBuf;
     /// use gitlsf::filter::FileFilter;
     ///
     /// let filter = FileFilter::new();
-    /// let files = vec![
-    ///     "src/main.rs".to_string(),
-    ///     "logo.png".to_string(),
-    ///     "README.md".to_string(),
-    /// ];
+    /// let files = vec!["src/main.rs", "logo.png", "README.md"];
     ///
     /// let filtered = filter.filter_files(files);
-    /// assert_eq!(filtered, vec!["src/main.rs".to_string()]);
+    /// assert_eq!(filtered, vec![PathBuf::from("src/main.rs")]);
     /// ```
-    pub fn filter_files<I, S>(&self, files: I) -> Vec<String>
+    pub fn filter_files<I, S>(&self, files: I) -> Vec<PathBuf>
     where
         I: IntoIterator<Item = S>,
-        S: AsRef<str>,
+        S: AsRef<Path>,
     {
         files
             .into_iter()
             .filter(|f| self.should_count(f.as_ref()))
-            .map(|f| f.as_ref().to_string())
+            .map(|f| f.as_ref().to_path_buf())
             .collect()
     }
 }
 
+/// Returns the replacement list if there is one, the built-in list otherwise.
+fn extension_list<'a>(
+    replacement: &'a Option<Vec<String>>,
+    builtin: &'static [&'static str],
+) -> impl Iterator<Item = &'a str> {
+    let (replaced, builtin) = match replacement {
+        Some(list) => (list.as_slice(), &[][..]),
+        None => (&[][..], builtin),
+    };
+
+    replaced
+        .iter()
+        .map(String::as_str)
+        .chain(builtin.iter().copied())
+}
+
+/// Converts extensions to lowercase strings.
+fn lowercase<I, S>(extensions: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    extensions
+        .into_iter()
+        .map(|e| e.as_ref().to_lowercase())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,6 +790,8 @@ mod tests {
 
         assert!(!filter.should_count("LICENSE"));
         assert!(!filter.should_count(".gitignore"));
+        assert!(!filter.should_count(".gitattributes"));
+        assert!(!filter.should_count(".gitmodules"));
         assert!(!filter.should_count("path/to/LICENSE"));
     }
 
@@ -274,8 +821,8 @@ mod tests {
         let filtered = filter.filter_files(files);
 
         assert_eq!(filtered.len(), 2);
-        assert!(filtered.contains(&"src/main.rs".to_string()));
-        assert!(filtered.contains(&"src/lib.rs".to_string()));
+        assert!(filtered.contains(&PathBuf::from("src/main.rs")));
+        assert!(filtered.contains(&PathBuf::from("src/lib.rs")));
     }
 
     #[test]
@@ -286,4 +833,183 @@ mod tests {
         assert!(!filter.should_count("image.Png"));
         assert!(!filter.should_count("data.JSON"));
     }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let filter = FileFilter::new()
+            .exclude_pattern("vendor/**")
+            .unwrap()
+            .exclude_pattern("**/*_generated.go")
+            .unwrap()
+            .exclude_pattern("third_party/")
+            .unwrap();
+
+        assert!(!filter.should_count("vendor/a/b.go"));
+        assert!(!filter.should_count("pkg/api/types_generated.go"));
+        assert!(!filter.should_count("types_generated.go"));
+        assert!(!filter.should_count("third_party/x.c"));
+        assert!(!filter.should_count("lib/third_party/x.c"));
+        assert!(filter.should_count("pkg/vendor.go"));
+        assert!(filter.should_count("src/main.go"));
+    }
+
+    #[test]
+    fn test_anchored_and_unanchored_patterns() {
+        let filter = FileFilter::new()
+            .exclude_pattern("/build.rs")
+            .unwrap()
+            .exclude_pattern("*.pb.go")
+            .unwrap()
+            .exclude_pattern("src/gen")
+            .unwrap();
+
+        assert!(!filter.should_count("build.rs"));
+        assert!(filter.should_count("tools/build.rs"));
+        assert!(!filter.should_count("api/v1/service.pb.go"));
+        assert!(!filter.should_count("src/gen/types.rs"));
+        assert!(filter.should_count("lib/src/gen/types.rs"));
+    }
+
+    #[test]
+    fn test_negated_exclude_pattern() {
+        let filter = FileFilter::new()
+            .exclude_pattern("vendor/")
+            .unwrap()
+            .exclude_pattern("!vendor/ours/")
+            .unwrap()
+            .exclude_pattern("!CHANGELOG.md")
+            .unwrap();
+
+        assert!(!filter.should_count("vendor/theirs/x.go"));
+        assert!(filter.should_count("vendor/ours/x.go"));
+        // Negated patterns override the built-in lists
+        assert!(filter.should_count("CHANGELOG.md"));
+        assert!(!filter.should_count("README.md"));
+    }
+
+    #[test]
+    fn test_last_matching_pattern_wins() {
+        let filter = FileFilter::new()
+            .exclude_pattern("!*.rs")
+            .unwrap()
+            .exclude_pattern("*.rs")
+            .unwrap();
+
+        assert!(!filter.should_count("main.rs"));
+    }
+
+    #[test]
+    fn test_include_patterns() {
+        let filter = FileFilter::new()
+            .include_pattern("src/**")
+            .unwrap()
+            .include_pattern("!src/gen/")
+            .unwrap();
+
+        assert!(filter.should_count("src/main.rs"));
+        assert!(filter.should_count("src/a/b.rs"));
+        assert!(!filter.should_count("src/gen/x.rs"));
+        assert!(!filter.should_count("tests/t.rs"));
+        // Built-in exclusions still apply to included files
+        assert!(!filter.should_count("src/logo.png"));
+    }
+
+    #[test]
+    fn test_include_and_exclude_patterns() {
+        let filter = FileFilter::new()
+            .include_pattern("*.go")
+            .unwrap()
+            .exclude_pattern("vendor/")
+            .unwrap();
+
+        assert!(filter.should_count("cmd/main.go"));
+        assert!(!filter.should_count("vendor/x.go"));
+        assert!(!filter.should_count("src/main.rs"));
+        assert_eq!(filter.include_patterns(), ["*.go"]);
+        assert_eq!(filter.exclude_patterns(), ["vendor/"]);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let result = FileFilter::new().exclude_pattern("src/{a,b");
+
+        assert!(matches!(
+            result,
+            Err(GitlsfError::Pattern { ref pattern, .. }) if pattern == "src/{a,b"
+        ));
+    }
+
+    #[test]
+    fn test_replaced_extension_lists() {
+        let filter = FileFilter::new()
+            .with_data_extensions(["LOCK"])
+            .with_doc_extensions(["md", "rst"]);
+
+        assert!(filter.should_count("package.json"));
+        assert!(!filter.should_count("Cargo.lock"));
+        assert!(!filter.should_count("index.rst"));
+        assert!(!filter.should_count("logo.png"));
+
+        let extensions = filter.excluded_extensions();
+        assert!(extensions.contains(&"lock".to_string()));
+        assert!(!extensions.contains(&"json".to_string()));
+    }
+
+    #[test]
+    fn test_language_overrides() {
+        let filter = FileFilter::new()
+            .override_language("*.h", Language::C)
+            .unwrap()
+            .override_language("cpp/", Language::Cpp)
+            .unwrap()
+            .override_language("!cpp/legacy/", Language::Cpp)
+            .unwrap();
+
+        assert_eq!(filter.language_override("lib/a.h"), Some(Language::C));
+        assert_eq!(filter.language_override("cpp/a.h"), Some(Language::Cpp));
+        assert_eq!(filter.language_override("cpp/legacy/a.h"), None);
+        assert_eq!(filter.language_override("lib/a.c"), None);
+        assert_eq!(filter.language_overrides().count(), 3);
+    }
+
+    #[test]
+    fn test_for_submodule() {
+        let filter = FileFilter::new()
+            .exclude_pattern("vendor/lib/gen/")
+            .unwrap()
+            .override_language("vendor/lib/*.h", Language::Cpp)
+            .unwrap();
+        let submodule = filter.for_submodule(Path::new("vendor/lib")).unwrap();
+
+        // Patterns still match paths relative to the outermost repository
+        assert!(!submodule.should_count("gen/api.rs"));
+        assert!(submodule.should_count("src/main.rs"));
+        assert_eq!(submodule.language_override("a.h"), Some(Language::Cpp));
+        assert!(submodule.pathspecs().is_empty());
+
+        let nested = submodule.for_submodule(Path::new("gen")).unwrap();
+        assert!(!nested.should_count("api.rs"));
+    }
+
+    #[test]
+    fn test_for_submodule_pathspecs() {
+        let filter = FileFilter::new()
+            .pathspec("src")
+            .pathspec("vendor/lib/src")
+            .pathspec("vendor/lib/build.rs");
+        let submodule = filter.for_submodule(Path::new("vendor/lib")).unwrap();
+        assert_eq!(
+            submodule.pathspecs(),
+            [PathBuf::from("src"), PathBuf::from("build.rs")]
+        );
+
+        let filter = FileFilter::new()
+            .pathspec("vendor")
+            .pathspec("vendor/lib/src");
+        let submodule = filter.for_submodule(Path::new("vendor/lib")).unwrap();
+        assert!(submodule.pathspecs().is_empty());
+
+        let filter = FileFilter::new().pathspec("src");
+        assert!(filter.for_submodule(Path::new("vendor/lib")).is_none());
+    }
 }
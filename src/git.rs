@@ -1,50 +1,1391 @@
 //! Git command interaction module.
 //!
 //! This module provides functions for interacting with Git repositories,
-//! primarily through the `git ls-files` command to retrieve tracked files.
+//! primarily through the `git ls-files` command to retrieve tracked files,
+//! and `git ls-tree` with `git cat-file` to read files at a given revision.
+//!
+//! Paths are read from NUL-separated output (`-z`), so Git never quotes
+//! them, and are kept as [`PathBuf`]s: on Unix, filenames that are not valid
+//! UTF-8 are preserved byte for byte.
+//!
+//! The functions listing files take pathspecs, paths relative to the queried
+//! directory that limit the listing to the files they name or contain. They
+//! are matched literally, without glob or other pathspec magic.
+//!
+//! [`list_files`] lists one [`FileSet`]: the tracked files, or the untracked,
+//! modified or staged files of a change in progress.
+//!
+//! With the `native-index` cargo feature, the tracked files, the index
+//! entries, whether a directory is in a repository, the repository root and
+//! the modified files are read from the Git directory by the `index` module
+//! instead, without running `git`.
+//!
+//! [`blame_file`] and [`commit_authors`] attribute the lines of a file to the
+//! commits that last changed them, and those commits to their authors;
+//! [`log_numstat`] lists the lines each commit of a range added and deleted.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use serde::Serialize;
+
+use crate::error::{GitlsfError, Result};
+
+/// File mode of symbolic links in Git trees.
+const SYMLINK_MODE: &str = "120000";
+
+/// Prefix of the file modes of regular files in Git trees, such as `100644`.
+const REGULAR_FILE_MODE_PREFIX: &str = "100";
+
+/// File mode of submodules (gitlinks) in Git trees.
+const SUBMODULE_MODE: &str = "160000";
+
+/// A selection of the files of a repository.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileSet {
+    /// The files tracked in the index.
+    #[default]
+    Tracked,
+    /// Files in the working tree that are neither tracked nor ignored.
+    Untracked,
+    /// Tracked files whose working tree content differs from the index,
+    /// except deleted files.
+    Modified,
+    /// Files whose content in the index differs from `HEAD`, except deleted
+    /// files: the files the next commit would add or change.
+    Staged,
+}
+
+impl FileSet {
+    /// Returns the name of the file set, as used in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileSet::Tracked => "tracked",
+            FileSet::Untracked => "untracked",
+            FileSet::Modified => "modified",
+            FileSet::Staged => "staged",
+        }
+    }
+
+    /// Returns the Git command and arguments listing the file set.
+    fn command(&self) -> &'static [&'static str] {
+        match self {
+            FileSet::Tracked => &["ls-files", "-z"],
+            FileSet::Untracked => &["ls-files", "-z", "--others", "--exclude-standard"],
+            FileSet::Modified => &[
+                "diff",
+                "--name-only",
+                "--relative",
+                "--no-renames",
+                "--diff-filter=d",
+                "-z",
+            ],
+            FileSet::Staged => &[
+                "diff",
+                "--cached",
+                "--name-only",
+                "--relative",
+                "--no-renames",
+                "--diff-filter=d",
+                "-z",
+            ],
+        }
+    }
+}
+
+/// A file entry in a Git tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// The file mode, such as `100644`.
+    pub mode: String,
+    /// The object ID of the blob, or of the commit checked out in a submodule.
+    pub oid: String,
+    /// The path of the file relative to the queried directory.
+    pub path: PathBuf,
+}
+
+impl TreeEntry {
+    /// Returns `true` if the entry is a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.mode == SYMLINK_MODE
+    }
+
+    /// Returns `true` if the entry is a regular file, executable or not.
+    pub fn is_regular_file(&self) -> bool {
+        self.mode.starts_with(REGULAR_FILE_MODE_PREFIX)
+    }
+
+    /// Returns `true` if the entry is a submodule.
+    pub fn is_submodule(&self) -> bool {
+        self.mode == SUBMODULE_MODE
+    }
+}
+
+/// Converts a path as written by Git into a [`PathBuf`].
+///
+/// Git paths are arbitrary bytes. On Unix they are kept as they are;
+/// elsewhere Git writes them in UTF-8.
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// Adds pathspecs to a Git command after `--`, to be matched literally.
+fn add_pathspecs<'a>(command: &'a mut Command, pathspecs: &[PathBuf]) -> &'a mut Command {
+    command.arg("--");
+    for pathspec in pathspecs {
+        let mut arg = OsString::from(":(literal)");
+        arg.push(pathspec);
+        command.arg(arg);
+    }
+    command
+}
+
+/// Splits NUL-terminated Git output into paths.
+#[cfg(any(test, not(feature = "native-index")))]
+fn split_paths(output: &[u8]) -> impl Iterator<Item = PathBuf> + '_ {
+    output
+        .split(|&b| b == 0)
+        .filter(|path| !path.is_empty())
+        .map(path_from_bytes)
+}
+
+/// Retrieves the list of files of a file set in the specified directory.
+///
+/// Uses `git ls-files -z` to get all tracked files in the repository, with
+/// `--others --exclude-standard` for untracked files, and `git diff
+/// --name-only -z` for modified and staged files. Untracked directories that
+/// are repositories of their own are not listed. With the `native-index`
+/// feature, tracked files are read from the index without running `git`.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository (or a subdirectory within it).
+/// * `file_set` - The files to list.
+/// * `pathspecs` - Paths limiting the listing, or an empty slice for all files.
+///
+/// # Returns
+///
+/// A vector of file paths relative to `path`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::{FileSet, list_files};
+///
+/// let files = list_files(".", FileSet::Staged, &[]).unwrap();
+/// for file in files {
+///     println!("about to commit {}", file.display());
+/// }
+/// ```
+pub fn list_files(
+    path: impl AsRef<Path>,
+    file_set: FileSet,
+    pathspecs: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    #[cfg(feature = "native-index")]
+    if file_set == FileSet::Tracked {
+        return crate::index::list_files(path, pathspecs);
+    }
+
+    ls_files(path.as_ref(), file_set, pathspecs)
+}
+
+/// Lists the files of a file set by running `git`, see [`list_files`].
+pub(crate) fn ls_files(
+    path: &Path,
+    file_set: FileSet,
+    pathspecs: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    let command = file_set.command();
+
+    let output = add_pathspecs(Command::new("git").args(command), pathspecs)
+        .current_dir(path)
+        .output()
+        .map_err(|e| {
+            GitlsfError::git_with_source(format!("Failed to execute git {}", command[0]), e)
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("not a git repository") {
+            return Err(GitlsfError::NotAGitRepository);
+        }
+        return Err(GitlsfError::git(format!(
+            "git {} failed: {}",
+            command[0],
+            stderr.trim()
+        )));
+    }
+
+    // Untracked repositories are listed as directories, with a trailing slash
+    Ok(output
+        .stdout
+        .split(|&b| b == 0)
+        .filter(|path| !path.is_empty() && !path.ends_with(b"/"))
+        .map(path_from_bytes)
+        .collect())
+}
+
+/// Retrieves the files in the tree of a revision, without checking it out.
+///
+/// Uses `git ls-tree -r -z`. Like [`list_files`], only the part of the tree
+/// below `path` is listed, with paths relative to `path`. Submodule entries
+/// are omitted because they have no blob content.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository (or a subdirectory within it).
+/// * `revision` - Any revision understood by Git, such as `v1.0` or `HEAD~3`.
+/// * `pathspecs` - Paths limiting the listing, or an empty slice for all files.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::list_tree;
+///
+/// for entry in list_tree(".", "HEAD", &[]).unwrap() {
+///     println!("{} {}", entry.oid, entry.path.display());
+/// }
+/// ```
+pub fn list_tree(
+    path: impl AsRef<Path>,
+    revision: &str,
+    pathspecs: &[PathBuf],
+) -> Result<Vec<TreeEntry>> {
+    let path = path.as_ref();
+
+    // Verify the revision first, so that a typo is reported as such
+    resolve_revision(path, revision)?;
+
+    ls_tree(path, revision, pathspecs, "blob")
+}
+
+/// Lists the entries of one object type in the tree of a revision.
+fn ls_tree(
+    path: &Path,
+    revision: &str,
+    pathspecs: &[PathBuf],
+    object_type: &str,
+) -> Result<Vec<TreeEntry>> {
+    let output = add_pathspecs(
+        Command::new("git").args(["ls-tree", "-r", "-z", revision]),
+        pathspecs,
+    )
+    .current_dir(path)
+    .output()
+    .map_err(|e| GitlsfError::git_with_source("Failed to execute git ls-tree", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitlsfError::git(format!(
+            "git ls-tree failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    // Each record is "<mode> SP <type> SP <oid> TAB <path>"
+    let entries = split_records(&output.stdout)
+        .filter_map(|(meta, path)| {
+            let mut fields = meta.split(' ');
+            let (mode, kind, oid) = (fields.next()?, fields.next()?, fields.next()?);
+
+            (kind == object_type).then(|| TreeEntry {
+                mode: mode.to_string(),
+                oid: oid.to_string(),
+                path,
+            })
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// Splits NUL-terminated `<metadata> TAB <path>` records of Git output.
+///
+/// The metadata of a record is ASCII; records without a tab are skipped.
+fn split_records(output: &[u8]) -> impl Iterator<Item = (&str, PathBuf)> {
+    output.split(|&b| b == 0).filter_map(|record| {
+        let tab = memchr::memchr(b'\t', record)?;
+        let meta = std::str::from_utf8(&record[..tab]).ok()?;
+        Some((meta, path_from_bytes(&record[tab + 1..])))
+    })
+}
+
+/// Retrieves the files in the index, with the blob object ID of each.
+///
+/// Uses `git ls-files -s -z`, or reads the index with the `native-index`
+/// feature. Paths are the same as those of [`list_files`]. A file with merge
+/// conflicts is listed once, with the first of its stages.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::list_index;
+///
+/// for entry in list_index(".", &[]).unwrap() {
+///     println!("{} {}", entry.oid, entry.path.display());
+/// }
+/// ```
+pub fn list_index(path: impl AsRef<Path>, pathspecs: &[PathBuf]) -> Result<Vec<TreeEntry>> {
+    #[cfg(feature = "native-index")]
+    return crate::index::list_index(path, pathspecs);
+
+    #[cfg(not(feature = "native-index"))]
+    ls_files_stage(path.as_ref(), pathspecs)
+}
+
+/// Lists the files in the index by running `git`, see [`list_index`].
+#[cfg(any(test, not(feature = "native-index")))]
+pub(crate) fn ls_files_stage(path: &Path, pathspecs: &[PathBuf]) -> Result<Vec<TreeEntry>> {
+    let output = add_pathspecs(
+        Command::new("git").args(["ls-files", "-s", "-z"]),
+        pathspecs,
+    )
+    .current_dir(path)
+    .output()
+    .map_err(|e| GitlsfError::git_with_source("Failed to execute git ls-files", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("not a git repository") {
+            return Err(GitlsfError::NotAGitRepository);
+        }
+        return Err(GitlsfError::git(format!(
+            "git ls-files failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    // Each record is "<mode> SP <oid> SP <stage> TAB <path>"
+    let mut entries: Vec<TreeEntry> = Vec::new();
+    for (meta, path) in split_records(&output.stdout) {
+        let mut fields = meta.split(' ');
+        let (Some(mode), Some(oid)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+
+        // Stages of a conflicted file are listed next to each other
+        if entries.last().is_some_and(|last| last.path == path) {
+            continue;
+        }
+        entries.push(TreeEntry {
+            mode: mode.to_string(),
+            oid: oid.to_string(),
+            path,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Retrieves the submodules in the index, or in the tree of a revision.
+///
+/// Submodules are recorded as gitlink entries, whose object ID is the commit
+/// checked out in the submodule. Their paths are relative to `path`; files
+/// inside them are not listed.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository (or a subdirectory within it).
+/// * `revision` - The revision whose tree is read, or `None` for the index.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::list_submodules;
+///
+/// for submodule in list_submodules(".", None).unwrap() {
+///     println!("{} at {}", submodule.path.display(), submodule.oid);
+/// }
+/// ```
+pub fn list_submodules(path: impl AsRef<Path>, revision: Option<&str>) -> Result<Vec<TreeEntry>> {
+    let path = path.as_ref();
+
+    match revision {
+        Some(revision) => {
+            resolve_revision(path, revision)?;
+            ls_tree(path, revision, &[], "commit")
+        }
+        None => Ok(list_index(path, &[])?
+            .into_iter()
+            .filter(TreeEntry::is_submodule)
+            .collect()),
+    }
+}
+
+/// Retrieves the tracked files whose working tree content differs from the index.
+///
+/// Uses `git diff --name-only --relative -z`, so paths are relative to
+/// `path` like those of [`list_files`], and only files named or contained by
+/// `pathspecs` are listed if any are given. Deleted and conflicted files are
+/// included. With the `native-index` feature, the size and modification time
+/// of files are compared with the index instead, which may also list files
+/// that were only touched.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::modified_files;
+///
+/// for file in modified_files(".", &[]).unwrap() {
+///     println!("modified: {}", file.display());
+/// }
+/// ```
+pub fn modified_files(path: impl AsRef<Path>, pathspecs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    #[cfg(feature = "native-index")]
+    return crate::index::modified_files(path, pathspecs);
+
+    #[cfg(not(feature = "native-index"))]
+    diff_name_only(path.as_ref(), pathspecs)
+}
+
+/// Lists the modified files by running `git`, see [`modified_files`].
+#[cfg(any(test, not(feature = "native-index")))]
+pub(crate) fn diff_name_only(path: &Path, pathspecs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let output = add_pathspecs(
+        Command::new("git").args(["diff", "--name-only", "--relative", "--no-renames", "-z"]),
+        pathspecs,
+    )
+    .current_dir(path)
+    .output()
+    .map_err(|e| GitlsfError::git_with_source("Failed to execute git diff", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("not a git repository") {
+            return Err(GitlsfError::NotAGitRepository);
+        }
+        return Err(GitlsfError::git(format!(
+            "git diff failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    Ok(split_paths(&output.stdout).collect())
+}
+
+/// Resolves a revision to the object ID of its commit.
+///
+/// # Errors
+///
+/// Returns [`GitlsfError::UnknownRevision`] if the revision does not exist,
+/// or [`GitlsfError::NotAGitRepository`] if the path is not within a Git repository.
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::resolve_revision;
+///
+/// let oid = resolve_revision(".", "HEAD").unwrap();
+/// assert_eq!(oid.len(), 40);
+/// ```
+pub fn resolve_revision(path: impl AsRef<Path>, revision: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", "--end-of-options"])
+        .arg(format!("{revision}^{{commit}}"))
+        .current_dir(path.as_ref())
+        .output()
+        .map_err(|e| GitlsfError::git_with_source("Failed to execute git rev-parse", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("not a git repository") {
+            return Err(GitlsfError::NotAGitRepository);
+        }
+        return Err(GitlsfError::UnknownRevision {
+            revision: revision.to_string(),
+        });
+    }
+
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|e| GitlsfError::utf8("git rev-parse output", e))?;
+
+    Ok(stdout.trim().to_string())
+}
+
+/// Streams the contents of blobs through a single `git cat-file --batch` process.
+///
+/// `visit` is called once per object ID, in order, with a reader limited to
+/// the blob content. Object IDs are written to Git from a separate thread so
+/// that large batches cannot deadlock on full pipes.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `oids` - The object IDs of the blobs to read.
+/// * `visit` - Called with the index of the object ID and a reader for its content.
+///
+/// # Errors
+///
+/// Returns an error if the `git` command fails, an object is missing, or
+/// `visit` returns an error.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::io::Read;
+/// use gitlsf::git::{list_tree, read_blobs};
+///
+/// let entries = list_tree(".", "HEAD", &[]).unwrap();
+/// let oids: Vec<&str> = entries.iter().map(|e| e.oid.as_str()).collect();
+///
+/// read_blobs(".", &oids, |index, blob| {
+///     let mut content = Vec::new();
+///     blob.read_to_end(&mut content).unwrap();
+///     println!("{}: {} bytes", entries[index].path.display(), content.len());
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn read_blobs<F>(path: impl AsRef<Path>, oids: &[&str], mut visit: F) -> Result<()>
+where
+    F: FnMut(usize, &mut dyn Read) -> Result<()>,
+{
+    if oids.is_empty() {
+        return Ok(());
+    }
+
+    let mut child = Command::new("git")
+        .args(["cat-file", "--batch"])
+        .current_dir(path.as_ref())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| GitlsfError::git_with_source("Failed to execute git cat-file", e))?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let requests: String = oids.iter().map(|oid| format!("{oid}\n")).collect();
+    let writer = thread::spawn(move || stdin.write_all(requests.as_bytes()));
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let result = read_batch(&mut stdout, oids.len(), &mut visit);
+
+    // Unblock the writer if reading stopped early, then reap the process
+    drop(stdout);
+    let _ = writer.join();
+    let _ = child.wait();
+
+    result
+}
+
+/// Reads `count` responses of `git cat-file --batch` from `stdout`.
+fn read_batch<R, F>(stdout: &mut R, count: usize, visit: &mut F) -> Result<()>
+where
+    R: BufRead,
+    F: FnMut(usize, &mut dyn Read) -> Result<()>,
+{
+    let read_error = |e| GitlsfError::git_with_source("Failed to read git cat-file output", e);
+    let mut header = String::new();
+
+    for index in 0..count {
+        header.clear();
+        stdout.read_line(&mut header).map_err(read_error)?;
+
+        // Header is "<oid> SP <type> SP <size> LF" or "<oid> SP missing LF"
+        let mut fields = header.split_whitespace();
+        let (oid, kind, size) = (fields.next(), fields.next(), fields.next());
+        let size: u64 = match (kind, size.and_then(|s| s.parse().ok())) {
+            (Some("blob"), Some(size)) => size,
+            _ => {
+                return Err(GitlsfError::git(format!(
+                    "git cat-file could not read blob {}",
+                    oid.unwrap_or_default()
+                )));
+            }
+        };
+
+        let mut blob = stdout.by_ref().take(size);
+        visit(index, &mut blob)?;
+
+        // Skip whatever the visitor left unread, plus the trailing LF
+        std::io::copy(&mut blob, &mut std::io::sink()).map_err(read_error)?;
+        stdout.read_exact(&mut [0u8; 1]).map_err(read_error)?;
+    }
+
+    Ok(())
+}
+
+/// The state of a Git attribute for a path, as reported by `git check-attr`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeValue {
+    /// The attribute is not mentioned for the path.
+    Unspecified,
+    /// The attribute is set (`attr`).
+    Set,
+    /// The attribute is unset (`-attr`).
+    Unset,
+    /// The attribute is set to a value (`attr=value`).
+    Value(String),
+}
+
+impl AttributeValue {
+    /// Parses the value column of `git check-attr` output.
+    fn parse(value: &str) -> Self {
+        match value {
+            "unspecified" => AttributeValue::Unspecified,
+            "set" => AttributeValue::Set,
+            "unset" => AttributeValue::Unset,
+            value => AttributeValue::Value(value.to_string()),
+        }
+    }
+}
+
+/// Looks up Git attributes of many files through a single `git check-attr` process.
+///
+/// Attributes are resolved from the `.gitattributes` files of the working
+/// tree, or of `revision` if one is given. In the latter case, the tree of the
+/// revision is read into a temporary index, so nothing needs to be checked out.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository (or a subdirectory within it).
+/// * `revision` - The revision whose `.gitattributes` files apply, if any.
+/// * `files` - File paths relative to `path`.
+/// * `attributes` - The names of the attributes to look up.
+///
+/// # Returns
+///
+/// One row per file, in the order of `files`, holding the value of each
+/// attribute in the order of `attributes`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::{AttributeValue, FileSet, check_attributes, list_files};
+///
+/// let files = list_files(".", FileSet::Tracked, &[]).unwrap();
+/// let values = check_attributes(".", None, &files, &["linguist-generated"]).unwrap();
+/// for (file, row) in files.iter().zip(&values) {
+///     if row[0] == AttributeValue::Set {
+///         println!("generated: {}", file.display());
+///     }
+/// }
+/// ```
+pub fn check_attributes<S: AsRef<Path>>(
+    path: impl AsRef<Path>,
+    revision: Option<&str>,
+    files: &[S],
+    attributes: &[&str],
+) -> Result<Vec<Vec<AttributeValue>>> {
+    let path = path.as_ref();
+
+    if files.is_empty() || attributes.is_empty() {
+        return Ok(vec![Vec::new(); files.len()]);
+    }
+
+    let mut command = Command::new("git");
+    command.args(["check-attr", "--stdin", "-z"]);
+
+    // Keep the temporary index alive until Git has exited
+    let _index = match revision {
+        Some(revision) => {
+            let index = TemporaryIndex::read_tree(path, revision)?;
+            command.arg("--cached").env("GIT_INDEX_FILE", &index.0);
+            Some(index)
+        }
+        None => None,
+    };
+
+    let mut child = command
+        .args(attributes)
+        .current_dir(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| GitlsfError::git_with_source("Failed to execute git check-attr", e))?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let requests: Vec<u8> = files
+        .iter()
+        .flat_map(|file| {
+            let bytes = file.as_ref().as_os_str().as_encoded_bytes();
+            bytes.iter().copied().chain([0])
+        })
+        .collect();
+    let writer = thread::spawn(move || stdin.write_all(&requests));
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| GitlsfError::git_with_source("Failed to read git check-attr output", e))?;
+    let _ = writer.join();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("not a git repository") {
+            return Err(GitlsfError::NotAGitRepository);
+        }
+        return Err(GitlsfError::git(format!(
+            "git check-attr failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    // Output is "<path> NUL <attribute> NUL <value> NUL" per file and
+    // attribute, in the order they were requested. Paths may not be UTF-8,
+    // so only the values are decoded
+    let stdout = output.stdout.strip_suffix(b"\0").unwrap_or(&output.stdout);
+    let values: Vec<AttributeValue> = stdout
+        .split(|&b| b == 0)
+        .skip(2)
+        .step_by(3)
+        .map(|value| AttributeValue::parse(&String::from_utf8_lossy(value)))
+        .collect();
+
+    if values.len() != files.len() * attributes.len() {
+        return Err(GitlsfError::git(
+            "git check-attr returned an unexpected number of attributes",
+        ));
+    }
+
+    Ok(values
+        .chunks(attributes.len())
+        .map(<[AttributeValue]>::to_vec)
+        .collect())
+}
+
+/// A temporary index file holding the tree of a revision.
+///
+/// The file is removed when the value is dropped.
+struct TemporaryIndex(PathBuf);
+
+impl TemporaryIndex {
+    /// Reads the tree of `revision` into a new temporary index file.
+    fn read_tree(path: &Path, revision: &str) -> Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let commit = resolve_revision(path, revision)?;
+        let index = TemporaryIndex(std::env::temp_dir().join(format!(
+            "gitlsf-index-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        )));
+
+        let output = Command::new("git")
+            .args(["read-tree", &commit])
+            .env("GIT_INDEX_FILE", &index.0)
+            .current_dir(path)
+            .output()
+            .map_err(|e| GitlsfError::git_with_source("Failed to execute git read-tree", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitlsfError::git(format!(
+                "git read-tree failed: {}",
+                stderr.trim()
+            )));
+        }
+
+        Ok(index)
+    }
+}
+
+impl Drop for TemporaryIndex {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// A commit in the history of a revision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    /// The commit object ID.
+    pub oid: String,
+    /// The committer date as seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// The committer date in strict ISO 8601 format.
+    pub date: String,
+}
+
+/// Retrieves the first-parent history of a revision, newest first.
+///
+/// Uses `git log --first-parent`, so merged branches contribute their merge
+/// commit rather than each of their own commits.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `revision` - The revision to start from, such as `HEAD` or `main`.
+/// * `since` - An optional lower bound on the commit date, in any format
+///   accepted by `git log --since` (`2 years ago`, `2024-01-01`).
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::list_commits;
+///
+/// for commit in list_commits(".", "HEAD", Some("1 month ago")).unwrap() {
+///     println!("{} {}", commit.date, commit.oid);
+/// }
+/// ```
+pub fn list_commits(
+    path: impl AsRef<Path>,
+    revision: &str,
+    since: Option<&str>,
+) -> Result<Vec<CommitInfo>> {
+    let path = path.as_ref();
+
+    resolve_revision(path, revision)?;
+
+    let mut command = Command::new("git");
+    command.args(["log", "--first-parent", "--format=%H %ct %cI"]);
+    if let Some(since) = since {
+        command.arg(format!("--since={since}"));
+    }
+
+    let output = command
+        .args([revision, "--"])
+        .current_dir(path)
+        .output()
+        .map_err(|e| GitlsfError::git_with_source("Failed to execute git log", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitlsfError::git(format!(
+            "git log failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    let stdout =
+        String::from_utf8(output.stdout).map_err(|e| GitlsfError::utf8("git log output", e))?;
+
+    let commits = stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let (oid, timestamp, date) = (fields.next()?, fields.next()?, fields.next()?);
+
+            Some(CommitInfo {
+                oid: oid.to_string(),
+                timestamp: timestamp.parse().ok()?,
+                date: date.to_string(),
+            })
+        })
+        .collect();
+
+    Ok(commits)
+}
+
+/// Returns whether an object ID is the all-zero ID that `git blame` reports
+/// for lines that are not committed yet.
+pub fn is_uncommitted(oid: &str) -> bool {
+    oid.bytes().all(|b| b == b'0')
+}
+
+/// Attributes the lines of a file to the commits that last changed them.
+///
+/// Uses `git blame --porcelain`. Without a revision, the file in the working
+/// tree is blamed, and lines that are not committed yet are attributed to the
+/// all-zero object ID (see [`is_uncommitted`]).
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `revision` - The revision to blame the file at, or `None` for the working tree.
+/// * `file` - The path of the file, relative to `path`.
+///
+/// # Returns
+///
+/// The number of lines of the file last changed by each commit.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The file does not exist at the revision
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::blame_file;
+///
+/// for (commit, lines) in blame_file(".", Some("HEAD"), "src/main.rs").unwrap() {
+///     println!("{commit}: {lines} lines");
+/// }
+/// ```
+pub fn blame_file(
+    path: impl AsRef<Path>,
+    revision: Option<&str>,
+    file: impl AsRef<Path>,
+) -> Result<HashMap<String, usize>> {
+    let mut command = Command::new("git");
+    command.args(["blame", "--porcelain"]);
+    if let Some(revision) = revision {
+        command.arg(revision);
+    }
+
+    let output = command
+        .arg("--")
+        .arg(file.as_ref())
+        .current_dir(path.as_ref())
+        .output()
+        .map_err(|e| GitlsfError::git_with_source("Failed to execute git blame", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitlsfError::git(format!(
+            "git blame failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    Ok(parse_blame(&output.stdout))
+}
+
+/// Sums the line counts of the groups in `git blame --porcelain` output.
+///
+/// Each group of consecutive lines from the same commit starts with a
+/// `<oid> <source line> <result line> <count>` header; the lines themselves
+/// are prefixed with a tab and may contain any bytes.
+fn parse_blame(output: &[u8]) -> HashMap<String, usize> {
+    let mut commits = HashMap::new();
+
+    for line in output.split(|&b| b == b'\n') {
+        if line.first() == Some(&b'\t') {
+            continue;
+        }
+        let Ok(line) = std::str::from_utf8(line) else {
+            continue;
+        };
+
+        let mut fields = line.split(' ');
+        if let (Some(oid), Some(_), Some(_), Some(count), None) = (
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+        ) && oid.len() >= 40
+            && oid.bytes().all(|b| b.is_ascii_hexdigit())
+            && let Ok(count) = count.parse::<usize>()
+        {
+            *commits.entry(oid.to_string()).or_insert(0) += count;
+        }
+    }
+
+    commits
+}
+
+/// The author of a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Author {
+    /// The author name.
+    pub name: String,
+    /// The author email address.
+    pub email: String,
+}
+
+/// Looks up the authors of commits.
+///
+/// Uses a single `git log --no-walk --stdin` process. With `mailmap`, names
+/// and email addresses are resolved through the repository's `.mailmap`, as
+/// `git shortlog` does; otherwise they are returned as recorded in the
+/// commits.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `oids` - The commit object IDs.
+/// * `mailmap` - Whether to resolve authors through `.mailmap`.
+///
+/// # Returns
+///
+/// The author of each commit, keyed by object ID.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - A commit does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::{commit_authors, resolve_revision};
+///
+/// let head = resolve_revision(".", "HEAD").unwrap();
+/// let authors = commit_authors(".", &[head.as_str()], true).unwrap();
+/// println!("{}", authors[&head].email);
+/// ```
+pub fn commit_authors(
+    path: impl AsRef<Path>,
+    oids: &[&str],
+    mailmap: bool,
+) -> Result<HashMap<String, Author>> {
+    if oids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let format = if mailmap {
+        "--format=%H%x00%aN%x00%aE"
+    } else {
+        "--format=%H%x00%an%x00%ae"
+    };
+
+    let mut child = Command::new("git")
+        .args(["log", "--no-walk=unsorted", "--stdin", format])
+        .current_dir(path.as_ref())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| GitlsfError::git_with_source("Failed to execute git log", e))?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let requests: String = oids.iter().map(|oid| format!("{oid}\n")).collect();
+    let writer = thread::spawn(move || stdin.write_all(requests.as_bytes()));
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| GitlsfError::git_with_source("Failed to read git log output", e))?;
+    let _ = writer.join();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitlsfError::git(format!(
+            "git log failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    // Commits written without an encoding header may carry author names in a
+    // legacy encoding, which must not fail the whole run
+    let text = |field: &[u8]| String::from_utf8_lossy(field).into_owned();
+
+    let authors = output
+        .stdout
+        .split(|&b| b == b'\n')
+        .filter_map(|line| {
+            let mut fields = line.split(|&b| b == 0);
+            let (oid, name, email) = (fields.next()?, fields.next()?, fields.next()?);
+
+            Some((
+                text(oid),
+                Author {
+                    name: text(name),
+                    email: text(email),
+                },
+            ))
+        })
+        .collect();
+
+    Ok(authors)
+}
+
+/// The lines added and deleted in one file by a commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedFile {
+    /// The path of the file after the commit, relative to the repository root.
+    pub path: PathBuf,
+    /// Number of lines added, or `None` for a binary file.
+    pub added: Option<usize>,
+    /// Number of lines deleted, or `None` for a binary file.
+    pub deleted: Option<usize>,
+}
+
+/// A commit with the lines it added and deleted per file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitChanges {
+    /// The commit object ID.
+    pub oid: String,
+    /// The author date as seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// The author of the commit.
+    pub author: Author,
+    /// The files the commit changed.
+    pub files: Vec<ChangedFile>,
+}
+
+/// Retrieves the lines added and deleted per file by the commits of a range.
+///
+/// Uses `git log --numstat -z`. Renamed files are detected and reported under
+/// their new path, with only the lines that changed; changes to submodules
+/// are left out, and merge commits have no changes of their own.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `range` - The commits to walk, as accepted by `git log`: a revision such
+///   as `HEAD` for its whole history, or a range such as `v1.0..main`.
+/// * `since` - An optional lower bound on the commit date, in any format
+///   accepted by `git log --since`.
+/// * `until` - An optional upper bound on the commit date, in any format
+///   accepted by `git log --until`.
+/// * `pathspecs` - Limits the changes to these files and directories.
+/// * `mailmap` - Whether to resolve authors through `.mailmap`.
+///
+/// # Returns
+///
+/// The commits, newest first.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The range names a revision that does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::log_numstat;
+///
+/// for commit in log_numstat(".", "v1.0..HEAD", None, None, &[], true).unwrap() {
+///     for file in &commit.files {
+///         println!("{} {:?} {:?}", file.path.display(), file.added, file.deleted);
+///     }
+/// }
+/// ```
+pub fn log_numstat(
+    path: impl AsRef<Path>,
+    range: &str,
+    since: Option<&str>,
+    until: Option<&str>,
+    pathspecs: &[PathBuf],
+    mailmap: bool,
+) -> Result<Vec<CommitChanges>> {
+    let format = if mailmap {
+        "--format=%x01%H%x00%at%x00%aN%x00%aE"
+    } else {
+        "--format=%x01%H%x00%at%x00%an%x00%ae"
+    };
+
+    let mut command = Command::new("git");
+    command.args([
+        "log",
+        "--numstat",
+        "-z",
+        "--find-renames",
+        "--ignore-submodules",
+        format,
+    ]);
+    if let Some(since) = since {
+        command.arg(format!("--since={since}"));
+    }
+    if let Some(until) = until {
+        command.arg(format!("--until={until}"));
+    }
+    command.arg(range);
 
-use std::path::Path;
-use std::process::Command;
+    let output = add_pathspecs(&mut command, pathspecs)
+        .current_dir(path.as_ref())
+        .output()
+        .map_err(|e| GitlsfError::git_with_source("Failed to execute git log", e))?;
 
-use crate::error::{GitlsfError, Result};
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(GitlsfError::git(format!(
+            "git log failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    parse_numstat(&output.stdout)
+}
+
+/// Parses the output of `git log --numstat -z` with the format of [`log_numstat`].
+///
+/// The output is a sequence of NUL-terminated fields. Each commit starts with
+/// a field marked by `\x01`, followed by the timestamp, name and email fields.
+/// Each changed file is an `<added>\t<deleted>\t<path>` field, with `-` counts
+/// for binary files; a renamed file has an empty path, followed by the old and
+/// new paths as fields of their own.
+fn parse_numstat(output: &[u8]) -> Result<Vec<CommitChanges>> {
+    let invalid = || GitlsfError::git("Unexpected git log output");
+    let text = |field: &[u8]| String::from_utf8_lossy(field).into_owned();
+
+    let mut commits: Vec<CommitChanges> = Vec::new();
+    let mut fields = output.split(|&b| b == 0);
+
+    while let Some(field) = fields.next() {
+        // Git separates the header of a commit from its changes with a newline
+        let field = field.strip_prefix(b"\n").unwrap_or(field);
+        if field.is_empty() {
+            continue;
+        }
+
+        if let Some(oid) = field.strip_prefix(b"\x01") {
+            let (timestamp, name, email) = (
+                fields.next().ok_or_else(invalid)?,
+                fields.next().ok_or_else(invalid)?,
+                fields.next().ok_or_else(invalid)?,
+            );
+            commits.push(CommitChanges {
+                oid: text(oid),
+                timestamp: text(timestamp).parse().map_err(|_| invalid())?,
+                author: Author {
+                    name: text(name),
+                    email: text(email),
+                },
+                files: Vec::new(),
+            });
+            continue;
+        }
+
+        let mut stat = field.splitn(3, |&b| b == b'\t');
+        let (added, deleted, file) = (
+            stat.next().ok_or_else(invalid)?,
+            stat.next().ok_or_else(invalid)?,
+            stat.next().ok_or_else(invalid)?,
+        );
+        let path = if file.is_empty() {
+            // A rename: the old path, then the new one
+            fields.next().ok_or_else(invalid)?;
+            fields.next().ok_or_else(invalid)?
+        } else {
+            file
+        };
+
+        let commit = commits.last_mut().ok_or_else(invalid)?;
+        commit.files.push(ChangedFile {
+            path: path_from_bytes(path),
+            added: text(added).parse().ok(),
+            deleted: text(deleted).parse().ok(),
+        });
+    }
+
+    Ok(commits)
+}
 
-/// Retrieves the list of files tracked by Git in the specified directory.
+/// Checks if a directory is within a Git repository.
 ///
-/// Uses `git ls-files` to get all tracked files in the repository.
+/// Uses `git rev-parse --git-dir`, or looks for the Git directory itself
+/// with the `native-index` feature.
 ///
 /// # Arguments
 ///
-/// * `path` - The path to the Git repository (or a subdirectory within it).
+/// * `path` - The path to check.
 ///
 /// # Returns
 ///
-/// A vector of file paths relative to the repository root.
+/// `true` if the path is within a Git repository, `false` otherwise.
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::git::is_git_repository;
+///
+/// if is_git_repository(".") {
+///     println!("This is a Git repository!");
+/// }
+/// ```
+pub fn is_git_repository(path: impl AsRef<Path>) -> bool {
+    #[cfg(feature = "native-index")]
+    return crate::index::is_git_repository(path);
+
+    #[cfg(not(feature = "native-index"))]
+    rev_parse_git_dir(path.as_ref())
+}
+
+/// Checks for a Git repository by running `git`, see [`is_git_repository`].
+#[cfg(any(test, not(feature = "native-index")))]
+pub(crate) fn rev_parse_git_dir(path: &Path) -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--git-dir"])
+        .current_dir(path)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Returns the top-level directory of the working tree containing `path`.
+///
+/// Uses `git rev-parse --show-toplevel`, or finds the `.git` directory
+/// itself with the `native-index` feature.
 ///
 /// # Errors
 ///
 /// Returns an error if:
 /// - The path is not within a Git repository
 /// - The `git` command fails to execute
-/// - The output cannot be parsed as UTF-8
 ///
 /// # Example
 ///
 /// ```no_run
-/// use gitlsf::git::list_files;
+/// use gitlsf::git::repository_root;
 ///
-/// let files = list_files(".").unwrap();
-/// for file in files {
-///     println!("{}", file);
-/// }
+/// let root = repository_root("src").unwrap();
+/// println!("Repository root: {}", root.display());
 /// ```
-pub fn list_files(path: impl AsRef<Path>) -> Result<Vec<String>> {
-    let path = path.as_ref();
+pub fn repository_root(path: impl AsRef<Path>) -> Result<PathBuf> {
+    #[cfg(feature = "native-index")]
+    return crate::index::repository_root(path);
+
+    #[cfg(not(feature = "native-index"))]
+    show_toplevel(path.as_ref())
+}
 
+/// Finds the working tree root by running `git`, see [`repository_root`].
+#[cfg(any(test, not(feature = "native-index")))]
+pub(crate) fn show_toplevel(path: &Path) -> Result<PathBuf> {
     let output = Command::new("git")
-        .arg("ls-files")
+        .args(["rev-parse", "--show-toplevel"])
         .current_dir(path)
         .output()
-        .map_err(|e| GitlsfError::git_with_source("Failed to execute git ls-files", e))?;
+        .map_err(|e| GitlsfError::git_with_source("Failed to execute git rev-parse", e))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -52,50 +1393,69 @@ pub fn list_files(path: impl AsRef<Path>) -> Result<Vec<String>> {
             return Err(GitlsfError::NotAGitRepository);
         }
         return Err(GitlsfError::git(format!(
-            "git ls-files failed: {}",
+            "git rev-parse failed: {}",
             stderr.trim()
         )));
     }
 
     let stdout = String::from_utf8(output.stdout)
-        .map_err(|e| GitlsfError::utf8("git ls-files output", e))?;
+        .map_err(|e| GitlsfError::utf8("git rev-parse output", e))?;
 
-    let files: Vec<String> = stdout
-        .lines()
-        .filter(|line| !line.is_empty())
-        .map(String::from)
-        .collect();
-
-    Ok(files)
+    Ok(PathBuf::from(stdout.trim_end_matches('\n')))
 }
 
-/// Checks if a directory is within a Git repository.
+/// Returns the Git directory shared by all worktrees of the repository containing `path`.
 ///
-/// # Arguments
-///
-/// * `path` - The path to check.
+/// Uses `git rev-parse --git-common-dir`, or finds the `.git` directory
+/// itself with the `native-index` feature. The returned path is absolute.
 ///
-/// # Returns
+/// # Errors
 ///
-/// `true` if the path is within a Git repository, `false` otherwise.
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The `git` command fails to execute
 ///
 /// # Example
 ///
 /// ```no_run
-/// use gitlsf::git::is_git_repository;
+/// use gitlsf::git::common_dir;
 ///
-/// if is_git_repository(".") {
-///     println!("This is a Git repository!");
-/// }
+/// let git_dir = common_dir(".").unwrap();
+/// println!("Git directory: {}", git_dir.display());
 /// ```
-pub fn is_git_repository(path: impl AsRef<Path>) -> bool {
-    let path = path.as_ref();
+pub fn common_dir(path: impl AsRef<Path>) -> Result<PathBuf> {
+    #[cfg(feature = "native-index")]
+    return crate::index::common_dir(path);
 
-    Command::new("git")
-        .args(["rev-parse", "--git-dir"])
+    #[cfg(not(feature = "native-index"))]
+    git_common_dir(path.as_ref())
+}
+
+/// Finds the common Git directory by running `git`, see [`common_dir`].
+#[cfg(any(test, not(feature = "native-index")))]
+pub(crate) fn git_common_dir(path: &Path) -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-common-dir"])
         .current_dir(path)
         .output()
-        .is_ok_and(|output| output.status.success())
+        .map_err(|e| GitlsfError::git_with_source("Failed to execute git rev-parse", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("not a git repository") {
+            return Err(GitlsfError::NotAGitRepository);
+        }
+        return Err(GitlsfError::git(format!(
+            "git rev-parse failed: {}",
+            stderr.trim()
+        )));
+    }
+
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|e| GitlsfError::utf8("git rev-parse output", e))?;
+
+    // The directory is relative to `path` unless it is outside of it
+    Ok(path.join(stdout.trim_end_matches('\n')))
 }
 
 #[cfg(test)]
@@ -141,25 +1501,481 @@ mod tests {
         temp_dir
     }
 
+    fn commit_all(path: &Path, message: &str) {
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        Command::new("git")
+            .args(["commit", "-q", "-m", message])
+            .current_dir(path)
+            .output()
+            .unwrap();
+    }
+
     #[test]
     fn test_list_files_in_git_repo() {
         let temp_dir = setup_git_repo();
-        let files = list_files(temp_dir.path()).unwrap();
+        let files = list_files(temp_dir.path(), FileSet::Tracked, &[]).unwrap();
 
         assert_eq!(files.len(), 2);
-        assert!(files.contains(&"file1.rs".to_string()));
-        assert!(files.contains(&"file2.txt".to_string()));
+        assert!(files.contains(&PathBuf::from("file1.rs")));
+        assert!(files.contains(&PathBuf::from("file2.txt")));
+    }
+
+    #[test]
+    fn test_list_file_sets() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        commit_all(path, "first");
+
+        fs::write(path.join("file1.rs"), "fn main() { changed(); }\n").unwrap();
+        fs::remove_file(path.join("file2.txt")).unwrap();
+        fs::write(path.join("staged.rs"), "fn staged() {}\n").unwrap();
+        Command::new("git")
+            .args(["add", "staged.rs"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        fs::write(path.join("new.rs"), "fn new() {}\n").unwrap();
+        fs::write(path.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(path.join("build.log"), "ignored\n").unwrap();
+        fs::create_dir(path.join("nested")).unwrap();
+        Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(path.join("nested"))
+            .output()
+            .unwrap();
+
+        let list = |file_set| list_files(path, file_set, &[]).unwrap();
+        assert_eq!(
+            list(FileSet::Tracked),
+            ["file1.rs", "file2.txt", "staged.rs"].map(PathBuf::from)
+        );
+        assert_eq!(
+            list(FileSet::Untracked),
+            [".gitignore", "new.rs"].map(PathBuf::from)
+        );
+        assert_eq!(list(FileSet::Modified), [PathBuf::from("file1.rs")]);
+        assert_eq!(list(FileSet::Staged), [PathBuf::from("staged.rs")]);
+        assert!(
+            list_files(path, FileSet::Staged, &[PathBuf::from("file1.rs")])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_list_files_with_pathspecs() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+
+        assert_eq!(
+            list_files(path, FileSet::Tracked, &[PathBuf::from("file1.rs")]).unwrap(),
+            [PathBuf::from("file1.rs")]
+        );
+        // Pathspecs are literal paths, not globs
+        assert!(
+            list_files(path, FileSet::Tracked, &[PathBuf::from("file*")])
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            list_index(path, &[PathBuf::from("file2.txt")])
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect::<Vec<_>>(),
+            [PathBuf::from("file2.txt")]
+        );
     }
 
     #[test]
     fn test_list_files_not_a_git_repo() {
         let temp_dir = TempDir::new().unwrap();
-        let result = list_files(temp_dir.path());
+        let result = list_files(temp_dir.path(), FileSet::Tracked, &[]);
 
         assert!(result.is_err());
         matches!(result.unwrap_err(), GitlsfError::NotAGitRepository);
     }
 
+    #[test]
+    fn test_list_tree_at_revision() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        commit_all(path, "first");
+        fs::write(path.join("file3.rs"), "fn x() {}\n").unwrap();
+        commit_all(path, "second");
+
+        let old = list_tree(path, "HEAD~1", &[]).unwrap();
+        let new = list_tree(path, "HEAD", &[]).unwrap();
+
+        assert_eq!(old.len(), 2);
+        assert_eq!(new.len(), 3);
+        assert!(
+            new.iter()
+                .any(|e| e.path == Path::new("file3.rs") && e.mode == "100644")
+        );
+    }
+
+    #[test]
+    fn test_list_submodules() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        commit_all(path, "first");
+        let head = resolve_revision(path, "HEAD").unwrap();
+        // An uninitialized submodule is an empty directory
+        fs::create_dir_all(path.join("vendor/lib")).unwrap();
+        Command::new("git")
+            .args(["update-index", "--add", "--cacheinfo"])
+            .arg(format!("{SUBMODULE_MODE},{head},vendor/lib"))
+            .current_dir(path)
+            .output()
+            .unwrap();
+
+        let submodules = list_submodules(path, None).unwrap();
+        assert_eq!(
+            submodules,
+            [TreeEntry {
+                mode: SUBMODULE_MODE.to_string(),
+                oid: head,
+                path: PathBuf::from("vendor/lib"),
+            }]
+        );
+        assert!(submodules[0].is_submodule());
+        assert!(list_submodules(path, Some("HEAD")).unwrap().is_empty());
+
+        commit_all(path, "second");
+        assert_eq!(list_submodules(path, Some("HEAD")).unwrap(), submodules);
+        assert!(
+            list_tree(path, "HEAD", &[])
+                .unwrap()
+                .iter()
+                .all(|entry| !entry.is_submodule())
+        );
+    }
+
+    #[test]
+    fn test_list_tree_unknown_revision() {
+        let temp_dir = setup_git_repo();
+        commit_all(temp_dir.path(), "first");
+
+        let result = list_tree(temp_dir.path(), "no-such-tag", &[]);
+
+        assert!(matches!(
+            result,
+            Err(GitlsfError::UnknownRevision { ref revision }) if revision == "no-such-tag"
+        ));
+    }
+
+    #[test]
+    fn test_read_blobs() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        commit_all(path, "first");
+
+        let entries = list_tree(path, "HEAD", &[]).unwrap();
+        let oids: Vec<&str> = entries.iter().map(|e| e.oid.as_str()).collect();
+        let mut contents = Vec::new();
+
+        read_blobs(path, &oids, |index, blob| {
+            let mut content = String::new();
+            blob.read_to_string(&mut content).unwrap();
+            contents.push((entries[index].path.clone(), content));
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            contents,
+            vec![
+                (PathBuf::from("file1.rs"), "fn main() {}\n".to_string()),
+                (PathBuf::from("file2.txt"), "hello\nworld\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_blobs_partial_reads() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        commit_all(path, "first");
+
+        let entries = list_tree(path, "HEAD", &[]).unwrap();
+        let oids: Vec<&str> = entries.iter().map(|e| e.oid.as_str()).collect();
+        let mut first_bytes = Vec::new();
+
+        read_blobs(path, &oids, |_, blob| {
+            let mut byte = [0u8; 1];
+            blob.read_exact(&mut byte).unwrap();
+            first_bytes.push(byte[0]);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(first_bytes, b"fh");
+    }
+
+    #[test]
+    fn test_list_commits() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        commit_all(path, "first");
+        fs::write(path.join("file3.rs"), "fn x() {}\n").unwrap();
+        commit_all(path, "second");
+
+        let commits = list_commits(path, "HEAD", None).unwrap();
+
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].oid, resolve_revision(path, "HEAD").unwrap());
+        assert_eq!(commits[1].oid, resolve_revision(path, "HEAD~1").unwrap());
+        assert!(commits[0].timestamp >= commits[1].timestamp);
+    }
+
+    #[test]
+    fn test_blame_file() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        commit_all(path, "first");
+        let first = resolve_revision(path, "HEAD").unwrap();
+
+        fs::write(path.join("file2.txt"), "hello\nthere\nworld\n").unwrap();
+        commit_all(path, "second");
+        let second = resolve_revision(path, "HEAD").unwrap();
+
+        let blame = blame_file(path, Some("HEAD"), "file2.txt").unwrap();
+        assert_eq!(blame.len(), 2);
+        assert_eq!(blame[&first], 2);
+        assert_eq!(blame[&second], 1);
+
+        assert_eq!(
+            blame_file(path, Some("HEAD~1"), "file2.txt").unwrap()[&first],
+            2
+        );
+
+        fs::write(path.join("file2.txt"), "hello\nthere\nworld\n\tagain\n").unwrap();
+        let blame = blame_file(path, None, "file2.txt").unwrap();
+        let (uncommitted, lines) = blame.iter().find(|(oid, _)| is_uncommitted(oid)).unwrap();
+        assert_eq!(uncommitted.len(), 40);
+        assert_eq!(*lines, 1);
+
+        assert!(blame_file(path, Some("HEAD"), "missing.rs").is_err());
+    }
+
+    #[test]
+    fn test_parse_blame() {
+        let output = b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa 1 1 2\n\
+author A\n\
+summary 1 2 3 4\n\
+filename f\n\
+\tbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb 1 1 9\n\
+aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa 2 2\n\
+\tsecond\n";
+
+        let blame = parse_blame(output);
+
+        assert_eq!(blame.len(), 1);
+        assert_eq!(blame["aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"], 2);
+    }
+
+    #[test]
+    fn test_commit_authors() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        commit_all(path, "first");
+        let head = resolve_revision(path, "HEAD").unwrap();
+        fs::write(
+            path.join(".mailmap"),
+            "Mapped Name <mapped@test.com> <test@test.com>\n",
+        )
+        .unwrap();
+
+        let authors = commit_authors(path, &[head.as_str()], false).unwrap();
+        assert_eq!(
+            authors[&head],
+            Author {
+                name: "Test User".to_string(),
+                email: "test@test.com".to_string(),
+            }
+        );
+
+        let authors = commit_authors(path, &[head.as_str()], true).unwrap();
+        assert_eq!(authors[&head].name, "Mapped Name");
+        assert_eq!(authors[&head].email, "mapped@test.com");
+
+        assert!(commit_authors(path, &[], true).unwrap().is_empty());
+        assert!(commit_authors(path, &["0123456789012345678901234567890123456789"], true).is_err());
+    }
+
+    #[test]
+    fn test_commit_authors_legacy_encoding() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+
+        // `git commit` converts invalid author names to UTF-8, so write a raw
+        // commit object like older tools did
+        let output = Command::new("git")
+            .arg("write-tree")
+            .current_dir(path)
+            .output()
+            .unwrap();
+        let tree = String::from_utf8(output.stdout).unwrap();
+        let mut object = format!("tree {}\n", tree.trim()).into_bytes();
+        object.extend_from_slice(b"author Jos\xe9 <test@test.com> 0 +0000\n");
+        object.extend_from_slice(b"committer Jos\xe9 <test@test.com> 0 +0000\n\nfirst\n");
+        let object_path = path.join("commit.bin");
+        fs::write(&object_path, object).unwrap();
+        let output = Command::new("git")
+            .args(["hash-object", "-t", "commit", "-w"])
+            .arg(&object_path)
+            .current_dir(path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let head = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+        let authors = commit_authors(path, &[head.as_str()], false).unwrap();
+        assert_eq!(authors[&head].name, "Jos\u{fffd}");
+        assert_eq!(authors[&head].email, "test@test.com");
+    }
+
+    #[test]
+    fn test_log_numstat() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        commit_all(path, "first");
+
+        fs::write(path.join("file2.txt"), "hello\nthere\n").unwrap();
+        Command::new("git")
+            .args(["mv", "file1.rs", "main.rs"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        fs::write(path.join("image.bin"), [0u8, 1, 2]).unwrap();
+        commit_all(path, "second");
+
+        let commits = log_numstat(path, "HEAD", None, None, &[], true).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].oid, resolve_revision(path, "HEAD").unwrap());
+        assert_eq!(commits[0].author.email, "test@test.com");
+        assert_eq!(
+            commits[0].files,
+            [
+                ChangedFile {
+                    path: PathBuf::from("file2.txt"),
+                    added: Some(1),
+                    deleted: Some(1),
+                },
+                ChangedFile {
+                    path: PathBuf::from("image.bin"),
+                    added: None,
+                    deleted: None,
+                },
+                ChangedFile {
+                    path: PathBuf::from("main.rs"),
+                    added: Some(0),
+                    deleted: Some(0),
+                },
+            ]
+        );
+        assert_eq!(commits[1].files.len(), 2);
+
+        let commits = log_numstat(path, "HEAD~1..HEAD", None, None, &[], true).unwrap();
+        assert_eq!(commits.len(), 1);
+
+        let commits = log_numstat(
+            path,
+            "HEAD",
+            None,
+            None,
+            &[PathBuf::from("file2.txt")],
+            true,
+        )
+        .unwrap();
+        assert!(commits.iter().all(|commit| commit.files.len() == 1));
+
+        let commits = log_numstat(
+            path,
+            "HEAD",
+            Some("1970-01-02"),
+            Some("1970-01-03"),
+            &[],
+            true,
+        );
+        assert!(commits.unwrap().is_empty());
+
+        assert!(log_numstat(path, "missing..HEAD", None, None, &[], true).is_err());
+    }
+
+    #[test]
+    fn test_parse_numstat() {
+        let output = b"\x01aaaa\x001700000000\x00Ann\x00ann@x.org\x00\n\
+3\t1\tsrc/a.rs\x000\t0\t\x00old\tname.rs\x00new\tname.rs\x00\
+\x01bbbb\x001600000000\x00Bob\x00bob@x.org\x00";
+
+        let commits = parse_numstat(output).unwrap();
+
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].timestamp, 1_700_000_000);
+        assert_eq!(commits[0].files[0].added, Some(3));
+        assert_eq!(commits[0].files[1].path, Path::new("new\tname.rs"));
+        assert_eq!(commits[1].author.name, "Bob");
+        assert!(commits[1].files.is_empty());
+
+        assert!(parse_numstat(b"1\t2\tfile.rs\x00").is_err());
+    }
+
+    #[test]
+    fn test_list_index() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+
+        let entries = list_index(path, &[]).unwrap();
+        let paths: Vec<&Path> = entries.iter().map(|e| e.path.as_path()).collect();
+
+        assert_eq!(paths, [Path::new("file1.rs"), Path::new("file2.txt")]);
+        assert_eq!(entries[0].mode, "100644");
+        assert_eq!(entries[0].oid.len(), 40);
+
+        // The index holds the same blobs as the committed tree
+        commit_all(path, "initial");
+        assert_eq!(entries, list_tree(path, "HEAD", &[]).unwrap());
+    }
+
+    #[test]
+    fn test_modified_files() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        assert!(modified_files(path, &[]).unwrap().is_empty());
+
+        fs::write(path.join("file2.txt"), "changed\n").unwrap();
+        fs::remove_file(path.join("file1.rs")).unwrap();
+        fs::write(path.join("untracked.rs"), "\n").unwrap();
+
+        assert_eq!(
+            modified_files(path, &[]).unwrap(),
+            [PathBuf::from("file1.rs"), PathBuf::from("file2.txt")]
+        );
+    }
+
+    #[test]
+    fn test_common_dir() {
+        let temp_dir = setup_git_repo();
+        let path = temp_dir.path();
+        fs::create_dir(path.join("sub")).unwrap();
+
+        let from_root = common_dir(path).unwrap();
+        let from_sub = common_dir(path.join("sub")).unwrap();
+
+        assert!(from_root.ends_with(".git"));
+        assert_eq!(
+            fs::canonicalize(from_root).unwrap(),
+            fs::canonicalize(from_sub).unwrap()
+        );
+        assert!(common_dir(TempDir::new().unwrap().path()).is_err());
+    }
+
     #[test]
     fn test_is_git_repository_true() {
         let temp_dir = setup_git_repo();
@@ -171,4 +1987,17 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         assert!(!is_git_repository(temp_dir.path()));
     }
+
+    #[test]
+    fn test_repository_root() {
+        let temp_dir = setup_git_repo();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+
+        assert_eq!(repository_root(root.join("src")).unwrap(), root);
+        assert!(matches!(
+            repository_root(TempDir::new().unwrap().path()),
+            Err(GitlsfError::NotAGitRepository)
+        ));
+    }
 }
@@ -0,0 +1,453 @@
+//! Line count history module.
+//!
+//! This module samples commits along the first-parent history of a revision
+//! and counts lines at each sample, producing a time series of codebase size.
+//! Counts of blobs that did not change between samples are reused, so only
+//! modified files are read again.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::counter::{CountSummary, FileCount, SkipReason, SkippedFile};
+use crate::error::Result;
+use crate::filter::FileFilter;
+use crate::git::{self, CommitInfo};
+use crate::language::Language;
+
+/// Number of seconds in a day.
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Identifies a file count that can be reused between samples: the path, the
+/// blob object ID and the language assigned by `linguist-language`, so that a
+/// `.gitattributes` change recounts the files it affects.
+type CountKey = (PathBuf, String, Option<Language>);
+
+/// How commits are sampled from the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sampling {
+    /// Every N-th commit, counting back from the newest one.
+    EveryN(usize),
+    /// The last commit of each day (UTC, by committer date).
+    Daily,
+    /// The last commit of each week starting on Monday (UTC, by committer date).
+    Weekly,
+}
+
+impl Sampling {
+    /// Selects the sampled commits from a newest-first list, returning them oldest first.
+    ///
+    /// The newest commit is always included.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::git::CommitInfo;
+    /// use gitlsf::history::Sampling;
+    ///
+    /// let commits: Vec<CommitInfo> = (0..5)
+    ///     .rev()
+    ///     .map(|i| CommitInfo {
+    ///         oid: format!("c{i}"),
+    ///         timestamp: i * 3600,
+    ///         date: String::new(),
+    ///     })
+    ///     .collect();
+    ///
+    /// let sampled = Sampling::EveryN(2).select(&commits);
+    /// let oids: Vec<&str> = sampled.iter().map(|c| c.oid.as_str()).collect();
+    /// assert_eq!(oids, ["c0", "c2", "c4"]);
+    ///
+    /// // All five commits are on the same day
+    /// assert_eq!(Sampling::Daily.select(&commits).len(), 1);
+    /// ```
+    pub fn select<'a>(&self, commits: &'a [CommitInfo]) -> Vec<&'a CommitInfo> {
+        let mut selected: Vec<&CommitInfo> = match *self {
+            Sampling::EveryN(n) => commits.iter().step_by(n.max(1)).collect(),
+            Sampling::Daily => latest_per_bucket(commits, |ts| ts.div_euclid(SECONDS_PER_DAY)),
+            Sampling::Weekly => latest_per_bucket(commits, |ts| {
+                // The Unix epoch was a Thursday; shift so that weeks start on Monday
+                (ts.div_euclid(SECONDS_PER_DAY) + 3).div_euclid(7)
+            }),
+        };
+
+        selected.reverse();
+        selected
+    }
+}
+
+/// Keeps the first (newest) commit of each bucket from a newest-first list.
+fn latest_per_bucket(commits: &[CommitInfo], bucket: impl Fn(i64) -> i64) -> Vec<&CommitInfo> {
+    let mut seen = BTreeSet::new();
+
+    commits
+        .iter()
+        .filter(|commit| seen.insert(bucket(commit.timestamp)))
+        .collect()
+}
+
+/// The line counts at one sampled commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryPoint {
+    /// The commit object ID.
+    pub commit: String,
+    /// The committer date as seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// The committer date in strict ISO 8601 format.
+    pub date: String,
+    /// The counting results at the commit.
+    pub summary: CountSummary,
+}
+
+/// Computes line counts at sampled commits of a revision's history.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `revision` - The revision whose history is walked, such as `HEAD`.
+/// * `since` - An optional lower bound on the commit date, in any format
+///   accepted by `git log --since`.
+/// * `sampling` - How commits are sampled.
+/// * `filter` - The file filter to use at every sample.
+///
+/// # Returns
+///
+/// One point per sampled commit, oldest first.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::filter::FileFilter;
+/// use gitlsf::history::{Sampling, history};
+///
+/// let points = history(".", "HEAD", Some("2 years ago"), Sampling::Weekly, FileFilter::new())
+///     .unwrap();
+/// for point in &points {
+///     println!("{} {}", point.date, point.summary.total_lines);
+/// }
+/// ```
+pub fn history(
+    path: impl AsRef<Path>,
+    revision: &str,
+    since: Option<&str>,
+    sampling: Sampling,
+    filter: FileFilter,
+) -> Result<Vec<HistoryPoint>> {
+    let path = path.as_ref();
+    let commits = git::list_commits(path, revision, since)?;
+
+    // Counts from the previous sample; files skipped by their attributes are
+    // never kept, and binary blobs are kept as `None` so they are not read again
+    let mut previous: HashMap<CountKey, Option<FileCount>> = HashMap::new();
+    let mut points = Vec::new();
+
+    for commit in sampling.select(&commits) {
+        let entries: Vec<git::TreeEntry> = git::list_tree(path, &commit.oid, filter.pathspecs())?
+            .into_iter()
+            .filter(|entry| !entry.is_symlink() && filter.should_count(&entry.path))
+            .collect();
+        let selection =
+            crate::apply_attributes(path, Some(&commit.oid), &filter, entries, |entry| {
+                &entry.path
+            })?;
+
+        let mut current = HashMap::with_capacity(selection.files.len());
+        let mut changed = Vec::new();
+
+        let key = |entry: &git::TreeEntry| {
+            let language = selection.languages.get(&entry.path).copied();
+            (entry.path.clone(), entry.oid.clone(), language)
+        };
+
+        for entry in selection.files {
+            let key = key(&entry);
+            match previous.remove(&key) {
+                Some(count) => {
+                    current.insert(key, count);
+                }
+                None => changed.push(entry),
+            }
+        }
+
+        for (entry, count) in changed.iter().zip(crate::count_tree_entries(
+            path,
+            &changed,
+            &filter,
+            &selection.languages,
+        )?) {
+            current.insert(key(entry), count);
+        }
+
+        let mut counts = Vec::with_capacity(current.len());
+        let mut skipped = selection.skipped;
+        for ((file, _, _), count) in &current {
+            match count {
+                Some(count) => counts.push(count.clone()),
+                None => skipped.push(SkippedFile::new(file.clone(), SkipReason::Binary)),
+            }
+        }
+        counts.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+
+        points.push(HistoryPoint {
+            commit: commit.oid.clone(),
+            timestamp: commit.timestamp,
+            date: commit.date.clone(),
+            summary: CountSummary::from_counts(counts).with_skipped(skipped),
+        });
+
+        previous = current;
+    }
+
+    Ok(points)
+}
+
+/// A history point flattened for serialization.
+#[derive(Debug, Serialize)]
+struct HistoryRow<'a> {
+    timestamp: &'a str,
+    commit: &'a str,
+    files: usize,
+    lines: usize,
+    code: usize,
+    comment: usize,
+    blank: usize,
+    languages: BTreeMap<&'static str, usize>,
+}
+
+impl<'a> From<&'a HistoryPoint> for HistoryRow<'a> {
+    fn from(point: &'a HistoryPoint) -> Self {
+        let summary = &point.summary;
+
+        Self {
+            timestamp: &point.date,
+            commit: &point.commit,
+            files: summary.file_count,
+            lines: summary.total_lines,
+            code: summary.total_code,
+            comment: summary.total_comment,
+            blank: summary.total_blank,
+            languages: summary
+                .by_language()
+                .iter()
+                .map(|language| (language.name(), language.stats.lines))
+                .collect(),
+        }
+    }
+}
+
+/// Writes history points as CSV.
+///
+/// The columns are `timestamp`, `commit`, `files`, `lines`, `code`,
+/// `comment` and `blank`, followed by one column per language that appears
+/// in any point, holding its line count.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::filter::FileFilter;
+/// use gitlsf::history::{Sampling, history, write_csv};
+///
+/// let points = history(".", "HEAD", None, Sampling::EveryN(10), FileFilter::new()).unwrap();
+/// write_csv(std::io::stdout().lock(), &points).unwrap();
+/// ```
+pub fn write_csv<W: Write>(mut writer: W, points: &[HistoryPoint]) -> io::Result<()> {
+    let rows: Vec<HistoryRow> = points.iter().map(HistoryRow::from).collect();
+    let languages: BTreeSet<&str> = rows
+        .iter()
+        .flat_map(|row| row.languages.keys().copied())
+        .collect();
+
+    write!(writer, "timestamp,commit,files,lines,code,comment,blank")?;
+    for language in &languages {
+        write!(writer, ",{language}")?;
+    }
+    writeln!(writer)?;
+
+    for row in &rows {
+        write!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            row.timestamp, row.commit, row.files, row.lines, row.code, row.comment, row.blank
+        )?;
+        for language in &languages {
+            write!(writer, ",{}", row.languages.get(language).unwrap_or(&0))?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes history points as a JSON array.
+///
+/// Each element has the fields `timestamp`, `commit`, `files`, `lines`,
+/// `code`, `comment` and `blank`, plus a `languages` object mapping
+/// language names to line counts.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+pub fn write_json<W: Write>(mut writer: W, points: &[HistoryPoint]) -> io::Result<()> {
+    let rows: Vec<HistoryRow> = points.iter().map(HistoryRow::from).collect();
+
+    serde_json::to_writer_pretty(&mut writer, &rows)?;
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn commit(oid: &str, timestamp: i64) -> CommitInfo {
+        CommitInfo {
+            oid: oid.to_string(),
+            timestamp,
+            date: String::new(),
+        }
+    }
+
+    fn oids(commits: Vec<&CommitInfo>) -> Vec<&str> {
+        commits.into_iter().map(|c| c.oid.as_str()).collect()
+    }
+
+    #[test]
+    fn test_every_n_includes_newest() {
+        let commits: Vec<CommitInfo> = (0..7).rev().map(|i| commit(&i.to_string(), i)).collect();
+
+        assert_eq!(oids(Sampling::EveryN(3).select(&commits)), ["0", "3", "6"]);
+    }
+
+    #[test]
+    fn test_daily_keeps_last_commit_of_day() {
+        let day = SECONDS_PER_DAY;
+        let commits = vec![
+            commit("d2-late", 2 * day + 500),
+            commit("d2-early", 2 * day + 100),
+            commit("d1", day + 10),
+            commit("d0-late", 900),
+            commit("d0-early", 100),
+        ];
+
+        assert_eq!(
+            oids(Sampling::Daily.select(&commits)),
+            ["d0-late", "d1", "d2-late"]
+        );
+    }
+
+    #[test]
+    fn test_weekly_starts_on_monday() {
+        let day = SECONDS_PER_DAY;
+        // 1970-01-04 was a Sunday and 1970-01-05 a Monday
+        let commits = vec![
+            commit("monday", 4 * day),
+            commit("sunday", 3 * day),
+            commit("thursday", 0),
+        ];
+
+        assert_eq!(
+            oids(Sampling::Weekly.select(&commits)),
+            ["sunday", "monday"]
+        );
+    }
+
+    #[test]
+    fn test_history_counts_each_sample() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        git(path, &["init", "-q"]);
+        git(path, &["config", "user.email", "test@test.com"]);
+        git(path, &["config", "user.name", "Test User"]);
+
+        fs::write(path.join("a.rs"), "fn a() {}\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "one"]);
+
+        fs::write(path.join("b.py"), "# b\nprint(1)\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "two"]);
+
+        fs::write(path.join("a.rs"), "fn a() {}\nfn c() {}\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "three"]);
+
+        let points = history(path, "HEAD", None, Sampling::EveryN(1), FileFilter::new()).unwrap();
+        let totals: Vec<usize> = points.iter().map(|p| p.summary.total_lines).collect();
+
+        assert_eq!(totals, [1, 3, 4]);
+        assert_eq!(points[2].summary.total_comment, 1);
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &points).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "timestamp,commit,files,lines,code,comment,blank,Python,Rust"
+        );
+        assert!(lines[1].ends_with(",1,1,1,0,0,0,1"));
+        assert!(lines[3].ends_with(",2,4,3,1,0,2,2"));
+    }
+
+    #[test]
+    fn test_history_recounts_after_attribute_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        git(path, &["init", "-q"]);
+        git(path, &["config", "user.email", "test@test.com"]);
+        git(path, &["config", "user.name", "Test User"]);
+
+        fs::write(path.join("build"), "# setup\nrun()\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "one"]);
+
+        fs::write(
+            path.join(".gitattributes"),
+            "build linguist-language=Python\n",
+        )
+        .unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "two"]);
+
+        let points = history(path, "HEAD", None, Sampling::EveryN(1), FileFilter::new()).unwrap();
+        let languages: Vec<_> = points.iter().map(|p| p.summary.files[0].language).collect();
+
+        assert_eq!(languages, [None, Some(Language::Python)]);
+        assert_eq!(points[0].summary.total_comment, 0);
+        assert_eq!(points[1].summary.total_comment, 1);
+    }
+
+    #[test]
+    fn test_write_json() {
+        let points = vec![HistoryPoint {
+            commit: "abc".to_string(),
+            timestamp: 0,
+            date: "1970-01-01T00:00:00+00:00".to_string(),
+            summary: CountSummary::from_counts(vec![FileCount::new("a.rs", 3)]),
+        }];
+
+        let mut json = Vec::new();
+        write_json(&mut json, &points).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(value[0]["commit"], "abc");
+        assert_eq!(value[0]["lines"], 3);
+        assert_eq!(value[0]["languages"]["Other"], 3);
+    }
+}
@@ -0,0 +1,1731 @@
+//! Native Git index reader module.
+//!
+//! This module reads the files tracked in a repository directly from its
+//! index file, `.git/index`, without running `git`. It is compiled with the
+//! `native-index` cargo feature, which makes [`git::list_files`] for
+//! [`FileSet::Tracked`], [`git::list_index`], [`git::is_git_repository`],
+//! [`git::repository_root`] and [`git::common_dir`] use it instead of
+//! `git ls-files` and `git rev-parse`. gitlsf then lists
+//! files where no `git` binary is installed, such as in minimal containers,
+//! and without spawning a process for each listing.
+//!
+//! The repository is found as Git finds it, from the nearest ancestor
+//! directory that contains a `.git` directory, or a `.git` file naming the
+//! Git directory of a linked worktree or submodule, or that is a Git
+//! directory itself. Linked worktrees have an index of their own but share
+//! the objects of the main repository, found through their `commondir` file.
+//! Environment variables such as `GIT_DIR` and `GIT_INDEX_FILE` and the
+//! `core.worktree` setting are not read.
+//!
+//! Index versions 2 to 4 are supported, including:
+//!
+//! - Extended flags, as of intent-to-add and skip-worktree entries (version 3)
+//! - Paths compressed against the previous entry (version 4)
+//! - Split indexes, whose entries are merged into those of the shared index
+//!   named by their `link` extension
+//! - Sparse directory entries of sparse indexes, expanded into the files of
+//!   their trees as `git ls-files` does; trees are read from loose objects
+//!   and pack files
+//!
+//! The root of the working tree and the common Git directory are found the
+//! same way, and [`git::modified_files`] compares the size and modification
+//! time of files with those recorded in the index instead of running
+//! `git diff`. Attributes are only looked up with `git check-attr` if an
+//! attributes file may apply, so that a repository without any can be counted
+//! without `git`.
+//!
+//! Repositories with SHA-256 object IDs are read as well. Other extensions,
+//! such as the cache tree and the untracked cache, are skipped, and the
+//! checksum at the end of the index is not verified.
+//!
+//! [`git::list_files`]: crate::git::list_files
+//! [`git::list_index`]: crate::git::list_index
+//! [`git::is_git_repository`]: crate::git::is_git_repository
+//! [`git::repository_root`]: crate::git::repository_root
+//! [`git::common_dir`]: crate::git::common_dir
+//! [`git::modified_files`]: crate::git::modified_files
+//! [`FileSet::Tracked`]: crate::git::FileSet::Tracked
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+use memmap2::Mmap;
+
+use crate::error::{GitlsfError, Result};
+use crate::git::{TreeEntry, path_from_bytes};
+
+/// Type bits of file modes.
+const TYPE_MASK: u32 = 0o170000;
+
+/// File mode of trees, and type bits of sparse directory entries.
+const DIRECTORY_MODE: u32 = 0o040000;
+
+/// Flag of index entries with a second, extended flags field.
+const EXTENDED_FLAG: u16 = 0x4000;
+
+/// Mask of the path length in the flags of index entries.
+const NAME_MASK: u16 = 0x0fff;
+
+/// Length of the timestamps, device, inode, mode, user, group and size
+/// fields at the start of each index entry.
+const STAT_LENGTH: usize = 40;
+
+/// Offset of the modification time within the fields of [`STAT_LENGTH`].
+const MTIME_OFFSET: usize = 8;
+
+/// Offset of the mode within the fields of [`STAT_LENGTH`].
+const MODE_OFFSET: usize = 24;
+
+/// Offset of the size within the fields of [`STAT_LENGTH`].
+const SIZE_OFFSET: usize = 36;
+
+/// File mode of symbolic links.
+const SYMLINK_MODE: u32 = 0o120000;
+
+/// File mode of submodules.
+const GITLINK_MODE: u32 = 0o160000;
+
+/// Object type of trees, in pack files.
+const TREE_TYPE: u8 = 2;
+
+/// Object type of deltas against an object at an earlier offset of a pack.
+const OFS_DELTA_TYPE: u8 = 6;
+
+/// Object type of deltas against an object named by its ID.
+const REF_DELTA_TYPE: u8 = 7;
+
+/// The Git directories of a repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitDir {
+    /// The root of the working tree, or `None` for a bare repository.
+    pub work_tree: Option<PathBuf>,
+    /// The Git directory of the working tree, holding its index.
+    pub git_dir: PathBuf,
+    /// The Git directory shared by all worktrees, holding objects and refs.
+    pub common_dir: PathBuf,
+}
+
+impl GitDir {
+    /// Finds the repository containing `path`.
+    ///
+    /// Each directory from `path` up to the root of the file system is
+    /// checked for a `.git` directory or file, then for being a Git directory
+    /// itself. The working tree root is returned with symbolic links resolved.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The path does not exist
+    /// - The path is not within a Git repository
+    /// - A `.git` file does not name a Git directory
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::index::GitDir;
+    ///
+    /// let git_dir = GitDir::discover("src").unwrap();
+    /// println!("Index: {}", git_dir.git_dir.join("index").display());
+    /// ```
+    pub fn discover(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let start = path.canonicalize().map_err(|e| GitlsfError::io(path, e))?;
+
+        for directory in start.ancestors() {
+            let dot_git = directory.join(".git");
+            let git_dir = if dot_git.is_file() {
+                Some(read_gitfile(&dot_git)?)
+            } else if dot_git.is_dir() {
+                Some(dot_git)
+            } else {
+                None
+            };
+
+            if let Some(found) = git_dir.and_then(|git_dir| Self::open(git_dir, Some(directory))) {
+                return Ok(found);
+            }
+            if let Some(found) = Self::open(directory.to_path_buf(), None) {
+                return Ok(found);
+            }
+        }
+
+        Err(GitlsfError::NotAGitRepository)
+    }
+
+    /// Returns the repository at a Git directory, if it is one.
+    ///
+    /// Like Git, a directory is taken as a Git directory if it has a `HEAD`
+    /// file, and its common directory `objects` and `refs` directories.
+    fn open(git_dir: PathBuf, work_tree: Option<&Path>) -> Option<Self> {
+        let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common_dir) => git_dir.join(common_dir.trim_end_matches(['\n', '\r'])),
+            Err(_) => git_dir.clone(),
+        };
+
+        let valid = git_dir.join("HEAD").is_file()
+            && common_dir.join("objects").is_dir()
+            && common_dir.join("refs").is_dir();
+
+        valid.then(|| GitDir {
+            work_tree: work_tree.map(Path::to_path_buf),
+            git_dir,
+            common_dir,
+        })
+    }
+}
+
+/// Reads the Git directory named by a `.git` file, as `gitdir: <path>`.
+///
+/// A relative path is relative to the directory of the `.git` file.
+fn read_gitfile(path: &Path) -> Result<PathBuf> {
+    let contents = fs::read(path).map_err(|e| GitlsfError::io(path, e))?;
+    let target = contents
+        .strip_prefix(b"gitdir: ")
+        .ok_or_else(|| GitlsfError::git_file(path, "expected 'gitdir: <path>'"))?;
+    let target = target.strip_suffix(b"\n").unwrap_or(target);
+    let target = target.strip_suffix(b"\r").unwrap_or(target);
+
+    let parent = path.parent().unwrap_or(Path::new("."));
+    Ok(parent.join(path_from_bytes(target)))
+}
+
+/// Returns the length of the object IDs of a repository, in bytes.
+///
+/// Object IDs are SHA-1 hashes unless `extensions.objectFormat` is `sha256`
+/// in the repository configuration.
+fn object_id_length(common_dir: &Path) -> Result<usize> {
+    let path = common_dir.join("config");
+    let config = match fs::read(&path) {
+        Ok(config) => config,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(20),
+        Err(e) => return Err(GitlsfError::io(path, e)),
+    };
+
+    let mut section = String::new();
+    for line in String::from_utf8_lossy(&config).lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            section = header.trim_end_matches(']').trim().to_ascii_lowercase();
+        } else if section == "extensions" {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if key.trim().eq_ignore_ascii_case("objectformat")
+                && value.trim().eq_ignore_ascii_case("sha256")
+            {
+                return Ok(32);
+            }
+        }
+    }
+
+    Ok(20)
+}
+
+/// An entry of the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The file mode, such as `0o100644`.
+    pub mode: u32,
+    /// The object ID of the blob, or of the commit checked out in a submodule.
+    pub oid: Vec<u8>,
+    /// The merge stage: 0 for files without conflicts, 1 to 3 for the
+    /// common ancestor, ours and theirs versions of a conflicted file.
+    pub stage: u8,
+    /// The path relative to the root of the working tree, as recorded by Git.
+    pub path: Vec<u8>,
+    /// The modification time of the file when it was added, in seconds and
+    /// nanoseconds, or zero for the files of expanded sparse directories.
+    pub mtime: (u32, u32),
+    /// The size of the file when it was added, truncated to 32 bits.
+    pub size: u32,
+}
+
+impl IndexEntry {
+    /// Returns the object ID as a hexadecimal string.
+    pub fn oid_hex(&self) -> String {
+        hex(&self.oid)
+    }
+
+    /// Returns `true` if the entry is a directory of a sparse index, standing
+    /// for all files of its tree.
+    fn is_sparse_directory(&self) -> bool {
+        self.mode & TYPE_MASK == DIRECTORY_MODE && self.path.ends_with(b"/")
+    }
+
+    /// Orders entries as in the index, by path and then by stage.
+    fn key(&self) -> (&[u8], u8) {
+        (&self.path, self.stage)
+    }
+}
+
+/// The entries of an index, merged with its shared index and with sparse
+/// directories expanded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Index {
+    /// The version of the index file, or 0 if there is none.
+    pub version: u32,
+    /// The entries, sorted by path and stage.
+    pub entries: Vec<IndexEntry>,
+}
+
+impl Index {
+    /// Reads the index of a working tree.
+    ///
+    /// A missing index file, as in a repository where nothing was ever
+    /// added, yields an empty index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The index or an object it refers to cannot be read
+    /// - The index uses an unsupported version or is truncated
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::index::{GitDir, Index};
+    ///
+    /// let index = Index::read(&GitDir::discover(".").unwrap()).unwrap();
+    /// for entry in &index.entries {
+    ///     println!("{:o} {}", entry.mode, String::from_utf8_lossy(&entry.path));
+    /// }
+    /// ```
+    pub fn read(git_dir: &GitDir) -> Result<Self> {
+        let id_length = object_id_length(&git_dir.common_dir)?;
+        let path = git_dir.git_dir.join("index");
+        let Some(mut index) = parse_index_file(&path, id_length)? else {
+            return Ok(Index::default());
+        };
+
+        if let Some(link) = index.link.take() {
+            let shared = format!("sharedindex.{}", hex(&link.shared));
+            let shared_path = git_dir.git_dir.join(shared);
+            let base = parse_index_file(&shared_path, id_length)?
+                .ok_or_else(|| GitlsfError::git_file(&path, "the shared index does not exist"))?;
+            index.entries = merge_split_index(base.entries, index.entries, &link)
+                .map_err(|message| GitlsfError::git_file(&path, message))?;
+        }
+
+        if index.entries.iter().any(IndexEntry::is_sparse_directory) {
+            let objects = ObjectDatabase::open(&git_dir.common_dir.join("objects"), id_length)?;
+            let mut entries = Vec::with_capacity(index.entries.len());
+            for entry in index.entries {
+                if entry.is_sparse_directory() {
+                    objects.expand_tree(&entry.oid, entry.path, &mut entries)?;
+                } else {
+                    entries.push(entry);
+                }
+            }
+            index.entries = entries;
+        }
+
+        Ok(Index {
+            version: index.version,
+            entries: index.entries,
+        })
+    }
+}
+
+/// The `link` extension of a split index.
+#[derive(Debug, Default)]
+struct Link {
+    /// The object ID naming the shared index.
+    shared: Vec<u8>,
+    /// Positions of the shared entries deleted by the split index.
+    delete: Vec<usize>,
+    /// Positions of the shared entries replaced by the first entries of the
+    /// split index, in order.
+    replace: Vec<usize>,
+}
+
+/// The contents of one index file.
+#[derive(Debug)]
+struct IndexFile {
+    version: u32,
+    entries: Vec<IndexEntry>,
+    link: Option<Link>,
+}
+
+/// A reader of big-endian binary data.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    fn take(&mut self, length: usize) -> std::result::Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or("unexpected end of data")?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> std::result::Result<u16, String> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> std::result::Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> std::result::Result<u64, String> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads a variable-length integer with Git's offset encoding, used for
+    /// path prefixes in index version 4 and for delta bases in pack files.
+    fn offset(&mut self) -> std::result::Result<usize, String> {
+        let mut byte = self.take(1)?[0];
+        let mut value = usize::from(byte & 0x7f);
+        while byte & 0x80 != 0 {
+            byte = self.take(1)?[0];
+            value = ((value + 1) << 7) | usize::from(byte & 0x7f);
+        }
+        Ok(value)
+    }
+
+    /// Reads bytes up to a NUL byte, which is skipped.
+    fn until_nul(&mut self) -> std::result::Result<&'a [u8], String> {
+        let length = memchr::memchr(0, &self.data[self.position..]).ok_or("unterminated path")?;
+        let bytes = self.take(length)?;
+        self.position += 1;
+        Ok(bytes)
+    }
+}
+
+/// Reads an index file, or returns `None` if it does not exist.
+fn parse_index_file(path: &Path, id_length: usize) -> Result<Option<IndexFile>> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(GitlsfError::io(path, e)),
+    };
+
+    parse_index(&data, id_length)
+        .map(Some)
+        .map_err(|message| GitlsfError::git_file(path, message))
+}
+
+/// Parses the header, entries and extensions of an index file.
+fn parse_index(data: &[u8], id_length: usize) -> std::result::Result<IndexFile, String> {
+    let mut reader = Reader::new(data);
+    if reader.take(4)? != b"DIRC" {
+        return Err("missing index signature".to_string());
+    }
+    let version = reader.u32()?;
+    if !(2..=4).contains(&version) {
+        return Err(format!("unsupported index version {version}"));
+    }
+    let count = reader.u32()? as usize;
+
+    let mut entries: Vec<IndexEntry> = Vec::with_capacity(count);
+    for _ in 0..count {
+        let start = reader.position;
+        let stat = reader.take(STAT_LENGTH)?;
+        let field =
+            |offset: usize| u32::from_be_bytes(stat[offset..offset + 4].try_into().unwrap());
+        let mode = field(MODE_OFFSET);
+        let oid = reader.take(id_length)?.to_vec();
+        let flags = reader.u16()?;
+        if version >= 3 && flags & EXTENDED_FLAG != 0 {
+            reader.u16()?;
+        }
+
+        let path = if version == 4 {
+            // The path replaces the end of the previous one
+            let previous = entries.last().map_or(&[][..], |entry| &entry.path);
+            let strip = reader.offset()?;
+            let kept = previous
+                .len()
+                .checked_sub(strip)
+                .ok_or("invalid path prefix")?;
+            let mut path = previous[..kept].to_vec();
+            path.extend_from_slice(reader.until_nul()?);
+            path
+        } else {
+            let length = flags & NAME_MASK;
+            let path = if length == NAME_MASK {
+                reader.until_nul()?
+            } else {
+                reader.take(usize::from(length))?
+            };
+            // Entries are padded with 1 to 8 NUL bytes to a multiple of 8
+            let entry_length = (reader.position - start + 8) & !7;
+            reader.position = start;
+            reader.take(entry_length)?;
+            path.to_vec()
+        };
+
+        entries.push(IndexEntry {
+            mode,
+            oid,
+            stage: ((flags >> 12) & 3) as u8,
+            path,
+            mtime: (field(MTIME_OFFSET), field(MTIME_OFFSET + 4)),
+            size: field(SIZE_OFFSET),
+        });
+    }
+
+    let mut link = None;
+    while reader.remaining() > id_length {
+        let signature = reader.take(4)?;
+        let length = reader.u32()? as usize;
+        let extension = reader.take(length)?;
+        if signature == b"link" {
+            link = Some(parse_link(extension, id_length)?);
+        }
+    }
+
+    Ok(IndexFile {
+        version,
+        entries,
+        link: link.filter(|link| link.shared.iter().any(|&byte| byte != 0)),
+    })
+}
+
+/// Parses the `link` extension: the shared index ID, then optional delete
+/// and replace bitmaps.
+fn parse_link(data: &[u8], id_length: usize) -> std::result::Result<Link, String> {
+    let mut reader = Reader::new(data);
+    let mut link = Link {
+        shared: reader.take(id_length)?.to_vec(),
+        ..Link::default()
+    };
+    if reader.remaining() > 0 {
+        link.delete = parse_ewah(&mut reader)?;
+        link.replace = parse_ewah(&mut reader)?;
+    }
+    Ok(link)
+}
+
+/// Parses an EWAH compressed bitmap, returning the positions of its set bits.
+///
+/// The bitmap is a sequence of 64-bit words. Each marker word holds a run of
+/// all-zero or all-one words (bit 0 the running bit, bits 1 to 32 the run
+/// length) and the number of literal words that follow it (bits 33 to 63).
+fn parse_ewah(reader: &mut Reader) -> std::result::Result<Vec<usize>, String> {
+    let bit_count = reader.u32()? as usize;
+    let word_count = reader.u32()? as usize;
+    let words = (0..word_count)
+        .map(|_| reader.u64())
+        .collect::<std::result::Result<Vec<u64>, String>>()?;
+    // Position of the last marker word, only needed to append to the bitmap
+    reader.u32()?;
+
+    let mut bits = Vec::new();
+    let mut position = 0;
+    let mut words = words.into_iter();
+    while let Some(marker) = words.next() {
+        let run_length = ((marker >> 1) & 0xffff_ffff) as usize * 64;
+        if marker & 1 != 0 {
+            bits.extend(position..position + run_length);
+        }
+        position += run_length;
+
+        for _ in 0..marker >> 33 {
+            let word = words.next().ok_or("truncated bitmap")?;
+            bits.extend(
+                (0..64)
+                    .filter(|bit| word >> bit & 1 != 0)
+                    .map(|bit| position + bit),
+            );
+            position += 64;
+        }
+    }
+
+    bits.retain(|&bit| bit < bit_count);
+    Ok(bits)
+}
+
+/// Merges the entries of a split index into those of its shared index.
+///
+/// The first entries of the split index replace the shared entries at the
+/// positions of the replace bitmap, keeping their paths; shared entries at
+/// the positions of the delete bitmap are removed; the remaining split
+/// entries are added, replacing any shared entry of the same path and stage.
+fn merge_split_index(
+    shared: Vec<IndexEntry>,
+    split: Vec<IndexEntry>,
+    link: &Link,
+) -> std::result::Result<Vec<IndexEntry>, String> {
+    let mut entries: Vec<Option<IndexEntry>> = shared.into_iter().map(Some).collect();
+    let mut split = split.into_iter();
+
+    for &position in &link.replace {
+        let mut replacement = split.next().ok_or("missing replacement entry")?;
+        let entry = entries
+            .get_mut(position)
+            .and_then(Option::as_mut)
+            .ok_or("replaced entry out of range")?;
+        replacement.path = std::mem::take(&mut entry.path);
+        *entry = replacement;
+    }
+    for &position in &link.delete {
+        *entries
+            .get_mut(position)
+            .ok_or("deleted entry out of range")? = None;
+    }
+
+    let mut entries: Vec<IndexEntry> = entries.into_iter().flatten().collect();
+    for entry in split {
+        match entries.binary_search_by(|existing| existing.key().cmp(&entry.key())) {
+            Ok(position) => entries[position] = entry,
+            Err(position) => entries.insert(position, entry),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Formats bytes as lowercase hexadecimal.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// A pack file with its index.
+struct Pack {
+    /// The path of the pack file, for errors.
+    path: PathBuf,
+    /// The `.idx` file, mapping object IDs to offsets in the pack.
+    index: Mmap,
+    /// The `.pack` file.
+    data: Mmap,
+}
+
+impl Pack {
+    /// Opens a pack from the path of its `.idx` file.
+    fn open(index_path: &Path) -> Result<Self> {
+        let path = index_path.with_extension("pack");
+        let index = map_file(index_path)?;
+        if index.get(..8) != Some(b"\xfftOc\x00\x00\x00\x02") {
+            return Err(GitlsfError::git_file(
+                index_path,
+                "unsupported pack index version",
+            ));
+        }
+        let data = map_file(&path)?;
+        Ok(Pack { path, index, data })
+    }
+
+    /// Returns the offset of an object in the pack, if it is there.
+    ///
+    /// Version 2 indexes start with a fan-out table of the number of objects
+    /// whose ID starts with each byte value, followed by the sorted IDs,
+    /// their CRC-32 checksums, their 31-bit offsets and a table of 64-bit
+    /// offsets for packs larger than 2 GiB.
+    fn find(&self, oid: &[u8]) -> Option<usize> {
+        let fanout = |byte: usize| {
+            let start = 8 + byte * 4;
+            u32::from_be_bytes(self.index[start..start + 4].try_into().unwrap()) as usize
+        };
+        let count = fanout(255);
+        let first = usize::from(oid[0]);
+        let (mut low, mut high) = (
+            if first == 0 { 0 } else { fanout(first - 1) },
+            fanout(first),
+        );
+
+        let ids = 8 + 256 * 4;
+        let length = oid.len();
+        let position = loop {
+            if low >= high {
+                return None;
+            }
+            let middle = (low + high) / 2;
+            let start = ids + middle * length;
+            match self.index.get(start..start + length)?.cmp(oid) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => break middle,
+            }
+        };
+
+        let offsets = ids + count * (length + 4);
+        let start = offsets + position * 4;
+        let offset = u32::from_be_bytes(self.index.get(start..start + 4)?.try_into().ok()?);
+        if offset & 0x8000_0000 == 0 {
+            return Some(offset as usize);
+        }
+        let start = offsets + count * 4 + (offset & 0x7fff_ffff) as usize * 8;
+        Some(u64::from_be_bytes(self.index.get(start..start + 8)?.try_into().ok()?) as usize)
+    }
+}
+
+/// Memory-maps a file of the object database.
+///
+/// Git writes pack files once and never changes them afterwards; a pack
+/// removed by a concurrent repack stays readable while it is mapped.
+fn map_file(path: &Path) -> Result<Mmap> {
+    let file = File::open(path).map_err(|e| GitlsfError::io(path, e))?;
+    // SAFETY: The mapping is only read, and Git does not modify pack files
+    unsafe { Mmap::map(&file) }.map_err(|e| GitlsfError::io(path, e))
+}
+
+/// The objects of a repository, loose and packed, with those of its alternates.
+struct ObjectDatabase {
+    /// The object directories, the repository's own first.
+    directories: Vec<PathBuf>,
+    /// The packs of all object directories.
+    packs: Vec<Pack>,
+    /// The length of object IDs, in bytes.
+    id_length: usize,
+}
+
+impl ObjectDatabase {
+    /// Opens the object directory of a repository and the alternate object
+    /// directories listed in its `info/alternates` file.
+    fn open(objects: &Path, id_length: usize) -> Result<Self> {
+        let mut directories = vec![objects.to_path_buf()];
+        if let Ok(alternates) = fs::read(objects.join("info/alternates")) {
+            directories.extend(
+                alternates
+                    .split(|&b| b == b'\n')
+                    .filter(|line| !line.is_empty() && !line.starts_with(b"#"))
+                    .map(|line| objects.join(path_from_bytes(line))),
+            );
+        }
+
+        let mut packs = Vec::new();
+        for directory in &directories {
+            let Ok(files) = fs::read_dir(directory.join("pack")) else {
+                continue;
+            };
+            for file in files {
+                let path = file.map_err(|e| GitlsfError::io(directory, e))?.path();
+                if path.extension().is_some_and(|extension| extension == "idx") {
+                    packs.push(Pack::open(&path)?);
+                }
+            }
+        }
+
+        Ok(ObjectDatabase {
+            directories,
+            packs,
+            id_length,
+        })
+    }
+
+    /// Reads an object, returning its pack type and contents.
+    fn read(&self, oid: &[u8]) -> Result<(u8, Vec<u8>)> {
+        for pack in &self.packs {
+            if let Some(offset) = pack.find(oid) {
+                return self.read_packed(pack, offset);
+            }
+        }
+
+        let name = hex(oid);
+        for directory in &self.directories {
+            let path = directory.join(&name[..2]).join(&name[2..]);
+            match fs::read(&path) {
+                Ok(data) => {
+                    return read_loose(&data)
+                        .map_err(|message| GitlsfError::git_file(&path, message));
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(GitlsfError::io(path, e)),
+            }
+        }
+
+        Err(GitlsfError::git_file(
+            &self.directories[0],
+            format!("object {name} not found"),
+        ))
+    }
+
+    /// Reads the object at an offset of a pack, resolving deltas.
+    fn read_packed(&self, pack: &Pack, offset: usize) -> Result<(u8, Vec<u8>)> {
+        let invalid = |message: String| GitlsfError::git_file(&pack.path, message);
+        let mut reader = Reader::new(&pack.data);
+        reader.position = offset;
+
+        // The type and size, in little-endian groups of 7 bits after the first 4
+        let mut byte = reader.take(1).map_err(invalid)?[0];
+        let kind = (byte >> 4) & 7;
+        let mut size = usize::from(byte & 0x0f);
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = reader.take(1).map_err(invalid)?[0];
+            size |= usize::from(byte & 0x7f) << shift;
+            shift += 7;
+        }
+
+        let (kind, base) = match kind {
+            OFS_DELTA_TYPE => {
+                let distance = reader.offset().map_err(invalid)?;
+                let base_offset = offset
+                    .checked_sub(distance)
+                    .ok_or_else(|| invalid("invalid delta base".to_string()))?;
+                self.read_packed(pack, base_offset)?
+            }
+            REF_DELTA_TYPE => self.read(reader.take(self.id_length).map_err(invalid)?)?,
+            _ => {
+                let object = inflate(&pack.data[reader.position..], size).map_err(invalid)?;
+                return Ok((kind, object));
+            }
+        };
+
+        let delta = inflate(&pack.data[reader.position..], size).map_err(invalid)?;
+        Ok((kind, apply_delta(&base, &delta).map_err(invalid)?))
+    }
+
+    /// Appends the files of a tree to `entries`, recursively, with paths
+    /// starting with `prefix`.
+    fn expand_tree(
+        &self,
+        oid: &[u8],
+        prefix: Vec<u8>,
+        entries: &mut Vec<IndexEntry>,
+    ) -> Result<()> {
+        let (kind, tree) = self.read(oid)?;
+        let invalid = |message: &str| {
+            GitlsfError::git_file(
+                &self.directories[0],
+                format!("{message} in tree {}", hex(oid)),
+            )
+        };
+        if kind != TREE_TYPE {
+            return Err(invalid("not a tree"));
+        }
+
+        // Each entry is "<octal mode> SP <name> NUL <binary object ID>"
+        let mut reader = Reader::new(&tree);
+        while reader.remaining() > 0 {
+            let space = memchr::memchr(b' ', &tree[reader.position..])
+                .ok_or_else(|| invalid("invalid entry"))?;
+            let mode = std::str::from_utf8(&tree[reader.position..reader.position + space])
+                .ok()
+                .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+                .ok_or_else(|| invalid("invalid mode"))?;
+            reader.position += space + 1;
+            let name = reader.until_nul().map_err(|e| invalid(&e))?;
+            let entry_oid = reader
+                .take(self.id_length)
+                .map_err(|e| invalid(&e))?
+                .to_vec();
+
+            let mut path = prefix.clone();
+            path.extend_from_slice(name);
+            if mode == DIRECTORY_MODE {
+                path.push(b'/');
+                self.expand_tree(&entry_oid, path, entries)?;
+            } else {
+                entries.push(IndexEntry {
+                    mode,
+                    oid: entry_oid,
+                    stage: 0,
+                    path,
+                    mtime: (0, 0),
+                    size: 0,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Decompresses zlib data of a known size.
+fn inflate(data: &[u8], size: usize) -> std::result::Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(size);
+    ZlibDecoder::new(data)
+        .read_to_end(&mut output)
+        .map_err(|e| format!("invalid compressed data: {e}"))?;
+    if output.len() != size {
+        return Err("unexpected object size".to_string());
+    }
+    Ok(output)
+}
+
+/// Parses a loose object: a zlib-compressed `<type> SP <size> NUL` header
+/// followed by the contents.
+fn read_loose(data: &[u8]) -> std::result::Result<(u8, Vec<u8>), String> {
+    let mut object = Vec::new();
+    ZlibDecoder::new(data)
+        .read_to_end(&mut object)
+        .map_err(|e| format!("invalid compressed data: {e}"))?;
+
+    let nul = memchr::memchr(0, &object).ok_or("missing object header")?;
+    let kind = match object.split(|&b| b == b' ').next() {
+        Some(b"commit") => 1,
+        Some(b"tree") => TREE_TYPE,
+        Some(b"blob") => 3,
+        Some(b"tag") => 4,
+        _ => return Err("unknown object type".to_string()),
+    };
+    Ok((kind, object.split_off(nul + 1)))
+}
+
+/// Applies a pack delta to its base object.
+///
+/// A delta starts with the sizes of the base and the result, then has
+/// instructions that either copy a range of the base (high bit set, with the
+/// offset and size bytes present given by the low bits) or insert the next
+/// 1 to 127 bytes of the delta.
+fn apply_delta(base: &[u8], delta: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    let mut reader = Reader::new(delta);
+    let mut size = || -> std::result::Result<usize, String> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = reader.take(1)?[0];
+            value |= usize::from(byte & 0x7f) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    };
+    let (base_size, result_size) = (size()?, size()?);
+    if base_size != base.len() {
+        return Err("delta base size mismatch".to_string());
+    }
+
+    let mut result = Vec::with_capacity(result_size);
+    while reader.remaining() > 0 {
+        let instruction = reader.take(1)?[0];
+        if instruction & 0x80 != 0 {
+            let mut offset = 0;
+            for i in 0..4 {
+                if instruction & (1 << i) != 0 {
+                    offset |= usize::from(reader.take(1)?[0]) << (8 * i);
+                }
+            }
+            let mut length = 0;
+            for i in 0..3 {
+                if instruction & (0x10 << i) != 0 {
+                    length |= usize::from(reader.take(1)?[0]) << (8 * i);
+                }
+            }
+            if length == 0 {
+                length = 0x10000;
+            }
+            let copied = base
+                .get(offset..offset + length)
+                .ok_or("delta copy out of range")?;
+            result.extend_from_slice(copied);
+        } else if instruction != 0 {
+            result.extend_from_slice(reader.take(usize::from(instruction))?);
+        } else {
+            return Err("invalid delta instruction".to_string());
+        }
+    }
+
+    if result.len() != result_size {
+        return Err("delta result size mismatch".to_string());
+    }
+    Ok(result)
+}
+
+/// Converts a relative path into the `/`-separated bytes Git records.
+fn path_bytes(path: &Path) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for component in path.components() {
+        if !bytes.is_empty() {
+            bytes.push(b'/');
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            bytes.extend_from_slice(component.as_os_str().as_bytes());
+        }
+        #[cfg(not(unix))]
+        bytes.extend_from_slice(component.as_os_str().to_string_lossy().as_bytes());
+    }
+    bytes
+}
+
+/// Returns `true` if `path` is `directory` or inside it; the empty
+/// directory is the root.
+fn is_within(path: &[u8], directory: &[u8]) -> bool {
+    directory.is_empty()
+        || path.starts_with(directory)
+            && (path.len() == directory.len() || path[directory.len()] == b'/')
+}
+
+/// Makes a path relative to the root relative to another directory of the
+/// working tree, with `..` components to leave it.
+fn relative_to(path: &[u8], directory: &[u8]) -> PathBuf {
+    if is_within(path, directory) {
+        let start = if directory.is_empty() {
+            0
+        } else {
+            directory.len() + 1
+        };
+        return path_from_bytes(path.get(start..).unwrap_or_default());
+    }
+
+    let path: Vec<&[u8]> = path.split(|&b| b == b'/').collect();
+    let directory: Vec<&[u8]> = directory.split(|&b| b == b'/').collect();
+    let common = path
+        .iter()
+        .zip(&directory)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..directory.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(path_from_bytes(component));
+    }
+    relative
+}
+
+/// Reads the index of the repository containing `path` and selects the
+/// entries below `path`, or named or contained by the pathspecs, which are
+/// relative to `path`.
+///
+/// Returns each entry with its path relative to `path`.
+fn select(path: &Path, pathspecs: &[PathBuf]) -> Result<Vec<(PathBuf, IndexEntry)>> {
+    let git_dir = GitDir::discover(path)?;
+    let work_tree = git_dir
+        .work_tree
+        .as_deref()
+        .ok_or_else(|| GitlsfError::git("this operation must be run in a work tree"))?;
+    let directory = path.canonicalize().map_err(|e| GitlsfError::io(path, e))?;
+    let prefix = directory.strip_prefix(work_tree).unwrap_or(Path::new(""));
+
+    // Pathspecs are resolved against `path`, and may leave it through `..`
+    let mut scopes = Vec::new();
+    for pathspec in pathspecs {
+        let mut scope = if pathspec.is_absolute() {
+            PathBuf::new()
+        } else {
+            prefix.to_path_buf()
+        };
+        let components = match pathspec.strip_prefix(work_tree) {
+            Ok(relative) => relative.components(),
+            Err(_) if pathspec.is_absolute() => {
+                return Err(GitlsfError::OutsideRepository {
+                    path: pathspec.clone(),
+                    root: work_tree.to_path_buf(),
+                });
+            }
+            Err(_) => pathspec.components(),
+        };
+        for component in components {
+            match component {
+                Component::Normal(name) => scope.push(name),
+                Component::ParentDir if scope.pop() => {}
+                Component::CurDir => {}
+                _ => {
+                    return Err(GitlsfError::OutsideRepository {
+                        path: pathspec.clone(),
+                        root: work_tree.to_path_buf(),
+                    });
+                }
+            }
+        }
+        scopes.push(path_bytes(&scope));
+    }
+    let prefix = path_bytes(prefix);
+    if scopes.is_empty() {
+        scopes.push(prefix.clone());
+    }
+
+    Ok(Index::read(&git_dir)?
+        .entries
+        .into_iter()
+        .filter(|entry| scopes.iter().any(|scope| is_within(&entry.path, scope)))
+        .map(|entry| (relative_to(&entry.path, &prefix), entry))
+        .collect())
+}
+
+/// Retrieves the files tracked in the index, as `git ls-files -z` does.
+///
+/// Paths are relative to `path`, and a file with merge conflicts is listed
+/// once for each of its stages.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository (or a subdirectory within it).
+/// * `pathspecs` - Paths limiting the listing, or an empty slice for all files.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within the working tree of a Git repository
+/// - A pathspec is outside the working tree
+/// - The index cannot be read
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::index::list_files;
+///
+/// for file in list_files(".", &[]).unwrap() {
+///     println!("{}", file.display());
+/// }
+/// ```
+pub fn list_files(path: impl AsRef<Path>, pathspecs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    Ok(select(path.as_ref(), pathspecs)?
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// Retrieves the files in the index with the object ID of each, as
+/// `git ls-files -s -z` does.
+///
+/// Paths are the same as those of [`list_files`]. A file with merge
+/// conflicts is listed once, with the first of its stages.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within the working tree of a Git repository
+/// - A pathspec is outside the working tree
+/// - The index cannot be read
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::index::list_index;
+///
+/// for entry in list_index(".", &[]).unwrap() {
+///     println!("{} {} {}", entry.mode, entry.oid, entry.path.display());
+/// }
+/// ```
+pub fn list_index(path: impl AsRef<Path>, pathspecs: &[PathBuf]) -> Result<Vec<TreeEntry>> {
+    let mut entries: Vec<TreeEntry> = Vec::new();
+    for (path, entry) in select(path.as_ref(), pathspecs)? {
+        // Stages of a conflicted file are next to each other
+        if entries.last().is_some_and(|last| last.path == path) {
+            continue;
+        }
+        entries.push(TreeEntry {
+            mode: format!("{:06o}", entry.mode),
+            oid: entry.oid_hex(),
+            path,
+        });
+    }
+    Ok(entries)
+}
+
+/// Checks if a directory is within a Git repository, as `git rev-parse
+/// --git-dir` does.
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::index::is_git_repository;
+///
+/// if is_git_repository(".") {
+///     println!("This is a Git repository!");
+/// }
+/// ```
+pub fn is_git_repository(path: impl AsRef<Path>) -> bool {
+    GitDir::discover(path).is_ok()
+}
+
+/// Returns the top-level directory of the working tree containing `path`,
+/// as `git rev-parse --show-toplevel` does.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The repository is bare
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::index::repository_root;
+///
+/// let root = repository_root("src").unwrap();
+/// println!("Repository root: {}", root.display());
+/// ```
+pub fn repository_root(path: impl AsRef<Path>) -> Result<PathBuf> {
+    GitDir::discover(path)?
+        .work_tree
+        .ok_or_else(|| GitlsfError::git("this operation must be run in a work tree"))
+}
+
+/// Returns the Git directory shared by all worktrees of the repository
+/// containing `path`, as `git rev-parse --git-common-dir` does.
+///
+/// # Errors
+///
+/// Returns an error if the path is not within a Git repository.
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::index::common_dir;
+///
+/// let git_dir = common_dir(".").unwrap();
+/// println!("Git directory: {}", git_dir.display());
+/// ```
+pub fn common_dir(path: impl AsRef<Path>) -> Result<PathBuf> {
+    Ok(GitDir::discover(path)?.common_dir)
+}
+
+/// Retrieves the tracked files that may differ from the index, by comparing
+/// the size and modification time recorded in the index with those of the
+/// working tree files.
+///
+/// Unlike `git diff`, contents are not compared, so a file that was only
+/// touched is listed too. Files modified too close to the time the index
+/// was written to be told apart by their time, deleted files, files whose
+/// type changed and conflicted files are listed as well. Submodules are not.
+/// Paths are the same as those of [`list_files`].
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within the working tree of a Git repository
+/// - A pathspec is outside the working tree
+/// - The index cannot be read
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::index::modified_files;
+///
+/// for file in modified_files(".", &[]).unwrap() {
+///     println!("maybe modified: {}", file.display());
+/// }
+/// ```
+pub fn modified_files(path: impl AsRef<Path>, pathspecs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let index_path = GitDir::discover(path)?.git_dir.join("index");
+    let index_mtime = fs::metadata(&index_path)
+        .ok()
+        .and_then(|metadata| modification_time(&metadata));
+
+    let mut modified: Vec<PathBuf> = Vec::new();
+    for (file, entry) in select(path, pathspecs)? {
+        if entry.mode & TYPE_MASK == GITLINK_MODE
+            || modified.last().is_some_and(|last| *last == file)
+        {
+            continue;
+        }
+        let clean = entry.stage == 0
+            && fs::symlink_metadata(path.join(&file))
+                .is_ok_and(|metadata| matches_stat(&entry, &metadata, index_mtime));
+        if !clean {
+            modified.push(file);
+        }
+    }
+    Ok(modified)
+}
+
+/// Returns `true` if an attributes file may apply to some of `files`, which
+/// are relative to `path`.
+///
+/// These are the `info/attributes` file of the repository, the user's
+/// `git/attributes` file in `$XDG_CONFIG_HOME` or `~/.config`, the system's
+/// `/etc/gitattributes`, and the `.gitattributes` files of the directories
+/// containing the files, up to the root of the working tree. A file named by
+/// the `core.attributesFile` setting is not looked for.
+///
+/// # Errors
+///
+/// Returns an error if the path is not within the working tree of a Git
+/// repository.
+pub(crate) fn has_attributes<S: AsRef<Path>>(path: &Path, files: &[S]) -> Result<bool> {
+    let git_dir = GitDir::discover(path)?;
+    let work_tree = git_dir
+        .work_tree
+        .as_deref()
+        .ok_or_else(|| GitlsfError::git("this operation must be run in a work tree"))?;
+
+    let user_file = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|directory| !directory.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|directory| directory.join("git").join("attributes"));
+    let global_files = [
+        Some(git_dir.common_dir.join("info").join("attributes")),
+        user_file,
+        Some(PathBuf::from("/etc/gitattributes")),
+    ];
+    if global_files.iter().flatten().any(|file| file.is_file()) {
+        return Ok(true);
+    }
+
+    let directory = path.canonicalize().map_err(|e| GitlsfError::io(path, e))?;
+    let mut checked = std::collections::HashSet::new();
+    for file in files {
+        let Some(parent) = directory.join(file).parent().map(Path::to_path_buf) else {
+            continue;
+        };
+        for ancestor in parent.ancestors() {
+            if !ancestor.starts_with(work_tree) || !checked.insert(ancestor.to_path_buf()) {
+                break;
+            }
+            if ancestor.join(".gitattributes").is_file() {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+/// Returns the modification time of a file in seconds and nanoseconds, as
+/// recorded in the index.
+fn modification_time(metadata: &fs::Metadata) -> Option<(u32, u32)> {
+    let time = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?;
+    Some((time.as_secs() as u32, time.subsec_nanos()))
+}
+
+/// Returns `true` if a working tree file has the type, size and
+/// modification time recorded in its index entry.
+///
+/// A file modified in the same second as the index was written, or later,
+/// does not match: it may have changed after it was added without its time
+/// changing, as Git's "racily clean" entries.
+fn matches_stat(
+    entry: &IndexEntry,
+    metadata: &fs::Metadata,
+    index_mtime: Option<(u32, u32)>,
+) -> bool {
+    let is_symlink = entry.mode & TYPE_MASK == SYMLINK_MODE;
+    if metadata.file_type().is_symlink() != is_symlink || !(is_symlink || metadata.is_file()) {
+        return false;
+    }
+    let Some(mtime) = modification_time(metadata) else {
+        return false;
+    };
+
+    // Nanoseconds are compared unless the index lacks them
+    let same_time = mtime.0 == entry.mtime.0 && (entry.mtime.1 == 0 || mtime.1 == entry.mtime.1);
+    // Files changed in the same tick as the index was written are racy
+    let racy = index_mtime.is_none_or(|index| {
+        entry.mtime.0 > index.0
+            || entry.mtime.0 == index.0 && (entry.mtime.1 == 0 || entry.mtime.1 >= index.1)
+    });
+
+    same_time && !racy && metadata.len() as u32 == entry.size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{self, FileSet};
+    use crate::test_util;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    /// Runs `git` with a committer identity and local submodule clones allowed.
+    fn git(path: &Path, args: &[&str]) {
+        let config = [
+            "-c",
+            "user.name=Test",
+            "-c",
+            "user.email=test@test.com",
+            "-c",
+            "protocol.file.allow=always",
+        ];
+        test_util::git(path, &[&config, args].concat());
+    }
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// Creates a repository with nested directories, a symbolic link and an
+    /// executable, committed with the given `git init` arguments.
+    fn setup_fixture(init: &[&str]) -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        git(path, &[&["init", "-q"], init].concat());
+
+        write(&path.join("README.md"), "# readme\n");
+        write(&path.join("src/main.rs"), "fn main() {}\n");
+        write(&path.join("src/parser/lexer.rs"), "// lexer\n");
+        write(&path.join("src/parser/mod.rs"), "mod lexer;\n");
+        write(&path.join("src-old/main.rs"), "fn main() {}\n");
+        write(&path.join("docs/a b.txt"), "spaces\n");
+        write(&path.join("scripts/run.sh"), "#!/bin/sh\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{PermissionsExt, symlink};
+            fs::set_permissions(
+                path.join("scripts/run.sh"),
+                fs::Permissions::from_mode(0o755),
+            )
+            .unwrap();
+            symlink("src/main.rs", path.join("link.rs")).unwrap();
+        }
+        git(path, &["add", "-A"]);
+        git(path, &["commit", "-q", "-m", "first"]);
+        temp_dir
+    }
+
+    /// Checks that the native and `git` listings agree from several
+    /// directories of the working tree and with several pathspecs.
+    fn assert_same_listings(root: &Path) {
+        let cases: [(&str, &[&str]); 7] = [
+            ("", &[]),
+            ("", &["src"]),
+            ("", &["src/main.rs", "docs"]),
+            ("", &["src/par"]),
+            ("src", &[]),
+            ("src", &["parser", "../docs"]),
+            ("src/parser", &[".."]),
+        ];
+
+        for (directory, pathspecs) in cases {
+            let path = root.join(directory);
+            let pathspecs: Vec<PathBuf> = pathspecs.iter().map(PathBuf::from).collect();
+            assert_eq!(
+                list_files(&path, &pathspecs).unwrap(),
+                git::ls_files(&path, FileSet::Tracked, &pathspecs).unwrap(),
+                "files in {directory:?} with {pathspecs:?}"
+            );
+            assert_eq!(
+                list_index(&path, &pathspecs).unwrap(),
+                git::ls_files_stage(&path, &pathspecs).unwrap(),
+                "index in {directory:?} with {pathspecs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_index_versions() {
+        let temp_dir = setup_fixture(&[]);
+        let path = temp_dir.path();
+
+        // Intent-to-add entries have extended flags, which need version 3
+        write(&path.join("src/new.rs"), "// new\n");
+        git(path, &["add", "-N", "src/new.rs"]);
+
+        for version in ["2", "3", "4"] {
+            if version == "2" {
+                git(path, &["reset", "-q", "src/new.rs"]);
+            }
+            git(path, &["update-index", "--index-version", version]);
+            let index = Index::read(&GitDir::discover(path).unwrap()).unwrap();
+            assert_eq!(index.version.to_string(), version);
+            assert_same_listings(path);
+            git(path, &["add", "-N", "src/new.rs"]);
+        }
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let temp_dir = setup_fixture(&[]);
+        let path = temp_dir.path();
+
+        git(path, &["checkout", "-q", "-b", "other"]);
+        write(&path.join("src/main.rs"), "fn main() { other() }\n");
+        git(path, &["commit", "-q", "-a", "-m", "other"]);
+        git(path, &["checkout", "-q", "-"]);
+        write(&path.join("src/main.rs"), "fn main() { ours() }\n");
+        git(path, &["commit", "-q", "-a", "-m", "ours"]);
+        let merge = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@test.com"])
+            .args(["merge", "other"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        assert!(!merge.status.success());
+
+        let files = list_files(path, &[PathBuf::from("src/main.rs")]).unwrap();
+        assert_eq!(files.len(), 3);
+        assert_same_listings(path);
+    }
+
+    #[test]
+    fn test_split_index() {
+        let temp_dir = setup_fixture(&[]);
+        let path = temp_dir.path();
+        // Keep the shared index however many entries change
+        git(path, &["config", "splitIndex.maxPercentChange", "100"]);
+        git(path, &["update-index", "--split-index"]);
+
+        // Replaced, deleted and added entries are recorded in the split index
+        write(&path.join("src/main.rs"), "fn main() { changed() }\n");
+        write(&path.join("src/added.rs"), "// added\n");
+        write(&path.join("zz.rs"), "// last\n");
+        git(path, &["add", "-A"]);
+        git(path, &["rm", "-q", "--cached", "src/parser/mod.rs"]);
+
+        let git_dir = GitDir::discover(path).unwrap();
+        let split = parse_index_file(&git_dir.git_dir.join("index"), 20)
+            .unwrap()
+            .unwrap();
+        let link = split.link.unwrap();
+        assert!(!link.replace.is_empty());
+        assert!(!link.delete.is_empty());
+
+        assert_same_listings(path);
+    }
+
+    #[test]
+    fn test_sparse_index() {
+        let temp_dir = setup_fixture(&[]);
+        let path = temp_dir.path();
+        for i in 0..40 {
+            write(&path.join(format!("docs/{i}.txt")), &"line\n".repeat(i + 1));
+        }
+        for i in 0..3 {
+            write(&path.join(format!("docs/{i}.txt")), "changed\n");
+            git(path, &["add", "-A"]);
+            git(path, &["commit", "-q", "-m", "docs"]);
+        }
+
+        git(
+            path,
+            &["sparse-checkout", "init", "--cone", "--sparse-index"],
+        );
+        git(path, &["sparse-checkout", "set", "src"]);
+        let sparse = parse_index_file(&path.join(".git/index"), 20)
+            .unwrap()
+            .unwrap();
+        assert!(sparse.entries.iter().any(IndexEntry::is_sparse_directory));
+
+        // Trees are read as loose objects, then from a pack with deltas
+        assert_same_listings(path);
+        git(path, &["gc", "-q", "--aggressive"]);
+        assert_same_listings(path);
+
+        // Older trees are stored as deltas against newer ones
+        let objects = ObjectDatabase::open(&path.join(".git/objects"), 20).unwrap();
+        let log = Command::new("git")
+            .args(["log", "--format=%T"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        for tree in String::from_utf8(log.stdout).unwrap().lines() {
+            let expected = Command::new("git")
+                .args(["cat-file", "tree", tree])
+                .current_dir(path)
+                .output()
+                .unwrap();
+            let oid: Vec<u8> = (0..tree.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&tree[i..i + 2], 16).unwrap())
+                .collect();
+            assert_eq!(objects.read(&oid).unwrap(), (TREE_TYPE, expected.stdout));
+        }
+    }
+
+    #[test]
+    fn test_sha256_repository() {
+        let temp_dir = setup_fixture(&["--object-format=sha256"]);
+        let path = temp_dir.path();
+
+        let index = Index::read(&GitDir::discover(path).unwrap()).unwrap();
+        assert_eq!(index.entries[0].oid.len(), 32);
+        assert_same_listings(path);
+    }
+
+    #[test]
+    fn test_worktree_and_submodule() {
+        let temp_dir = setup_fixture(&[]);
+        let path = temp_dir.path();
+        let worktree = TempDir::new().unwrap();
+        let worktree_path = worktree.path().join("linked");
+        git(
+            path,
+            &["worktree", "add", "-q", worktree_path.to_str().unwrap()],
+        );
+        write(&worktree_path.join("src/only-here.rs"), "// new\n");
+        git(&worktree_path, &["add", "src/only-here.rs"]);
+
+        let git_dir = GitDir::discover(worktree_path.join("src")).unwrap();
+        assert_eq!(
+            git_dir.work_tree.unwrap(),
+            worktree_path.canonicalize().unwrap()
+        );
+        assert_eq!(
+            git_dir.common_dir.canonicalize().unwrap(),
+            path.join(".git").canonicalize().unwrap()
+        );
+        assert_same_listings(&worktree_path);
+        assert_ne!(
+            list_files(&worktree_path, &[]).unwrap(),
+            list_files(path, &[]).unwrap()
+        );
+
+        let library = setup_fixture(&[]);
+        git(
+            path,
+            &[
+                "submodule",
+                "add",
+                "-q",
+                library.path().to_str().unwrap(),
+                "lib",
+            ],
+        );
+        let submodule = path.join("lib");
+        assert!(submodule.join(".git").is_file());
+        assert_same_listings(&submodule);
+        assert_same_listings(path);
+    }
+
+    #[test]
+    fn test_is_git_repository() {
+        let temp_dir = setup_fixture(&[]);
+        let path = temp_dir.path();
+        let bare = TempDir::new().unwrap();
+        git(bare.path(), &["init", "-q", "--bare"]);
+        let outside = TempDir::new().unwrap();
+
+        for directory in [
+            path.to_path_buf(),
+            path.join("src/parser"),
+            path.join(".git"),
+            path.join(".git/objects"),
+            bare.path().to_path_buf(),
+            outside.path().to_path_buf(),
+        ] {
+            assert_eq!(
+                is_git_repository(&directory),
+                git::rev_parse_git_dir(&directory),
+                "{}",
+                directory.display()
+            );
+        }
+
+        assert!(list_files(bare.path(), &[]).is_err());
+        assert!(matches!(
+            list_files(outside.path(), &[]),
+            Err(GitlsfError::NotAGitRepository)
+        ));
+    }
+
+    #[test]
+    fn test_repository_root_and_common_dir() {
+        let temp_dir = setup_fixture(&[]);
+        let path = temp_dir.path();
+        let worktree = TempDir::new().unwrap();
+        let linked = worktree.path().join("linked");
+        git(path, &["worktree", "add", "-q", linked.to_str().unwrap()]);
+
+        for directory in [
+            path.to_path_buf(),
+            path.join("src/parser"),
+            linked.join("src"),
+        ] {
+            assert_eq!(
+                repository_root(&directory).unwrap(),
+                git::show_toplevel(&directory)
+                    .unwrap()
+                    .canonicalize()
+                    .unwrap(),
+                "{}",
+                directory.display()
+            );
+            assert_eq!(
+                common_dir(&directory).unwrap().canonicalize().unwrap(),
+                git::git_common_dir(&directory)
+                    .unwrap()
+                    .canonicalize()
+                    .unwrap(),
+                "{}",
+                directory.display()
+            );
+        }
+
+        assert!(repository_root(path.join(".git")).is_err());
+        assert!(matches!(
+            common_dir(TempDir::new().unwrap().path()),
+            Err(GitlsfError::NotAGitRepository)
+        ));
+    }
+
+    #[test]
+    fn test_modified_files() {
+        let temp_dir = setup_fixture(&[]);
+        let path = temp_dir.path();
+
+        // Entries written in the same tick as the index are racy, so let the
+        // clock move on and have Git record them again
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        git(path, &["update-index", "-q", "--refresh"]);
+        assert!(modified_files(path, &[]).unwrap().is_empty());
+
+        write(&path.join("src/main.rs"), "fn main() { run(); }\n");
+        write(&path.join("src/untracked.rs"), "// untracked\n");
+        fs::remove_file(path.join("docs/a b.txt")).unwrap();
+        fs::remove_file(path.join("link.rs")).unwrap();
+        write(&path.join("link.rs"), "// no longer a link\n");
+
+        for (directory, pathspecs) in [("", &[][..]), ("src", &[][..]), ("", &["docs"][..])] {
+            let path = path.join(directory);
+            let pathspecs: Vec<PathBuf> = pathspecs.iter().map(PathBuf::from).collect();
+            assert_eq!(
+                modified_files(&path, &pathspecs).unwrap(),
+                git::diff_name_only(&path, &pathspecs).unwrap(),
+                "modified in {directory:?} with {pathspecs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_repository() {
+        let temp_dir = TempDir::new().unwrap();
+        git(temp_dir.path(), &["init", "-q"]);
+
+        assert!(list_files(temp_dir.path(), &[]).unwrap().is_empty());
+        assert_eq!(
+            Index::read(&GitDir::discover(temp_dir.path()).unwrap())
+                .unwrap()
+                .version,
+            0
+        );
+    }
+
+    #[test]
+    fn test_parse_ewah() {
+        // A run of one all-ones word, then one literal word with bits 0 and 3
+        let mut data = Vec::new();
+        data.extend_from_slice(&80u32.to_be_bytes());
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&(1u64 << 33 | 1 << 1 | 1).to_be_bytes());
+        data.extend_from_slice(&0b1001u64.to_be_bytes());
+        data.extend_from_slice(&0u32.to_be_bytes());
+
+        let bits = parse_ewah(&mut Reader::new(&data)).unwrap();
+        assert_eq!(bits.len(), 66);
+        assert_eq!(bits[63..], [63, 64, 67]);
+        assert!(parse_ewah(&mut Reader::new(&data[..12])).is_err());
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        // Base size 11, result size 9: copy 5 bytes at offset 6, insert 4 bytes
+        let delta = [11, 9, 0x80 | 0x01 | 0x10, 6, 5, 4, b' ', b'n', b'e', b'w'];
+        assert_eq!(apply_delta(b"hello world", &delta).unwrap(), b"world new");
+
+        assert!(apply_delta(b"short", &delta).is_err());
+        assert!(apply_delta(b"hello world", &[11, 9, 0]).is_err());
+    }
+
+    #[test]
+    fn test_relative_to() {
+        assert_eq!(relative_to(b"src/a.rs", b""), Path::new("src/a.rs"));
+        assert_eq!(relative_to(b"src/a.rs", b"src"), Path::new("a.rs"));
+        assert_eq!(
+            relative_to(b"docs/a.md", b"src/parser"),
+            Path::new("../../docs/a.md")
+        );
+        assert_eq!(
+            relative_to(b"src-old/a.rs", b"src"),
+            Path::new("../src-old/a.rs")
+        );
+    }
+}
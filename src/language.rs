@@ -0,0 +1,988 @@
+//! Language definitions module.
+//!
+//! This module describes the programming languages gitlsf knows about,
+//! together with the comment and string syntax used to classify lines.
+
+use std::fmt;
+use std::path::Path;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+
+/// Comment and string literal syntax of a language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Syntax {
+    /// Markers that start a comment running to the end of the line.
+    pub line_comments: &'static [&'static str],
+    /// Start and end markers of block comments.
+    pub block_comments: &'static [(&'static str, &'static str)],
+    /// Whether block comments can be nested.
+    pub nested: bool,
+    /// Start and end markers of string literals.
+    pub quotes: &'static [(&'static str, &'static str)],
+    /// Whether a backslash is an ordinary character inside string literals,
+    /// which escape quotes by doubling them instead, as in SQL.
+    pub verbatim_strings: bool,
+    /// Whether `'` starts a character literal such as `'"'` or `'\''` if one
+    /// follows, and is a lone character otherwise, as in Rust lifetimes.
+    pub char_literals: bool,
+    /// Whether `r"…"`, `r#"…"#` and `br"…"` are raw strings, as in Rust.
+    pub raw_strings: bool,
+    /// Whether `'` separates the digits of a number, as in `1'000'000`
+    /// in C++14 and C23.
+    pub digit_separators: bool,
+}
+
+impl Syntax {
+    /// Syntax without any comments or strings; every non-blank line is code.
+    pub const PLAIN: Syntax = Syntax {
+        line_comments: &[],
+        block_comments: &[],
+        nested: false,
+        quotes: &[],
+        verbatim_strings: false,
+        char_literals: false,
+        raw_strings: false,
+        digit_separators: false,
+    };
+
+    /// Returns `true` if the syntax has neither comments nor strings.
+    pub fn is_plain(&self) -> bool {
+        self.line_comments.is_empty() && self.block_comments.is_empty() && self.quotes.is_empty()
+    }
+}
+
+const C_QUOTES: &[(&str, &str)] = &[("\"", "\""), ("'", "'")];
+const DOUBLE_QUOTES: &[(&str, &str)] = &[("\"", "\"")];
+const JS_QUOTES: &[(&str, &str)] = &[("\"", "\""), ("'", "'"), ("`", "`")];
+const PYTHON_QUOTES: &[(&str, &str)] = &[
+    ("\"\"\"", "\"\"\""),
+    ("'''", "'''"),
+    ("\"", "\""),
+    ("'", "'"),
+];
+const C_BLOCK: &[(&str, &str)] = &[("/*", "*/")];
+const HTML_BLOCK: &[(&str, &str)] = &[("<!--", "-->")];
+
+const C_STYLE: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: C_BLOCK,
+    nested: false,
+    quotes: C_QUOTES,
+    ..Syntax::PLAIN
+};
+
+/// C and C++, whose numbers may contain `'` digit separators.
+const C_FAMILY: Syntax = Syntax {
+    digit_separators: true,
+    ..C_STYLE
+};
+
+const HASH_STYLE: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    nested: false,
+    quotes: C_QUOTES,
+    ..Syntax::PLAIN
+};
+
+/// A programming language recognised by gitlsf.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Language {
+    /// Assembly.
+    Assembly,
+    /// C.
+    C,
+    /// C header.
+    CHeader,
+    /// CMake.
+    CMake,
+    /// C++.
+    Cpp,
+    /// C#.
+    CSharp,
+    /// CSS.
+    Css,
+    /// Dart.
+    Dart,
+    /// Dockerfile.
+    Dockerfile,
+    /// Elixir.
+    Elixir,
+    /// Erlang.
+    Erlang,
+    /// Go.
+    Go,
+    /// Haskell.
+    Haskell,
+    /// HTML.
+    Html,
+    /// Java.
+    Java,
+    /// JavaScript.
+    JavaScript,
+    /// JSON.
+    Json,
+    /// JSX.
+    Jsx,
+    /// Kotlin.
+    Kotlin,
+    /// Lua.
+    Lua,
+    /// Makefile.
+    Makefile,
+    /// Markdown.
+    Markdown,
+    /// Nix.
+    Nix,
+    /// Objective-C.
+    ObjectiveC,
+    /// OCaml.
+    OCaml,
+    /// Perl.
+    Perl,
+    /// PHP.
+    Php,
+    /// Protocol Buffers.
+    Protobuf,
+    /// Python.
+    Python,
+    /// R.
+    R,
+    /// Ruby.
+    Ruby,
+    /// Rust.
+    Rust,
+    /// Scala.
+    Scala,
+    /// Sass (SCSS syntax).
+    Scss,
+    /// Shell script.
+    Shell,
+    /// SQL.
+    Sql,
+    /// Svelte.
+    Svelte,
+    /// Swift.
+    Swift,
+    /// TOML.
+    Toml,
+    /// TSX.
+    Tsx,
+    /// TypeScript.
+    TypeScript,
+    /// Vim script.
+    VimScript,
+    /// Vue.
+    Vue,
+    /// XML.
+    Xml,
+    /// YAML.
+    Yaml,
+    /// Zig.
+    Zig,
+}
+
+/// Static description of a language.
+struct LanguageDef {
+    language: Language,
+    name: &'static str,
+    extensions: &'static [&'static str],
+    /// Exact filenames, such as `Makefile`.
+    filenames: &'static [&'static str],
+    /// Lowercase alternative names used by shebangs and modelines.
+    aliases: &'static [&'static str],
+    syntax: Syntax,
+}
+
+/// Language definitions, in the same order as the [`Language`] variants.
+const LANGUAGES: &[LanguageDef] = &[
+    LanguageDef {
+        language: Language::Assembly,
+        name: "Assembly",
+        extensions: &["asm", "s"],
+        filenames: &[],
+        aliases: &["asm", "nasm"],
+        syntax: Syntax {
+            line_comments: &[";"],
+            block_comments: &[],
+            nested: false,
+            quotes: DOUBLE_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::C,
+        name: "C",
+        extensions: &["c"],
+        filenames: &[],
+        aliases: &[],
+        syntax: C_FAMILY,
+    },
+    LanguageDef {
+        language: Language::CHeader,
+        name: "C Header",
+        extensions: &["h"],
+        filenames: &[],
+        aliases: &[],
+        syntax: C_FAMILY,
+    },
+    LanguageDef {
+        language: Language::CMake,
+        name: "CMake",
+        extensions: &["cmake"],
+        filenames: &["CMakeLists.txt"],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["#"],
+            block_comments: &[("#[[", "]]")],
+            nested: false,
+            quotes: DOUBLE_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Cpp,
+        name: "C++",
+        extensions: &["cc", "cpp", "cxx", "c++", "hh", "hpp", "hxx", "inl"],
+        filenames: &[],
+        aliases: &["cpp", "c++", "cxx"],
+        syntax: C_FAMILY,
+    },
+    LanguageDef {
+        language: Language::CSharp,
+        name: "C#",
+        extensions: &["cs"],
+        filenames: &[],
+        aliases: &["csharp", "cs"],
+        syntax: C_STYLE,
+    },
+    LanguageDef {
+        language: Language::Css,
+        name: "CSS",
+        extensions: &["css"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &[],
+            block_comments: C_BLOCK,
+            nested: false,
+            quotes: C_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Dart,
+        name: "Dart",
+        extensions: &["dart"],
+        filenames: &[],
+        aliases: &[],
+        syntax: C_STYLE,
+    },
+    LanguageDef {
+        language: Language::Dockerfile,
+        name: "Dockerfile",
+        extensions: &["dockerfile"],
+        filenames: &["Dockerfile", "Containerfile"],
+        aliases: &["docker"],
+        syntax: Syntax {
+            line_comments: &["#"],
+            block_comments: &[],
+            nested: false,
+            quotes: DOUBLE_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Elixir,
+        name: "Elixir",
+        extensions: &["ex", "exs"],
+        filenames: &[],
+        aliases: &["elixir"],
+        syntax: Syntax {
+            line_comments: &["#"],
+            block_comments: &[],
+            nested: false,
+            quotes: PYTHON_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Erlang,
+        name: "Erlang",
+        extensions: &["erl", "hrl"],
+        filenames: &[],
+        aliases: &["escript"],
+        syntax: Syntax {
+            line_comments: &["%"],
+            block_comments: &[],
+            nested: false,
+            quotes: DOUBLE_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Go,
+        name: "Go",
+        extensions: &["go"],
+        filenames: &[],
+        aliases: &["golang"],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: C_BLOCK,
+            nested: false,
+            quotes: &[("\"", "\""), ("`", "`")],
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Haskell,
+        name: "Haskell",
+        extensions: &["hs", "lhs"],
+        filenames: &[],
+        aliases: &["runhaskell", "runghc"],
+        syntax: Syntax {
+            line_comments: &["--"],
+            block_comments: &[("{-", "-}")],
+            nested: true,
+            quotes: DOUBLE_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Html,
+        name: "HTML",
+        extensions: &["html", "htm", "xhtml"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &[],
+            block_comments: HTML_BLOCK,
+            nested: false,
+            quotes: &[],
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Java,
+        name: "Java",
+        extensions: &["java"],
+        filenames: &[],
+        aliases: &[],
+        syntax: C_STYLE,
+    },
+    LanguageDef {
+        language: Language::JavaScript,
+        name: "JavaScript",
+        extensions: &["js", "mjs", "cjs"],
+        filenames: &[],
+        aliases: &["js", "node", "nodejs"],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: C_BLOCK,
+            nested: false,
+            quotes: JS_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Json,
+        name: "JSON",
+        extensions: &["json"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax::PLAIN,
+    },
+    LanguageDef {
+        language: Language::Jsx,
+        name: "JSX",
+        extensions: &["jsx"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: C_BLOCK,
+            nested: false,
+            quotes: JS_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Kotlin,
+        name: "Kotlin",
+        extensions: &["kt", "kts"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: C_BLOCK,
+            nested: true,
+            quotes: &[("\"\"\"", "\"\"\""), ("\"", "\""), ("'", "'")],
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Lua,
+        name: "Lua",
+        extensions: &["lua"],
+        filenames: &[],
+        aliases: &["luajit"],
+        syntax: Syntax {
+            line_comments: &["--"],
+            block_comments: &[("--[[", "]]")],
+            nested: false,
+            quotes: C_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Makefile,
+        name: "Makefile",
+        extensions: &["mk", "mak"],
+        filenames: &["Makefile", "makefile", "GNUmakefile"],
+        aliases: &["make"],
+        syntax: Syntax {
+            line_comments: &["#"],
+            block_comments: &[],
+            nested: false,
+            quotes: &[],
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Markdown,
+        name: "Markdown",
+        extensions: &["md", "markdown"],
+        filenames: &[],
+        aliases: &["md"],
+        syntax: Syntax::PLAIN,
+    },
+    LanguageDef {
+        language: Language::Nix,
+        name: "Nix",
+        extensions: &["nix"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["#"],
+            block_comments: C_BLOCK,
+            nested: false,
+            quotes: &[("\"", "\""), ("''", "''")],
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::ObjectiveC,
+        name: "Objective-C",
+        extensions: &["m", "mm"],
+        filenames: &[],
+        aliases: &["objc", "objective-c"],
+        syntax: C_STYLE,
+    },
+    LanguageDef {
+        language: Language::OCaml,
+        name: "OCaml",
+        extensions: &["ml", "mli"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &[],
+            block_comments: &[("(*", "*)")],
+            nested: true,
+            quotes: DOUBLE_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Perl,
+        name: "Perl",
+        extensions: &["pl", "pm"],
+        filenames: &[],
+        aliases: &[],
+        syntax: HASH_STYLE,
+    },
+    LanguageDef {
+        language: Language::Php,
+        name: "PHP",
+        extensions: &["php"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["//", "#"],
+            block_comments: C_BLOCK,
+            nested: false,
+            quotes: C_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Protobuf,
+        name: "Protocol Buffers",
+        extensions: &["proto"],
+        filenames: &[],
+        aliases: &["proto", "protobuf"],
+        syntax: C_STYLE,
+    },
+    LanguageDef {
+        language: Language::Python,
+        name: "Python",
+        extensions: &["py", "pyi", "pyw"],
+        filenames: &["SConstruct", "SConscript"],
+        aliases: &["python2", "python3", "py"],
+        syntax: Syntax {
+            line_comments: &["#"],
+            block_comments: &[],
+            nested: false,
+            quotes: PYTHON_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::R,
+        name: "R",
+        extensions: &["r"],
+        filenames: &[],
+        aliases: &["rscript"],
+        syntax: HASH_STYLE,
+    },
+    LanguageDef {
+        language: Language::Ruby,
+        name: "Ruby",
+        extensions: &["rb", "rake", "gemspec"],
+        filenames: &["Rakefile", "Gemfile", "Vagrantfile", "Podfile"],
+        aliases: &["rb", "jruby"],
+        syntax: Syntax {
+            line_comments: &["#"],
+            block_comments: &[("=begin", "=end")],
+            nested: false,
+            quotes: C_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Rust,
+        name: "Rust",
+        extensions: &["rs"],
+        filenames: &[],
+        aliases: &["rs"],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: C_BLOCK,
+            nested: true,
+            quotes: DOUBLE_QUOTES,
+            char_literals: true,
+            raw_strings: true,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Scala,
+        name: "Scala",
+        extensions: &["scala", "sc"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: C_BLOCK,
+            nested: true,
+            quotes: &[("\"\"\"", "\"\"\""), ("\"", "\"")],
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Scss,
+        name: "Sass",
+        extensions: &["scss"],
+        filenames: &[],
+        aliases: &[],
+        syntax: C_STYLE,
+    },
+    LanguageDef {
+        language: Language::Shell,
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh", "ksh", "fish"],
+        filenames: &[".bashrc", ".bash_profile", ".zshrc", ".profile", "PKGBUILD"],
+        aliases: &["sh", "bash", "zsh", "ksh", "dash", "ash", "fish", "shell"],
+        syntax: HASH_STYLE,
+    },
+    LanguageDef {
+        language: Language::Sql,
+        name: "SQL",
+        extensions: &["sql"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["--"],
+            block_comments: C_BLOCK,
+            nested: false,
+            quotes: &[("'", "'")],
+            verbatim_strings: true,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Svelte,
+        name: "Svelte",
+        extensions: &["svelte"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: &[("<!--", "-->"), ("/*", "*/")],
+            nested: false,
+            quotes: JS_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Swift,
+        name: "Swift",
+        extensions: &["swift"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: C_BLOCK,
+            nested: true,
+            quotes: &[("\"\"\"", "\"\"\""), ("\"", "\"")],
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Toml,
+        name: "TOML",
+        extensions: &["toml"],
+        filenames: &[],
+        aliases: &[],
+        syntax: HASH_STYLE,
+    },
+    LanguageDef {
+        language: Language::Tsx,
+        name: "TSX",
+        extensions: &["tsx"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: C_BLOCK,
+            nested: false,
+            quotes: JS_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::TypeScript,
+        name: "TypeScript",
+        extensions: &["ts", "mts", "cts"],
+        filenames: &[],
+        aliases: &["ts", "deno", "ts-node"],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: C_BLOCK,
+            nested: false,
+            quotes: JS_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::VimScript,
+        name: "Vim Script",
+        extensions: &["vim"],
+        filenames: &[".vimrc", "_vimrc"],
+        aliases: &["vim", "viml"],
+        syntax: Syntax {
+            line_comments: &["\""],
+            block_comments: &[],
+            nested: false,
+            quotes: &[("'", "'")],
+            verbatim_strings: true,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Vue,
+        name: "Vue",
+        extensions: &["vue"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: &[("<!--", "-->"), ("/*", "*/")],
+            nested: false,
+            quotes: JS_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Xml,
+        name: "XML",
+        extensions: &["xml", "xsd", "xsl", "xslt", "plist"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &[],
+            block_comments: HTML_BLOCK,
+            nested: false,
+            quotes: &[],
+            ..Syntax::PLAIN
+        },
+    },
+    LanguageDef {
+        language: Language::Yaml,
+        name: "YAML",
+        extensions: &["yaml", "yml"],
+        filenames: &[],
+        aliases: &["yml"],
+        syntax: HASH_STYLE,
+    },
+    LanguageDef {
+        language: Language::Zig,
+        name: "Zig",
+        extensions: &["zig"],
+        filenames: &[],
+        aliases: &[],
+        syntax: Syntax {
+            line_comments: &["//"],
+            block_comments: &[],
+            nested: false,
+            quotes: DOUBLE_QUOTES,
+            ..Syntax::PLAIN
+        },
+    },
+];
+
+impl Language {
+    /// Returns the human-readable name of the language.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::language::Language;
+    ///
+    /// assert_eq!(Language::Cpp.name(), "C++");
+    /// ```
+    pub fn name(self) -> &'static str {
+        self.def().name
+    }
+
+    /// Returns the comment and string syntax of the language.
+    pub fn syntax(self) -> &'static Syntax {
+        &self.def().syntax
+    }
+
+    /// Returns all known languages.
+    pub fn all() -> impl Iterator<Item = Language> {
+        LANGUAGES.iter().map(|def| def.language)
+    }
+
+    /// Looks up a language by file extension (case insensitive, without the leading dot).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::language::Language;
+    ///
+    /// assert_eq!(Language::from_extension("rs"), Some(Language::Rust));
+    /// assert_eq!(Language::from_extension("PY"), Some(Language::Python));
+    /// assert_eq!(Language::from_extension("unknown"), None);
+    /// ```
+    pub fn from_extension(extension: &str) -> Option<Language> {
+        let ext_lower = extension.to_lowercase();
+
+        LANGUAGES
+            .iter()
+            .find(|def| def.extensions.iter().any(|&e| e == ext_lower))
+            .map(|def| def.language)
+    }
+
+    /// Looks up a language by exact filename, such as `Makefile` or `CMakeLists.txt`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::language::Language;
+    ///
+    /// assert_eq!(Language::from_filename("Dockerfile"), Some(Language::Dockerfile));
+    /// assert_eq!(Language::from_filename("main.rs"), None);
+    /// ```
+    pub fn from_filename(filename: &str) -> Option<Language> {
+        LANGUAGES
+            .iter()
+            .find(|def| def.filenames.contains(&filename))
+            .map(|def| def.language)
+    }
+
+    /// Looks up a language by name or alias (case insensitive).
+    ///
+    /// Aliases cover interpreter names (`python3`, `bash`, `node`) and the
+    /// filetype names used in editor modelines (`cpp`, `sh`, `make`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::language::Language;
+    ///
+    /// assert_eq!(Language::from_name("TypeScript"), Some(Language::TypeScript));
+    /// assert_eq!(Language::from_name("bash"), Some(Language::Shell));
+    /// assert_eq!(Language::from_name("c++"), Some(Language::Cpp));
+    /// ```
+    pub fn from_name(name: &str) -> Option<Language> {
+        let name_lower = name.to_lowercase();
+
+        LANGUAGES
+            .iter()
+            .find(|def| {
+                def.name.to_lowercase() == name_lower || def.aliases.contains(&name_lower.as_str())
+            })
+            .map(|def| def.language)
+    }
+
+    /// Looks up a language from the name of a path.
+    ///
+    /// Exact filenames take precedence over extensions. Names such as
+    /// `Dockerfile.dev` or `Makefile.am` fall back to their stem when the
+    /// extension is unknown.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::language::Language;
+    ///
+    /// assert_eq!(Language::from_path("src/main.rs"), Some(Language::Rust));
+    /// assert_eq!(Language::from_path("build/Makefile"), Some(Language::Makefile));
+    /// assert_eq!(Language::from_path("Dockerfile.dev"), Some(Language::Dockerfile));
+    /// assert_eq!(Language::from_path("notes.txt"), None);
+    /// ```
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Language> {
+        let path = path.as_ref();
+
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .and_then(Language::from_filename)
+            .or_else(|| {
+                path.extension()
+                    .and_then(|e| e.to_str())
+                    .and_then(Language::from_extension)
+            })
+            .or_else(|| {
+                path.file_stem()
+                    .and_then(|n| n.to_str())
+                    .and_then(Language::from_filename)
+            })
+    }
+
+    fn def(self) -> &'static LanguageDef {
+        &LANGUAGES[self as usize]
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Languages are serialized as their human-readable name.
+impl Serialize for Language {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// Languages are deserialized from any name accepted by [`Language::from_name`].
+impl<'de> Deserialize<'de> for Language {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Language::from_name(&name)
+            .ok_or_else(|| de::Error::custom(format!("unknown language '{name}'")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definitions_match_variant_order() {
+        for (index, def) in LANGUAGES.iter().enumerate() {
+            assert_eq!(def.language as usize, index, "{} is out of order", def.name);
+        }
+    }
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(Language::from_extension("rs"), Some(Language::Rust));
+        assert_eq!(Language::from_extension("tsx"), Some(Language::Tsx));
+        assert_eq!(Language::from_extension("H"), Some(Language::CHeader));
+        assert_eq!(Language::from_extension("xyz"), None);
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Language::from_path("a/b/c.go"), Some(Language::Go));
+        assert_eq!(Language::from_path("Makefile"), Some(Language::Makefile));
+        assert_eq!(Language::from_path("CMakeLists.txt"), Some(Language::CMake));
+        assert_eq!(Language::from_path("home/.bashrc"), Some(Language::Shell));
+        assert_eq!(
+            Language::from_path("Dockerfile.prod"),
+            Some(Language::Dockerfile)
+        );
+        assert_eq!(Language::from_path("script"), None);
+        assert_eq!(Language::from_path("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Language::from_name("rust"), Some(Language::Rust));
+        assert_eq!(Language::from_name("Python3"), Some(Language::Python));
+        assert_eq!(Language::from_name("C Header"), Some(Language::CHeader));
+        assert_eq!(
+            Language::from_name("objective-c"),
+            Some(Language::ObjectiveC)
+        );
+        assert_eq!(Language::from_name("klingon"), None);
+    }
+
+    #[test]
+    fn test_names_and_aliases_are_unique() {
+        let mut seen = std::collections::HashSet::new();
+        for def in LANGUAGES {
+            assert!(
+                seen.insert(def.name.to_lowercase()),
+                "duplicate {}",
+                def.name
+            );
+            for alias in def.aliases {
+                if *alias != def.name.to_lowercase() {
+                    assert!(seen.insert(alias.to_string()), "duplicate alias {alias}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_extensions_are_unique() {
+        let mut seen = std::collections::HashSet::new();
+        for def in LANGUAGES {
+            for ext in def.extensions {
+                assert!(seen.insert(*ext), "duplicate extension {ext}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Language::CSharp.to_string(), "C#");
+        assert_eq!(Language::Rust.to_string(), "Rust");
+    }
+
+    #[test]
+    fn test_serde_uses_names() {
+        assert_eq!(serde_json::to_string(&Language::Cpp).unwrap(), "\"C++\"");
+        assert_eq!(
+            serde_json::from_str::<Language>("\"cpp\"").unwrap(),
+            Language::Cpp
+        );
+        assert!(serde_json::from_str::<Language>("\"Klingon\"").is_err());
+    }
+}
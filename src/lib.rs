@@ -7,9 +7,27 @@
 //! ## Features
 //!
 //! - Fast parallel processing using rayon
-//! - Automatic filtering of non-code files
-//! - Multiple output modes (verbose, quiet, summary)
-//! - Customizable file filtering
+//! - Automatic filtering of non-code files, including binaries detected by content
+//! - Code, comment and blank line breakdown per language
+//! - Language detection by extension, filename, shebang and modeline
+//! - Counting at any Git revision without a checkout
+//! - Line count comparison between revisions
+//! - Line count history over sampled commits
+//! - Attribution of surviving lines to their authors with `git blame`, per author and directory
+//! - Lines added and deleted over a revision range or date window, per file, directory, author and language
+//! - Multiple output modes (verbose, quiet, summary, languages, tree)
+//! - Versioned JSON reports and CSV/TSV tables for downstream tools
+//! - Customizable file filtering, including gitignore-style glob patterns
+//! - Linguist attributes in `.gitattributes` for generated, vendored and documentation files
+//! - Shared settings in a `.gitlsf.toml` repository configuration file
+//! - Line count budgets per file, per glob pattern and in total
+//! - A cache of line counts by blob object ID, so unchanged files are not read again
+//! - File names that are not valid UTF-8, quoted in text and JSON output as Git quotes them
+//! - Running from any directory of a checkout, optionally limited to some of its paths
+//! - Counting the files of submodules, with subtotals per submodule
+//! - Counting only untracked, modified or staged files, to measure a change in progress
+//! - Counting the working tree without a `git` binary, by reading the index (`native-index` feature)
+//! - Counting directories outside Git, tarballs and zip archives of releases, and lists of files
 //!
 //! ## Example Usage
 //!
@@ -24,25 +42,75 @@
 //! // With custom filter
 //! let filter = FileFilter::new().exclude_extension("log");
 //! let summary = count_repository_with_filter(".", filter).unwrap();
+//!
+//! // At a tag, without checking it out
+//! let summary = gitlsf::count_repository_at(".", "v1.0").unwrap();
 //! ```
 //!
 //! ## Modules
 //!
 //! - [`error`] - Error types for the crate
 //! - [`git`] - Git command interaction
+//! - [`repository`] - Repository discovery and path conversion
+//! - [`source`] - Files to count from Git, a directory, an archive or a list
+//! - `index` - Native Git index reader, with the `native-index` feature
 //! - [`filter`] - File filtering logic
+//! - [`attributes`] - Linguist attributes from `.gitattributes`
+//! - [`config`] - Configuration files
 //! - [`counter`] - Line counting functionality
+//! - [`classify`] - Code, comment and blank line classification
+//! - [`language`] - Language definitions and comment syntax
+//! - [`detect`] - Language detection
+//! - [`diff`] - Line count comparison between summaries
+//! - [`history`] - Line count time series over commits
+//! - [`blame`] - Per-author line attribution
+//! - [`churn`] - Lines added and deleted by commits
+//! - [`output`] - Machine-readable report formats
+//! - [`tree`] - Per-directory aggregation
+//! - [`budget`] - Line count limits for CI checks
+//! - [`cache`] - Line counts of unchanged blobs kept between runs
 
+pub mod attributes;
+pub mod blame;
+pub mod budget;
+pub mod cache;
+pub mod churn;
+pub mod classify;
+pub mod config;
 pub mod counter;
+pub mod detect;
+pub mod diff;
 pub mod error;
 pub mod filter;
 pub mod git;
+pub mod history;
+#[cfg(feature = "native-index")]
+pub mod index;
+pub mod language;
+pub mod output;
+pub mod repository;
+pub mod source;
+#[cfg(test)]
+mod test_util;
+pub mod tree;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-use std::path::Path;
+use cache::{CachedCount, CountCache};
+use source::GitSource;
 
-pub use counter::{CountSummary, FileCount};
+pub use classify::LineStats;
+pub use counter::{
+    CountSummary, FileCount, LanguageSummary, SkipReason, SkippedFile, SubmoduleSummary,
+};
+pub use diff::CountDiff;
 pub use error::{GitlsfError, Result};
 pub use filter::FileFilter;
+pub use git::FileSet;
+pub use language::Language;
+pub use repository::Repository;
+pub use source::FileSource;
 
 /// Counts lines of code in a Git repository.
 ///
@@ -80,6 +148,8 @@ pub fn count_repository(path: impl AsRef<Path>) -> Result<CountSummary> {
 /// Counts lines of code in a Git repository with a custom filter.
 ///
 /// This allows you to customize which files are included in the count.
+/// Submodules are reported as skipped, unless the filter
+/// [recurses into them](FileFilter::recurse_submodules).
 ///
 /// # Arguments
 ///
@@ -113,16 +183,611 @@ pub fn count_repository_with_filter(
 ) -> Result<CountSummary> {
     let path = path.as_ref();
 
-    // Get list of tracked files from Git
-    let files = git::list_files(path)?;
+    if filter.file_set() == FileSet::Staged {
+        return count_staged(path, filter);
+    }
 
-    // Filter files
-    let filtered_files = filter.filter_files(files);
+    let source = GitSource::new(path, filter.file_set(), filter.pathspecs())?;
+    let summary = count_files(&source, &filter)?;
 
-    // Count lines in parallel
-    let summary = counter::count_lines_parallel(path, filtered_files);
+    add_submodules(
+        path,
+        source.submodules().to_vec(),
+        &filter,
+        summary,
+        |path, _, filter| count_repository_with_filter(path, filter),
+    )
+}
 
-    Ok(summary)
+/// Counts lines of code in the files of any [`FileSource`].
+///
+/// Files are filtered and counted as in a Git repository. The
+/// `.gitattributes` of the [repository](FileSource::repository) of the
+/// source apply, if it has one. Files on disk are counted in parallel; the
+/// others are read through [`FileSource::read_files`], and those that are
+/// not found are reported as unreadable.
+///
+/// # Arguments
+///
+/// * `source` - The files to count.
+/// * `filter` - The file filter to use. Pathspecs and file sets apply to Git
+///   sources only, and are given to them when they are created.
+///
+/// # Returns
+///
+/// A summary of the counting results.
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be listed or read.
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::{count_source, filter::FileFilter, source::DirectorySource};
+///
+/// let source = DirectorySource::new("unpacked-release");
+/// let summary = count_source(&source, FileFilter::new()).unwrap();
+/// println!("Total: {} lines in {} files", summary.total_lines, summary.file_count);
+/// ```
+pub fn count_source(source: &dyn FileSource, filter: FileFilter) -> Result<CountSummary> {
+    count_files(source, &filter)
+}
+
+/// Filters and counts the files of a source, see [`count_source`].
+fn count_files(source: &dyn FileSource, filter: &FileFilter) -> Result<CountSummary> {
+    let files = filter.filter_files(source.list_files()?);
+    let selection = match source.repository() {
+        Some(path) => apply_attributes(path, None, filter, files, PathBuf::as_path)?,
+        None => Selection {
+            files,
+            languages: HashMap::new(),
+            skipped: Vec::new(),
+        },
+    };
+    let language = |file: &Path| {
+        filter
+            .language_override(file)
+            .or_else(|| selection.languages.get(file).copied())
+    };
+
+    // Count files on disk in parallel
+    if let Some(directory) = source.directory() {
+        let summary =
+            counter::count_lines_parallel_with_languages(directory, &selection.files, language);
+        return Ok(summary.with_skipped(selection.skipped));
+    }
+
+    // Stream the others into the counter
+    let mut results = Vec::new();
+    results.resize_with(selection.files.len(), || None);
+    source.read_files(&selection.files, &mut |index, reader| {
+        let file = &selection.files[index];
+        results[index] = Some(counter::count_text_reader(file, reader, language(file)));
+        Ok(())
+    })?;
+
+    let mut counts = Vec::with_capacity(results.len());
+    let mut skipped = selection.skipped;
+    for (file, result) in selection.files.iter().zip(results) {
+        match result {
+            Some(Ok(Some(count))) => counts.push(count),
+            Some(Ok(None)) => skipped.push(SkippedFile::new(file, SkipReason::Binary)),
+            Some(Err(e)) => skipped.push(SkippedFile::unreadable(file, GitlsfError::io(file, e))),
+            None => skipped.push(SkippedFile::unreadable(
+                file,
+                GitlsfError::io(file, std::io::ErrorKind::NotFound.into()),
+            )),
+        }
+    }
+
+    Ok(CountSummary::from_counts(counts).with_skipped(skipped))
+}
+
+/// Counts lines of code in a Git repository, reusing the counts of unchanged files.
+///
+/// Like [`count_repository_with_filter`], but files whose content matches
+/// the index are looked up by their blob object ID in the repository's
+/// [`CountCache`](cache::CountCache) instead of being read. Files modified in
+/// the working tree, as reported by `git diff`, are always read. The cache
+/// is updated afterwards; failing to write it does not fail the count.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `filter` - The file filter to use.
+///
+/// # Returns
+///
+/// A summary of the counting results.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::{count_repository_with_cache, filter::FileFilter};
+///
+/// // The second run only reads files modified in between
+/// let first = count_repository_with_cache(".", FileFilter::new()).unwrap();
+/// let second = count_repository_with_cache(".", FileFilter::new()).unwrap();
+/// ```
+pub fn count_repository_with_cache(
+    path: impl AsRef<Path>,
+    filter: FileFilter,
+) -> Result<CountSummary> {
+    let path = path.as_ref();
+
+    // Only the blobs of tracked files are known to match their content
+    if filter.file_set() != FileSet::Tracked {
+        return count_repository_with_filter(path, filter);
+    }
+
+    let submodules = git::list_submodules(path, None)?;
+    let entries: Vec<git::TreeEntry> = git::list_index(path, filter.pathspecs())?
+        .into_iter()
+        .filter(|entry| !entry.is_submodule() && filter.should_count(&entry.path))
+        .collect();
+    let modified: HashSet<PathBuf> = git::modified_files(path, filter.pathspecs())?
+        .into_iter()
+        .collect();
+    let selection = apply_attributes(path, None, &filter, entries, |entry| &entry.path)?;
+
+    let language = |file: &Path| {
+        filter
+            .language_override(file)
+            .or_else(|| selection.languages.get(file).copied())
+    };
+    // Symbolic links are read through to their target, whose blob is unknown
+    let cacheable =
+        |entry: &git::TreeEntry| entry.is_regular_file() && !modified.contains(&entry.path);
+
+    let mut cache = CountCache::open(path)?;
+    let mut counts = Vec::with_capacity(selection.files.len());
+    let mut skipped = selection.skipped;
+    let mut uncached = Vec::new();
+
+    for entry in &selection.files {
+        let cached = if cacheable(entry) {
+            cache.get(&entry.oid, &entry.path, language(&entry.path))
+        } else {
+            None
+        };
+
+        match cached {
+            Some(CachedCount::Text { language, stats }) => {
+                counts
+                    .push(FileCount::with_stats(entry.path.clone(), stats).with_language(language));
+            }
+            Some(CachedCount::Binary) => {
+                skipped.push(SkippedFile::new(entry.path.clone(), SkipReason::Binary));
+            }
+            None => uncached.push(entry),
+        }
+    }
+
+    let summary = counter::count_lines_parallel_with_languages(
+        path,
+        uncached.iter().map(|entry| &entry.path),
+        language,
+    );
+
+    // Remember the counts of files whose content is that of their blob
+    let blobs: HashMap<&Path, &git::TreeEntry> = uncached
+        .iter()
+        .filter(|entry| cacheable(entry))
+        .map(|entry| (entry.path.as_path(), *entry))
+        .collect();
+    for file in &summary.files {
+        if let Some(entry) = blobs.get(file.path.as_path()) {
+            let count = CachedCount::Text {
+                language: file.language,
+                stats: file.stats(),
+            };
+            cache.insert(&entry.oid, &file.path, language(&file.path), count);
+        }
+    }
+    for file in &summary.skipped {
+        if let Some(entry) = blobs.get(file.path.as_path())
+            && file.reason == SkipReason::Binary
+        {
+            cache.insert(
+                &entry.oid,
+                &file.path,
+                language(&file.path),
+                CachedCount::Binary,
+            );
+        }
+    }
+
+    // Only a count of every tracked file knows which blobs are still needed;
+    // the root of a working tree is the directory holding `.git`
+    let full = filter.pathspecs().is_empty()
+        && filter.include_patterns().is_empty()
+        && filter.exclude_patterns().is_empty()
+        && path.join(".git").exists();
+
+    // A cache that cannot be written only costs time on the next run
+    let _ = cache.save(full);
+
+    counts.extend(summary.files);
+    counts.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+    skipped.extend(summary.skipped);
+
+    add_submodules(
+        path,
+        submodules,
+        &filter,
+        CountSummary::from_counts(counts).with_skipped(skipped),
+        |path, _, filter| count_repository_with_cache(path, filter),
+    )
+}
+
+/// Counts lines of code in a Git repository at a given revision.
+///
+/// Files are listed with `git ls-tree` and read through a single
+/// `git cat-file --batch` process, so the revision does not need to be
+/// checked out. The default file filter is used.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `revision` - Any revision understood by Git, such as a tag, branch or commit.
+///
+/// # Returns
+///
+/// A summary of the counting results.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::count_repository_at;
+///
+/// let v1 = count_repository_at(".", "v1.0").unwrap();
+/// let v2 = count_repository_at(".", "v2.0").unwrap();
+/// println!("Grew by {} lines", v2.total_lines as i64 - v1.total_lines as i64);
+/// ```
+pub fn count_repository_at(path: impl AsRef<Path>, revision: &str) -> Result<CountSummary> {
+    count_repository_at_with_filter(path, revision, FileFilter::new())
+}
+
+/// Counts lines of code in a Git repository at a given revision with a custom filter.
+///
+/// Symbolic links are not counted, since their blob content is the link target.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `revision` - Any revision understood by Git, such as a tag, branch or commit.
+/// * `filter` - The file filter to use.
+///
+/// # Returns
+///
+/// A summary of the counting results.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - The revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::{count_repository_at_with_filter, filter::FileFilter};
+///
+/// let filter = FileFilter::new().exclude_extension("sql");
+/// let summary = count_repository_at_with_filter(".", "main", filter).unwrap();
+/// ```
+pub fn count_repository_at_with_filter(
+    path: impl AsRef<Path>,
+    revision: &str,
+    filter: FileFilter,
+) -> Result<CountSummary> {
+    let path = path.as_ref();
+
+    // Get list of files in the revision's tree
+    let entries: Vec<git::TreeEntry> = git::list_tree(path, revision, filter.pathspecs())?
+        .into_iter()
+        .filter(|entry| !entry.is_symlink() && filter.should_count(&entry.path))
+        .collect();
+    let selection = apply_attributes(path, Some(revision), &filter, entries, |entry| &entry.path)?;
+    let summary = count_selected_blobs(path, selection, &filter)?;
+
+    add_submodules(
+        path,
+        git::list_submodules(path, Some(revision))?,
+        &filter,
+        summary,
+        |path, commit, filter| count_repository_at_with_filter(path, commit, filter),
+    )
+}
+
+/// Counts the staged files of a repository from their blobs in the index.
+///
+/// Symbolic links are not counted, as for revisions.
+fn count_staged(path: &Path, filter: FileFilter) -> Result<CountSummary> {
+    let staged: HashSet<PathBuf> = git::list_files(path, FileSet::Staged, filter.pathspecs())?
+        .into_iter()
+        .collect();
+    let entries: Vec<git::TreeEntry> = git::list_index(path, filter.pathspecs())?
+        .into_iter()
+        .filter(|entry| {
+            entry.is_regular_file()
+                && staged.contains(&entry.path)
+                && filter.should_count(&entry.path)
+        })
+        .collect();
+    let selection = apply_attributes(path, None, &filter, entries, |entry| &entry.path)?;
+    let summary = count_selected_blobs(path, selection, &filter)?;
+
+    add_submodules(
+        path,
+        git::list_submodules(path, None)?,
+        &filter,
+        summary,
+        |path, _, filter| count_staged(path, filter),
+    )
+}
+
+/// Counts the blobs of the selected tree entries, skipping binary blobs.
+fn count_selected_blobs(
+    path: &Path,
+    selection: Selection<git::TreeEntry>,
+    filter: &FileFilter,
+) -> Result<CountSummary> {
+    let mut counts = Vec::with_capacity(selection.files.len());
+    let mut skipped = selection.skipped;
+    let results = count_tree_entries(path, &selection.files, filter, &selection.languages)?;
+    for (entry, count) in selection.files.iter().zip(results) {
+        match count {
+            Some(count) => counts.push(count),
+            None => skipped.push(SkippedFile::new(&entry.path, SkipReason::Binary)),
+        }
+    }
+
+    Ok(CountSummary::from_counts(counts).with_skipped(skipped))
+}
+
+/// Adds the submodules of the repository at `path` to its summary.
+///
+/// If the filter recurses into submodules, each initialized submodule is
+/// counted by `count`, given its directory, the commit recorded for it and
+/// the filter for its files; the paths of the results are prefixed by the
+/// path of the submodule, and counted files are tagged with it. Other
+/// submodules are reported as skipped, as is a submodule that could not be
+/// counted, such as one that lacks the recorded commit. Submodules outside
+/// the pathspecs of the filter are left out.
+fn add_submodules(
+    path: &Path,
+    submodules: Vec<git::TreeEntry>,
+    filter: &FileFilter,
+    summary: CountSummary,
+    count: impl Fn(&Path, &str, FileFilter) -> Result<CountSummary>,
+) -> Result<CountSummary> {
+    if submodules.is_empty() {
+        return Ok(summary);
+    }
+
+    let mut counts = summary.files;
+    let mut skipped = summary.skipped;
+
+    for submodule in submodules {
+        let Some(submodule_filter) = filter.for_submodule(&submodule.path) else {
+            continue;
+        };
+        let directory = path.join(&submodule.path);
+
+        if !filter.recurses_submodules() {
+            // Only tracked files would include the submodule itself
+            if filter.file_set() == FileSet::Tracked {
+                skipped.push(SkippedFile::new(submodule.path, SkipReason::Submodule));
+            }
+            continue;
+        }
+        // Git creates an empty directory for a submodule until it is initialized
+        if !directory.join(".git").exists() {
+            skipped.push(SkippedFile::new(submodule.path, SkipReason::Uninitialized));
+            continue;
+        }
+
+        let summary = match count(&directory, &submodule.oid, submodule_filter) {
+            Ok(summary) => summary,
+            Err(e) => {
+                skipped.push(SkippedFile::unreadable(submodule.path, e));
+                continue;
+            }
+        };
+
+        for mut file in summary.files {
+            file.path = submodule.path.join(&file.path);
+            file.submodule = Some(match file.submodule {
+                Some(nested) => submodule.path.join(nested),
+                None => submodule.path.clone(),
+            });
+            counts.push(file);
+        }
+        for mut file in summary.skipped {
+            file.path = submodule.path.join(&file.path);
+            skipped.push(file);
+        }
+    }
+
+    counts.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+    Ok(CountSummary::from_counts(counts).with_skipped(skipped))
+}
+
+/// Files selected for counting after their `.gitattributes` were applied.
+pub(crate) struct Selection<T> {
+    /// The files to count.
+    pub(crate) files: Vec<T>,
+    /// Languages assigned by `linguist-language`, keyed by path.
+    pub(crate) languages: HashMap<PathBuf, Language>,
+    /// Files excluded by their attributes.
+    pub(crate) skipped: Vec<SkippedFile>,
+}
+
+/// Removes the files excluded by their `.gitattributes`, and collects the
+/// languages assigned by `linguist-language`.
+///
+/// Attributes are read from the working tree, or from `revision` if given.
+/// Nothing is removed if the filter disregards attributes.
+pub(crate) fn apply_attributes<T>(
+    path: &Path,
+    revision: Option<&str>,
+    filter: &FileFilter,
+    files: Vec<T>,
+    file_path: impl Fn(&T) -> &Path,
+) -> Result<Selection<T>> {
+    let mut selection = Selection {
+        files: Vec::with_capacity(files.len()),
+        languages: HashMap::new(),
+        skipped: Vec::new(),
+    };
+
+    if !filter.uses_attributes() {
+        selection.files = files;
+        return Ok(selection);
+    }
+
+    let paths: Vec<&Path> = files.iter().map(&file_path).collect();
+    let attributes = attributes::read_attributes(path, revision, &paths)?;
+
+    for (file, attributes) in files.into_iter().zip(attributes) {
+        let path = file_path(&file);
+        if let Some(reason) = attributes.skip_reason() {
+            selection.skipped.push(SkippedFile::new(path, reason));
+            continue;
+        }
+        if let Some(language) = attributes.language {
+            selection.languages.insert(path.to_path_buf(), language);
+        }
+        selection.files.push(file);
+    }
+
+    Ok(selection)
+}
+
+/// Counts the blobs of tree entries through a single `git cat-file --batch` process.
+///
+/// Languages are assigned by the filter's overrides where they match, then
+/// by `languages`. Returns one result per entry, `None` for binary blobs.
+pub(crate) fn count_tree_entries(
+    path: &Path,
+    entries: &[git::TreeEntry],
+    filter: &FileFilter,
+    languages: &HashMap<PathBuf, Language>,
+) -> Result<Vec<Option<FileCount>>> {
+    let oids: Vec<&str> = entries.iter().map(|entry| entry.oid.as_str()).collect();
+    let mut counts = Vec::with_capacity(entries.len());
+
+    // Stream blob contents into the counter
+    git::read_blobs(path, &oids, |index, blob| {
+        let entry = &entries[index];
+        let language = filter
+            .language_override(&entry.path)
+            .or_else(|| languages.get(&entry.path).copied());
+        let count = counter::count_text_reader(&entry.path, blob, language).map_err(|e| {
+            GitlsfError::git_with_source(
+                format!("Failed to read blob of '{}'", entry.path.display()),
+                e,
+            )
+        })?;
+        counts.push(count);
+        Ok(())
+    })?;
+
+    Ok(counts)
+}
+
+/// Compares line counts between two revisions of a Git repository.
+///
+/// Uses the default file filter.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `from` - The old revision.
+/// * `to` - The new revision, or `None` to compare against the working tree.
+///
+/// # Returns
+///
+/// The per-file and total line count differences.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - Either revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::diff_repository;
+///
+/// let diff = diff_repository(".", "v1.0", Some("HEAD")).unwrap();
+/// println!("+{} -{}", diff.lines_added(), diff.lines_removed());
+/// ```
+pub fn diff_repository(path: impl AsRef<Path>, from: &str, to: Option<&str>) -> Result<CountDiff> {
+    diff_repository_with_filter(path, from, to, FileFilter::new())
+}
+
+/// Compares line counts between two revisions of a Git repository with a custom filter.
+///
+/// # Arguments
+///
+/// * `path` - The path to the Git repository.
+/// * `from` - The old revision.
+/// * `to` - The new revision, or `None` to compare against the working tree.
+/// * `filter` - The file filter to use for both sides.
+///
+/// # Returns
+///
+/// The per-file and total line count differences.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path is not within a Git repository
+/// - Either revision does not exist
+/// - The `git` command fails to execute
+///
+/// # Example
+///
+/// ```no_run
+/// use gitlsf::{diff_repository_with_filter, filter::FileFilter};
+///
+/// let filter = FileFilter::new().exclude_extension("sql");
+/// let diff = diff_repository_with_filter(".", "v1.0", None, filter).unwrap();
+/// ```
+pub fn diff_repository_with_filter(
+    path: impl AsRef<Path>,
+    from: &str,
+    to: Option<&str>,
+    filter: FileFilter,
+) -> Result<CountDiff> {
+    let path = path.as_ref();
+
+    let old = count_repository_at_with_filter(path, from, filter.clone())?;
+    let new = match to {
+        Some(to) => count_repository_at_with_filter(path, to, filter)?,
+        None => count_repository_with_filter(path, filter)?,
+    };
+
+    Ok(CountDiff::between(&old, &new))
 }
 
 #[cfg(test)]
@@ -164,7 +829,7 @@ mod tests {
         fs::write(path.join("lib.rs"), "pub fn hello() {\n}\n").unwrap();
         fs::write(path.join("README.md"), "# Test\n\nThis is a test.\n").unwrap();
         fs::write(path.join("config.json"), "{\"key\": \"value\"}\n").unwrap();
-        fs::write(path.join("image.png"), &[0x89, 0x50, 0x4E, 0x47]).unwrap(); // PNG header
+        fs::write(path.join("image.png"), [0x89, 0x50, 0x4E, 0x47]).unwrap(); // PNG header
 
         // Add files to git
         Command::new("git")
@@ -184,6 +849,7 @@ mod tests {
         // Should only count .rs files (main.rs: 3 lines, lib.rs: 2 lines)
         assert_eq!(summary.file_count, 2);
         assert_eq!(summary.total_lines, 5);
+        assert_eq!(summary.total_code, 5);
     }
 
     #[test]
@@ -198,6 +864,183 @@ mod tests {
         assert_eq!(summary.file_count, 0);
     }
 
+    #[test]
+    fn test_count_repository_with_cache() {
+        let temp_dir = setup_git_repo_with_files();
+        let path = temp_dir.path();
+        fs::write(path.join("data.rs"), b"\0\x01").unwrap();
+        Command::new("git")
+            .args(["add", "data.rs"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+
+        let uncached = count_repository(path).unwrap();
+        let first = count_repository_with_cache(path, FileFilter::new()).unwrap();
+        assert_eq!(first, uncached);
+        assert_eq!(CountCache::open(path).unwrap().len(), 3);
+
+        // Clean files are taken from the cache without being read
+        let index = git::list_index(path, &[]).unwrap();
+        let lib = index
+            .iter()
+            .find(|entry| entry.path == Path::new("lib.rs"))
+            .unwrap();
+        let mut cache = CountCache::open(path).unwrap();
+        let stats = LineStats {
+            lines: 100,
+            code: 100,
+            comment: 0,
+            blank: 0,
+        };
+        let count = CachedCount::Text {
+            language: Some(Language::Rust),
+            stats,
+        };
+        cache.insert(&lib.oid, "lib.rs", None, count);
+        cache.save(false).unwrap();
+
+        let second = count_repository_with_cache(path, FileFilter::new()).unwrap();
+        assert_eq!(second.total_lines, 103);
+        assert_eq!(second.skipped, uncached.skipped);
+
+        // Modified files are read again
+        fs::write(path.join("lib.rs"), "pub fn hello() {}\n").unwrap();
+        let third = count_repository_with_cache(path, FileFilter::new()).unwrap();
+        assert_eq!(third, count_repository(path).unwrap());
+        assert_eq!(third.total_lines, 4);
+    }
+
+    #[test]
+    fn test_scoped_count_keeps_cache_entries() {
+        let temp_dir = setup_git_repo_with_files();
+        let path = temp_dir.path();
+
+        count_repository_with_cache(path, FileFilter::new()).unwrap();
+        assert_eq!(CountCache::open(path).unwrap().len(), 2);
+
+        // Runs limited to some files keep the entries of the others
+        count_repository_with_cache(path, FileFilter::new().pathspec("main.rs")).unwrap();
+        let filter = FileFilter::new().exclude_pattern("lib.rs").unwrap();
+        count_repository_with_cache(path, filter).unwrap();
+        assert_eq!(CountCache::open(path).unwrap().len(), 2);
+
+        // A full run drops the entries of files no longer tracked
+        Command::new("git")
+            .args(["rm", "-q", "--cached", "lib.rs"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        count_repository_with_cache(path, FileFilter::new()).unwrap();
+        assert_eq!(CountCache::open(path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_count_repository_at_revision() {
+        let temp_dir = setup_git_repo_with_files();
+        let path = temp_dir.path();
+        Command::new("git")
+            .args(["commit", "-q", "-m", "initial"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+
+        // Working tree changes must not affect the count at HEAD
+        fs::write(path.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(path.join("extra.rs"), "// extra\n").unwrap();
+
+        let summary = count_repository_at(path, "HEAD").unwrap();
+
+        assert_eq!(summary.file_count, 2);
+        assert_eq!(summary.total_lines, 5);
+        assert_eq!(count_repository(path).unwrap().total_lines, 3);
+    }
+
+    #[test]
+    fn test_count_repository_at_unknown_revision() {
+        let temp_dir = setup_git_repo_with_files();
+        let result = count_repository_at(temp_dir.path(), "v0.0.0");
+
+        assert!(matches!(result, Err(GitlsfError::UnknownRevision { .. })));
+    }
+
+    #[test]
+    fn test_diff_repository() {
+        let temp_dir = setup_git_repo_with_files();
+        let path = temp_dir.path();
+        Command::new("git")
+            .args(["commit", "-q", "-m", "initial"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+
+        fs::write(path.join("lib.rs"), "pub fn hello() {\n    todo!()\n}\n").unwrap();
+        fs::write(path.join("new.rs"), "fn new() {}\n").unwrap();
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(path)
+            .output()
+            .unwrap();
+
+        let diff = diff_repository(path, "HEAD", None).unwrap();
+
+        assert_eq!(diff.files.len(), 2);
+        assert_eq!(diff.old_total, 5);
+        assert_eq!(diff.new_total, 7);
+        assert_eq!(diff.lines_added(), 2);
+
+        Command::new("git")
+            .args(["commit", "-q", "-m", "second"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+
+        let diff = diff_repository(path, "HEAD~1", Some("HEAD")).unwrap();
+        assert_eq!(diff.delta(), 2);
+    }
+
+    #[test]
+    fn test_count_source_streams_files() {
+        struct Files(Vec<(&'static str, &'static [u8])>);
+
+        impl FileSource for Files {
+            fn list_files(&self) -> Result<Vec<PathBuf>> {
+                Ok(self.0.iter().map(|(name, _)| PathBuf::from(name)).collect())
+            }
+
+            fn read_files(
+                &self,
+                files: &[PathBuf],
+                visit: &mut dyn FnMut(usize, &mut dyn std::io::Read) -> Result<()>,
+            ) -> Result<()> {
+                for (index, file) in files.iter().enumerate() {
+                    if let Some(&(_, content)) =
+                        self.0.iter().find(|(name, _)| file == Path::new(name))
+                    {
+                        visit(index, &mut &content[..])?;
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        let source = Files(vec![
+            ("main.rs", b"fn main() {}\n// done\n"),
+            ("logo.png", b"\x89PNG"),
+            ("blob.rs", b"\0\x01"),
+        ]);
+
+        let summary = count_source(&source, FileFilter::new()).unwrap();
+
+        assert_eq!(summary.file_count, 1);
+        assert_eq!(summary.total_lines, 2);
+        assert_eq!(summary.files[0].comment, 1);
+        assert_eq!(
+            summary.skipped,
+            vec![SkippedFile::new("blob.rs", SkipReason::Binary)]
+        );
+    }
+
     #[test]
     fn test_count_repository_not_a_git_repo() {
         let temp_dir = TempDir::new().unwrap();
@@ -2,11 +2,29 @@
 //!
 //! This is the CLI entry point for the gitlsf tool.
 
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
-use gitlsf::{CountSummary, count_repository};
+use gitlsf::blame::{self, Attribution, AttributionReport};
+use gitlsf::budget::{Budgets, Violation};
+use gitlsf::churn::{self, Churn, ChurnReport, ChurnStats, Grouping};
+use gitlsf::config::Config;
+use gitlsf::diff::FileStatus;
+use gitlsf::filter::FileFilter;
+use gitlsf::git::{self, FileSet};
+use gitlsf::history::{self, Sampling};
+use gitlsf::output::{self, Report, quote_path};
+#[cfg(feature = "archive")]
+use gitlsf::source::ArchiveSource;
+use gitlsf::source::{DirectorySource, ListSource};
+use gitlsf::tree::DirectoryTree;
+use gitlsf::{
+    CountDiff, CountSummary, FileSource, GitlsfError, Repository, SkipReason,
+    count_repository_at_with_filter, count_repository_with_cache, count_repository_with_filter,
+    count_source, diff_repository_with_filter,
+};
 
 /// A fast Git repository line counter.
 ///
@@ -15,24 +33,602 @@ use gitlsf::{CountSummary, count_repository};
 #[derive(Parser, Debug)]
 #[command(name = "gitlsf")]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
-    /// Path to the Git repository (defaults to current directory)
-    #[arg(default_value = ".")]
-    path: String,
+    #[command(subcommand)]
+    command: Option<Command>,
 
     /// Verbose mode - show each file with its line count (default)
-    #[arg(short, long, conflicts_with_all = ["quiet", "summary"])]
+    #[arg(short, long, conflicts_with_all = ["quiet", "summary", "languages", "tree", "depth"])]
     verbose: bool,
 
     /// Quiet mode - only show the total line count
-    #[arg(short, long, conflicts_with_all = ["verbose", "summary"])]
+    #[arg(short, long, conflicts_with_all = ["verbose", "summary", "languages", "tree", "depth"])]
     quiet: bool,
 
     /// Summary mode - show total lines and file count
-    #[arg(short, long, conflicts_with_all = ["verbose", "quiet"])]
+    #[arg(short, long, conflicts_with_all = ["verbose", "quiet", "languages", "tree", "depth"])]
     summary: bool,
+
+    /// Languages mode - show file and line counts per language
+    #[arg(short, long, conflicts_with_all = ["verbose", "quiet", "summary", "tree", "depth"])]
+    languages: bool,
+
+    /// Tree mode - show line counts per directory as an indented tree
+    #[arg(short, long, conflicts_with_all = ["verbose", "quiet", "summary", "languages"])]
+    tree: bool,
+
+    /// Number of directory levels shown in tree mode (implies --tree) [default: 2]
+    #[arg(short, long, value_name = "N")]
+    depth: Option<usize>,
+
+    /// Count files at a Git revision (tag, branch or commit) instead of the working tree
+    #[arg(short, long, value_name = "REV")]
+    rev: Option<String>,
+
+    /// Show code, comment and blank line columns
+    #[arg(short, long, conflicts_with = "quiet")]
+    breakdown: bool,
+
+    /// List files that were not counted, with the reason for each
+    #[arg(long, conflicts_with = "quiet")]
+    show_skipped: bool,
+
+    /// Output format; JSON, CSV and TSV always include every file and the full breakdown [default: text]
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// Append a row with the totals to CSV and TSV output
+    #[arg(long)]
+    totals: bool,
+
+    /// Exit with an error if any file could not be read
+    #[arg(long)]
+    strict: bool,
+
+    /// Read every file instead of reusing counts of unchanged files from .git/gitlsf-cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Count the files of initialized submodules as well
+    #[arg(long)]
+    recurse_submodules: bool,
+
+    /// Show paths relative to the current directory instead of the repository root
+    #[arg(long)]
+    relative: bool,
+
+    #[command(flatten)]
+    files: FileSetArgs,
+
+    #[command(flatten)]
+    source: SourceArgs,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+/// Selection of the files to count instead of all tracked files.
+#[derive(clap::Args, Debug, Default)]
+struct FileSetArgs {
+    /// Only count untracked files that are not ignored
+    #[arg(long, conflicts_with_all = ["modified", "staged", "rev"])]
+    untracked: bool,
+
+    /// Only count tracked files modified in the working tree
+    #[arg(long, conflicts_with_all = ["staged", "rev"])]
+    modified: bool,
+
+    /// Only count staged files, as they would be committed
+    #[arg(long, conflicts_with = "rev")]
+    staged: bool,
+}
+
+impl FileSetArgs {
+    /// Determines the file set based on the command-line arguments.
+    fn file_set(&self) -> FileSet {
+        if self.untracked {
+            FileSet::Untracked
+        } else if self.modified {
+            FileSet::Modified
+        } else if self.staged {
+            FileSet::Staged
+        } else {
+            FileSet::Tracked
+        }
+    }
+}
+
+/// Files to count from outside a Git repository.
+#[derive(clap::Args, Debug, Default)]
+struct SourceArgs {
+    /// Count the files below a directory instead of tracked files, skipping those ignored by .gitignore
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["stdin", "paths", "rev", "untracked", "modified", "staged", "recurse_submodules", "relative"]
+    )]
+    walk: Option<PathBuf>,
+
+    /// Count the files of a .tar, .tar.gz, .tgz or .zip archive instead of tracked files
+    #[cfg(feature = "archive")]
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["walk", "stdin", "paths", "rev", "untracked", "modified", "staged", "recurse_submodules", "relative"]
+    )]
+    archive: Option<PathBuf>,
+
+    /// Count the files listed on standard input, one per line, instead of tracked files
+    #[arg(
+        long,
+        conflicts_with_all = ["paths", "rev", "untracked", "modified", "staged", "recurse_submodules", "relative"]
+    )]
+    stdin: bool,
+
+    /// Separate the paths read with --stdin by NUL bytes, as written by `find -print0`
+    #[arg(long, requires = "stdin")]
+    null: bool,
+}
+
+impl SourceArgs {
+    /// Opens the file source selected on the command line, if any.
+    ///
+    /// Paths listed on standard input are relative to the current directory.
+    fn open(&self) -> gitlsf::Result<Option<Input>> {
+        if let Some(directory) = &self.walk {
+            return Ok(Some(Input::Source {
+                source: Box::new(DirectorySource::new(directory)),
+                root: directory.clone(),
+            }));
+        }
+        #[cfg(feature = "archive")]
+        if let Some(archive) = &self.archive {
+            return Ok(Some(Input::Source {
+                source: Box::new(ArchiveSource::open(archive)?),
+                root: archive.clone(),
+            }));
+        }
+        if self.stdin {
+            let separator = if self.null { b'\0' } else { b'\n' };
+            let source = ListSource::from_reader(".", std::io::stdin().lock(), separator)?;
+            return Ok(Some(Input::Source {
+                source: Box::new(source),
+                root: current_directory()?,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Returns the directory whose `.gitlsf.toml` applies to the source.
+    fn config_directory(&self) -> &Path {
+        self.walk.as_deref().unwrap_or(Path::new("."))
+    }
+}
+
+/// The files to count: those of a Git repository, or of another source.
+enum Input {
+    /// The files of a file set of a repository.
+    Repository(Repository),
+    /// The files of a directory, archive or list.
+    Source {
+        /// The files to count.
+        source: Box<dyn FileSource>,
+        /// The directory, archive or base directory of the files, as shown in reports.
+        root: PathBuf,
+    },
+}
+
+impl Input {
+    /// Returns the root shown in reports.
+    fn root(&self) -> &Path {
+        match self {
+            Input::Repository(repository) => repository.root(),
+            Input::Source { root, .. } => root,
+        }
+    }
+}
+
+/// File selection arguments shared by all commands.
+#[derive(clap::Args, Debug, Default)]
+struct FilterArgs {
+    /// Only count files below these paths (defaults to the whole repository)
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Only count files matching a gitignore-style glob (repeatable, `!` negates)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Do not count files matching a gitignore-style glob (repeatable, `!` re-includes)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Count files marked generated, vendored, documentation or binary in .gitattributes
+    #[arg(long)]
+    no_attributes: bool,
+
+    /// Ignore .gitlsf.toml and the user configuration file
+    #[arg(long)]
+    no_config: bool,
+}
+
+impl FilterArgs {
+    /// Finds the repository, loads its configuration and builds the file
+    /// filter for a file set, recursing into submodules if requested.
+    ///
+    /// The repository is the one containing the current directory, unless
+    /// the first path is outside of it, such as another repository: then it
+    /// is the one containing that path. Paths inside a submodule thus stay
+    /// paths of the enclosing repository. Paths are turned into pathspecs
+    /// relative to the root; none are needed if one of them is the root itself.
+    fn load(
+        &self,
+        file_set: FileSet,
+        recurse_submodules: bool,
+    ) -> gitlsf::Result<(Repository, Config, FileFilter)> {
+        let repository = match (Repository::discover("."), self.paths.first()) {
+            (Ok(repository), None) => repository,
+            (Ok(repository), Some(path)) if repository.repository_path(path).is_ok() => repository,
+            (Err(e), None) => return Err(e),
+            (_, Some(path)) => Repository::discover(path)?,
+        };
+        let config = self.config(repository.root())?;
+        let mut filter = self.filter(&config)?.with_file_set(file_set);
+        if recurse_submodules {
+            filter = filter.recurse_submodules();
+        }
+
+        let pathspecs = self
+            .paths
+            .iter()
+            .map(|path| repository.repository_path(path))
+            .collect::<gitlsf::Result<Vec<_>>>()?;
+        if !pathspecs.iter().any(|path| path.as_os_str().is_empty()) {
+            for pathspec in pathspecs {
+                filter = filter.pathspec(pathspec);
+            }
+        }
+
+        Ok((repository, config, filter))
+    }
+
+    /// Loads the configuration files of a directory outside Git and builds
+    /// the file filter.
+    ///
+    /// Paths are not used: other sources are selected as a whole.
+    fn load_in(&self, directory: &Path) -> gitlsf::Result<(Config, FileFilter)> {
+        let config = if self.no_config {
+            Config::default()
+        } else {
+            Config::discover_in(directory)?
+        };
+        let filter = self.filter(&config)?;
+        Ok((config, filter))
+    }
+
+    /// Loads the configuration files of the repository at `path`, unless disabled.
+    fn config(&self, path: &Path) -> gitlsf::Result<Config> {
+        if self.no_config {
+            Ok(Config::default())
+        } else {
+            Config::discover(path)
+        }
+    }
+
+    /// Builds the file filter from the default exclusions, the configuration
+    /// and the given patterns.
+    ///
+    /// Patterns given on the command line come last, so they take precedence.
+    fn filter(&self, config: &Config) -> gitlsf::Result<FileFilter> {
+        let mut filter = config.apply(FileFilter::new())?;
+        if self.no_attributes {
+            filter = filter.without_attributes();
+        }
+        for pattern in &self.include {
+            filter = filter.include_pattern(pattern)?;
+        }
+        for pattern in &self.exclude {
+            filter = filter.exclude_pattern(pattern)?;
+        }
+        Ok(filter)
+    }
+}
+
+/// Output format of the line count results.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// Human-readable text, shaped by the output mode
+    Text,
+    /// A versioned JSON report
+    Json,
+    /// Comma-separated values
+    Csv,
+    /// Tab-separated values
+    Tsv,
+}
+
+impl Format {
+    /// Parses a format name from a configuration file.
+    fn from_config(name: &str) -> Result<Self, String> {
+        Format::from_str(name, true)
+            .map_err(|_| format!("invalid format '{name}' in configuration file"))
+    }
+}
+
+/// Subcommands besides the default line count.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare line counts between two revisions
+    Diff(DiffArgs),
+    /// Show line counts over the commit history
+    History(HistoryArgs),
+    /// Attribute surviving lines to their authors with git blame
+    Blame(BlameArgs),
+    /// Sum the lines added and deleted by the commits of a range
+    Churn(ChurnArgs),
+    /// Check line counts against budgets, for CI jobs and pre-commit hooks
+    Check(CheckArgs),
+}
+
+/// Arguments of the `diff` subcommand.
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// Revision range `OLD..NEW`, or `OLD` to compare against the working tree
+    range: String,
+
+    /// Number of leading path components used to group directory totals
+    #[arg(short, long, default_value_t = 1)]
+    depth: usize,
+
+    /// Count the files of initialized submodules as well
+    #[arg(long)]
+    recurse_submodules: bool,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+impl DiffArgs {
+    /// Splits the range into the old revision and the optional new revision.
+    ///
+    /// As in Git, an omitted side of `..` stands for `HEAD`.
+    fn revisions(&self) -> (&str, Option<&str>) {
+        match self.range.split_once("..") {
+            Some((from, to)) => {
+                let from = if from.is_empty() { "HEAD" } else { from };
+                let to = if to.is_empty() { "HEAD" } else { to };
+                (from, Some(to))
+            }
+            None => (&self.range, None),
+        }
+    }
+}
+
+/// Arguments of the `history` subcommand.
+#[derive(clap::Args, Debug)]
+struct HistoryArgs {
+    /// Revision whose first-parent history is walked
+    #[arg(short, long, value_name = "REV", default_value = "HEAD")]
+    rev: String,
+
+    /// Only include commits more recent than a date (e.g. "2 years ago")
+    #[arg(long, value_name = "DATE")]
+    since: Option<String>,
+
+    /// Sample every N-th commit
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        conflicts_with_all = ["daily", "weekly"]
+    )]
+    every: usize,
+
+    /// Sample the last commit of each day
+    #[arg(long, conflicts_with = "weekly")]
+    daily: bool,
+
+    /// Sample the last commit of each week
+    #[arg(long)]
+    weekly: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = HistoryFormat::Csv)]
+    format: HistoryFormat,
+
+    #[command(flatten)]
+    filter: FilterArgs,
 }
 
+/// Output format of the `history` subcommand.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryFormat {
+    /// One CSV row per sampled commit
+    Csv,
+    /// A JSON array with one object per sampled commit
+    Json,
+}
+
+impl HistoryArgs {
+    /// Determines the commit sampling based on the command-line arguments.
+    fn sampling(&self) -> Sampling {
+        if self.daily {
+            Sampling::Daily
+        } else if self.weekly {
+            Sampling::Weekly
+        } else {
+            Sampling::EveryN(self.every)
+        }
+    }
+}
+
+/// Arguments of the `blame` subcommand.
+#[derive(clap::Args, Debug)]
+struct BlameArgs {
+    /// Blame files at a Git revision (tag, branch or commit) instead of the working tree
+    #[arg(short, long, value_name = "REV")]
+    rev: Option<String>,
+
+    /// Report authors as recorded in commits, without resolving them through .mailmap
+    #[arg(long)]
+    no_mailmap: bool,
+
+    /// Break the totals down per directory, grouped by this many leading path components
+    #[arg(short, long, value_name = "N")]
+    depth: Option<usize>,
+
+    /// Output format; CSV and TSV have one row per author, or per author of each directory with --depth
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+/// Arguments of the `churn` subcommand.
+#[derive(clap::Args, Debug)]
+struct ChurnArgs {
+    /// Revision whose history is walked, or range `OLD..NEW` of commits
+    #[arg(short, long, value_name = "REV", default_value = "HEAD")]
+    rev: String,
+
+    /// Only include commits more recent than a date (e.g. "3 months ago")
+    #[arg(long, value_name = "DATE")]
+    since: Option<String>,
+
+    /// Only include commits older than a date
+    #[arg(long, value_name = "DATE")]
+    until: Option<String>,
+
+    /// How to group the totals
+    #[arg(long, value_enum, default_value_t = ChurnGrouping::Author)]
+    by: ChurnGrouping,
+
+    /// Number of leading path components used to group directory totals
+    #[arg(short, long, default_value_t = 1)]
+    depth: usize,
+
+    /// Report authors as recorded in commits, without resolving them through .mailmap
+    #[arg(long)]
+    no_mailmap: bool,
+
+    /// Output format; CSV and TSV have one row per group of --by
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+/// Grouping of the `churn` subcommand totals.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ChurnGrouping {
+    /// One row per file
+    File,
+    /// One row per directory, see --depth
+    Directory,
+    /// One row per author
+    Author,
+    /// One row per language
+    Language,
+}
+
+impl ChurnArgs {
+    /// Determines the table grouping based on the command-line arguments.
+    fn grouping(&self) -> Grouping {
+        match self.by {
+            ChurnGrouping::File => Grouping::File,
+            ChurnGrouping::Directory => Grouping::Directory(self.depth),
+            ChurnGrouping::Author => Grouping::Author,
+            ChurnGrouping::Language => Grouping::Language,
+        }
+    }
+}
+
+/// Arguments of the `check` subcommand.
+#[derive(clap::Args, Debug)]
+struct CheckArgs {
+    /// Check files at a Git revision (tag, branch or commit) instead of the working tree
+    #[arg(short, long, value_name = "REV")]
+    rev: Option<String>,
+
+    /// Maximum number of lines of any single file
+    #[arg(long, value_name = "N")]
+    max_file_lines: Option<usize>,
+
+    /// Maximum total number of lines
+    #[arg(long, value_name = "N")]
+    max_total_lines: Option<usize>,
+
+    /// Maximum lines of the files matching a glob together (repeatable)
+    #[arg(long, value_name = "GLOB=N", value_parser = parse_budget)]
+    budget: Vec<(String, usize)>,
+
+    /// Exit with an error if any file could not be read
+    #[arg(long)]
+    strict: bool,
+
+    /// Read every file instead of reusing counts of unchanged files from .git/gitlsf-cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Count the files of initialized submodules as well
+    #[arg(long)]
+    recurse_submodules: bool,
+
+    /// Show paths relative to the current directory instead of the repository root
+    #[arg(long)]
+    relative: bool,
+
+    #[command(flatten)]
+    files: FileSetArgs,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+impl CheckArgs {
+    /// Builds the budgets from the configuration and the command-line limits.
+    ///
+    /// Limits given on the command line replace those of the configuration.
+    fn budgets(&self, config: &Config) -> gitlsf::Result<Budgets> {
+        let mut budgets = config.budgets()?;
+        if let Some(limit) = self.max_file_lines {
+            budgets = budgets.max_file_lines(limit);
+        }
+        if let Some(limit) = self.max_total_lines {
+            budgets = budgets.max_total_lines(limit);
+        }
+        for (pattern, limit) in &self.budget {
+            budgets = budgets.budget(pattern, *limit)?;
+        }
+        Ok(budgets)
+    }
+}
+
+/// Parses a `GLOB=N` budget argument.
+///
+/// The pattern is split at the last `=`, so patterns may contain `=` themselves.
+fn parse_budget(value: &str) -> Result<(String, usize), String> {
+    let (pattern, limit) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected GLOB=N, got '{value}'"))?;
+    if pattern.is_empty() {
+        return Err(format!("missing pattern in '{value}'"));
+    }
+    let limit = limit
+        .parse()
+        .map_err(|_| format!("invalid line count '{limit}'"))?;
+    Ok((pattern.to_string(), limit))
+}
+
+/// Exit status of `check` when a budget is exceeded, distinct from errors (1)
+/// and usage errors (2).
+const EXIT_BUDGET_EXCEEDED: u8 = 3;
+
 /// Output mode for the line count results.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum OutputMode {
@@ -42,8 +638,15 @@ enum OutputMode {
     Quiet,
     /// Show total lines and file count.
     Summary,
+    /// Show file and line counts per language.
+    Languages,
+    /// Show line counts per directory, down to the given depth.
+    Tree(usize),
 }
 
+/// Number of directory levels shown in tree mode unless `--depth` is given.
+const DEFAULT_TREE_DEPTH: usize = 2;
+
 impl Args {
     /// Determines the output mode based on the command-line arguments.
     fn output_mode(&self) -> OutputMode {
@@ -51,6 +654,10 @@ impl Args {
             OutputMode::Quiet
         } else if self.summary {
             OutputMode::Summary
+        } else if self.languages {
+            OutputMode::Languages
+        } else if self.tree || self.depth.is_some() {
+            OutputMode::Tree(self.depth.unwrap_or(DEFAULT_TREE_DEPTH))
         } else {
             // Default to verbose (including when -v is explicitly passed)
             OutputMode::Verbose
@@ -59,47 +666,816 @@ impl Args {
 }
 
 /// Prints the results according to the specified output mode.
-fn print_results(summary: &CountSummary, mode: OutputMode) {
+///
+/// With `breakdown`, the code, comment and blank line counts are shown
+/// alongside the total line count.
+fn print_results(summary: &CountSummary, mode: OutputMode, breakdown: bool) {
     match mode {
         OutputMode::Verbose => {
             // Sort files by path for consistent output
             let mut files = summary.files.clone();
-            files.sort_by(|a, b| a.path.cmp(&b.path));
+            files.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
 
             // Calculate the width needed for line numbers
             let max_lines = files.iter().map(|f| f.lines).max().unwrap_or(0);
             let max_lines = max_lines.max(summary.total_lines);
             let width = max_lines.to_string().len().max(4);
 
-            for file in &files {
-                println!("{:>width$} {}", file.lines, file.path);
+            if breakdown {
+                let width = width.max("comment".len());
+                println!(
+                    "{:>width$} {:>width$} {:>width$} {:>width$} path",
+                    "lines", "code", "comment", "blank"
+                );
+                for file in &files {
+                    println!(
+                        "{:>width$} {:>width$} {:>width$} {:>width$} {}",
+                        file.lines,
+                        file.code,
+                        file.comment,
+                        file.blank,
+                        quote_path(&file.path)
+                    );
+                }
+                println!(
+                    "{:>width$} {:>width$} {:>width$} {:>width$} total",
+                    summary.total_lines,
+                    summary.total_code,
+                    summary.total_comment,
+                    summary.total_blank
+                );
+            } else {
+                for file in &files {
+                    println!("{:>width$} {}", file.lines, quote_path(&file.path));
+                }
+                println!("{:>width$} total", summary.total_lines);
             }
-            println!("{:>width$} total", summary.total_lines);
         }
         OutputMode::Quiet => {
             println!("{}", summary.total_lines);
         }
+        OutputMode::Languages => {
+            let languages = summary.by_language();
+            let width = summary.total_lines.to_string().len().max("comment".len());
+
+            if breakdown {
+                println!(
+                    "{:>width$} {:>width$} {:>width$} {:>width$} {:>width$} language",
+                    "files", "lines", "code", "comment", "blank"
+                );
+                for language in &languages {
+                    let stats = language.stats;
+                    println!(
+                        "{:>width$} {:>width$} {:>width$} {:>width$} {:>width$} {}",
+                        language.files,
+                        stats.lines,
+                        stats.code,
+                        stats.comment,
+                        stats.blank,
+                        language.name()
+                    );
+                }
+                println!(
+                    "{:>width$} {:>width$} {:>width$} {:>width$} {:>width$} total",
+                    summary.file_count,
+                    summary.total_lines,
+                    summary.total_code,
+                    summary.total_comment,
+                    summary.total_blank
+                );
+            } else {
+                println!("{:>width$} {:>width$} language", "files", "lines");
+                for language in &languages {
+                    println!(
+                        "{:>width$} {:>width$} {}",
+                        language.files,
+                        language.stats.lines,
+                        language.name()
+                    );
+                }
+                println!(
+                    "{:>width$} {:>width$} total",
+                    summary.file_count, summary.total_lines
+                );
+            }
+        }
+        OutputMode::Tree(depth) => {
+            let tree = DirectoryTree::from_summary(summary, depth);
+            let width = summary.total_lines.to_string().len().max("comment".len());
+
+            if breakdown {
+                println!(
+                    "{:>width$} {:>width$} {:>width$} {:>width$} {:>width$} {:>6} directory",
+                    "files", "lines", "code", "comment", "blank", "%"
+                );
+            } else {
+                println!(
+                    "{:>width$} {:>width$} {:>6} directory",
+                    "files", "lines", "%"
+                );
+            }
+            print_tree(&tree, summary.total_lines, width, 0, breakdown);
+        }
         OutputMode::Summary => {
             println!("Files: {}", summary.file_count);
             println!("Lines: {}", summary.total_lines);
+            if breakdown {
+                println!("Code: {}", summary.total_code);
+                println!("Comments: {}", summary.total_comment);
+                println!("Blank: {}", summary.total_blank);
+            }
+            if !summary.skipped.is_empty() {
+                println!("Skipped: {}", summary.skipped.len());
+            }
+            let submodules = summary.by_submodule();
+            if !submodules.is_empty() {
+                println!("Submodules:");
+                for submodule in &submodules {
+                    println!(
+                        "  {}: {} files, {} lines",
+                        quote_path(&submodule.path),
+                        submodule.files,
+                        submodule.stats.lines
+                    );
+                }
+            }
         }
     }
 }
 
+/// Prints the files that were skipped, with the reason for each.
+fn print_skipped(summary: &CountSummary) {
+    if summary.skipped.is_empty() {
+        return;
+    }
+
+    let width = summary
+        .skipped
+        .iter()
+        .map(|file| file.reason.name().len())
+        .max()
+        .unwrap_or(0);
+
+    println!();
+    println!("Skipped:");
+    for file in &summary.skipped {
+        println!(
+            "  {:<width$} {}",
+            file.reason.name(),
+            quote_path(&file.path)
+        );
+    }
+}
+
+/// Prints a directory and its subdirectories, indented by their level.
+fn print_tree(tree: &DirectoryTree, total: usize, width: usize, level: usize, breakdown: bool) {
+    let stats = tree.stats;
+    let percentage = format!("{:.1}%", tree.percentage(total));
+    let indent = "  ".repeat(level);
+    let name = if level == 0 {
+        tree.name.clone()
+    } else {
+        format!("{}/", tree.name)
+    };
+
+    if breakdown {
+        println!(
+            "{:>width$} {:>width$} {:>width$} {:>width$} {:>width$} {percentage:>6} {indent}{name}",
+            tree.files, stats.lines, stats.code, stats.comment, stats.blank
+        );
+    } else {
+        println!(
+            "{:>width$} {:>width$} {percentage:>6} {indent}{name}",
+            tree.files, stats.lines
+        );
+    }
+
+    for child in &tree.children {
+        print_tree(child, total, width, level + 1, breakdown);
+    }
+}
+
+/// Prints the results as a versioned JSON report.
+///
+/// In tree mode, the directory tree is nested in the report.
+fn print_json(args: &Args, root: &Path, summary: &CountSummary, filter: &FileFilter) -> ExitCode {
+    let mut report = Report::new(quote_path(root).into_owned(), summary, filter);
+
+    if let OutputMode::Tree(depth) = args.output_mode() {
+        report = report.with_tree(DirectoryTree::from_summary(summary, depth));
+    }
+
+    if let Some(rev) = &args.rev {
+        match git::resolve_revision(root, rev) {
+            Ok(commit) => report = report.with_revision(rev, commit),
+            Err(e) => {
+                eprintln!("error: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    exit_status(report.write_json(std::io::stdout().lock()))
+}
+
+/// Reports a write error, if any, and returns the matching exit status.
+fn exit_status(result: std::io::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints a warning listing the files that could not be read.
+///
+/// Returns the number of such files.
+fn print_unreadable_warning(summary: &CountSummary) -> usize {
+    let unreadable: Vec<_> = summary.unreadable().collect();
+
+    if !unreadable.is_empty() {
+        let files = if unreadable.len() == 1 {
+            "file"
+        } else {
+            "files"
+        };
+        eprintln!(
+            "warning: {} {files} could not be read and {} not counted:",
+            unreadable.len(),
+            if unreadable.len() == 1 { "was" } else { "were" }
+        );
+        for file in &unreadable {
+            match &file.error {
+                Some(error) => eprintln!("  {error}"),
+                None => eprintln!("  {}", quote_path(&file.path)),
+            }
+        }
+    }
+
+    unreadable.len()
+}
+
+/// Prints a warning listing the submodules that are not initialized.
+fn print_uninitialized_warning(summary: &CountSummary) {
+    let uninitialized: Vec<_> = summary
+        .skipped
+        .iter()
+        .filter(|file| file.reason == SkipReason::Uninitialized)
+        .collect();
+
+    if !uninitialized.is_empty() {
+        eprintln!(
+            "warning: {} {} not initialized and {} not counted; run `git submodule update --init`:",
+            uninitialized.len(),
+            if uninitialized.len() == 1 {
+                "submodule is"
+            } else {
+                "submodules are"
+            },
+            if uninitialized.len() == 1 {
+                "was"
+            } else {
+                "were"
+            }
+        );
+        for submodule in &uninitialized {
+            eprintln!("  {}", quote_path(&submodule.path));
+        }
+    }
+}
+
+/// Prints a line count comparison.
+fn print_diff(diff: &CountDiff, depth: usize) {
+    let width = diff
+        .files
+        .iter()
+        .map(|f| format!("{:+}", f.delta()).len())
+        .max()
+        .unwrap_or(0)
+        .max(5);
+
+    for file in &diff.files {
+        println!(
+            "{} {:>width$} {}",
+            file.status.code(),
+            format!("{:+}", file.delta()),
+            quote_path(&file.path)
+        );
+    }
+
+    let directories = diff.by_directory(depth);
+    if !directories.is_empty() {
+        println!();
+        for directory in &directories {
+            println!(
+                "  {:>width$} {}/",
+                format!("{:+}", directory.delta()),
+                directory.path
+            );
+        }
+    }
+
+    println!();
+    println!(
+        "{} files changed ({} added, {} removed, {} changed), +{} -{} lines",
+        diff.files.len(),
+        diff.count(FileStatus::Added),
+        diff.count(FileStatus::Removed),
+        diff.count(FileStatus::Changed),
+        diff.lines_added(),
+        diff.lines_removed()
+    );
+    println!(
+        "Lines: {} -> {} ({:+})",
+        diff.old_total,
+        diff.new_total,
+        diff.delta()
+    );
+}
+
+/// Runs the `diff` subcommand.
+fn run_diff(args: &DiffArgs) -> ExitCode {
+    let (from, to) = args.revisions();
+    let result = args
+        .filter
+        .load(FileSet::Tracked, args.recurse_submodules)
+        .and_then(|(repository, _, filter)| {
+            diff_repository_with_filter(repository.root(), from, to, filter)
+        });
+
+    match result {
+        Ok(diff) => {
+            print_diff(&diff, args.depth);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the `history` subcommand.
+fn run_history(args: &HistoryArgs) -> ExitCode {
+    let result = args
+        .filter
+        .load(FileSet::Tracked, false)
+        .and_then(|(repository, _, filter)| {
+            history::history(
+                repository.root(),
+                &args.rev,
+                args.since.as_deref(),
+                args.sampling(),
+                filter,
+            )
+        });
+
+    let points = match result {
+        Ok(points) => points,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let stdout = std::io::stdout().lock();
+    exit_status(match args.format {
+        HistoryFormat::Csv => history::write_csv(stdout, &points),
+        HistoryFormat::Json => history::write_json(stdout, &points),
+    })
+}
+
+/// Prints per-author line totals, optionally broken down per directory.
+fn print_attribution(attribution: &Attribution, depth: Option<usize>) {
+    let total = attribution.total_lines();
+    let width = total.to_string().len().max("files".len());
+    let percentage = |lines: usize| {
+        if total == 0 {
+            "0.0%".to_string()
+        } else {
+            format!("{:.1}%", lines as f64 * 100.0 / total as f64)
+        }
+    };
+
+    println!("{:>width$} {:>width$} {:>6} author", "files", "lines", "%");
+
+    match depth {
+        Some(depth) => {
+            for directory in attribution.by_directory(depth) {
+                println!(
+                    "{:>width$} {:>width$} {:>6} {}/",
+                    directory.files,
+                    directory.lines,
+                    percentage(directory.lines),
+                    quote_path(&directory.path)
+                );
+                for author in &directory.authors {
+                    println!(
+                        "{:>width$} {:>width$} {:>6}   {} <{}>",
+                        author.files,
+                        author.lines,
+                        percentage(author.lines),
+                        author.name,
+                        author.email
+                    );
+                }
+            }
+        }
+        None => {
+            for author in attribution.by_author() {
+                println!(
+                    "{:>width$} {:>width$} {:>6} {} <{}>",
+                    author.files,
+                    author.lines,
+                    percentage(author.lines),
+                    author.name,
+                    author.email
+                );
+            }
+        }
+    }
+
+    println!(
+        "{:>width$} {:>width$} {:>6} total",
+        attribution.files.len(),
+        total,
+        percentage(total)
+    );
+}
+
+/// Runs the `blame` subcommand.
+fn run_blame(args: &BlameArgs) -> ExitCode {
+    let result = args
+        .filter
+        .load(FileSet::Tracked, false)
+        .and_then(|(repository, _, filter)| {
+            // Every file is blamed anyway, so the cache is neither read nor written
+            let summary = count(repository.root(), args.rev.as_deref(), true, filter.clone())?;
+            let attribution = blame::attribute(
+                repository.root(),
+                args.rev.as_deref(),
+                &summary,
+                !args.no_mailmap,
+            )?;
+            Ok((repository, filter, attribution))
+        });
+
+    let (repository, filter, attribution) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let stdout = std::io::stdout().lock();
+    match args.format {
+        Format::Text => {
+            print_attribution(&attribution, args.depth);
+            ExitCode::SUCCESS
+        }
+        Format::Json => {
+            let mut report = AttributionReport::new(
+                quote_path(repository.root()).into_owned(),
+                &attribution,
+                &filter,
+            );
+            if let Some(depth) = args.depth {
+                report = report.with_directories(&attribution, depth);
+            }
+            if let Some(rev) = &args.rev {
+                match git::resolve_revision(repository.root(), rev) {
+                    Ok(commit) => report = report.with_revision(rev, commit),
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            exit_status(report.write_json(stdout))
+        }
+        Format::Csv => exit_status(blame::write_csv(stdout, &attribution, args.depth)),
+        Format::Tsv => exit_status(blame::write_tsv(stdout, &attribution, args.depth)),
+    }
+}
+
+/// Prints churn totals, one row per group.
+fn print_churn(churn: &Churn, grouping: Grouping) {
+    let rows: Vec<(String, ChurnStats)> = match grouping {
+        Grouping::File => churn
+            .by_file()
+            .into_iter()
+            .map(|file| (quote_path(&file.path).into_owned(), file.stats))
+            .collect(),
+        Grouping::Directory(depth) => churn
+            .by_directory(depth)
+            .into_iter()
+            .map(|directory| (format!("{}/", quote_path(&directory.path)), directory.stats))
+            .collect(),
+        Grouping::Author => churn
+            .by_author()
+            .into_iter()
+            .map(|author| (format!("{} <{}>", author.name, author.email), author.stats))
+            .collect(),
+        Grouping::Language => churn
+            .by_language()
+            .into_iter()
+            .map(|language| (language.name().to_string(), language.stats))
+            .collect(),
+    };
+    let label = match grouping {
+        Grouping::File => "file",
+        Grouping::Directory(_) => "directory",
+        Grouping::Author => "author",
+        Grouping::Language => "language",
+    };
+
+    let totals = churn.totals();
+    let width = (totals.added.max(totals.deleted).to_string().len() + 1).max("deleted".len());
+
+    println!(
+        "{:>width$} {:>width$} {:>width$} {:>width$} {label}",
+        "files", "commits", "added", "deleted"
+    );
+    for (name, stats) in rows.iter().chain([&("total".to_string(), totals)]) {
+        println!(
+            "{:>width$} {:>width$} {:>width$} {:>width$} {name}",
+            stats.files,
+            stats.commits,
+            format!("+{}", stats.added),
+            format!("-{}", stats.deleted)
+        );
+    }
+}
+
+/// Runs the `churn` subcommand.
+fn run_churn(args: &ChurnArgs) -> ExitCode {
+    let result = args
+        .filter
+        .load(FileSet::Tracked, false)
+        .and_then(|(repository, _, filter)| {
+            let churn = churn::churn(
+                repository.root(),
+                &args.rev,
+                args.since.as_deref(),
+                args.until.as_deref(),
+                &filter,
+                !args.no_mailmap,
+            )?;
+            Ok((repository, filter, churn))
+        });
+
+    let (repository, filter, churn) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let stdout = std::io::stdout().lock();
+    match args.format {
+        Format::Text => {
+            print_churn(&churn, args.grouping());
+            ExitCode::SUCCESS
+        }
+        Format::Json => {
+            let mut report = ChurnReport::new(
+                quote_path(repository.root()).into_owned(),
+                &args.rev,
+                &churn,
+                &filter,
+            )
+            .with_dates(args.since.as_deref(), args.until.as_deref());
+            if args.by == ChurnGrouping::Directory {
+                report = report.with_directories(&churn, args.depth);
+            }
+            exit_status(report.write_json(stdout))
+        }
+        Format::Csv => exit_status(churn::write_csv(stdout, &churn, args.grouping())),
+        Format::Tsv => exit_status(churn::write_tsv(stdout, &churn, args.grouping())),
+    }
+}
+
+/// Counts the working tree, using the cache unless `no_cache` is set, or a revision.
+fn count(
+    path: &Path,
+    rev: Option<&str>,
+    no_cache: bool,
+    filter: FileFilter,
+) -> gitlsf::Result<CountSummary> {
+    match rev {
+        Some(rev) => count_repository_at_with_filter(path, rev, filter),
+        None if no_cache => count_repository_with_filter(path, filter),
+        None => count_repository_with_cache(path, filter),
+    }
+}
+
+/// Returns the current directory with symbolic links resolved, for
+/// [`Repository::relative_path`].
+fn current_directory() -> gitlsf::Result<PathBuf> {
+    std::env::current_dir()
+        .and_then(|directory| directory.canonicalize())
+        .map_err(|e| GitlsfError::io(".", e))
+}
+
+/// Rewrites the paths of counted and skipped files relative to the current directory.
+fn make_relative(summary: &mut CountSummary, repository: &Repository) -> gitlsf::Result<()> {
+    let directory = current_directory()?;
+    for file in &mut summary.files {
+        file.path = repository.relative_path(&file.path, &directory);
+    }
+    for file in &mut summary.skipped {
+        file.path = repository.relative_path(&file.path, &directory);
+    }
+    Ok(())
+}
+
+/// Runs the `check` subcommand.
+fn run_check(args: &CheckArgs) -> ExitCode {
+    let result = args
+        .filter
+        .load(args.files.file_set(), args.recurse_submodules)
+        .and_then(|(repository, config, filter)| {
+            let budgets = args.budgets(&config)?;
+            Ok((repository, budgets, filter))
+        });
+
+    let (repository, budgets, filter) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if budgets.is_empty() {
+        eprintln!(
+            "error: no line budgets configured; use --max-file-lines, --max-total-lines, \
+             --budget or the [check] table of .gitlsf.toml"
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let result = count(
+        repository.root(),
+        args.rev.as_deref(),
+        args.no_cache,
+        filter,
+    )
+    .and_then(|summary| {
+        let mut violations = budgets.check(&summary);
+        if args.relative {
+            let directory = current_directory()?;
+            for violation in &mut violations {
+                if let Violation::File { path, .. } = violation {
+                    *path = repository.relative_path(path, &directory);
+                }
+            }
+        }
+        Ok((summary, violations))
+    });
+
+    let (summary, violations) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    print_uninitialized_warning(&summary);
+    let unreadable = print_unreadable_warning(&summary);
+
+    for violation in &violations {
+        println!("{violation}");
+    }
+
+    if !violations.is_empty() {
+        println!();
+        println!(
+            "{} line {} exceeded",
+            violations.len(),
+            if violations.len() == 1 {
+                "budget"
+            } else {
+                "budgets"
+            }
+        );
+        return ExitCode::from(EXIT_BUDGET_EXCEEDED);
+    }
+
+    println!(
+        "All line budgets met ({} files, {} lines)",
+        summary.file_count, summary.total_lines
+    );
+
+    if args.strict && unreadable > 0 {
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
 fn main() -> ExitCode {
     let args = Args::parse();
     let mode = args.output_mode();
 
-    match count_repository(&args.path) {
-        Ok(summary) => {
-            print_results(&summary, mode);
-            ExitCode::SUCCESS
+    match &args.command {
+        Some(Command::Diff(diff_args)) => return run_diff(diff_args),
+        Some(Command::History(history_args)) => return run_history(history_args),
+        Some(Command::Blame(blame_args)) => return run_blame(blame_args),
+        Some(Command::Churn(churn_args)) => return run_churn(churn_args),
+        Some(Command::Check(check_args)) => return run_check(check_args),
+        None => {}
+    }
+
+    let loaded = match args.source.open() {
+        Ok(Some(input)) => args
+            .filter
+            .load_in(args.source.config_directory())
+            .map(|(config, filter)| (input, config, filter)),
+        Ok(None) => args
+            .filter
+            .load(args.files.file_set(), args.recurse_submodules)
+            .map(|(repository, config, filter)| (Input::Repository(repository), config, filter)),
+        Err(e) => Err(e),
+    };
+
+    let (input, config, filter) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
         }
+    };
+
+    let format = match (args.format, &config.format) {
+        (Some(format), _) => format,
+        (None, Some(name)) => match Format::from_config(name) {
+            Ok(format) => format,
+            Err(e) => {
+                eprintln!("error: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, None) => Format::Text,
+    };
+
+    let result = match &input {
+        Input::Repository(repository) => count(
+            repository.root(),
+            args.rev.as_deref(),
+            args.no_cache,
+            filter.clone(),
+        )
+        .and_then(|mut summary| {
+            if args.relative {
+                make_relative(&mut summary, repository)?;
+            }
+            Ok(summary)
+        }),
+        Input::Source { source, .. } => count_source(source.as_ref(), filter.clone()),
+    };
+
+    let summary = match result {
+        Ok(summary) => summary,
         Err(e) => {
             eprintln!("error: {e}");
-            ExitCode::FAILURE
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let status = match format {
+        Format::Text => {
+            print_results(&summary, mode, args.breakdown);
+            if args.show_skipped {
+                print_skipped(&summary);
+            }
+            ExitCode::SUCCESS
         }
+        Format::Json => print_json(&args, input.root(), &summary, &filter),
+        Format::Csv => exit_status(output::write_csv(
+            std::io::stdout().lock(),
+            &summary,
+            args.totals,
+        )),
+        Format::Tsv => exit_status(output::write_tsv(
+            std::io::stdout().lock(),
+            &summary,
+            args.totals,
+        )),
+    };
+
+    print_uninitialized_warning(&summary);
+    let unreadable = print_unreadable_warning(&summary);
+    if args.strict && unreadable > 0 {
+        return ExitCode::FAILURE;
     }
+
+    status
 }
 
 #[cfg(test)]
@@ -109,10 +1485,25 @@ mod tests {
     #[test]
     fn test_output_mode_default_is_verbose() {
         let args = Args {
-            path: ".".to_string(),
+            command: None,
             verbose: false,
             quiet: false,
             summary: false,
+            languages: false,
+            tree: false,
+            depth: None,
+            rev: None,
+            breakdown: false,
+            show_skipped: false,
+            format: None,
+            totals: false,
+            strict: false,
+            no_cache: false,
+            recurse_submodules: false,
+            relative: false,
+            files: FileSetArgs::default(),
+            source: SourceArgs::default(),
+            filter: FilterArgs::default(),
         };
         assert_eq!(args.output_mode(), OutputMode::Verbose);
     }
@@ -120,10 +1511,25 @@ mod tests {
     #[test]
     fn test_output_mode_verbose() {
         let args = Args {
-            path: ".".to_string(),
+            command: None,
             verbose: true,
             quiet: false,
             summary: false,
+            languages: false,
+            tree: false,
+            depth: None,
+            rev: None,
+            breakdown: false,
+            show_skipped: false,
+            format: None,
+            totals: false,
+            strict: false,
+            no_cache: false,
+            recurse_submodules: false,
+            relative: false,
+            files: FileSetArgs::default(),
+            source: SourceArgs::default(),
+            filter: FilterArgs::default(),
         };
         assert_eq!(args.output_mode(), OutputMode::Verbose);
     }
@@ -131,10 +1537,25 @@ mod tests {
     #[test]
     fn test_output_mode_quiet() {
         let args = Args {
-            path: ".".to_string(),
+            command: None,
             verbose: false,
             quiet: true,
             summary: false,
+            languages: false,
+            tree: false,
+            depth: None,
+            rev: None,
+            breakdown: false,
+            show_skipped: false,
+            format: None,
+            totals: false,
+            strict: false,
+            no_cache: false,
+            recurse_submodules: false,
+            relative: false,
+            files: FileSetArgs::default(),
+            source: SourceArgs::default(),
+            filter: FilterArgs::default(),
         };
         assert_eq!(args.output_mode(), OutputMode::Quiet);
     }
@@ -142,11 +1563,232 @@ mod tests {
     #[test]
     fn test_output_mode_summary() {
         let args = Args {
-            path: ".".to_string(),
+            command: None,
             verbose: false,
             quiet: false,
             summary: true,
+            languages: false,
+            tree: false,
+            depth: None,
+            rev: None,
+            breakdown: false,
+            show_skipped: false,
+            format: None,
+            totals: false,
+            strict: false,
+            no_cache: false,
+            recurse_submodules: false,
+            relative: false,
+            files: FileSetArgs::default(),
+            source: SourceArgs::default(),
+            filter: FilterArgs::default(),
         };
         assert_eq!(args.output_mode(), OutputMode::Summary);
     }
+
+    #[test]
+    fn test_output_mode_languages() {
+        let args = Args {
+            command: None,
+            verbose: false,
+            quiet: false,
+            summary: false,
+            languages: true,
+            tree: false,
+            depth: None,
+            rev: None,
+            breakdown: false,
+            show_skipped: false,
+            format: None,
+            totals: false,
+            strict: false,
+            no_cache: false,
+            recurse_submodules: false,
+            relative: false,
+            files: FileSetArgs::default(),
+            source: SourceArgs::default(),
+            filter: FilterArgs::default(),
+        };
+        assert_eq!(args.output_mode(), OutputMode::Languages);
+    }
+
+    #[test]
+    fn test_output_mode_tree() {
+        let mut args = Args {
+            command: None,
+            verbose: false,
+            quiet: false,
+            summary: false,
+            languages: false,
+            tree: true,
+            depth: None,
+            rev: None,
+            breakdown: false,
+            show_skipped: false,
+            format: None,
+            totals: false,
+            strict: false,
+            no_cache: false,
+            recurse_submodules: false,
+            relative: false,
+            files: FileSetArgs::default(),
+            source: SourceArgs::default(),
+            filter: FilterArgs::default(),
+        };
+        assert_eq!(args.output_mode(), OutputMode::Tree(DEFAULT_TREE_DEPTH));
+
+        args.tree = false;
+        args.depth = Some(4);
+        assert_eq!(args.output_mode(), OutputMode::Tree(4));
+    }
+
+    fn diff_args(range: &str) -> DiffArgs {
+        DiffArgs {
+            range: range.to_string(),
+            depth: 1,
+            recurse_submodules: false,
+            filter: FilterArgs::default(),
+        }
+    }
+
+    #[test]
+    fn test_history_sampling() {
+        let mut args = HistoryArgs {
+            rev: "HEAD".to_string(),
+            since: None,
+            every: 5,
+            daily: false,
+            weekly: false,
+            format: HistoryFormat::Csv,
+            filter: FilterArgs::default(),
+        };
+        assert_eq!(args.sampling(), Sampling::EveryN(5));
+
+        args.weekly = true;
+        assert_eq!(args.sampling(), Sampling::Weekly);
+
+        args.weekly = false;
+        args.daily = true;
+        assert_eq!(args.sampling(), Sampling::Daily);
+    }
+
+    #[test]
+    fn test_file_set() {
+        let mut args = FileSetArgs::default();
+        assert_eq!(args.file_set(), FileSet::Tracked);
+
+        args.staged = true;
+        assert_eq!(args.file_set(), FileSet::Staged);
+
+        args.staged = false;
+        args.untracked = true;
+        assert_eq!(args.file_set(), FileSet::Untracked);
+
+        args.untracked = false;
+        args.modified = true;
+        assert_eq!(args.file_set(), FileSet::Modified);
+    }
+
+    #[test]
+    fn test_filter_args() {
+        let args = FilterArgs {
+            paths: vec![],
+            include: vec!["src/**".to_string()],
+            exclude: vec!["src/gen/".to_string()],
+            no_attributes: false,
+            no_config: false,
+        };
+        let filter = args.filter(&Config::default()).unwrap();
+
+        assert!(filter.should_count("src/main.rs"));
+        assert!(!filter.should_count("src/gen/api.rs"));
+        assert!(!filter.should_count("build.rs"));
+
+        let args = FilterArgs {
+            paths: vec![],
+            include: vec![],
+            exclude: vec!["{a,b".to_string()],
+            no_attributes: false,
+            no_config: false,
+        };
+        assert!(args.filter(&Config::default()).is_err());
+    }
+
+    #[test]
+    fn test_filter_args_after_config() {
+        let config = Config {
+            exclude: vec!["*.gen.rs".to_string()],
+            ..Config::default()
+        };
+        let args = FilterArgs {
+            paths: vec![],
+            include: vec![],
+            exclude: vec!["!keep.gen.rs".to_string()],
+            no_attributes: false,
+            no_config: false,
+        };
+        let filter = args.filter(&config).unwrap();
+
+        assert!(!filter.should_count("api.gen.rs"));
+        assert!(filter.should_count("keep.gen.rs"));
+    }
+
+    #[test]
+    fn test_format_from_config() {
+        assert_eq!(Format::from_config("json"), Ok(Format::Json));
+        assert_eq!(Format::from_config("CSV"), Ok(Format::Csv));
+        assert!(Format::from_config("yaml").is_err());
+    }
+
+    #[test]
+    fn test_parse_budget() {
+        assert_eq!(parse_budget("src/=100"), Ok(("src/".to_string(), 100)));
+        assert_eq!(parse_budget("a=b=5"), Ok(("a=b".to_string(), 5)));
+        assert!(parse_budget("src/").is_err());
+        assert!(parse_budget("=5").is_err());
+        assert!(parse_budget("src/=many").is_err());
+    }
+
+    #[test]
+    fn test_check_budgets_override_config() {
+        let mut config = Config::default();
+        config.check.max_file_lines = Some(500);
+        config.check.budgets = vec![("src/".to_string(), 100)];
+
+        let args = CheckArgs {
+            rev: None,
+            max_file_lines: None,
+            max_total_lines: Some(1000),
+            budget: vec![("src/".to_string(), 200)],
+            strict: false,
+            no_cache: false,
+            recurse_submodules: false,
+            relative: false,
+            files: FileSetArgs::default(),
+            filter: FilterArgs::default(),
+        };
+        let budgets = args.budgets(&config).unwrap();
+
+        assert_eq!(
+            budgets.pattern_budgets().collect::<Vec<_>>(),
+            [("src/", 200)]
+        );
+        assert!(
+            !CheckArgs {
+                budget: vec![],
+                ..args
+            }
+            .budgets(&Config::default())
+            .unwrap()
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_diff_revisions() {
+        assert_eq!(diff_args("v1.0..v2.0").revisions(), ("v1.0", Some("v2.0")));
+        assert_eq!(diff_args("v1.0..").revisions(), ("v1.0", Some("HEAD")));
+        assert_eq!(diff_args("..main").revisions(), ("HEAD", Some("main")));
+        assert_eq!(diff_args("v1.0").revisions(), ("v1.0", None));
+    }
 }
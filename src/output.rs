@@ -0,0 +1,815 @@
+//! Output formatting module.
+//!
+//! This module renders counting results in machine-readable formats: a
+//! versioned JSON report and CSV or TSV tables.
+//!
+//! ## Paths
+//!
+//! File paths are bytes, which need not be valid UTF-8 and may contain control
+//! characters. JSON reports and text output write them with [`quote_path`],
+//! following the C-style quoting Git uses with `core.quotePath` off:
+//!
+//! - A path that is valid UTF-8 without control characters, double quotes
+//!   or backslashes is written as it is, including non-ASCII characters
+//! - Any other path is enclosed in double quotes, with `"` and `\` escaped by
+//!   a backslash, `\a`, `\b`, `\t`, `\n`, `\v`, `\f` and `\r` for those control
+//!   characters, and every other control character or byte that is not part
+//!   of valid UTF-8 written as a three-digit octal escape such as `\351`
+//!
+//! For example, `tab<TAB>name.rs` is written as `"tab\tname.rs"`, and a
+//! Latin-1 encoded `café.rs` as `"caf\351.rs"`. Files are sorted by the bytes
+//! of their paths, as Git lists them.
+//!
+//! CSV and TSV fields can hold any text, so paths that are valid UTF-8 are
+//! written there as they are, see [`table_path`], and only quoted by the
+//! rules below. Paths that are not, or that start with a double quote, are
+//! written with [`quote_path`] instead, so that distinct paths never share a
+//! field: `caf\351.rs` and `caf\352.rs` stay apart, and a file actually named
+//! `"caf\351.rs"` is written as `"\"caf\\351.rs\""` before CSV quoting.
+//!
+//! ## JSON schema
+//!
+//! [`Report::write_json`] produces a single JSON object. The layout is
+//! versioned by `schema_version`: new fields may be added without a version
+//! change, but removing, renaming or changing the type of a field increments
+//! it.
+//!
+//! | Field | Type | Description |
+//! |-------|------|-------------|
+//! | `schema_version` | integer | Version of this layout, currently `1` |
+//! | `tool.name` | string | Always `"gitlsf"` |
+//! | `tool.version` | string | Version of gitlsf that produced the report |
+//! | `repository` | string | Path of the counted repository, quoted like file paths |
+//! | `revision` | object or null | `null` when the working tree was counted |
+//! | `revision.name` | string | The revision as given, such as `"v1.0"` |
+//! | `revision.commit` | string | The commit object ID it resolved to |
+//! | `filter.excluded_extensions` | array of strings | Extensions that were not counted |
+//! | `filter.excluded_filenames` | array of strings | Filenames that were not counted |
+//! | `filter.include_patterns` | array of strings | Glob patterns files had to match, in order |
+//! | `filter.exclude_patterns` | array of strings | Glob patterns of files that were not counted, in order |
+//! | `filter.language_overrides` | array of objects | `pattern` and `language` of each language override, in order |
+//! | `filter.attributes` | boolean | Whether Linguist attributes in `.gitattributes` were honored |
+//! | `filter.pathspecs` | array of strings | Files and directories counting was limited to, empty for the whole repository |
+//! | `filter.file_set` | string | The files that were listed: `"tracked"`, `"untracked"`, `"modified"` or `"staged"` |
+//! | `totals` | object | `files`, `lines`, `code`, `comment` and `blank` totals |
+//! | `languages` | array of objects | Per-language `language`, `files`, `lines`, `code`, `comment` and `blank`, largest first |
+//! | `files` | array of objects | Per-file `path`, `language`, `lines`, `code`, `comment` and `blank`, and `submodule` for files of submodules, sorted by path |
+//! | `skipped` | array of objects | `path`, `reason` and, for unreadable files, `error` of each file that was not counted, sorted by path |
+//! | `submodules` | array of objects, optional | Per-submodule `path`, `files`, `lines`, `code`, `comment` and `blank`, sorted by path; only present when files of submodules were counted |
+//! | `tree` | object, optional | Only present when requested, see below |
+//!
+//! Languages are given by name (`"Rust"`, `"C++"`), or `null` when unknown.
+//! A skipped file's `reason` is `"binary"`, `"generated"`, `"vendored"`,
+//! `"documentation"` or `"unreadable"`, or `"submodule"` and
+//! `"uninitialized"` for submodules that were not recursed into or are not
+//! checked out; only unreadable files have an `error` message.
+//!
+//! The `tree` object is the root of a [`DirectoryTree`]: each directory has
+//! `name`, `path`, `files`, `lines`, `code`, `comment` and `blank` fields and
+//! a `children` array of subdirectories in the same shape, largest first. The
+//! root has the name and path `"."`.
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "tool": { "name": "gitlsf", "version": "0.1.0" },
+//!   "repository": "/home/me/project",
+//!   "revision": { "name": "v1.0", "commit": "3f9c2e..." },
+//!   "filter": {
+//!     "excluded_extensions": ["png", "..."],
+//!     "excluded_filenames": ["LICENSE", "..."],
+//!     "include_patterns": [],
+//!     "exclude_patterns": ["vendor/"],
+//!     "language_overrides": [{ "pattern": "*.h", "language": "C++" }],
+//!     "attributes": true,
+//!     "pathspecs": ["src"],
+//!     "file_set": "tracked"
+//!   },
+//!   "totals": { "files": 1, "lines": 3, "code": 2, "comment": 1, "blank": 0 },
+//!   "languages": [
+//!     { "language": "Rust", "files": 1, "lines": 3, "code": 2, "comment": 1, "blank": 0 }
+//!   ],
+//!   "files": [
+//!     { "path": "src/main.rs", "language": "Rust", "lines": 3, "code": 2, "comment": 1, "blank": 0 }
+//!   ],
+//!   "skipped": [
+//!     { "path": "assets/font.bin", "reason": "binary" }
+//!   ]
+//! }
+//! ```
+//!
+//! ## CSV and TSV
+//!
+//! [`write_csv`] and [`write_tsv`] emit one row per counted file, sorted by
+//! path, under the header `path,language,lines,code,comment,blank`. The
+//! language column is empty for files in unknown languages. When files of
+//! submodules were counted, a `submodule` column follows with the path of
+//! each file's submodule, empty for files of the repository itself. An
+//! optional footer row holds the totals, with an empty path column, which no
+//! file has, and `total` in the language column.
+//!
+//! Fields containing the delimiter, a double quote, a carriage return or a
+//! newline are enclosed in double quotes, with embedded double quotes doubled
+//! (RFC 4180). TSV uses the same rules with a tab as the delimiter, which is
+//! what spreadsheet applications expect when importing tab-separated text.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::{Serialize, Serializer};
+
+use crate::counter::{CountSummary, FileCount, LanguageSummary, SkippedFile, SubmoduleSummary};
+use crate::filter::FileFilter;
+use crate::git::FileSet;
+use crate::language::Language;
+use crate::tree::DirectoryTree;
+
+/// Version of the JSON report layout.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The tool that produced a report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ToolInfo {
+    /// Always `gitlsf`.
+    pub name: &'static str,
+    /// The crate version.
+    pub version: &'static str,
+}
+
+impl Default for ToolInfo {
+    fn default() -> Self {
+        Self {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+}
+
+/// The revision a report was counted at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RevisionInfo {
+    /// The revision as given, such as `v1.0`.
+    pub name: String,
+    /// The commit object ID the revision resolved to.
+    pub commit: String,
+}
+
+/// The filter configuration a report was counted with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FilterInfo {
+    /// Extensions that were not counted.
+    pub excluded_extensions: Vec<String>,
+    /// Filenames that were not counted.
+    pub excluded_filenames: Vec<String>,
+    /// Glob patterns that files had to match to be counted.
+    pub include_patterns: Vec<String>,
+    /// Glob patterns of files that were not counted.
+    pub exclude_patterns: Vec<String>,
+    /// Glob patterns and the language assigned to matching files.
+    pub language_overrides: Vec<LanguageOverride>,
+    /// Whether Linguist attributes in `.gitattributes` were honored.
+    pub attributes: bool,
+    /// Files and directories counting was limited to, quoted by [`quote_path`].
+    pub pathspecs: Vec<String>,
+    /// The files that were listed for counting.
+    pub file_set: FileSet,
+}
+
+/// A glob pattern and the language assigned to the files it matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LanguageOverride {
+    /// The glob pattern.
+    pub pattern: String,
+    /// The language of matching files.
+    pub language: Language,
+}
+
+impl From<&FileFilter> for FilterInfo {
+    fn from(filter: &FileFilter) -> Self {
+        Self {
+            excluded_extensions: filter.excluded_extensions(),
+            excluded_filenames: filter.excluded_filenames(),
+            include_patterns: filter.include_patterns().to_vec(),
+            exclude_patterns: filter.exclude_patterns().to_vec(),
+            language_overrides: filter
+                .language_overrides()
+                .map(|(pattern, language)| LanguageOverride {
+                    pattern: pattern.to_string(),
+                    language,
+                })
+                .collect(),
+            attributes: filter.uses_attributes(),
+            pathspecs: filter
+                .pathspecs()
+                .iter()
+                .map(|path| quote_path(path).into_owned())
+                .collect(),
+            file_set: filter.file_set(),
+        }
+    }
+}
+
+/// Totals of a report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Totals {
+    /// Number of files counted.
+    pub files: usize,
+    /// Total number of lines.
+    pub lines: usize,
+    /// Total number of code lines.
+    pub code: usize,
+    /// Total number of comment lines.
+    pub comment: usize,
+    /// Total number of blank lines.
+    pub blank: usize,
+}
+
+impl From<&CountSummary> for Totals {
+    fn from(summary: &CountSummary) -> Self {
+        Self {
+            files: summary.file_count,
+            lines: summary.total_lines,
+            code: summary.total_code,
+            comment: summary.total_comment,
+            blank: summary.total_blank,
+        }
+    }
+}
+
+/// A complete, self-describing counting report.
+///
+/// See the [module documentation](self) for the JSON schema.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::filter::FileFilter;
+/// use gitlsf::output::Report;
+/// use gitlsf::{CountSummary, FileCount};
+///
+/// let summary = CountSummary::from_counts(vec![FileCount::new("src/main.rs", 3)]);
+/// let report = Report::new(".", &summary, &FileFilter::new());
+///
+/// let mut json = Vec::new();
+/// report.write_json(&mut json).unwrap();
+/// let json = String::from_utf8(json).unwrap();
+/// assert!(json.contains("\"schema_version\": 1"));
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct Report<'a> {
+    /// Version of the report layout, see [`SCHEMA_VERSION`].
+    pub schema_version: u32,
+    /// The tool that produced the report.
+    pub tool: ToolInfo,
+    /// Path of the counted repository, quoted by [`quote_path`].
+    pub repository: String,
+    /// The revision counted, or `None` for the working tree.
+    pub revision: Option<RevisionInfo>,
+    /// The filter configuration used.
+    pub filter: FilterInfo,
+    /// Totals across all files.
+    pub totals: Totals,
+    /// Per-language totals, largest first.
+    pub languages: Vec<LanguageSummary>,
+    /// Per-file counts, sorted by path.
+    pub files: Vec<&'a FileCount>,
+    /// Files that were not counted, sorted by path.
+    pub skipped: &'a [SkippedFile],
+    /// Per-submodule totals, sorted by path.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub submodules: Vec<SubmoduleSummary>,
+    /// Per-directory totals, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tree: Option<DirectoryTree>,
+}
+
+impl<'a> Report<'a> {
+    /// Creates a report of the working tree of a repository.
+    pub fn new(
+        repository: impl Into<String>,
+        summary: &'a CountSummary,
+        filter: &FileFilter,
+    ) -> Self {
+        let mut files: Vec<&FileCount> = summary.files.iter().collect();
+        files.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+
+        Self {
+            schema_version: SCHEMA_VERSION,
+            tool: ToolInfo::default(),
+            repository: repository.into(),
+            revision: None,
+            filter: FilterInfo::from(filter),
+            totals: Totals::from(summary),
+            languages: summary.by_language(),
+            files,
+            skipped: &summary.skipped,
+            submodules: summary.by_submodule(),
+            tree: None,
+        }
+    }
+
+    /// Records the revision the summary was counted at.
+    pub fn with_revision(mut self, name: impl Into<String>, commit: impl Into<String>) -> Self {
+        self.revision = Some(RevisionInfo {
+            name: name.into(),
+            commit: commit.into(),
+        });
+        self
+    }
+
+    /// Nests a directory tree in the report.
+    pub fn with_tree(mut self, tree: DirectoryTree) -> Self {
+        self.tree = Some(tree);
+        self
+    }
+
+    /// Writes the report as pretty-printed JSON, followed by a newline.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)
+    }
+}
+
+/// Column names of the CSV and TSV output; `submodule` is only written when
+/// files of submodules were counted.
+const TABLE_HEADER: [&str; 7] = [
+    "path",
+    "language",
+    "lines",
+    "code",
+    "comment",
+    "blank",
+    "submodule",
+];
+
+/// Writes per-file counts as comma-separated values.
+///
+/// See the [module documentation](self) for the columns and quoting rules.
+///
+/// # Arguments
+///
+/// * `writer` - Where the output is written.
+/// * `summary` - The counting results.
+/// * `footer` - Whether to append a row with the totals.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+///
+/// # Example
+///
+/// ```
+/// use gitlsf::output::write_csv;
+/// use gitlsf::{CountSummary, FileCount};
+///
+/// let summary = CountSummary::from_counts(vec![FileCount::new("a,b.txt", 3)]);
+///
+/// let mut csv = Vec::new();
+/// write_csv(&mut csv, &summary, true).unwrap();
+/// assert_eq!(
+///     String::from_utf8(csv).unwrap(),
+///     "path,language,lines,code,comment,blank\n\"a,b.txt\",,3,3,0,0\n,total,3,3,0,0\n"
+/// );
+/// ```
+pub fn write_csv<W: Write>(writer: W, summary: &CountSummary, footer: bool) -> io::Result<()> {
+    write_delimited(writer, summary, ',', footer)
+}
+
+/// Writes per-file counts as tab-separated values.
+///
+/// See the [module documentation](self) for the columns and quoting rules.
+///
+/// # Arguments
+///
+/// * `writer` - Where the output is written.
+/// * `summary` - The counting results.
+/// * `footer` - Whether to append a row with the totals.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+pub fn write_tsv<W: Write>(writer: W, summary: &CountSummary, footer: bool) -> io::Result<()> {
+    write_delimited(writer, summary, '\t', footer)
+}
+
+/// Writes the table shared by the CSV and TSV formats.
+fn write_delimited<W: Write>(
+    mut writer: W,
+    summary: &CountSummary,
+    delimiter: char,
+    footer: bool,
+) -> io::Result<()> {
+    let columns = if summary.files.iter().any(|file| file.submodule.is_some()) {
+        TABLE_HEADER.len()
+    } else {
+        TABLE_HEADER.len() - 1
+    };
+    write_row(&mut writer, delimiter, &TABLE_HEADER[..columns])?;
+
+    let mut files: Vec<&FileCount> = summary.files.iter().collect();
+    files.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+
+    for file in files {
+        let submodule = file.submodule.as_deref().map(table_path);
+        let row: [&str; 7] = [
+            &table_path(&file.path),
+            file.language.map_or("", |language| language.name()),
+            &file.lines.to_string(),
+            &file.code.to_string(),
+            &file.comment.to_string(),
+            &file.blank.to_string(),
+            submodule.as_deref().unwrap_or(""),
+        ];
+        write_row(&mut writer, delimiter, &row[..columns])?;
+    }
+
+    if footer {
+        let row: [&str; 7] = [
+            "",
+            "total",
+            &summary.total_lines.to_string(),
+            &summary.total_code.to_string(),
+            &summary.total_comment.to_string(),
+            &summary.total_blank.to_string(),
+            "",
+        ];
+        write_row(&mut writer, delimiter, &row[..columns])?;
+    }
+
+    Ok(())
+}
+
+/// Writes one row of delimited fields, followed by a newline.
+pub(crate) fn write_row<W: Write>(
+    writer: &mut W,
+    delimiter: char,
+    fields: &[&str],
+) -> io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(writer, "{delimiter}")?;
+        }
+        write!(writer, "{}", quote_field(field, delimiter))?;
+    }
+    writeln!(writer)
+}
+
+/// Returns a path as it is written in CSV and TSV fields.
+///
+/// Paths that are valid UTF-8 and do not start with a double quote are
+/// returned as they are, control characters included; other paths are quoted
+/// by [`quote_path`]. See the [module documentation](self#paths).
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use gitlsf::output::table_path;
+///
+/// assert_eq!(table_path(Path::new("tab\tname.rs")), "tab\tname.rs");
+/// assert_eq!(table_path(Path::new("\"quoted\".rs")), "\"\\\"quoted\\\".rs\"");
+/// ```
+pub fn table_path(path: &Path) -> Cow<'_, str> {
+    match path.to_str() {
+        Some(text) if !text.starts_with('"') => Cow::Borrowed(text),
+        _ => quote_path(path),
+    }
+}
+
+/// Quotes a path for display, the way Git does with `core.quotePath` off.
+///
+/// Paths that are valid UTF-8 without control characters, double quotes or
+/// backslashes are returned as they are. Other paths are enclosed in double
+/// quotes with C-style escapes; bytes that are not valid UTF-8 are written as
+/// octal escapes. See the [module documentation](self#paths).
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use gitlsf::output::quote_path;
+///
+/// assert_eq!(quote_path(Path::new("src/café.rs")), "src/café.rs");
+/// assert_eq!(quote_path(Path::new("tab\tname.rs")), "\"tab\\tname.rs\"");
+/// ```
+pub fn quote_path(path: &Path) -> Cow<'_, str> {
+    let bytes = path.as_os_str().as_encoded_bytes();
+    let needs_quoting = |byte: u8| byte < 0x20 || byte == 0x7f || byte == b'"' || byte == b'\\';
+
+    if let Ok(path) = std::str::from_utf8(bytes)
+        && !path.bytes().any(needs_quoting)
+    {
+        return Cow::Borrowed(path);
+    }
+
+    let mut quoted = String::with_capacity(bytes.len() + 2);
+    quoted.push('"');
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\x07' => quoted.push_str("\\a"),
+                '\x08' => quoted.push_str("\\b"),
+                '\t' => quoted.push_str("\\t"),
+                '\n' => quoted.push_str("\\n"),
+                '\x0b' => quoted.push_str("\\v"),
+                '\x0c' => quoted.push_str("\\f"),
+                '\r' => quoted.push_str("\\r"),
+                c if c.is_ascii_control() => {
+                    let _ = write!(quoted, "\\{:03o}", c as u8);
+                }
+                c => quoted.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            let _ = write!(quoted, "\\{byte:03o}");
+        }
+    }
+    quoted.push('"');
+
+    Cow::Owned(quoted)
+}
+
+/// Serializes a path as a string quoted by [`quote_path`].
+pub(crate) fn serialize_path<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&quote_path(path))
+}
+
+/// Quotes a field if it contains the delimiter, a double quote or a line break.
+fn quote_field(field: &str, delimiter: char) -> Cow<'_, str> {
+    if field.contains([delimiter, '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::LineStats;
+    use crate::counter::SkipReason;
+
+    fn report_json(report: &Report) -> serde_json::Value {
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        serde_json::from_slice(&json).unwrap()
+    }
+
+    fn summary() -> CountSummary {
+        CountSummary::from_counts(vec![
+            FileCount::with_stats(
+                "src/main.rs",
+                LineStats {
+                    lines: 4,
+                    code: 2,
+                    comment: 1,
+                    blank: 1,
+                },
+            )
+            .with_language(Some(Language::Rust)),
+            FileCount::new("notes.txt", 2),
+        ])
+        .with_skipped(vec![SkippedFile::new("lib.so", SkipReason::Binary)])
+    }
+
+    #[test]
+    fn test_report_layout() {
+        let summary = summary();
+        let filter = FileFilter::new()
+            .exclude_extension("log")
+            .exclude_pattern("vendor/")
+            .unwrap()
+            .override_language("*.h", Language::Cpp)
+            .unwrap()
+            .pathspec("src");
+        let report = Report::new("/repo", &summary, &filter);
+        let json = report_json(&report);
+
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["tool"]["name"], "gitlsf");
+        assert_eq!(json["tool"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["repository"], "/repo");
+        assert!(json["revision"].is_null());
+        assert!(
+            json["filter"]["excluded_extensions"]
+                .as_array()
+                .unwrap()
+                .contains(&"log".into())
+        );
+        assert_eq!(
+            json["filter"]["exclude_patterns"],
+            serde_json::json!(["vendor/"])
+        );
+        assert_eq!(json["filter"]["include_patterns"], serde_json::json!([]));
+        assert_eq!(
+            json["filter"]["language_overrides"],
+            serde_json::json!([{"pattern": "*.h", "language": "C++"}])
+        );
+        assert_eq!(json["filter"]["attributes"], true);
+        assert_eq!(json["filter"]["pathspecs"], serde_json::json!(["src"]));
+        assert_eq!(json["filter"]["file_set"], "tracked");
+        assert_eq!(
+            json["skipped"],
+            serde_json::json!([{"path": "lib.so", "reason": "binary"}])
+        );
+        assert_eq!(
+            json["totals"],
+            serde_json::json!({"files": 2, "lines": 6, "code": 4, "comment": 1, "blank": 1})
+        );
+    }
+
+    #[test]
+    fn test_report_files_sorted_with_languages() {
+        let summary = summary();
+        let json = report_json(&Report::new(".", &summary, &FileFilter::new()));
+
+        assert_eq!(
+            json["files"],
+            serde_json::json!([
+                {"path": "notes.txt", "language": null, "lines": 2, "code": 2, "comment": 0, "blank": 0},
+                {"path": "src/main.rs", "language": "Rust", "lines": 4, "code": 2, "comment": 1, "blank": 1},
+            ])
+        );
+        assert_eq!(json["languages"][0]["language"], "Rust");
+        assert_eq!(json["languages"][0]["files"], 1);
+        assert_eq!(json["languages"][0]["lines"], 4);
+    }
+
+    #[test]
+    fn test_report_submodules() {
+        let summary = summary();
+        let json = report_json(&Report::new(".", &summary, &FileFilter::new()));
+        assert!(json.get("submodules").is_none());
+
+        let mut vendored = FileCount::new("vendor/lib/a.c", 5);
+        vendored.submodule = Some("vendor/lib".into());
+        let summary = CountSummary::from_counts(vec![FileCount::new("main.c", 1), vendored]);
+        let json = report_json(&Report::new(".", &summary, &FileFilter::new()));
+
+        assert_eq!(json["files"][0].get("submodule"), None);
+        assert_eq!(json["files"][1]["submodule"], "vendor/lib");
+        assert_eq!(
+            json["submodules"],
+            serde_json::json!([
+                {"path": "vendor/lib", "files": 1, "lines": 5, "code": 5, "comment": 0, "blank": 0}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_report_revision() {
+        let summary = summary();
+        let report = Report::new(".", &summary, &FileFilter::new()).with_revision("v1.0", "abc123");
+        let json = report_json(&report);
+
+        assert_eq!(
+            json["revision"],
+            serde_json::json!({"name": "v1.0", "commit": "abc123"})
+        );
+    }
+
+    #[test]
+    fn test_report_tree() {
+        let summary = summary();
+        let json = report_json(&Report::new(".", &summary, &FileFilter::new()));
+        assert!(json.get("tree").is_none());
+
+        let report = Report::new(".", &summary, &FileFilter::new())
+            .with_tree(DirectoryTree::from_summary(&summary, 1));
+        let json = report_json(&report);
+
+        assert_eq!(json["tree"]["path"], ".");
+        assert_eq!(json["tree"]["lines"], 6);
+        assert_eq!(json["tree"]["children"][0]["path"], "src");
+        assert_eq!(json["tree"]["children"][0]["files"], 1);
+        assert_eq!(
+            json["tree"]["children"][0]["children"],
+            serde_json::json!([])
+        );
+    }
+
+    #[test]
+    fn test_quote_field() {
+        assert_eq!(quote_field("src/main.rs", ','), "src/main.rs");
+        assert_eq!(quote_field("a,b.rs", ','), "\"a,b.rs\"");
+        assert_eq!(quote_field("a,b.rs", '\t'), "a,b.rs");
+        assert_eq!(quote_field("a\tb.rs", '\t'), "\"a\tb.rs\"");
+        assert_eq!(quote_field("say \"hi\".rs", ','), "\"say \"\"hi\"\".rs\"");
+        assert_eq!(quote_field("line\nbreak.rs", '\t'), "\"line\nbreak.rs\"");
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &summary(), false).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "path,language,lines,code,comment,blank\n\
+             notes.txt,,2,2,0,0\n\
+             src/main.rs,Rust,4,2,1,1\n"
+        );
+    }
+
+    #[test]
+    fn test_write_csv_submodule_column() {
+        let mut vendored = FileCount::new("vendor/lib/a.c", 5);
+        vendored.submodule = Some("vendor/lib".into());
+        let summary = CountSummary::from_counts(vec![FileCount::new("main.c", 1), vendored]);
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &summary, true).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "path,language,lines,code,comment,blank,submodule\n\
+             main.c,,1,1,0,0,\n\
+             vendor/lib/a.c,,5,5,0,0,vendor/lib\n\
+             ,total,6,6,0,0,\n"
+        );
+    }
+
+    #[test]
+    fn test_quote_path() {
+        assert_eq!(quote_path(Path::new("src/main.rs")), "src/main.rs");
+        assert_eq!(quote_path(Path::new("naïve/日本.rs")), "naïve/日本.rs");
+        assert_eq!(
+            quote_path(Path::new("a \"b\" \\c.rs")),
+            "\"a \\\"b\\\" \\\\c.rs\""
+        );
+        assert_eq!(
+            quote_path(Path::new("\x07\x08\t\n\x0b\x0c\r\x1b\x7f")),
+            "\"\\a\\b\\t\\n\\v\\f\\r\\033\\177\""
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_quote_path_invalid_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"caf\xe9/\xff\xfe\xc3\xa9.rs"));
+        assert_eq!(quote_path(path), "\"caf\\351/\\377\\376é.rs\"");
+
+        let json = serde_json::to_value(FileCount::new(path, 1)).unwrap();
+        assert_eq!(json["path"], "\"caf\\351/\\377\\376é.rs\"");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_table_path_keeps_paths_apart() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let latin1 = Path::new(OsStr::from_bytes(b"caf\xe9.rs"));
+        let other = Path::new(OsStr::from_bytes(b"caf\xea.rs"));
+        let literal = Path::new("\"caf\\351.rs\"");
+        assert_eq!(table_path(latin1), "\"caf\\351.rs\"");
+        assert_eq!(table_path(other), "\"caf\\352.rs\"");
+        assert_eq!(table_path(literal), "\"\\\"caf\\\\351.rs\\\"\"");
+        assert_eq!(table_path(Path::new("café.rs")), "café.rs");
+
+        let summary =
+            CountSummary::from_counts(vec![FileCount::new(latin1, 1), FileCount::new(other, 2)]);
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &summary, false).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "path,language,lines,code,comment,blank\n\
+             \"\"\"caf\\351.rs\"\"\",,1,1,0,0\n\
+             \"\"\"caf\\352.rs\"\"\",,2,2,0,0\n"
+        );
+    }
+
+    #[test]
+    fn test_write_tsv_with_footer() {
+        // Paths are only quoted as TSV fields, and a file named `total` is
+        // not mistaken for the footer
+        let summary = CountSummary::from_counts(vec![
+            FileCount::new("with \"quotes\"\nand newline.c", 1).with_language(Some(Language::C)),
+            FileCount::new("comma,name.txt", 2),
+            FileCount::new("total", 3),
+        ]);
+
+        let mut tsv = Vec::new();
+        write_tsv(&mut tsv, &summary, true).unwrap();
+
+        assert_eq!(
+            String::from_utf8(tsv).unwrap(),
+            "path\tlanguage\tlines\tcode\tcomment\tblank\n\
+             comma,name.txt\t\t2\t2\t0\t0\n\
+             total\t\t3\t3\t0\t0\n\
+             \"with \"\"quotes\"\"\nand newline.c\"\tC\t1\t1\t0\t0\n\
+             \ttotal\t6\t6\t0\t0\n"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Repository discovery module.
+//!
+//! This module finds the root of the Git repository containing a path, so
+//! that gitlsf can be run from anywhere inside a checkout, and converts paths
+//! between the forms used on the command line and in results:
+//!
+//! - Paths given by the user are absolute or relative to the current
+//!   directory; [`Repository::repository_path`] makes them relative to the
+//!   repository root, for use as [pathspecs](crate::filter::FileFilter::pathspec)
+//! - Counted files have paths relative to the repository root;
+//!   [`Repository::relative_path`] makes them relative to another directory,
+//!   such as the current one, for display
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{GitlsfError, Result};
+use crate::git;
+
+/// The working tree of a Git repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    /// The root of the working tree, with symbolic links resolved.
+    root: PathBuf,
+}
+
+impl Repository {
+    /// Finds the repository containing `path`.
+    ///
+    /// Uses `git rev-parse --show-toplevel`. The path may be a file, or not
+    /// exist at all; the search then starts from its nearest existing parent
+    /// directory, or from the current directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The path is not within a Git repository
+    /// - The `git` command fails to execute
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::repository::Repository;
+    ///
+    /// let repository = Repository::discover("src/main.rs").unwrap();
+    /// println!("Repository root: {}", repository.root().display());
+    /// ```
+    pub fn discover(path: impl AsRef<Path>) -> Result<Self> {
+        let directory = path
+            .as_ref()
+            .ancestors()
+            .find(|ancestor| ancestor.is_dir())
+            .unwrap_or(Path::new("."));
+
+        let root = git::repository_root(directory)?;
+        let root = root.canonicalize().map_err(|e| GitlsfError::io(&root, e))?;
+
+        Ok(Repository { root })
+    }
+
+    /// Returns the root of the working tree.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Converts a path on disk to a path relative to the repository root.
+    ///
+    /// Relative paths are resolved against the current directory. The path
+    /// need not exist, as when it names a directory that was deleted since a
+    /// counted revision; symbolic links are resolved as far as it does. The
+    /// root itself yields an empty path.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GitlsfError::OutsideRepository`] if the path is not inside
+    /// the working tree, or [`GitlsfError::Io`] if the current directory
+    /// cannot be determined.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use gitlsf::repository::Repository;
+    ///
+    /// // Run from the `src` directory of the repository
+    /// let repository = Repository::discover(".").unwrap();
+    /// assert_eq!(repository.repository_path("parser").unwrap(), Path::new("src/parser"));
+    /// assert_eq!(repository.repository_path("..").unwrap(), Path::new(""));
+    /// ```
+    pub fn repository_path(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        let path = path.as_ref();
+        let absolute = std::path::absolute(path).map_err(|e| GitlsfError::io(path, e))?;
+
+        resolve(&absolute)
+            .strip_prefix(&self.root)
+            .map(Path::to_path_buf)
+            .map_err(|_| GitlsfError::OutsideRepository {
+                path: path.to_path_buf(),
+                root: self.root.clone(),
+            })
+    }
+
+    /// Converts a path relative to the repository root to a path relative to
+    /// `directory`.
+    ///
+    /// `directory` must be absolute with symbolic links resolved, such as the
+    /// canonicalized current directory. Files outside of it are reached
+    /// through `..` components.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use gitlsf::repository::Repository;
+    ///
+    /// let repository = Repository::discover(".").unwrap();
+    /// let src = repository.root().join("src");
+    ///
+    /// assert_eq!(repository.relative_path(Path::new("src/main.rs"), &src), Path::new("main.rs"));
+    /// assert_eq!(repository.relative_path(Path::new("build.rs"), &src), Path::new("../build.rs"));
+    /// ```
+    pub fn relative_path(&self, path: &Path, directory: &Path) -> PathBuf {
+        let path = self.root.join(path);
+        let mut components = path.components().peekable();
+        let mut directory = directory.components().peekable();
+
+        while let (Some(a), Some(b)) = (components.peek(), directory.peek())
+            && a == b
+        {
+            components.next();
+            directory.next();
+        }
+
+        let relative: PathBuf = directory
+            .map(|_| Component::ParentDir)
+            .chain(components)
+            .collect();
+
+        if relative.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            relative
+        }
+    }
+}
+
+/// Resolves `.` and `..` components lexically, and symbolic links in the
+/// longest part of an absolute path that exists.
+fn resolve(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    for ancestor in normalized.ancestors() {
+        if let Ok(canonical) = ancestor.canonicalize() {
+            let rest = normalized
+                .strip_prefix(ancestor)
+                .expect("an ancestor is a prefix");
+            return if rest.as_os_str().is_empty() {
+                canonical
+            } else {
+                canonical.join(rest)
+            };
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn setup_git_repo() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+        fs::create_dir_all(temp_dir.path().join("src/parser")).unwrap();
+        fs::write(temp_dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_discover() {
+        let temp_dir = setup_git_repo();
+        let root = temp_dir.path().canonicalize().unwrap();
+
+        for path in ["", "src", "src/parser", "src/main.rs", "src/gone/file.rs"] {
+            let repository = Repository::discover(temp_dir.path().join(path)).unwrap();
+            assert_eq!(repository.root(), root, "{path}");
+        }
+
+        let not_a_repository = TempDir::new().unwrap();
+        assert!(matches!(
+            Repository::discover(not_a_repository.path()),
+            Err(GitlsfError::NotAGitRepository)
+        ));
+    }
+
+    #[test]
+    fn test_repository_path() {
+        let temp_dir = setup_git_repo();
+        let repository = Repository::discover(temp_dir.path()).unwrap();
+        let path = |path: &str| repository.repository_path(temp_dir.path().join(path));
+
+        assert_eq!(path("").unwrap(), Path::new(""));
+        assert_eq!(path("src/main.rs").unwrap(), Path::new("src/main.rs"));
+        assert_eq!(path("src/./parser/").unwrap(), Path::new("src/parser"));
+        assert_eq!(
+            path("src/parser/../main.rs").unwrap(),
+            Path::new("src/main.rs")
+        );
+        assert_eq!(
+            path("src/gone/file.rs").unwrap(),
+            Path::new("src/gone/file.rs")
+        );
+        assert!(matches!(
+            path(".."),
+            Err(GitlsfError::OutsideRepository { .. })
+        ));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let repository = Repository {
+            root: PathBuf::from("/home/me/project"),
+        };
+        let relative = |path: &str, directory: &str| {
+            repository.relative_path(Path::new(path), Path::new(directory))
+        };
+
+        assert_eq!(
+            relative("src/main.rs", "/home/me/project"),
+            Path::new("src/main.rs")
+        );
+        assert_eq!(
+            relative("src/main.rs", "/home/me/project/src"),
+            Path::new("main.rs")
+        );
+        assert_eq!(
+            relative("build.rs", "/home/me/project/src/parser"),
+            Path::new("../../build.rs")
+        );
+        assert_eq!(relative("src", "/home/me/project/src"), Path::new("."));
+        assert_eq!(
+            relative("build.rs", "/home/me/other"),
+            Path::new("../project/build.rs")
+        );
+    }
+}
@@ -0,0 +1,791 @@
+//! File source module.
+//!
+//! This module abstracts where the files to count come from, so that the
+//! filtering, counting and output stages work the same for inputs other than
+//! a Git working tree. A [`FileSource`] lists its files and reads them:
+//!
+//! - [`GitSource`] lists the files of a Git repository with `git ls-files`
+//! - [`DirectorySource`] walks a directory tree, skipping the files ignored
+//!   by `.gitignore` and `.ignore` files
+//! - [`ArchiveSource`] reads a tarball or zip archive, such as a source
+//!   release, without unpacking it (`archive` feature)
+//! - [`ListSource`] takes an explicit list of files, such as one read from
+//!   standard input
+//!
+//! Sources whose files are on disk count them in parallel; the others stream
+//! their contents through the counter. See [`count_source`](crate::count_source).
+
+use std::fs;
+use std::io::{BufRead, ErrorKind, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{GitlsfError, Result};
+use crate::git::{self, FileSet, TreeEntry};
+
+/// A set of files to count, and the means to read them.
+///
+/// File paths are relative to the root of the source, and are shown as such
+/// in reports.
+pub trait FileSource {
+    /// Lists the files of the source.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files cannot be listed.
+    fn list_files(&self) -> Result<Vec<PathBuf>>;
+
+    /// Reads some of the listed files.
+    ///
+    /// `visit` is called with the index of each file in `files` and a reader
+    /// of its content, not necessarily in the order of `files`. A file that
+    /// is not found is not visited.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be read, or the first error
+    /// returned by `visit`.
+    fn read_files(
+        &self,
+        files: &[PathBuf],
+        visit: &mut dyn FnMut(usize, &mut dyn Read) -> Result<()>,
+    ) -> Result<()>;
+
+    /// Returns the directory holding the files, if they are on disk.
+    ///
+    /// Files on disk are counted in parallel instead of through
+    /// [`read_files`](Self::read_files).
+    fn directory(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Returns the Git repository whose `.gitattributes` apply to the files, if any.
+    fn repository(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Reads files below `directory`, for sources whose files are on disk.
+///
+/// Files that do not exist are not visited, as [`FileSource::read_files`]
+/// requires; other errors opening a file are returned.
+fn read_from_directory(
+    directory: &Path,
+    files: &[PathBuf],
+    visit: &mut dyn FnMut(usize, &mut dyn Read) -> Result<()>,
+) -> Result<()> {
+    for (index, file) in files.iter().enumerate() {
+        let path = directory.join(file);
+        let mut reader = match fs::File::open(&path) {
+            Ok(reader) => reader,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(GitlsfError::io(&path, e)),
+        };
+        visit(index, &mut reader)?;
+    }
+    Ok(())
+}
+
+/// The files of a file set of a Git repository, excluding submodules.
+///
+/// Files are read from the working tree. With [`FileSet::Staged`], this is
+/// their working tree content; [`count_repository_with_filter`] counts their
+/// staged content instead.
+///
+/// [`count_repository_with_filter`]: crate::count_repository_with_filter
+#[derive(Debug, Clone)]
+pub struct GitSource {
+    /// The path to the repository.
+    root: PathBuf,
+    /// The files to list.
+    file_set: FileSet,
+    /// Paths limiting the listing.
+    pathspecs: Vec<PathBuf>,
+    /// The submodules of the repository, which are not listed.
+    submodules: Vec<TreeEntry>,
+}
+
+impl GitSource {
+    /// Opens the repository at `path`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the Git repository.
+    /// * `file_set` - The files to list.
+    /// * `pathspecs` - Paths limiting the listing, or an empty slice for all files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The path is not within a Git repository
+    /// - The `git` command fails to execute
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::FileSet;
+    /// use gitlsf::source::{FileSource, GitSource};
+    ///
+    /// let source = GitSource::new(".", FileSet::Tracked, &[]).unwrap();
+    /// println!("{} tracked files", source.list_files().unwrap().len());
+    /// ```
+    pub fn new(path: impl AsRef<Path>, file_set: FileSet, pathspecs: &[PathBuf]) -> Result<Self> {
+        let root = path.as_ref().to_path_buf();
+        let submodules = git::list_submodules(&root, None)?;
+
+        Ok(GitSource {
+            root,
+            file_set,
+            pathspecs: pathspecs.to_vec(),
+            submodules,
+        })
+    }
+
+    /// Returns the submodules of the repository, which are not listed.
+    pub fn submodules(&self) -> &[TreeEntry] {
+        &self.submodules
+    }
+}
+
+impl FileSource for GitSource {
+    fn list_files(&self) -> Result<Vec<PathBuf>> {
+        Ok(git::list_files(&self.root, self.file_set, &self.pathspecs)?
+            .into_iter()
+            .filter(|file| {
+                !self
+                    .submodules
+                    .iter()
+                    .any(|submodule| submodule.path == *file)
+            })
+            .collect())
+    }
+
+    fn read_files(
+        &self,
+        files: &[PathBuf],
+        visit: &mut dyn FnMut(usize, &mut dyn Read) -> Result<()>,
+    ) -> Result<()> {
+        read_from_directory(&self.root, files, visit)
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+
+    fn repository(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+/// The files below a directory, whether or not it is in a Git repository.
+///
+/// Files ignored by `.gitignore` and `.ignore` files are skipped, as are
+/// those ignored by the exclude files of an enclosing repository and `.git`
+/// directories. Hidden files are listed, and symbolic links are not followed.
+#[derive(Debug, Clone)]
+pub struct DirectorySource {
+    /// The directory to walk.
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    /// Creates a source for the files below `path`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::filter::FileFilter;
+    /// use gitlsf::source::DirectorySource;
+    ///
+    /// let source = DirectorySource::new("/usr/src/linux");
+    /// let summary = gitlsf::count_source(&source, FileFilter::new()).unwrap();
+    /// ```
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        DirectorySource { root: path.into() }
+    }
+}
+
+impl FileSource for DirectorySource {
+    fn list_files(&self) -> Result<Vec<PathBuf>> {
+        let walker = ignore::WalkBuilder::new(&self.root)
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+
+        let mut files = Vec::new();
+        for entry in walker {
+            let entry = entry.map_err(|e| GitlsfError::io(&self.root, std::io::Error::other(e)))?;
+            if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+                continue;
+            }
+            if let Ok(file) = entry.path().strip_prefix(&self.root) {
+                files.push(file.to_path_buf());
+            }
+        }
+
+        files.sort();
+        Ok(files)
+    }
+
+    fn read_files(
+        &self,
+        files: &[PathBuf],
+        visit: &mut dyn FnMut(usize, &mut dyn Read) -> Result<()>,
+    ) -> Result<()> {
+        read_from_directory(&self.root, files, visit)
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+/// An explicit list of files, relative to a base directory.
+///
+/// Absolute paths below the base directory are made relative to it.
+/// Duplicates are listed once, and files that cannot be read are reported
+/// as unreadable when counted.
+#[derive(Debug, Clone)]
+pub struct ListSource {
+    /// The directory the paths are relative to.
+    base: PathBuf,
+    /// The listed files.
+    files: Vec<PathBuf>,
+}
+
+impl ListSource {
+    /// Creates a source for `files`, relative to `base`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::source::ListSource;
+    ///
+    /// let source = ListSource::new(".", ["src/main.rs", "src/lib.rs"]);
+    /// ```
+    pub fn new<I, S>(base: impl Into<PathBuf>, files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<Path>,
+    {
+        let base = base.into();
+        let mut files: Vec<PathBuf> = files
+            .into_iter()
+            .map(|file| {
+                let file = file.as_ref();
+                let file = file.strip_prefix(&base).unwrap_or(file);
+                file.components()
+                    .filter(|component| *component != Component::CurDir)
+                    .collect()
+            })
+            .filter(|file: &PathBuf| !file.as_os_str().is_empty())
+            .collect();
+        files.sort();
+        files.dedup();
+
+        ListSource { base, files }
+    }
+
+    /// Reads a list of files from `reader`, relative to `base`.
+    ///
+    /// Paths are separated by `separator`, usually `b'\n'`, or `b'\0'` for
+    /// the output of `find -print0` or `git ls-files -z`. With a newline
+    /// separator, a trailing carriage return is removed. Empty paths are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use gitlsf::source::ListSource;
+    ///
+    /// let source = ListSource::from_reader(".", std::io::stdin().lock(), b'\n').unwrap();
+    /// ```
+    pub fn from_reader(
+        base: impl Into<PathBuf>,
+        reader: impl BufRead,
+        separator: u8,
+    ) -> Result<Self> {
+        let mut files = Vec::new();
+        for line in reader.split(separator) {
+            let mut line = line.map_err(|e| GitlsfError::io("-", e))?;
+            if separator == b'\n' && line.ends_with(b"\r") {
+                line.pop();
+            }
+            files.push(git::path_from_bytes(&line));
+        }
+
+        Ok(Self::new(base, files))
+    }
+}
+
+impl FileSource for ListSource {
+    fn list_files(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.files.clone())
+    }
+
+    fn read_files(
+        &self,
+        files: &[PathBuf],
+        visit: &mut dyn FnMut(usize, &mut dyn Read) -> Result<()>,
+    ) -> Result<()> {
+        read_from_directory(&self.base, files, visit)
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        Some(&self.base)
+    }
+}
+
+#[cfg(feature = "archive")]
+pub use archive::ArchiveSource;
+
+#[cfg(feature = "archive")]
+mod archive {
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::{BufReader, Read};
+    use std::path::{Component, Path, PathBuf};
+
+    use super::FileSource;
+    use crate::error::{GitlsfError, Result};
+
+    /// A supported archive format.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Format {
+        Tar,
+        TarGz,
+        Zip,
+    }
+
+    impl Format {
+        /// Detects the format of an archive from its file name.
+        fn detect(path: &Path) -> Option<Format> {
+            let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+            if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+                Some(Format::TarGz)
+            } else if name.ends_with(".tar") {
+                Some(Format::Tar)
+            } else if name.ends_with(".zip") {
+                Some(Format::Zip)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// The regular files of a tarball or zip archive, read without unpacking it.
+    ///
+    /// The format is detected from the file name: `.tar`, `.tar.gz` or
+    /// `.tgz`, and `.zip`. Like the archives of source releases, whose files
+    /// are all in a directory named after the release, a single top-level
+    /// directory is removed from the paths. Symbolic links, and entries with
+    /// absolute paths or `..` components, are skipped. Tarballs cannot be
+    /// read at random, so they are read twice: once when opened, to list
+    /// their files, and once more to count them.
+    #[derive(Debug, Clone)]
+    pub struct ArchiveSource {
+        /// The path to the archive.
+        path: PathBuf,
+        /// The format of the archive.
+        format: Format,
+        /// The top-level directory removed from the paths, if any.
+        prefix: Option<PathBuf>,
+        /// The files of the archive, without the prefix.
+        files: Vec<PathBuf>,
+    }
+
+    impl ArchiveSource {
+        /// Opens an archive and lists its files.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if:
+        /// - The format of the archive is not supported
+        /// - The archive cannot be read
+        ///
+        /// # Example
+        ///
+        /// ```no_run
+        /// use gitlsf::source::{ArchiveSource, FileSource};
+        ///
+        /// let source = ArchiveSource::open("ripgrep-14.1.0.tar.gz").unwrap();
+        /// for file in source.list_files().unwrap() {
+        ///     println!("{}", file.display());
+        /// }
+        /// ```
+        pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+            let path = path.into();
+            let format = Format::detect(&path).ok_or_else(|| {
+                GitlsfError::archive(
+                    &path,
+                    "unsupported format, expected .tar, .tar.gz, .tgz or .zip",
+                )
+            })?;
+
+            let mut source = ArchiveSource {
+                path,
+                format,
+                prefix: None,
+                files: Vec::new(),
+            };
+
+            let mut names = Vec::new();
+            source.entries(&mut |name, _| {
+                names.push(name);
+                Ok(())
+            })?;
+            source.prefix = common_directory(&names);
+
+            let mut files: Vec<PathBuf> = names
+                .iter()
+                .map(|name| source.strip_prefix(name).to_path_buf())
+                .collect();
+            files.sort();
+            files.dedup();
+            source.files = files;
+
+            Ok(source)
+        }
+
+        /// Removes the top-level directory, if any, from the path of an entry.
+        fn strip_prefix<'a>(&self, name: &'a Path) -> &'a Path {
+            match &self.prefix {
+                Some(prefix) => name.strip_prefix(prefix).unwrap_or(name),
+                None => name,
+            }
+        }
+
+        /// Calls `visit` with the path and content of each regular file, in archive order.
+        fn entries(
+            &self,
+            visit: &mut dyn FnMut(PathBuf, &mut dyn Read) -> Result<()>,
+        ) -> Result<()> {
+            let file = File::open(&self.path).map_err(|e| GitlsfError::io(&self.path, e))?;
+            match self.format {
+                Format::Tar => self.tar_entries(BufReader::new(file), visit),
+                Format::TarGz => {
+                    let decoder = flate2::read::MultiGzDecoder::new(BufReader::new(file));
+                    self.tar_entries(decoder, visit)
+                }
+                Format::Zip => self.zip_entries(file, visit),
+            }
+        }
+
+        fn tar_entries(
+            &self,
+            reader: impl Read,
+            visit: &mut dyn FnMut(PathBuf, &mut dyn Read) -> Result<()>,
+        ) -> Result<()> {
+            let read_error = |e| GitlsfError::io(&self.path, e);
+            let mut archive = tar::Archive::new(reader);
+
+            for entry in archive.entries().map_err(read_error)? {
+                let mut entry = entry.map_err(read_error)?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let Some(name) = normalize(&entry.path().map_err(read_error)?) else {
+                    continue;
+                };
+                visit(name, &mut entry)?;
+            }
+
+            Ok(())
+        }
+
+        fn zip_entries(
+            &self,
+            file: File,
+            visit: &mut dyn FnMut(PathBuf, &mut dyn Read) -> Result<()>,
+        ) -> Result<()> {
+            let zip_error =
+                |e: zip::result::ZipError| GitlsfError::archive(&self.path, e.to_string());
+            let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(zip_error)?;
+
+            for index in 0..archive.len() {
+                let mut entry = archive.by_index(index).map_err(zip_error)?;
+                if !entry.is_file() || entry.is_symlink() {
+                    continue;
+                }
+                let Some(name) = entry.enclosed_name().as_deref().and_then(normalize) else {
+                    continue;
+                };
+                visit(name, &mut entry)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl FileSource for ArchiveSource {
+        fn list_files(&self) -> Result<Vec<PathBuf>> {
+            Ok(self.files.clone())
+        }
+
+        fn read_files(
+            &self,
+            files: &[PathBuf],
+            visit: &mut dyn FnMut(usize, &mut dyn Read) -> Result<()>,
+        ) -> Result<()> {
+            let indices: HashMap<&Path, usize> = files
+                .iter()
+                .enumerate()
+                .map(|(index, file)| (file.as_path(), index))
+                .collect();
+
+            self.entries(
+                &mut |name, reader| match indices.get(self.strip_prefix(&name)) {
+                    Some(&index) => visit(index, reader),
+                    None => Ok(()),
+                },
+            )
+        }
+    }
+
+    /// Returns the path of an entry without `.` components, or `None` if it
+    /// is empty, absolute or has `..` components.
+    fn normalize(path: &Path) -> Option<PathBuf> {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => normalized.push(part),
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+        (!normalized.as_os_str().is_empty()).then_some(normalized)
+    }
+
+    /// Returns the top-level directory that holds every file, if there is one.
+    fn common_directory(names: &[PathBuf]) -> Option<PathBuf> {
+        let mut directory: Option<&Path> = None;
+        for name in names {
+            let mut components = name.components();
+            let first = Path::new(components.next()?.as_os_str());
+            // A file at the top level is not in a directory
+            components.next()?;
+            match directory {
+                Some(directory) if directory != first => return None,
+                _ => directory = Some(first),
+            }
+        }
+        directory.map(Path::to_path_buf)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_format_detect() {
+            assert_eq!(Format::detect(Path::new("a.tar")), Some(Format::Tar));
+            assert_eq!(Format::detect(Path::new("a.TAR.GZ")), Some(Format::TarGz));
+            assert_eq!(Format::detect(Path::new("a.tgz")), Some(Format::TarGz));
+            assert_eq!(Format::detect(Path::new("a.zip")), Some(Format::Zip));
+            assert_eq!(Format::detect(Path::new("a.tar.xz")), None);
+        }
+
+        #[test]
+        fn test_normalize() {
+            assert_eq!(normalize(Path::new("./a/./b")), Some(PathBuf::from("a/b")));
+            assert_eq!(normalize(Path::new("a/../b")), None);
+            assert_eq!(normalize(Path::new("/etc/passwd")), None);
+            assert_eq!(normalize(Path::new(".")), None);
+        }
+
+        #[test]
+        fn test_common_directory() {
+            let names = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+            assert_eq!(
+                common_directory(&names(&["v1/src/a.rs", "v1/README"])),
+                Some(PathBuf::from("v1"))
+            );
+            assert_eq!(common_directory(&names(&["v1/a.rs", "v2/a.rs"])), None);
+            assert_eq!(common_directory(&names(&["v1/a.rs", "README"])), None);
+            assert_eq!(common_directory(&[]), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    /// Reads every listed file of a source into a string.
+    fn read_all(source: &dyn FileSource) -> Vec<(PathBuf, String)> {
+        let files = source.list_files().unwrap();
+        let mut contents = vec![String::new(); files.len()];
+        source
+            .read_files(&files, &mut |index, reader| {
+                reader.read_to_string(&mut contents[index]).unwrap();
+                Ok(())
+            })
+            .unwrap();
+        files.into_iter().zip(contents).collect()
+    }
+
+    #[test]
+    fn test_directory_source_honors_gitignore() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        fs::create_dir_all(path.join("src")).unwrap();
+        fs::create_dir_all(path.join("target")).unwrap();
+        fs::write(path.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(path.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(path.join("target/out.rs"), "fn out() {}\n").unwrap();
+        fs::write(path.join("debug.log"), "log\n").unwrap();
+
+        let source = DirectorySource::new(path);
+
+        assert_eq!(
+            source.list_files().unwrap(),
+            vec![PathBuf::from(".gitignore"), PathBuf::from("src/main.rs")]
+        );
+        assert_eq!(source.directory(), Some(path));
+        assert_eq!(source.repository(), None);
+    }
+
+    #[test]
+    fn test_directory_source_skips_git_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        fs::write(path.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let source = DirectorySource::new(path);
+
+        assert_eq!(source.list_files().unwrap(), vec![PathBuf::from("main.rs")]);
+    }
+
+    #[test]
+    fn test_list_source_from_reader() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::write(base.join("a.rs"), "a\n").unwrap();
+        fs::write(base.join("b.rs"), "b\n").unwrap();
+        let input = format!("./b.rs\r\n{}\n\nb.rs\n", base.join("a.rs").display());
+
+        let source = ListSource::from_reader(base, input.as_bytes(), b'\n').unwrap();
+
+        assert_eq!(
+            read_all(&source),
+            vec![
+                (PathBuf::from("a.rs"), "a\n".to_string()),
+                (PathBuf::from("b.rs"), "b\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_files_skips_missing_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::write(base.join("a.rs"), "a\n").unwrap();
+        let files = [PathBuf::from("missing.rs"), PathBuf::from("a.rs")];
+
+        for source in [
+            &DirectorySource::new(base) as &dyn FileSource,
+            &ListSource::from_reader(base, &b"a.rs\n"[..], b'\n').unwrap(),
+        ] {
+            let mut visited = Vec::new();
+            source
+                .read_files(&files, &mut |index, _| {
+                    visited.push(index);
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(visited, [1]);
+        }
+    }
+
+    #[test]
+    fn test_list_source_nul_separated() {
+        let source = ListSource::from_reader(".", &b"a b.rs\0c.rs\0"[..], b'\0').unwrap();
+
+        assert_eq!(
+            source.list_files().unwrap(),
+            vec![PathBuf::from("a b.rs"), PathBuf::from("c.rs")]
+        );
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn test_archive_source_tarball() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path();
+        fs::create_dir_all(path.join("release-1.0/src")).unwrap();
+        fs::write(path.join("release-1.0/src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(path.join("release-1.0/README"), "Read me\n").unwrap();
+        let output = Command::new("tar")
+            .args(["-czf", "release-1.0.tar.gz", "release-1.0"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let source = ArchiveSource::open(path.join("release-1.0.tar.gz")).unwrap();
+
+        assert_eq!(
+            read_all(&source),
+            vec![
+                (PathBuf::from("README"), "Read me\n".to_string()),
+                (PathBuf::from("src/main.rs"), "fn main() {}\n".to_string()),
+            ]
+        );
+        assert_eq!(source.directory(), None);
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn test_archive_source_zip() {
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sources.zip");
+        let mut writer = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        writer.start_file("main.py", options).unwrap();
+        writer.write_all(b"print('hi')\n").unwrap();
+        writer.add_directory("lib/", options).unwrap();
+        writer.start_file("lib/util.py", options).unwrap();
+        writer.write_all(b"x = 1\n").unwrap();
+        writer.finish().unwrap();
+
+        let source = ArchiveSource::open(&path).unwrap();
+        let files = source.list_files().unwrap();
+        let mut contents = Vec::new();
+        source
+            .read_files(&files[1..], &mut |index, reader| {
+                let mut content = String::new();
+                reader.read_to_string(&mut content).unwrap();
+                contents.push((index, content));
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(
+            files,
+            vec![PathBuf::from("lib/util.py"), PathBuf::from("main.py")]
+        );
+        assert_eq!(contents, vec![(0, "print('hi')\n".to_string())]);
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn test_archive_source_unsupported_format() {
+        let err = ArchiveSource::open("release.rar").unwrap_err();
+
+        assert!(matches!(err, GitlsfError::Archive { .. }));
+    }
+}
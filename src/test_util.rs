@@ -0,0 +1,36 @@
+//! Helpers shared by the unit tests of modules that run `git`.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Runs `git` with `args` in `path`, failing the test if it fails.
+pub(crate) fn git(path: &Path, args: &[&str]) {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Stages all changes in `path` and commits them as the given author.
+pub(crate) fn commit_as(path: &Path, name: &str, email: &str, message: &str) {
+    git(path, &["add", "-A"]);
+    git(
+        path,
+        &[
+            "-c",
+            &format!("user.name={name}"),
+            "-c",
+            &format!("user.email={email}"),
+            "commit",
+            "-q",
+            "-m",
+            message,
+        ],
+    );
+}
@@ -0,0 +1,233 @@
+//! Directory tree aggregation module.
+//!
+//! This module rolls per-file counts up into per-directory subtotals, giving
+//! a view of where the lines of a repository live.
+
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::classify::LineStats;
+use crate::counter::CountSummary;
+use crate::output::quote_path;
+
+/// Line counts of all files below a directory, with its subdirectories.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DirectoryTree {
+    /// The last component of the directory path, or `.` for the root.
+    ///
+    /// Like the path, it is quoted by [`quote_path`] if needed.
+    pub name: String,
+    /// The directory path relative to the root, or `.` for the root.
+    pub path: String,
+    /// Number of files below the directory, at any depth.
+    pub files: usize,
+    /// Line statistics summed over the files below the directory.
+    #[serde(flatten)]
+    pub stats: LineStats,
+    /// Subdirectories, largest first.
+    pub children: Vec<DirectoryTree>,
+}
+
+impl DirectoryTree {
+    /// Builds the directory tree of a summary.
+    ///
+    /// Directories are expanded to at most `depth` levels below the root;
+    /// the files of deeper directories are included in the subtotals of
+    /// their deepest shown ancestor. A depth of 0 yields the root alone.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gitlsf::tree::DirectoryTree;
+    /// use gitlsf::{CountSummary, FileCount};
+    ///
+    /// let summary = CountSummary::from_counts(vec![
+    ///     FileCount::new("build.rs", 10),
+    ///     FileCount::new("src/lib.rs", 30),
+    ///     FileCount::new("src/output/json.rs", 60),
+    /// ]);
+    ///
+    /// let tree = DirectoryTree::from_summary(&summary, 1);
+    /// assert_eq!(tree.stats.lines, 100);
+    /// assert_eq!(tree.children.len(), 1);
+    /// assert_eq!(tree.children[0].path, "src");
+    /// assert_eq!(tree.children[0].stats.lines, 90);
+    /// assert!(tree.children[0].children.is_empty());
+    /// ```
+    pub fn from_summary(summary: &CountSummary, depth: usize) -> Self {
+        let mut root = DirectoryTree::new(".", ".");
+
+        for file in &summary.files {
+            let stats = file.stats();
+            root.add(stats);
+
+            let mut node = &mut root;
+            let mut path = PathBuf::new();
+            let directories = file
+                .path
+                .parent()
+                .map(Path::components)
+                .into_iter()
+                .flatten();
+            for name in directories
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name),
+                    // Paths relative to a subdirectory reach its siblings through `..`
+                    Component::ParentDir => Some(OsStr::new("..")),
+                    _ => None,
+                })
+                .take(depth)
+            {
+                path.push(name);
+                node = node.child(&quote_path(Path::new(name)), &path);
+                node.add(stats);
+            }
+        }
+
+        root.sort();
+        root
+    }
+
+    /// Returns the share of `total` lines below this directory, as a percentage.
+    ///
+    /// Returns 0 if `total` is 0.
+    pub fn percentage(&self, total: usize) -> f64 {
+        if total == 0 {
+            0.0
+        } else {
+            self.stats.lines as f64 * 100.0 / total as f64
+        }
+    }
+
+    /// Creates an empty directory node.
+    fn new(name: &str, path: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            path: path.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds the statistics of one file to this directory.
+    fn add(&mut self, stats: LineStats) {
+        self.files += 1;
+        self.stats += stats;
+    }
+
+    /// Returns the subdirectory with the given quoted name at `path`,
+    /// creating it if needed.
+    ///
+    /// Children are kept sorted by name while the tree is being built.
+    fn child(&mut self, name: &str, path: &Path) -> &mut DirectoryTree {
+        let index = match self
+            .children
+            .binary_search_by(|child| child.name.as_str().cmp(name))
+        {
+            Ok(index) => index,
+            Err(index) => {
+                let path = quote_path(path);
+                self.children.insert(index, DirectoryTree::new(name, &path));
+                index
+            }
+        };
+
+        &mut self.children[index]
+    }
+
+    /// Sorts children by line count, largest first, with ties broken by name.
+    fn sort(&mut self) {
+        // The children are sorted by name, so a stable sort keeps ties in order
+        self.children
+            .sort_by_key(|child| std::cmp::Reverse(child.stats.lines));
+        for child in &mut self.children {
+            child.sort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::FileCount;
+
+    fn summary(files: &[(&str, usize)]) -> CountSummary {
+        CountSummary::from_counts(
+            files
+                .iter()
+                .map(|(path, lines)| FileCount::new(*path, *lines))
+                .collect(),
+        )
+    }
+
+    fn paths(tree: &DirectoryTree) -> Vec<(String, usize, usize)> {
+        let mut paths = vec![(tree.path.clone(), tree.files, tree.stats.lines)];
+        for child in &tree.children {
+            paths.extend(self::paths(child));
+        }
+        paths
+    }
+
+    #[test]
+    fn test_tree_depth() {
+        let summary = summary(&[
+            ("main.rs", 5),
+            ("src/a/x.rs", 10),
+            ("src/a/deep/y.rs", 20),
+            ("src/b.rs", 1),
+            ("tests/t.rs", 40),
+        ]);
+
+        assert_eq!(
+            paths(&DirectoryTree::from_summary(&summary, 0)),
+            [(".".to_string(), 5, 76)]
+        );
+        assert_eq!(
+            paths(&DirectoryTree::from_summary(&summary, 2)),
+            [
+                (".".to_string(), 5, 76),
+                ("tests".to_string(), 1, 40),
+                ("src".to_string(), 3, 31),
+                ("src/a".to_string(), 2, 30),
+            ]
+        );
+        assert_eq!(
+            paths(&DirectoryTree::from_summary(&summary, 5)).last(),
+            Some(&("src/a/deep".to_string(), 1, 20))
+        );
+    }
+
+    #[test]
+    fn test_tree_ties_sorted_by_name() {
+        let summary = summary(&[("b/x.rs", 3), ("c/x.rs", 1), ("a/x.rs", 3)]);
+
+        let tree = DirectoryTree::from_summary(&summary, 1);
+        let names: Vec<&str> = tree.children.iter().map(|c| c.name.as_str()).collect();
+
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_tree_parent_directories() {
+        let summary = summary(&[("main.rs", 5), ("../tests/t.rs", 40), ("../build.rs", 2)]);
+
+        assert_eq!(
+            paths(&DirectoryTree::from_summary(&summary, 2)),
+            [
+                (".".to_string(), 3, 47),
+                ("..".to_string(), 2, 42),
+                ("../tests".to_string(), 1, 40),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_percentage() {
+        let tree = DirectoryTree::from_summary(&summary(&[("src/a.rs", 1), ("b.rs", 3)]), 1);
+
+        assert_eq!(tree.percentage(4), 100.0);
+        assert_eq!(tree.children[0].percentage(4), 25.0);
+        assert_eq!(DirectoryTree::default().percentage(0), 0.0);
+    }
+}
@@ -154,7 +154,7 @@ fn test_filters_binary_files() {
     let path = temp_dir.path();
 
     // Add a binary file
-    fs::write(path.join("image.png"), &[0x89, 0x50, 0x4E, 0x47]).unwrap();
+    fs::write(path.join("image.png"), [0x89, 0x50, 0x4E, 0x47]).unwrap();
     Command::new("git")
         .args(["add", "image.png"])
         .current_dir(path)
@@ -253,3 +253,1343 @@ fn test_current_directory_default() {
         .stdout(predicate::str::contains("Files:"))
         .stdout(predicate::str::contains("Lines:"));
 }
+
+#[test]
+fn test_breakdown_verbose() {
+    let temp_dir = setup_git_repo();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+
+    // utils.rs has one comment line; every other line is code
+    cmd.arg("-b")
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("comment"))
+        .stdout(predicate::str::is_match(r"\s+3\s+2\s+1\s+0 src/utils.rs").unwrap())
+        .stdout(predicate::str::is_match(r"\s+9\s+8\s+1\s+0 total").unwrap());
+}
+
+#[test]
+fn test_breakdown_summary() {
+    let temp_dir = setup_git_repo();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+
+    cmd.args(["-s", "-b"])
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Code: 8"))
+        .stdout(predicate::str::contains("Comments: 1"))
+        .stdout(predicate::str::contains("Blank: 0"));
+}
+
+#[test]
+fn test_languages_mode() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+
+    fs::write(path.join("Makefile"), "all:\n\tcargo build\n").unwrap();
+    fs::write(path.join("deploy"), "#!/bin/sh\necho deploy\n").unwrap();
+    Command::new("git")
+        .args(["add", "Makefile", "deploy"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("-l")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"\s+3\s+9 Rust").unwrap())
+        .stdout(predicate::str::is_match(r"\s+1\s+2 Makefile").unwrap())
+        .stdout(predicate::str::is_match(r"\s+1\s+2 Shell").unwrap())
+        .stdout(predicate::str::is_match(r"\s+5\s+13 total").unwrap());
+}
+
+#[test]
+fn test_count_at_revision() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+
+    Command::new("git")
+        .args(["commit", "-q", "-m", "v1"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["tag", "v1.0"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    fs::write(path.join("src/new.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["-q", "--rev", "v1.0"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("9\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("-q").arg(path).assert().success().stdout("11\n");
+}
+
+#[test]
+fn test_count_at_unknown_revision() {
+    let temp_dir = setup_git_repo();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+
+    cmd.args(["--rev", "does-not-exist"])
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unknown revision 'does-not-exist'",
+        ));
+}
+
+#[test]
+fn test_diff_subcommand() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+
+    Command::new("git")
+        .args(["commit", "-q", "-m", "v1"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    fs::write(path.join("src/new.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    fs::remove_file(path.join("src/utils.rs")).unwrap();
+    Command::new("git")
+        .args(["add", "-A"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-q", "-m", "v2"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["diff", "HEAD~1..HEAD"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"A\s+\+2 src/new.rs").unwrap())
+        .stdout(predicate::str::is_match(r"D\s+-3 src/utils.rs").unwrap())
+        .stdout(predicate::str::is_match(r"-1 src/").unwrap())
+        .stdout(predicate::str::contains("Lines: 9 -> 8 (-1)"));
+}
+
+#[test]
+fn test_diff_against_working_tree() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+
+    Command::new("git")
+        .args(["commit", "-q", "-m", "v1"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    fs::write(path.join("src/lib.rs"), "pub fn add() {}\n").unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["diff", "HEAD"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"M\s+-2 src/lib.rs").unwrap())
+        .stdout(predicate::str::contains("1 changed"));
+}
+
+#[test]
+fn test_history_subcommand() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+
+    Command::new("git")
+        .args(["commit", "-q", "-m", "one"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    fs::write(path.join("src/new.rs"), "fn a() {}\n").unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-q", "-m", "two"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("history")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "timestamp,commit,files,lines,code,comment,blank,Rust\n",
+        ))
+        .stdout(predicate::str::is_match(r",3,9,8,1,0,9\n.*,4,10,9,1,0,10\n$").unwrap());
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["history", "--format", "json", "--every", "2"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"lines\": 10"))
+        .stdout(predicate::str::contains("\"lines\": 9").not());
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["history", "--every", "0"])
+        .arg(path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--every"));
+}
+
+#[test]
+fn test_blame_subcommand() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+
+    Command::new("git")
+        .args(["commit", "-q", "-m", "one"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    fs::write(path.join("src/new.rs"), "fn a() {}\n").unwrap();
+    fs::write(
+        path.join(".mailmap"),
+        "Test <test@test.com> <other@test.com>\n",
+    )
+    .unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args([
+            "-c",
+            "user.name=Other",
+            "-c",
+            "user.email=other@test.com",
+            "commit",
+            "-q",
+            "-m",
+            "two",
+        ])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["blame", "--no-mailmap"])
+        .arg(path.join("src"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "    3     9  90.0% Test User <test@test.com>\n",
+        ))
+        .stdout(predicate::str::contains(
+            "    1     1  10.0% Other <other@test.com>\n",
+        ))
+        .stdout(predicate::str::ends_with("    4    10 100.0% total\n"));
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args([
+        "blame", "--format", "csv", "--depth", "1", "--rev", "HEAD~1",
+    ])
+    .arg(path)
+    .assert()
+    .success()
+    .stdout("directory,email,name,files,lines\nsrc,test@test.com,Test User,3,9\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    let output = cmd
+        .args(["blame", "--format", "json"])
+        .arg(path.join("src"))
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(json["mailmap"], true);
+    assert_eq!(json["totals"]["lines"], 10);
+    assert_eq!(json["authors"][0]["email"], "test@test.com");
+    assert_eq!(json["authors"][0]["files"], 4);
+    assert_eq!(json["authors"][0]["name"], "Test User");
+    assert_eq!(json["authors"].as_array().unwrap().len(), 1);
+
+    // Blaming leaves the line count cache alone
+    assert!(!path.join(".git/gitlsf-cache").exists());
+}
+
+#[test]
+fn test_churn_subcommand() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+
+    Command::new("git")
+        .args(["commit", "-q", "-m", "one"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    fs::write(path.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(path.join("README.md"), "# Test Project\n").unwrap();
+    Command::new("git")
+        .args(["commit", "-q", "-a", "-m", "two"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("churn")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "      3       2     +10      -3 Test User <test@test.com>\n",
+        ))
+        .stdout(predicate::str::ends_with(
+            "      3       2     +10      -3 total\n",
+        ));
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args([
+        "churn",
+        "--rev",
+        "HEAD~1..HEAD",
+        "--by",
+        "file",
+        "--format",
+        "csv",
+    ])
+    .arg(path)
+    .assert()
+    .success()
+    .stdout("path,language,commits,added,deleted\nsrc/main.rs,Rust,1,1,3\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    let output = cmd
+        .args(["churn", "--by", "directory", "--format", "json"])
+        .arg(path)
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(json["range"], "HEAD");
+    assert_eq!(json["totals"]["commits"], 2);
+    assert_eq!(json["languages"][0]["language"], "Rust");
+    assert_eq!(json["directories"][0]["path"], "src");
+}
+
+#[test]
+fn test_json_format() {
+    let temp_dir = setup_git_repo();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+
+    let output = cmd
+        .args(["--format", "json", "-q"])
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["schema_version"], 1);
+    assert_eq!(report["tool"]["name"], "gitlsf");
+    assert!(report["revision"].is_null());
+    assert_eq!(report["totals"]["files"], 3);
+    assert_eq!(report["totals"]["lines"], 9);
+    assert_eq!(report["totals"]["comment"], 1);
+    assert_eq!(report["files"][0]["path"], "src/lib.rs");
+    assert_eq!(report["files"][0]["language"], "Rust");
+    assert_eq!(report["languages"][0]["language"], "Rust");
+}
+
+#[test]
+fn test_json_quotes_repository_path() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("my\tproject");
+    fs::create_dir(&path).unwrap();
+    Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(&path)
+        .output()
+        .unwrap();
+    fs::write(path.join("main.py"), "print(1)\n").unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    let output = cmd
+        .args(["-f", "json", "--untracked"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let repository = report["repository"].as_str().unwrap();
+    assert!(repository.starts_with('"'));
+    assert!(repository.ends_with("/my\\tproject\""));
+}
+
+#[test]
+fn test_json_format_at_revision() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+
+    Command::new("git")
+        .args(["commit", "-q", "-m", "v1"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    let output = cmd
+        .args(["-f", "json", "--rev", "HEAD"])
+        .arg(path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["revision"]["name"], "HEAD");
+    assert_eq!(report["revision"]["commit"].as_str().unwrap().len(), 40);
+    assert_eq!(report["totals"]["lines"], 9);
+}
+
+#[test]
+fn test_csv_format() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    fs::write(path.join("src/a,b.rs"), "fn f() {}\n").unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["--format", "csv", "--totals"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(
+            "path,language,lines,code,comment,blank\n\
+             \"src/a,b.rs\",Rust,1,1,0,0\n\
+             src/lib.rs,Rust,3,3,0,0\n\
+             src/main.rs,Rust,3,3,0,0\n\
+             src/utils.rs,Rust,3,2,1,0\n\
+             ,total,10,9,1,0\n",
+        );
+}
+
+#[test]
+fn test_tsv_format() {
+    let temp_dir = setup_git_repo();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+
+    cmd.args(["--format", "tsv"])
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "path\tlanguage\tlines\tcode\tcomment\tblank\nsrc/lib.rs\tRust\t3\t3\t0\t0\n",
+        ))
+        .stdout(predicate::str::contains("total").not());
+}
+
+#[test]
+fn test_tree_mode() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    fs::create_dir_all(path.join("src/nested")).unwrap();
+    fs::write(path.join("src/nested/deep.rs"), "fn deep() {}\n").unwrap();
+    fs::write(path.join("build.rs"), "fn main() {}\n").unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("--tree")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"5\s+11\s+100\.0% \.\n").unwrap())
+        .stdout(predicate::str::is_match(r"4\s+10\s+90\.9%   src/\n").unwrap())
+        .stdout(predicate::str::is_match(r"1\s+1\s+9\.1%     nested/\n").unwrap());
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["--depth", "1"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("nested/").not());
+}
+
+#[test]
+fn test_include_and_exclude_patterns() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    fs::create_dir_all(path.join("vendor/dep")).unwrap();
+    fs::write(path.join("vendor/dep/lib.rs"), "fn dep() {}\n").unwrap();
+    fs::write(path.join("build.rs"), "fn main() {}\n").unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["--exclude", "vendor/", "--exclude", "/build.rs"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("vendor").not())
+        .stdout(predicate::str::contains("build.rs").not())
+        .stdout(predicate::str::contains("9 total"));
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["-q", "--include", "src/**", "--include", "!src/utils.rs"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("6\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["-q", "--exclude", "!README.md"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("14\n");
+}
+
+#[test]
+fn test_invalid_pattern() {
+    let temp_dir = setup_git_repo();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+
+    cmd.args(["--exclude", "src/{a,b"])
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid pattern 'src/{a,b'"));
+}
+
+#[test]
+fn test_repository_config() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let config_home = TempDir::new().unwrap();
+    fs::write(
+        path.join(".gitlsf.toml"),
+        "exclude = [\"src/utils.rs\"]\nformat = \"csv\"\n\n[languages]\n\"src/lib.rs\" = \"C\"\n",
+    )
+    .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(
+            "path,language,lines,code,comment,blank\n\
+             src/lib.rs,C,3,3,0,0\n\
+             src/main.rs,Rust,3,3,0,0\n",
+        );
+
+    // Command-line flags take precedence over the configuration
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-q", "-f", "text", "--exclude", "!src/utils.rs"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("9\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-q", "--no-config"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("9\n");
+}
+
+#[test]
+fn test_user_config() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let config_home = TempDir::new().unwrap();
+    fs::create_dir_all(config_home.path().join("gitlsf")).unwrap();
+    fs::write(
+        config_home.path().join("gitlsf/config.toml"),
+        "exclude = [\"src/main.rs\"]\nformat = \"json\"\n",
+    )
+    .unwrap();
+    fs::write(path.join(".gitlsf.toml"), "format = \"text\"\n").unwrap();
+
+    // The repository configuration overrides the user format
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .arg("-s")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("Files: 2\nLines: 6\n");
+}
+
+#[test]
+fn test_invalid_config() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let config_home = TempDir::new().unwrap();
+    fs::write(path.join(".gitlsf.toml"), "exclud = [\"vendor/\"]\n").unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .arg(path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid configuration file"))
+        .stderr(predicate::str::contains("unknown field `exclud`"));
+
+    fs::write(path.join(".gitlsf.toml"), "format = \"yaml\"\n").unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .arg(path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid format 'yaml'"));
+}
+
+#[test]
+fn test_gitattributes() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let config_home = TempDir::new().unwrap();
+    fs::create_dir_all(path.join("src/proto")).unwrap();
+    fs::write(path.join("src/proto/api.pb.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    fs::write(path.join("script"), "echo hi\n").unwrap();
+    fs::write(
+        path.join(".gitattributes"),
+        "*.pb.rs linguist-generated\nsrc/utils.rs -diff\nscript linguist-language=Python\n",
+    )
+    .unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-q", "-m", "initial"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-l"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1       1 Python"))
+        .stdout(predicate::str::contains("3       7 total"));
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-q", "--no-attributes"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("12\n");
+
+    // Attributes are read from the counted revision, not the working tree
+    fs::write(path.join(".gitattributes"), "").unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-q", "-r", "HEAD"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("7\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .arg("-q")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("12\n");
+}
+
+#[test]
+fn test_binary_files_skipped() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let config_home = TempDir::new().unwrap();
+    fs::write(path.join("tool"), b"\x7fELF\x02\x01\x01\0\0\n\n\n").unwrap();
+    fs::write(path.join("module.wasm"), b"\0asm\x01\0\0\0\n").unwrap();
+    fs::write(path.join("src/gen.rs"), "fn generated() {}\n").unwrap();
+    fs::write(
+        path.join(".gitattributes"),
+        "src/gen.rs linguist-generated\n",
+    )
+    .unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-q", "-m", "initial"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-s", "--show-skipped"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(
+            "Files: 3\nLines: 9\nSkipped: 3\n\nSkipped:\n  binary    module.wasm\n  generated src/gen.rs\n  binary    tool\n",
+        );
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-q", "-r", "HEAD"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("9\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    let output = cmd
+        .env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-f", "json"])
+        .arg(path)
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        report["skipped"],
+        serde_json::json!([
+            {"path": "module.wasm", "reason": "binary"},
+            {"path": "src/gen.rs", "reason": "generated"},
+            {"path": "tool", "reason": "binary"},
+        ])
+    );
+}
+
+#[test]
+fn test_unreadable_files_reported() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let config_home = TempDir::new().unwrap();
+
+    // Deleted from the working tree, but still tracked
+    fs::remove_file(path.join("src/utils.rs")).unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .arg("-q")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("6\n")
+        .stderr(predicate::str::contains(
+            "warning: 1 file could not be read and was not counted:",
+        ))
+        .stderr(predicate::str::contains("utils.rs"));
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-q", "--strict"])
+        .arg(path)
+        .assert()
+        .failure()
+        .stdout("6\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    let output = cmd
+        .env("XDG_CONFIG_HOME", config_home.path())
+        .args(["-f", "json"])
+        .arg(path)
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["skipped"][0]["path"], "src/utils.rs");
+    assert_eq!(report["skipped"][0]["reason"], "unreadable");
+    assert!(
+        report["skipped"][0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Failed to read file")
+    );
+}
+
+#[test]
+fn test_strict_passes_when_all_files_read() {
+    let temp_dir = setup_git_repo();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+
+    cmd.args(["-q", "--strict"])
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn test_check_budgets() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let config_home = TempDir::new().unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["check", "--max-file-lines", "3", "--budget", "src/=9"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("All line budgets met (3 files, 9 lines)\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["check", "--max-file-lines", "2", "--budget", "src/=8"])
+        .arg(path)
+        .assert()
+        .code(3)
+        .stdout(
+            "src/lib.rs: 3 lines exceed the per-file limit of 2\n\
+             src/main.rs: 3 lines exceed the per-file limit of 2\n\
+             src/utils.rs: 3 lines exceed the per-file limit of 2\n\
+             src/: 9 lines exceed the budget of 8\n\
+             \n\
+             4 line budgets exceeded\n",
+        );
+}
+
+#[test]
+fn test_check_config() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let config_home = TempDir::new().unwrap();
+    fs::write(
+        path.join(".gitlsf.toml"),
+        "[check]\nmax_total_lines = 8\n\n[check.budgets]\n\"src/main.rs\" = 3\n",
+    )
+    .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .arg("check")
+        .arg(path)
+        .assert()
+        .code(3)
+        .stdout("total: 9 lines exceed the limit of 8\n\n1 line budget exceeded\n");
+
+    // Command-line limits take precedence over the configuration
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .args(["check", "--max-total-lines", "9"])
+        .arg(path)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_check_without_budgets() {
+    let temp_dir = setup_git_repo();
+    let config_home = TempDir::new().unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.env("XDG_CONFIG_HOME", config_home.path())
+        .arg("check")
+        .arg(temp_dir.path())
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("no line budgets configured"));
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["check", "--budget", "src/"])
+        .arg(temp_dir.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("expected GLOB=N"));
+}
+
+#[test]
+fn test_count_cache() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let cache = path.join(".git/gitlsf-cache");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["-q", "--no-cache"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("9\n");
+    assert!(!cache.exists());
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("-q").arg(path).assert().success().stdout("9\n");
+    assert!(cache.exists());
+
+    // A modified file is read again rather than taken from the cache
+    fs::write(path.join("src/main.rs"), "fn main() {}\n").unwrap();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("-q").arg(path).assert().success().stdout("7\n");
+
+    // An invalid cache is rebuilt
+    fs::write(&cache, "garbage").unwrap();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("-q").arg(path).assert().success().stdout("7\n");
+    assert!(fs::read_to_string(&cache).unwrap().starts_with(concat!(
+        "gitlsf-cache 1 ",
+        env!("CARGO_PKG_VERSION"),
+        "\n"
+    )));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unusual_filenames() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path();
+    Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    // A Latin-1 name that is not valid UTF-8, and names Git would quote
+    let names: [&[u8]; 4] = [
+        b"caf\xe9.rs",
+        "café.rs".as_bytes(),
+        b"tab\tname.rs",
+        b"new\nline.rs",
+    ];
+    for name in names {
+        fs::write(path.join(OsStr::from_bytes(name)), "fn f() {\n}\n").unwrap();
+    }
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    // Files are sorted by the bytes of their names
+    let expected = [
+        "   2 café.rs",
+        "   2 \"caf\\351.rs\"",
+        "   2 \"new\\nline.rs\"",
+        "   2 \"tab\\tname.rs\"",
+        "   8 total\n",
+    ]
+    .join("\n");
+    // The second run takes the counts it can from the cache
+    for args in [&[][..], &[], &["--no-cache"]] {
+        let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+        cmd.args(args)
+            .arg(path)
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+
+    Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@test.com"])
+        .args(["commit", "-q", "-m", "initial"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    let output = AssertCmd::cargo_bin("gitlsf")
+        .unwrap()
+        .args(["-f", "json", "--rev", "HEAD"])
+        .arg(path)
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let paths: Vec<&str> = report["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file| file["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "café.rs",
+            "\"caf\\351.rs\"",
+            "\"new\\nline.rs\"",
+            "\"tab\\tname.rs\""
+        ]
+    );
+}
+
+#[test]
+fn test_run_from_subdirectory() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    fs::create_dir(path.join("tests")).unwrap();
+    fs::write(path.join("tests/it.rs"), "#[test]\nfn it() {}\n").unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-qm", "Initial commit"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    // The whole repository is counted, with paths relative to its root
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.current_dir(path.join("src"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("src/main.rs"))
+        .stdout(predicate::str::contains("tests/it.rs"))
+        .stdout(predicate::str::contains("11 total"));
+
+    // Paths are resolved against the current directory
+    for (dir, args, total) in [
+        (path.to_path_buf(), &["src/main.rs", "tests"][..], "5\n"),
+        (path.join("src"), &["main.rs"][..], "3\n"),
+        (path.join("src"), &[".", "../tests"][..], "11\n"),
+        (path.join("src"), &["..", "main.rs"][..], "11\n"),
+        (path.join("src"), &["--rev", "HEAD", "."][..], "9\n"),
+    ] {
+        let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+        cmd.current_dir(dir)
+            .arg("-q")
+            .args(args)
+            .assert()
+            .success()
+            .stdout(total);
+    }
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.current_dir(path.join("src"))
+        .arg("--relative")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" main.rs\n"))
+        .stdout(predicate::str::contains(" ../tests/it.rs\n"))
+        .stdout(predicate::str::contains("src/").not());
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.current_dir(path.join("src"))
+        .args(["--relative", "--tree"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(".."));
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.current_dir(path.join("src"))
+        .args(["check", "--relative", "--max-file-lines", "2", "main.rs"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::starts_with(
+            "main.rs: 3 lines exceed the per-file limit of 2\n",
+        ));
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.current_dir(path.join("src"))
+        .args(["check", "--max-total-lines", "2", "../tests"])
+        .assert()
+        .success()
+        .stdout("All line budgets met (1 files, 2 lines)\n");
+
+    let outside = TempDir::new().unwrap();
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.current_dir(path)
+        .arg("src")
+        .arg(outside.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is outside the repository"));
+}
+
+#[test]
+fn test_submodules() {
+    let library = setup_git_repo();
+    Command::new("git")
+        .args(["commit", "-qm", "Library"])
+        .current_dir(library.path())
+        .output()
+        .unwrap();
+
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let output = Command::new("git")
+        .args(["-c", "protocol.file.allow=always", "submodule", "add", "-q"])
+        .arg(library.path())
+        .arg("vendor/lib")
+        .current_dir(path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    Command::new("git")
+        .args(["commit", "-qm", "Add library"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    // Without recursion, the submodule is reported rather than read
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("--show-skipped")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("9 total"))
+        .stdout(predicate::str::contains("submodule vendor/lib"))
+        .stderr("");
+
+    for args in [&[][..], &["--no-cache"][..], &["--rev", "HEAD"][..]] {
+        let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+        cmd.args(["-s", "--recurse-submodules"])
+            .args(args)
+            .arg(path)
+            .assert()
+            .success()
+            .stdout(
+                "Files: 6\n\
+                 Lines: 18\n\
+                 Submodules:\n  \
+                 vendor/lib: 3 files, 9 lines\n",
+            );
+    }
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["--recurse-submodules", "--format", "json"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\"path\": \"vendor/lib/src/main.rs\"",
+        ))
+        .stdout(predicate::str::contains("\"submodule\": \"vendor/lib\""));
+
+    // Pathspecs reach into submodules
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.current_dir(path)
+        .args([
+            "--recurse-submodules",
+            "vendor/lib/src/lib.rs",
+            "src/main.rs",
+        ])
+        .assert()
+        .success()
+        .stdout("   3 src/main.rs\n   3 vendor/lib/src/lib.rs\n   6 total\n");
+
+    // A fresh clone has the submodule, but not its files
+    let clone = TempDir::new().unwrap();
+    Command::new("git")
+        .args(["clone", "-q"])
+        .arg(path)
+        .arg(clone.path())
+        .output()
+        .unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["-q", "--recurse-submodules", "--format", "json"])
+        .arg(clone.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"reason\": \"uninitialized\""))
+        .stderr(predicate::str::contains(
+            "warning: 1 submodule is not initialized",
+        ))
+        .stderr(predicate::str::contains("  vendor/lib\n"));
+}
+
+#[test]
+fn test_file_sets() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    Command::new("git")
+        .args(["commit", "-qm", "Initial commit"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    // Four lines are staged, and a fifth is added in the working tree
+    let main = "fn main() {\n    println!(\"Hello, world!\");\n}\n";
+    fs::write(path.join("src/main.rs"), format!("{main}// staged\n")).unwrap();
+    Command::new("git")
+        .args(["add", "src/main.rs"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    fs::write(
+        path.join("src/main.rs"),
+        format!("{main}// staged\n// modified\n"),
+    )
+    .unwrap();
+    fs::write(path.join("src/new.rs"), "fn new() {}\n\n").unwrap();
+    fs::write(path.join(".gitignore"), "*.log\n").unwrap();
+    fs::write(path.join("debug.log"), "not counted\n").unwrap();
+
+    for (flag, output) in [
+        ("--staged", "   4 src/main.rs\n   4 total\n"),
+        ("--modified", "   5 src/main.rs\n   5 total\n"),
+        ("--untracked", "   2 src/new.rs\n   2 total\n"),
+    ] {
+        let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+        cmd.arg(flag).arg(path).assert().success().stdout(output);
+    }
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["--staged", "--format", "json"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"file_set\": \"staged\""));
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["check", "--staged", "--max-file-lines", "4"])
+        .arg(path)
+        .assert()
+        .success()
+        .stdout("All line budgets met (1 files, 4 lines)\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["--staged", "--untracked"])
+        .arg(path)
+        .assert()
+        .code(2);
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["--modified", "--rev", "HEAD"])
+        .arg(path)
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_count_other_sources() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path();
+    let release = path.join("release-1.0");
+    fs::create_dir_all(release.join("src")).unwrap();
+    fs::create_dir_all(release.join("target")).unwrap();
+    fs::write(release.join(".gitignore"), "target/\n").unwrap();
+    fs::write(release.join("src/main.rs"), "fn main() {\n}\n").unwrap();
+    fs::write(release.join("target/build.rs"), "fn build() {}\n").unwrap();
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("--walk")
+        .arg(&release)
+        .assert()
+        .success()
+        .stdout("   2 src/main.rs\n   2 total\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.args(["--stdin", "--null", "-q"])
+        .current_dir(&release)
+        .write_stdin("src/main.rs\0target/build.rs\0")
+        .assert()
+        .success()
+        .stdout("3\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("--walk")
+        .arg(&release)
+        .args(["--rev", "HEAD"])
+        .assert()
+        .code(2);
+}
+
+#[cfg(feature = "archive")]
+#[test]
+fn test_count_archive() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path();
+    let release = path.join("release-1.0");
+    fs::create_dir_all(release.join("target")).unwrap();
+    fs::write(release.join(".gitignore"), "target/\n").unwrap();
+    fs::write(release.join("main.py"), "print('hi')\n").unwrap();
+    fs::write(release.join("target/build.py"), "x = 1\ny = 2\n").unwrap();
+    Command::new("tar")
+        .args(["-czf", "release-1.0.tar.gz", "release-1.0"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+
+    // Archives are counted as released, without .gitignore
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("--archive")
+        .arg(path.join("release-1.0.tar.gz"))
+        .assert()
+        .success()
+        .stdout("   1 main.py\n   2 target/build.py\n   3 total\n");
+
+    let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+    cmd.arg("--archive")
+        .arg(path.join("release-1.0.tar.xz"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unsupported format"));
+}
+
+#[cfg(feature = "native-index")]
+#[test]
+fn test_count_without_git() {
+    let temp_dir = setup_git_repo();
+    let path = temp_dir.path();
+    let config_home = TempDir::new().unwrap();
+    let empty_path = TempDir::new().unwrap();
+
+    let run = |args: &[&str], git_path: &std::ffi::OsStr| {
+        let mut cmd = AssertCmd::cargo_bin("gitlsf").unwrap();
+        let output = cmd
+            .args(args)
+            .arg(path)
+            .env("PATH", git_path)
+            .env("XDG_CONFIG_HOME", config_home.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "gitlsf {args:?} failed");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let expected = run(&["--no-cache"], &std::env::var_os("PATH").unwrap());
+
+    // Both the cached and uncached counts read the index without git
+    assert_eq!(run(&[], empty_path.path().as_os_str()), expected);
+    assert_eq!(run(&[], empty_path.path().as_os_str()), expected);
+    assert_eq!(
+        run(&["--no-cache"], empty_path.path().as_os_str()),
+        expected
+    );
+
+    // Modified files are found without git diff
+    fs::write(path.join("src/utils.rs"), "pub fn helper() {}\n").unwrap();
+    let expected = run(&["--no-cache"], &std::env::var_os("PATH").unwrap());
+    assert_eq!(run(&[], empty_path.path().as_os_str()), expected);
+}